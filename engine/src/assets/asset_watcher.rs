@@ -0,0 +1,103 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use crossbeam_channel::Receiver;
+use notify_debouncer_mini::{
+    DebounceEventResult, Debouncer, new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+};
+
+use crate::assets::resource_packs::ResourcePacks;
+
+/// Editors often write a file in several steps, so changes are only reported once they've settled
+const ASSET_DEBOUNCE_DURATION_MS: u64 = 300;
+
+/// Watches the block definitions and world textures of every resource pack for changes,
+/// so they can be reloaded while the game is running.
+pub struct AssetWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+    changes: Receiver<()>,
+}
+
+impl AssetWatcher {
+    pub fn new(resource_packs: &ResourcePacks) -> anyhow::Result<Self> {
+        // Watched directories are reported with absolute paths, so they're matched in canonical form
+        let mut watched_paths = WatchedPaths::default();
+        let mut watches = Vec::new();
+        for pack in resource_packs.packs() {
+            let Ok(path) = pack.path().canonicalize() else {
+                continue;
+            };
+
+            if pack.is_archive() {
+                watched_paths.archives.push(path.clone());
+                watches.push((path, RecursiveMode::NonRecursive));
+                continue;
+            }
+
+            let defs_path = path.join("defs");
+            let textures_path = path.join("textures");
+            if defs_path.is_dir() {
+                watches.push((defs_path, RecursiveMode::NonRecursive));
+            }
+            if textures_path.is_dir() {
+                watched_paths.textures.push(textures_path.clone());
+                watches.push((textures_path, RecursiveMode::Recursive));
+            }
+        }
+
+        let (sender, changes) = crossbeam_channel::unbounded();
+        let mut debouncer = new_debouncer(
+            Duration::from_millis(ASSET_DEBOUNCE_DURATION_MS),
+            move |result: DebounceEventResult| match result {
+                Ok(events) => {
+                    if events
+                        .iter()
+                        .any(|event| watched_paths.is_block_asset(&event.path))
+                    {
+                        let _ = sender.send(());
+                    }
+                }
+                Err(err) => log::warn!("Error while watching assets: {}", err),
+            },
+        )
+        .context("Failed to create asset watcher")?;
+
+        for (path, mode) in watches {
+            debouncer
+                .watcher()
+                .watch(&path, mode)
+                .with_context(|| format!("Failed to watch {}", path.display()))?;
+        }
+
+        Ok(AssetWatcher {
+            _debouncer: debouncer,
+            changes,
+        })
+    }
+
+    /// Returns true if blocks or textures have changed since the last call.
+    pub fn has_changes(&self) -> bool {
+        self.changes.try_iter().count() > 0
+    }
+}
+
+#[derive(Default)]
+struct WatchedPaths {
+    textures: Vec<PathBuf>,
+    archives: Vec<PathBuf>,
+}
+
+impl WatchedPaths {
+    fn is_block_asset(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|name| name == "blocks.ron")
+            || self
+                .textures
+                .iter()
+                .any(|textures| path.starts_with(textures))
+            || self.archives.iter().any(|archive| path == archive)
+    }
+}
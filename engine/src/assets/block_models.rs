@@ -0,0 +1,346 @@
+use anyhow::ensure;
+use glam::{IVec3, U8Vec2};
+use serde::Deserialize;
+
+use crate::{
+    assets::block_orientation::{BlockOrientation, BlockRotation},
+    voxels::face::Face,
+};
+
+/// Block models are defined on a grid with this many units along each edge of a voxel
+pub const MODEL_GRID_SIZE: u8 = 16;
+
+const SLAB: ModelBox = ModelBox::new([0, 0, 0], [16, 8, 16]);
+/// Upper half of a stair, on top of a slab. Oriented stairs turn it with the rest of the model.
+const STAIR_STEP: ModelBox = ModelBox::new([0, 8, 0], [16, 16, 8]);
+
+/// Shape of a block, referenced by its definition in blocks.ron.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub enum BlockModelDefinition {
+    #[default]
+    Cube,
+    /// Two diagonal quads crossing in the middle of the voxel, used for plants
+    Cross,
+    /// Bottom half of a voxel
+    Slab,
+    /// Slab with a step on its back half
+    Stair,
+    /// Custom list of boxes, in model grid units
+    Boxes(Vec<ModelBox>),
+}
+
+impl BlockModelDefinition {
+    /// Boxes the model is made of, or None if it isn't made of boxes
+    pub fn boxes(&self) -> Option<&[ModelBox]> {
+        match self {
+            BlockModelDefinition::Cube | BlockModelDefinition::Cross => None,
+            BlockModelDefinition::Slab => Some(&[SLAB]),
+            BlockModelDefinition::Stair => Some(&[SLAB, STAIR_STEP]),
+            BlockModelDefinition::Boxes(boxes) => Some(boxes),
+        }
+    }
+}
+
+/// Axis-aligned box of a block model, in model grid units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct ModelBox {
+    pub min: [u8; 3],
+    pub max: [u8; 3],
+}
+
+impl ModelBox {
+    pub const fn new(min: [u8; 3], max: [u8; 3]) -> Self {
+        ModelBox { min, max }
+    }
+
+    /// Box turned around the center of the voxel from block space to world space
+    fn rotated(&self, rotation: BlockRotation) -> ModelBox {
+        let half = IVec3::splat(MODEL_GRID_SIZE as i32 / 2);
+        let corner = |corner: [u8; 3]| {
+            rotation.rotate(IVec3::from_array(corner.map(i32::from)) - half) + half
+        };
+        let (a, b) = (corner(self.min), corner(self.max));
+        ModelBox::new(
+            a.min(b).to_array().map(|v| v as u8),
+            a.max(b).to_array().map(|v| v as u8),
+        )
+    }
+
+    /// Rectangle covered by the box when projected onto the given face
+    fn face_rect(&self, face: Face) -> FaceRect {
+        let axis = face.axis();
+        let (u, v) = (axis.u_axis() as usize, axis.v_axis() as usize);
+        FaceRect {
+            min: U8Vec2::new(self.min[u], self.min[v]),
+            max: U8Vec2::new(self.max[u], self.max[v]),
+        }
+    }
+
+    /// Distance from the given side of the box to the voxel face on the same side
+    fn inset(&self, face: Face) -> u8 {
+        let d = face.axis() as usize;
+        if face.is_positive() {
+            MODEL_GRID_SIZE - self.max[d]
+        } else {
+            self.min[d]
+        }
+    }
+
+    /// Whether the given side of this box is hidden by `other` touching it from the outside
+    fn is_side_hidden_by(&self, face: Face, other: &ModelBox) -> bool {
+        let d = face.axis() as usize;
+        let touching = if face.is_positive() {
+            other.min[d] == self.max[d]
+        } else {
+            other.max[d] == self.min[d]
+        };
+        touching && other.face_rect(face).contains(&self.face_rect(face))
+    }
+}
+
+/// Rectangle on a face in model grid units, along the face's tangent (u) and bitangent (v) axes.
+/// The axes are the same as the ones used by the greedy mesher and the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceRect {
+    pub min: U8Vec2,
+    pub max: U8Vec2,
+}
+
+impl FaceRect {
+    pub const FULL: FaceRect = FaceRect {
+        min: U8Vec2::ZERO,
+        max: U8Vec2::splat(MODEL_GRID_SIZE),
+    };
+
+    pub fn size(&self) -> U8Vec2 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, other: &FaceRect) -> bool {
+        self.min.cmple(other.min).all() && self.max.cmpge(other.max).all()
+    }
+}
+
+/// Parts of a voxel face covered by a block model, one bit per model grid cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FaceCoverage([u16; MODEL_GRID_SIZE as usize]);
+
+impl FaceCoverage {
+    pub const FULL: FaceCoverage = FaceCoverage([u16::MAX; MODEL_GRID_SIZE as usize]);
+
+    fn row_mask(rect: &FaceRect) -> u16 {
+        ((1u32 << rect.max.x) - (1u32 << rect.min.x)) as u16
+    }
+
+    fn add(&mut self, rect: &FaceRect) {
+        let mask = Self::row_mask(rect);
+        for row in &mut self.0[rect.min.y as usize..rect.max.y as usize] {
+            *row |= mask;
+        }
+    }
+
+    pub fn contains(&self, rect: &FaceRect) -> bool {
+        let mask = Self::row_mask(rect);
+        self.0[rect.min.y as usize..rect.max.y as usize]
+            .iter()
+            .all(|row| row & mask == mask)
+    }
+}
+
+/// Vertical diagonal planes of a cross model, named after how they cross the voxel when seen from above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossDiagonal {
+    /// From the minimum corner (x = 0, z = 0) to x = 1, z = 1
+    Rising,
+    /// From x = 0, z = 1 to x = 1, z = 0
+    Falling,
+}
+
+/// Quad emitted by a block model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFace {
+    /// Side of a box, `inset` model grid units inwards from the voxel face in the same direction
+    Box {
+        face: Face,
+        rect: FaceRect,
+        inset: u8,
+    },
+    Diagonal(CrossDiagonal),
+}
+
+/// Geometry of a block type, built from its `BlockModelDefinition`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum BlockModel {
+    /// Full voxel, meshed by the greedy mesher
+    #[default]
+    Cube,
+    /// Any other shape. Each voxel emits its own faces, which are never merged.
+    Shaped {
+        faces: Vec<ModelFace>,
+        /// Parts of each voxel face covered by the model, in face order
+        coverage: Box<[FaceCoverage; 6]>,
+    },
+    /// Box model turned by the voxel's rotation, with one shaped model for each face its top can point at
+    Rotated(Box<[BlockModel; 6]>),
+}
+
+impl BlockModel {
+    /// Builds the model of a block. Box models of oriented blocks are built once for every rotation.
+    pub fn from_definition(
+        definition: &BlockModelDefinition,
+        orientation: BlockOrientation,
+    ) -> anyhow::Result<Self> {
+        let Some(boxes) = definition.boxes() else {
+            return Ok(match definition {
+                BlockModelDefinition::Cross => BlockModel::Shaped {
+                    faces: vec![
+                        ModelFace::Diagonal(CrossDiagonal::Rising),
+                        ModelFace::Diagonal(CrossDiagonal::Falling),
+                    ],
+                    coverage: Default::default(),
+                },
+                _ => BlockModel::Cube,
+            });
+        };
+
+        // Checks the boxes before they're rotated
+        let model = Self::from_boxes(boxes)?;
+        if orientation == BlockOrientation::Fixed {
+            return Ok(model);
+        }
+
+        let models = Face::all().map(|up| {
+            let rotation = BlockOrientation::Facing.rotation(up as u8);
+            let rotated: Vec<_> = boxes.iter().map(|b| b.rotated(rotation)).collect();
+            Self::from_boxes(&rotated).expect("Rotated boxes stay inside the voxel")
+        });
+        Ok(BlockModel::Rotated(Box::new(models)))
+    }
+
+    /// Builds the faces of a list of boxes. Sides that are hidden by another box of the same model are skipped.
+    pub fn from_boxes(boxes: &[ModelBox]) -> anyhow::Result<Self> {
+        ensure!(!boxes.is_empty(), "Block model must have at least one box");
+        for model_box in boxes {
+            ensure!(
+                (0..3)
+                    .all(|i| model_box.min[i] < model_box.max[i]
+                        && model_box.max[i] <= MODEL_GRID_SIZE),
+                "Invalid model box {:?}, coordinates must be between 0 and {} with min < max",
+                model_box,
+                MODEL_GRID_SIZE
+            );
+        }
+
+        let mut faces = Vec::new();
+        let mut coverage = [FaceCoverage::default(); 6];
+        for (index, model_box) in boxes.iter().enumerate() {
+            for face in Face::all() {
+                let hidden = boxes.iter().enumerate().any(|(other, other_box)| {
+                    other != index && model_box.is_side_hidden_by(face, other_box)
+                });
+                if hidden {
+                    continue;
+                }
+
+                let rect = model_box.face_rect(face);
+                let inset = model_box.inset(face);
+                if inset == 0 {
+                    coverage[face as usize].add(&rect);
+                }
+                faces.push(ModelFace::Box { face, rect, inset });
+            }
+        }
+
+        Ok(BlockModel::Shaped {
+            faces,
+            coverage: Box::new(coverage),
+        })
+    }
+
+    /// Model of a voxel with the given rotation. Models that aren't rotated are the same for every rotation.
+    pub fn rotated(&self, rotation: BlockRotation) -> &BlockModel {
+        match self {
+            BlockModel::Rotated(models) => &models[rotation.up() as usize],
+            model => model,
+        }
+    }
+
+    pub fn is_cube(&self) -> bool {
+        matches!(self, BlockModel::Cube)
+    }
+
+    /// Faces emitted by shaped models. Cubes are meshed separately and have none.
+    pub fn faces(&self) -> &[ModelFace] {
+        match self {
+            BlockModel::Cube => &[],
+            BlockModel::Shaped { faces, .. } => faces,
+            BlockModel::Rotated(models) => models[Face::Top as usize].faces(),
+        }
+    }
+
+    /// Parts of the given voxel face covered by the model
+    pub fn coverage(&self, face: Face) -> &FaceCoverage {
+        match self {
+            BlockModel::Cube => &FaceCoverage::FULL,
+            BlockModel::Shaped { coverage, .. } => &coverage[face as usize],
+            BlockModel::Rotated(models) => models[Face::Top as usize].coverage(face),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stair_faces_and_coverage() {
+        let stair =
+            BlockModel::from_definition(&BlockModelDefinition::Stair, BlockOrientation::Fixed)
+                .unwrap();
+
+        // The bottom of the step rests on the slab, so it's skipped
+        assert_eq!(stair.faces().len(), 11);
+        assert!(!stair.faces().contains(&ModelFace::Box {
+            face: Face::Bottom,
+            rect: STAIR_STEP.face_rect(Face::Bottom),
+            inset: 8,
+        }));
+
+        assert!(stair.coverage(Face::Bottom).contains(&FaceRect::FULL));
+        assert!(stair.coverage(Face::Back).contains(&FaceRect::FULL));
+        assert!(!stair.coverage(Face::Top).contains(&FaceRect::FULL));
+        // Top half of the back is covered by the step
+        let back_top = FaceRect {
+            min: U8Vec2::new(0, 8),
+            max: U8Vec2::new(16, 16),
+        };
+        assert!(stair.coverage(Face::Back).contains(&back_top));
+        assert!(!stair.coverage(Face::Front).contains(&back_top));
+        assert!(!stair.coverage(Face::Left).contains(&FaceRect::FULL));
+    }
+
+    #[test]
+    fn test_oriented_stair_rotates_its_boxes() {
+        let stair =
+            BlockModel::from_definition(&BlockModelDefinition::Stair, BlockOrientation::Facing)
+                .unwrap();
+        let fixed =
+            BlockModel::from_definition(&BlockModelDefinition::Stair, BlockOrientation::Fixed)
+                .unwrap();
+        assert_eq!(stair.rotated(BlockRotation::IDENTITY), &fixed);
+
+        // Upside down, the slab hangs from the top and the step moves to the front
+        let upside_down = stair.rotated(BlockOrientation::Facing.rotation(Face::Bottom as u8));
+        assert_eq!(upside_down.faces().len(), 11);
+        assert!(upside_down.coverage(Face::Top).contains(&FaceRect::FULL));
+        assert!(!upside_down.coverage(Face::Bottom).contains(&FaceRect::FULL));
+        assert!(upside_down.coverage(Face::Front).contains(&FaceRect::FULL));
+        assert!(!upside_down.coverage(Face::Back).contains(&FaceRect::FULL));
+
+        // Pointing its top at the front, the slab lies against the back
+        let forwards = stair.rotated(BlockOrientation::Facing.rotation(Face::Front as u8));
+        assert!(forwards.coverage(Face::Back).contains(&FaceRect::FULL));
+        assert!(!forwards.coverage(Face::Front).contains(&FaceRect::FULL));
+        assert!(!forwards.coverage(Face::Bottom).contains(&FaceRect::FULL));
+    }
+}
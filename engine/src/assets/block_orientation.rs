@@ -0,0 +1,142 @@
+use glam::IVec3;
+use serde::Deserialize;
+
+use crate::{math::axis::Axis, voxels::face::Face};
+
+/// How a block stores its orientation in the voxel metadata, declared in blocks.ron.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum BlockOrientation {
+    /// Always drawn the same way
+    #[default]
+    Fixed,
+    /// Top of the block points along an axis, like a log lying sideways.
+    /// Metadata 0 is Y, 1 is X and 2 is Z.
+    Axis,
+    /// Top of the block points towards any of the six faces, stored as the face's index
+    Facing,
+}
+
+impl BlockOrientation {
+    /// Rotation of a block with this orientation and the given metadata
+    pub fn rotation(self, metadata: u8) -> BlockRotation {
+        let up = match self {
+            BlockOrientation::Fixed => Face::Top,
+            BlockOrientation::Axis => match metadata {
+                1 => Face::Right,
+                2 => Face::Front,
+                _ => Face::Top,
+            },
+            BlockOrientation::Facing => Face::try_from(metadata).unwrap_or(Face::Top),
+        };
+        BlockRotation { up }
+    }
+
+    /// Metadata for a block placed against the given face of another block, which points the block away from it.
+    pub fn placement_metadata(self, clicked_face: Face) -> u8 {
+        match self {
+            BlockOrientation::Fixed => 0,
+            BlockOrientation::Axis => match clicked_face.axis() {
+                Axis::Y => 0,
+                Axis::X => 1,
+                Axis::Z => 2,
+            },
+            BlockOrientation::Facing => clicked_face as u8,
+        }
+    }
+}
+
+/// Rotation from the block's own space to the world, defined by the direction its top points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRotation {
+    up: Face,
+}
+
+impl BlockRotation {
+    pub const IDENTITY: BlockRotation = BlockRotation { up: Face::Top };
+
+    pub fn up(&self) -> Face {
+        self.up
+    }
+
+    /// Rotates a direction in block space to world space
+    pub fn rotate(&self, direction: IVec3) -> IVec3 {
+        let IVec3 { x, y, z } = direction;
+        match self.up {
+            Face::Top => direction,
+            Face::Bottom => IVec3::new(x, -y, -z),
+            Face::Left => IVec3::new(-y, x, z),
+            Face::Right => IVec3::new(y, -x, z),
+            Face::Front => IVec3::new(x, -z, y),
+            Face::Back => IVec3::new(x, z, -y),
+        }
+    }
+
+    /// Side of the block that ends up facing the given world direction
+    pub fn block_face(&self, world_face: Face) -> Face {
+        Face::all()
+            .into_iter()
+            .find(|face| self.rotate(face.to_ivec3()) == world_face.to_ivec3())
+            .expect("Rotations map every face to another face")
+    }
+
+    /// Counter-clockwise quarter turns of the texture on the given world face, from its tangent towards its bitangent.
+    /// Textures point up along the block's top on its sides, and along the bitangent on its top and bottom.
+    pub fn texture_rotation(&self, world_face: Face) -> u8 {
+        let block_face = self.block_face(world_face);
+        let texture_up = match block_face.axis() {
+            Axis::Y => Axis::Y.v_axis().as_unit_vector(),
+            _ => IVec3::Y,
+        };
+        let texture_up = self.rotate(texture_up);
+
+        let axis = world_face.axis();
+        let (tangent, bitangent) = (
+            axis.u_axis().as_unit_vector(),
+            axis.v_axis().as_unit_vector(),
+        );
+        match texture_up {
+            up if up == bitangent => 0,
+            up if up == -tangent => 1,
+            up if up == -bitangent => 2,
+            _ => 3,
+        }
+    }
+}
+
+impl Default for BlockRotation {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sideways_log_faces_and_texture_rotation() {
+        let upright = BlockOrientation::Axis.rotation(0);
+        for face in Face::all() {
+            assert_eq!(upright.block_face(face), face);
+            assert_eq!(upright.texture_rotation(face), 0);
+        }
+
+        // A log lying along X shows its ends on the left and right, and its bark runs along X
+        let along_x =
+            BlockOrientation::Axis.rotation(BlockOrientation::Axis.placement_metadata(Face::Left));
+        assert_eq!(along_x.up(), Face::Right);
+        assert_eq!(along_x.block_face(Face::Right), Face::Top);
+        assert_eq!(along_x.block_face(Face::Left), Face::Bottom);
+        assert!(!matches!(
+            along_x.block_face(Face::Top),
+            Face::Top | Face::Bottom
+        ));
+        // The bitangent of the top face is X, but the front face has to turn its texture sideways
+        assert_eq!(along_x.texture_rotation(Face::Top), 0);
+        assert_eq!(along_x.texture_rotation(Face::Front) % 2, 1);
+
+        let upside_down = BlockOrientation::Facing.rotation(Face::Bottom as u8);
+        assert_eq!(upside_down.block_face(Face::Top), Face::Bottom);
+        assert_eq!(upside_down.texture_rotation(Face::Front), 2);
+    }
+}
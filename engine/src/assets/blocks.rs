@@ -1,93 +1,259 @@
-use std::path::PathBuf;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
 use anyhow::Context;
 use serde::Deserialize;
 
 use crate::{
-    assets::world_textures::{TextureTransparency, WorldTextureHandle, WorldTextures},
-    voxels::face::Face,
+    assets::{
+        block_models::{BlockModel, BlockModelDefinition, FaceRect},
+        block_orientation::{BlockOrientation, BlockRotation},
+        resource_packs::ResourcePacks,
+        texture_variants::{
+            CONNECTED_TILE_COUNT, FaceTexture, FaceTextureDefinition, TextureVariant,
+        },
+        world_textures::{TextureTransparency, WorldTextureHandle, WorldTextures},
+    },
+    fluids::fluid::{FluidDefinition, MAX_FLOW_DISTANCE},
+    lighting::chunk_light::MAX_LIGHT,
+    voxels::{face::Face, voxel::Voxel},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct BlockTypeId(pub u16);
 
+/// Namespace of block names that don't specify one
+pub const DEFAULT_NAMESPACE: &str = "voxel";
+
+/// Block definitions in each resource pack, merged by block name
+const BLOCK_DEFINITIONS_PATH: &str = "defs/blocks.ron";
+
+/// Block IDs are stored in the 12 bit block type of a voxel
+const MAX_BLOCK_TYPES: usize = 1 << 12;
+
+/// Blocks the engine refers to directly through the constants in `Voxel`.
+/// Their IDs depend on their order in blocks.ron, which is checked when the blocks are loaded.
+const BUILTIN_BLOCKS: [(Voxel, &str); 4] = [
+    (Voxel::AIR, "voxel:air"),
+    (Voxel::GRASS, "voxel:grass"),
+    (Voxel::DIRT, "voxel:dirt"),
+    (Voxel::GOLD, "voxel:gold"),
+];
+
+/// Adds the default namespace to names that don't have one.
+pub fn qualify_block_name(name: &str) -> Cow<'_, str> {
+    if name.contains(':') {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("{}:{}", DEFAULT_NAMESPACE, name))
+    }
+}
+
 pub struct BlockDatabaseEntry {
     pub id: BlockTypeId,
     pub name: String,
     pub texture_indices: Option<TextureIndices>,
+    pub transparency: TextureTransparency,
+    pub model: BlockModel,
+    pub properties: BlockProperties,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct TextureIndices {
-    pub top: WorldTextureHandle,
-    pub bottom: WorldTextureHandle,
-    pub side: WorldTextureHandle,
+    pub top: FaceTexture,
+    pub bottom: FaceTexture,
+    pub side: FaceTexture,
 }
 
 impl TextureIndices {
     pub fn new_single(index: WorldTextureHandle) -> Self {
+        Self::new_uniform(FaceTexture::Single(index))
+    }
+
+    pub fn new_uniform(texture: FaceTexture) -> Self {
         TextureIndices {
-            top: index,
-            bottom: index,
-            side: index,
+            top: texture.clone(),
+            bottom: texture.clone(),
+            side: texture,
         }
     }
 
-    pub fn get_face_index(&self, face: Face) -> u16 {
-        match face {
-            Face::Top => self.top.0,
-            Face::Bottom => self.bottom.0,
-            _ => self.side.0,
+    /// Texture of the block side that ends up facing `face` after rotating the block.
+    /// See `BlockRotation::texture_rotation` for how the texture is turned on that face.
+    pub fn get_face_texture(&self, face: Face, rotation: BlockRotation) -> &FaceTexture {
+        match rotation.block_face(face) {
+            Face::Top => &self.top,
+            Face::Bottom => &self.bottom,
+            _ => &self.side,
         }
     }
 }
 
 pub struct TextureIndex(pub u16);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub enum BlockTextureDefinition {
+    #[default]
     Invisible,
-    Single(String),
+    Single(FaceTextureDefinition),
     PerFace {
-        top: String,
-        bottom: String,
-        side: String,
+        top: FaceTextureDefinition,
+        bottom: FaceTextureDefinition,
+        side: FaceTextureDefinition,
     },
+    /// Texture that connects to the same block next to it, so groups of blocks look like one big block.
+    /// A vertical strip of `CONNECTED_TILE_COUNT` square tiles, see `texture_variants::connected_tile` for their order.
+    Connected(String),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct BlockDefinition {
-    pub id: u16,
+    /// Namespaced name like `voxel:grass`. Names without a namespace use `DEFAULT_NAMESPACE`.
+    /// Numeric IDs are assigned in the order blocks are added.
     pub name: String,
     pub textures: BlockTextureDefinition,
     pub transparency: Option<TextureTransparency>,
+    /// Shape of the block, a full cube by default
+    #[serde(default)]
+    pub model: BlockModelDefinition,
+    /// Block light level emitted by the block (0-15). Emitting blocks are drawn full-bright.
+    #[serde(default)]
+    pub light_emission: u8,
+    /// Whether the block hides the faces behind it and stops light.
+    /// Defaults to true for visible cubes with opaque textures.
+    pub opaque: Option<bool>,
+    /// Defaults to true for visible blocks.
+    pub collidable: Option<bool>,
+    /// Whether placing a block can replace this one. Defaults to true for invisible blocks.
+    pub replaceable: Option<bool>,
+    /// How hard the block is to break, negative hardness makes it unbreakable.
+    /// Defaults to 1.0 for visible blocks and 0.0 for invisible ones.
+    pub hardness: Option<f32>,
+    /// Makes the block a fluid that flows from sources placed in the world.
+    /// Fluids aren't opaque or collidable, and can be replaced by default.
+    #[serde(default)]
+    pub fluid: Option<FluidDefinition>,
+    /// How the voxel metadata rotates the block. Placed blocks point away from the face they were placed on.
+    #[serde(default)]
+    pub orientation: BlockOrientation,
+    /// Which faces are coloured by the climate of their column
+    #[serde(default)]
+    pub tint: BiomeTint,
+}
+
+/// Faces of a block that are multiplied with the biome tint. Their textures should be greyscale.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum BiomeTint {
+    #[default]
+    None,
+    /// Only the top texture is tinted, like grass with plain dirt on its sides
+    Top,
+    All,
+}
+
+/// Properties of a block type used by meshing, lighting, physics and game logic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockProperties {
+    /// Invisible blocks like air have no faces
+    pub visible: bool,
+    /// Opaque blocks hide the faces of their neighbours and stop light
+    pub opaque: bool,
+    pub collidable: bool,
+    /// Placing a block can replace this one
+    pub replaceable: bool,
+    /// Negative hardness makes the block unbreakable
+    pub hardness: f32,
+    /// Block light level emitted by the block (0-15)
+    pub light_emission: u8,
+    /// Set for fluids, whose level is stored in the voxel metadata
+    pub fluid: Option<FluidDefinition>,
+    pub orientation: BlockOrientation,
+    pub tint: BiomeTint,
+}
+
+impl BlockProperties {
+    pub const AIR: BlockProperties = BlockProperties {
+        visible: false,
+        opaque: false,
+        collidable: false,
+        replaceable: true,
+        hardness: 0.0,
+        light_emission: 0,
+        fluid: None,
+        orientation: BlockOrientation::Fixed,
+        tint: BiomeTint::None,
+    };
+
+    pub const SOLID: BlockProperties = BlockProperties {
+        visible: true,
+        opaque: true,
+        collidable: true,
+        replaceable: false,
+        hardness: 1.0,
+        light_emission: 0,
+        fluid: None,
+        orientation: BlockOrientation::Fixed,
+        tint: BiomeTint::None,
+    };
+
+    pub fn from_definition(block: &BlockDefinition) -> Self {
+        let visible = !matches!(block.textures, BlockTextureDefinition::Invisible);
+        let opaque_texture = matches!(block.transparency, None | Some(TextureTransparency::Opaque));
+        let cube = matches!(block.model, BlockModelDefinition::Cube);
+        let fluid = block.fluid.is_some();
+        let defaults = if visible && !fluid {
+            BlockProperties::SOLID
+        } else {
+            BlockProperties::AIR
+        };
+
+        BlockProperties {
+            visible,
+            opaque: block
+                .opaque
+                .unwrap_or(visible && opaque_texture && cube && !fluid),
+            collidable: block.collidable.unwrap_or(defaults.collidable),
+            replaceable: block.replaceable.unwrap_or(defaults.replaceable),
+            hardness: block.hardness.unwrap_or(defaults.hardness),
+            light_emission: block.light_emission,
+            fluid: block.fluid,
+            orientation: block.orientation,
+            tint: block.tint,
+        }
+    }
 }
 
 pub struct BlockDatabase {
+    /// Indexed by block ID
     blocks: Vec<BlockDatabaseEntry>,
+    ids_by_name: HashMap<String, BlockTypeId>,
     // TODO: Should BlockDatabase really own WorldTextures?
     pub world_textures: WorldTextures,
-    assets_root: PathBuf,
+    resource_packs: Arc<ResourcePacks>,
 }
 
 impl BlockDatabase {
+    /// Loads blocks from the built-in assets only
     pub fn new() -> Self {
-        let assets_root = PathBuf::from("assets");
-
-        if !assets_root.exists() {
-            log::error!(
-                "Assets root path '{}' does not exist - check your working directory!",
-                assets_root.display()
-            );
-        }
+        Self::with_resource_packs(Arc::new(ResourcePacks::builtin()))
+    }
 
+    pub fn with_resource_packs(resource_packs: Arc<ResourcePacks>) -> Self {
         BlockDatabase {
-            assets_root,
             blocks: Vec::new(),
-            world_textures: WorldTextures::new(),
+            ids_by_name: HashMap::new(),
+            world_textures: WorldTextures::with_resource_packs(resource_packs.clone()),
+            resource_packs,
         }
     }
 
+    pub fn resource_packs(&self) -> &Arc<ResourcePacks> {
+        &self.resource_packs
+    }
+
     fn load_from_defs(&mut self, defs: Vec<BlockDefinition>) -> anyhow::Result<()> {
         // TODO: Load textures asynchronously
         for def in defs {
@@ -97,64 +263,266 @@ impl BlockDatabase {
         Ok(())
     }
 
+    /// Adds a block with the next free ID. The first block must be air, since empty voxels have ID 0.
     pub fn add_block_from_definition(
         &mut self,
         block: BlockDefinition,
     ) -> anyhow::Result<BlockTypeId> {
+        let name = qualify_block_name(&block.name).into_owned();
+        let id = BlockTypeId(self.blocks.len() as u16);
+
+        anyhow::ensure!(
+            self.blocks.len() < MAX_BLOCK_TYPES,
+            "Too many block types, the maximum is {}",
+            MAX_BLOCK_TYPES
+        );
+        anyhow::ensure!(
+            !self.ids_by_name.contains_key(&name),
+            "Block '{}' is defined more than once",
+            name
+        );
+        anyhow::ensure!(
+            id != Voxel::AIR.block_type_id() || name == BUILTIN_BLOCKS[0].1,
+            "The first block must be '{}', got '{}'",
+            BUILTIN_BLOCKS[0].1,
+            name
+        );
+
         let transparency = block.transparency.unwrap_or(TextureTransparency::Opaque);
+        let properties = BlockProperties::from_definition(&block);
+        let model = BlockModel::from_definition(&block.model, block.orientation)
+            .with_context(|| format!("Block '{}' has an invalid model", name))?;
+
+        anyhow::ensure!(
+            block.light_emission <= MAX_LIGHT,
+            "Block '{}' has light emission {}, the maximum is {}",
+            name,
+            block.light_emission,
+            MAX_LIGHT
+        );
+        if let Some(fluid) = &block.fluid {
+            anyhow::ensure!(
+                (1..=MAX_FLOW_DISTANCE).contains(&fluid.flow_distance) && fluid.tick_delay > 0,
+                "Block '{}' has an invalid fluid, flow distance must be between 1 and {} and tick delay above 0",
+                name,
+                MAX_FLOW_DISTANCE
+            );
+            anyhow::ensure!(
+                block.model == BlockModelDefinition::Cube,
+                "Fluid block '{}' can't have a model",
+                name
+            );
+        }
+        if block.orientation != BlockOrientation::Fixed {
+            anyhow::ensure!(
+                block.model != BlockModelDefinition::Cross && block.fluid.is_none(),
+                "Block '{}' can only have an orientation if it's a cube or made of boxes, and not a fluid",
+                name
+            );
+        }
 
-        let indices = match block.textures {
+        if block.tint == BiomeTint::Top {
+            anyhow::ensure!(
+                matches!(block.textures, BlockTextureDefinition::PerFace { .. }),
+                "Block '{}' can only tint its top if it has a texture per face",
+                name
+            );
+        }
+
+        let tint_top = block.tint != BiomeTint::None;
+        let tint_sides = block.tint == BiomeTint::All;
+        let indices = match &block.textures {
             BlockTextureDefinition::Invisible => None,
-            BlockTextureDefinition::Single(single) => {
-                let index = self
-                    .world_textures
-                    .load_from_path_and_allocate(&single, transparency)?;
-                Some(TextureIndices::new_single(index))
-            }
-            BlockTextureDefinition::PerFace { top, bottom, side } => {
-                let top_index = self
-                    .world_textures
-                    .load_from_path_and_allocate(&top, transparency)?;
-                let bottom_index = self
-                    .world_textures
-                    .load_from_path_and_allocate(&bottom, transparency)?;
-                let side_index = self
-                    .world_textures
-                    .load_from_path_and_allocate(&side, transparency)?;
-                Some(TextureIndices {
-                    top: top_index,
-                    bottom: bottom_index,
-                    side: side_index,
-                })
+            BlockTextureDefinition::Single(single) => Some(TextureIndices::new_uniform(
+                self.load_face_texture(&name, single, transparency, tint_sides)?,
+            )),
+            BlockTextureDefinition::PerFace { top, bottom, side } => Some(TextureIndices {
+                top: self.load_face_texture(&name, top, transparency, tint_top)?,
+                bottom: self.load_face_texture(&name, bottom, transparency, tint_sides)?,
+                side: self.load_face_texture(&name, side, transparency, tint_sides)?,
+            }),
+            BlockTextureDefinition::Connected(strip) => {
+                let first = self.world_textures.load_strip_and_allocate(
+                    strip,
+                    transparency,
+                    CONNECTED_TILE_COUNT,
+                )?;
+                if tint_sides {
+                    self.world_textures.set_tinted_from(first);
+                }
+                Some(TextureIndices::new_uniform(FaceTexture::Connected(first)))
             }
         };
 
         let block_entry = BlockDatabaseEntry {
-            id: BlockTypeId(block.id),
-            name: block.name,
+            id,
+            properties,
+            name: name.clone(),
             texture_indices: indices,
+            transparency,
+            model,
         };
 
         self.blocks.push(block_entry);
-        Ok(BlockTypeId(block.id))
+        self.ids_by_name.insert(name, id);
+        Ok(id)
+    }
+
+    fn load_face_texture(
+        &mut self,
+        block_name: &str,
+        definition: &FaceTextureDefinition,
+        transparency: TextureTransparency,
+        tinted: bool,
+    ) -> anyhow::Result<FaceTexture> {
+        let first = WorldTextureHandle(self.world_textures.textures.len() as u16);
+        let texture = self.allocate_face_texture(block_name, definition, transparency)?;
+        if tinted {
+            self.world_textures.set_tinted_from(first);
+        }
+        Ok(texture)
     }
 
+    fn allocate_face_texture(
+        &mut self,
+        block_name: &str,
+        definition: &FaceTextureDefinition,
+        transparency: TextureTransparency,
+    ) -> anyhow::Result<FaceTexture> {
+        match definition {
+            FaceTextureDefinition::Path(path) => Ok(FaceTexture::Single(
+                self.world_textures
+                    .load_from_path_and_allocate(path, transparency)?,
+            )),
+            FaceTextureDefinition::Variants(variants) => {
+                anyhow::ensure!(
+                    variants.iter().any(|variant| variant.weight > 0),
+                    "Block '{}' has texture variants without any weight",
+                    block_name
+                );
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        Ok(TextureVariant {
+                            texture: self
+                                .world_textures
+                                .load_from_path_and_allocate(&variant.texture, transparency)?,
+                            weight: variant.weight,
+                            rotate: variant.rotate,
+                            mirror: variant.mirror,
+                        })
+                    })
+                    .collect::<anyhow::Result<Box<[_]>>>()?;
+                Ok(FaceTexture::Variants(variants))
+            }
+            FaceTextureDefinition::Animated(animation) => Ok(FaceTexture::Single(
+                self.world_textures.load_animation_and_allocate(
+                    &animation.texture,
+                    transparency,
+                    animation.frame_time,
+                    animation.interpolate,
+                )?,
+            )),
+        }
+    }
+
+    /// Loads the block definitions of every resource pack. Blocks in later packs replace the blocks
+    /// of the same name in earlier ones and keep their IDs, while new blocks are added after them.
     pub fn load_all_blocks(&mut self) -> anyhow::Result<()> {
-        let blocks_path = self.assets_root.join("defs/blocks.ron");
-        let defs_data =
-            std::fs::read_to_string(&blocks_path).context("Failed to read block defs file")?;
-        let defs: Vec<BlockDefinition> =
-            ron::from_str(&defs_data).context("Failed to parse block defs file")?;
+        let mut defs = Vec::new();
+        for (pack, defs_data) in self.resource_packs.read_all(BLOCK_DEFINITIONS_PATH)? {
+            let pack_defs: Vec<BlockDefinition> =
+                ron::de::from_bytes(&defs_data).with_context(|| {
+                    format!(
+                        "Failed to parse block defs file of resource pack '{}'",
+                        pack.name
+                    )
+                })?;
+            merge_block_definitions(&mut defs, pack_defs);
+        }
         self.load_from_defs(defs)?;
+
+        for (voxel, name) in BUILTIN_BLOCKS {
+            let id = self.get_by_name(name).map(|block| block.id);
+            anyhow::ensure!(
+                id == Some(voxel.block_type_id()),
+                "Built-in block '{}' must be block number {} in {}",
+                name,
+                voxel.block_type(),
+                BLOCK_DEFINITIONS_PATH
+            );
+        }
+
         Ok(())
     }
 
+    /// Loads the block definitions again from the same resource packs.
+    /// Blocks may be added and changed, but the IDs of existing blocks must stay the same,
+    /// since loaded chunks refer to them.
+    pub fn reload(&self) -> anyhow::Result<BlockDatabase> {
+        self.reload_with(Arc::new(self.resource_packs.reopen()))
+    }
+
+    /// Loads the blocks from a different stack of resource packs, with the same restrictions as `reload`
+    pub fn reload_with(&self, resource_packs: Arc<ResourcePacks>) -> anyhow::Result<BlockDatabase> {
+        let mut reloaded = BlockDatabase::with_resource_packs(resource_packs);
+        reloaded.load_all_blocks()?;
+
+        for block in &self.blocks {
+            let id = reloaded.get_by_name(&block.name).map(|block| block.id);
+            anyhow::ensure!(
+                id == Some(block.id),
+                "Block '{}' was removed or moved, which needs a restart",
+                block.name
+            );
+        }
+
+        Ok(reloaded)
+    }
+
     pub fn iter_blocks(&self) -> impl Iterator<Item = &BlockDatabaseEntry> {
         self.blocks.iter()
     }
 
     pub fn get_by_id(&self, id: BlockTypeId) -> Option<&BlockDatabaseEntry> {
-        self.blocks.iter().find(|b| b.id == id)
+        self.blocks.get(id.0 as usize)
+    }
+
+    /// Finds a block by its namespaced name. Names without a namespace use `DEFAULT_NAMESPACE`.
+    pub fn get_by_name(&self, name: &str) -> Option<&BlockDatabaseEntry> {
+        let id = self.ids_by_name.get(qualify_block_name(name).as_ref())?;
+        self.get_by_id(*id)
+    }
+}
+
+#[cfg(test)]
+impl BlockDatabase {
+    /// Blocks with default definitions and IDs in the order of `names`, without any textures.
+    /// The first name must be air.
+    pub(crate) fn from_names(names: &[&str]) -> Self {
+        let mut db = BlockDatabase::new();
+        for name in names {
+            db.add_block_from_definition(BlockDefinition {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+        db
+    }
+}
+
+/// Replaces the definitions of blocks that are already defined, and appends the rest in order
+fn merge_block_definitions(defs: &mut Vec<BlockDefinition>, overrides: Vec<BlockDefinition>) {
+    for block in overrides {
+        let name = qualify_block_name(&block.name).into_owned();
+        match defs
+            .iter_mut()
+            .find(|existing| qualify_block_name(&existing.name) == name)
+        {
+            Some(existing) => *existing = block,
+            None => defs.push(block),
+        }
     }
 }
 
@@ -164,9 +532,19 @@ impl Default for BlockDatabase {
     }
 }
 
-// Minimal version of block database for use in voxel meshing where only block ID -> texture index mapping is needed
+/// Properties of a single block needed by meshing and lighting
+#[derive(Debug, Clone)]
+struct SlimBlockEntry {
+    texture_indices: TextureIndices,
+    transparency: TextureTransparency,
+    model: BlockModel,
+    properties: BlockProperties,
+}
+
+// Minimal version of block database for use in voxel meshing, lighting and physics,
+// where only the texture indices and properties of each block type are needed
 pub struct BlockDatabaseSlim {
-    blocks: Vec<TextureIndices>,
+    blocks: Vec<SlimBlockEntry>,
 }
 
 impl BlockDatabaseSlim {
@@ -176,24 +554,104 @@ impl BlockDatabaseSlim {
 
     /// This is only for testing purposes - in normal operation, BlockDatabaseSlim is always created from a full BlockDatabase
     pub fn add_block(&mut self, indices: TextureIndices) -> BlockTypeId {
-        self.blocks.push(indices);
+        self.add_block_with_properties(indices, BlockProperties::SOLID)
+    }
+
+    /// This is only for testing purposes, see `add_block`
+    pub fn add_block_with_properties(
+        &mut self,
+        indices: TextureIndices,
+        properties: BlockProperties,
+    ) -> BlockTypeId {
+        self.add_block_with_model(
+            indices,
+            properties,
+            TextureTransparency::Opaque,
+            BlockModel::Cube,
+        )
+    }
+
+    /// This is only for testing purposes, see `add_block`
+    pub fn add_block_with_model(
+        &mut self,
+        indices: TextureIndices,
+        properties: BlockProperties,
+        transparency: TextureTransparency,
+        model: BlockModel,
+    ) -> BlockTypeId {
+        self.blocks.push(SlimBlockEntry {
+            texture_indices: indices,
+            transparency,
+            model,
+            properties,
+        });
         BlockTypeId((self.blocks.len() - 1) as u16)
     }
 
     pub fn from_block_database(db: &BlockDatabase) -> Self {
+        // Blocks are stored in ID order, so their positions match their IDs
         let blocks = db
             .blocks
             .iter()
-            .map(|b| {
-                b.texture_indices
-                    .unwrap_or(TextureIndices::new_single(WorldTextureHandle::ERROR))
+            .map(|b| SlimBlockEntry {
+                texture_indices: b
+                    .texture_indices
+                    .clone()
+                    .unwrap_or_else(|| TextureIndices::new_single(WorldTextureHandle::ERROR)),
+                transparency: b.transparency,
+                model: b.model.clone(),
+                properties: b.properties,
             })
             .collect::<Vec<_>>();
         BlockDatabaseSlim { blocks }
     }
 
     pub fn get_texture_indices(&self, id: BlockTypeId) -> Option<&TextureIndices> {
-        self.blocks.get(id.0 as usize)
+        self.blocks.get(id.0 as usize).map(|b| &b.texture_indices)
+    }
+
+    /// Properties of the voxel's block type. Unknown blocks are treated as solid.
+    #[inline(always)]
+    pub fn properties(&self, voxel: Voxel) -> &BlockProperties {
+        self.blocks
+            .get(voxel.block_type() as usize)
+            .map_or(&BlockProperties::SOLID, |b| &b.properties)
+    }
+
+    /// Rotation of the voxel, from its block's orientation and the voxel metadata
+    #[inline(always)]
+    pub fn rotation(&self, voxel: Voxel) -> BlockRotation {
+        self.properties(voxel)
+            .orientation
+            .rotation(voxel.metadata())
+    }
+
+    /// Model of the voxel's block type, turned by its rotation. Unknown blocks are cubes.
+    #[inline(always)]
+    pub fn model(&self, voxel: Voxel) -> &BlockModel {
+        static CUBE: BlockModel = BlockModel::Cube;
+        self.blocks
+            .get(voxel.block_type() as usize)
+            .map_or(&CUBE, |b| &b.model)
+            .rotated(self.rotation(voxel))
+    }
+
+    pub fn transparency(&self, voxel: Voxel) -> TextureTransparency {
+        self.blocks
+            .get(voxel.block_type() as usize)
+            .map_or(TextureTransparency::Opaque, |b| b.transparency)
+    }
+
+    /// Whether the voxel hides the part of its neighbour's face that touches the voxel's `face` side.
+    /// Opaque blocks hide everything, other models only where they cover the face with an opaque texture.
+    pub fn occludes(&self, voxel: Voxel, face: Face, rect: &FaceRect) -> bool {
+        if self.properties(voxel).opaque {
+            return true;
+        }
+        let model = self.model(voxel);
+        !model.is_cube()
+            && self.transparency(voxel) == TextureTransparency::Opaque
+            && model.coverage(face).contains(rect)
     }
 }
 
@@ -203,8 +661,87 @@ impl Default for BlockDatabaseSlim {
     }
 }
 
+/// Block database shared between the world, the chunk loader workers and lighting.
+/// It can be replaced while the world is running, e.g. when the block definitions are reloaded.
+#[derive(Clone)]
+pub struct SharedBlockDatabase(Arc<RwLock<Arc<BlockDatabaseSlim>>>);
+
+impl SharedBlockDatabase {
+    pub fn new(block_database: Arc<BlockDatabaseSlim>) -> Self {
+        SharedBlockDatabase(Arc::new(RwLock::new(block_database)))
+    }
+
+    /// Returns the current database. Keep it for the duration of a job, so the job sees a consistent set of blocks.
+    pub fn load(&self) -> Arc<BlockDatabaseSlim> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, block_database: Arc<BlockDatabaseSlim>) {
+        *self.0.write().unwrap() = block_database;
+    }
+}
+
 impl From<&BlockDatabase> for BlockDatabaseSlim {
     fn from(db: &BlockDatabase) -> Self {
         BlockDatabaseSlim::from_block_database(db)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assets::resource_packs::ResourcePack, test_dir::TestDir};
+
+    #[test]
+    fn test_reload_keeps_existing_ids() {
+        let root = TestDir::new("block_reload_test");
+        let write_blocks = |names: &[&str]| {
+            let defs = names
+                .iter()
+                .map(|name| format!("(name: \"{}\", textures: Invisible)", name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            root.write(BLOCK_DEFINITIONS_PATH, format!("[{}]", defs));
+        };
+        let id = |db: &BlockDatabase, name| db.get_by_name(name).map(|block| block.id.0);
+
+        write_blocks(&["air", "grass", "dirt", "gold", "stone"]);
+        let pack = ResourcePack::open("test", root.path()).unwrap();
+        let mut db =
+            BlockDatabase::with_resource_packs(Arc::new(ResourcePacks::from_packs(vec![pack])));
+        db.load_all_blocks().unwrap();
+
+        write_blocks(&["air", "grass", "dirt", "gold", "stone", "marble"]);
+        let reloaded = db.reload().unwrap();
+        assert_eq!(id(&reloaded, "stone"), Some(4));
+        assert_eq!(id(&reloaded, "marble"), Some(5));
+
+        write_blocks(&["air", "grass", "dirt", "gold"]);
+        let removed = db.reload().err().unwrap().to_string();
+        assert!(removed.contains("voxel:stone"), "{}", removed);
+
+        write_blocks(&["air", "grass", "dirt", "gold", "marble", "stone"]);
+        let moved = db.reload().err().unwrap().to_string();
+        assert!(moved.contains("voxel:stone"), "{}", moved);
+    }
+
+    #[test]
+    fn test_merge_block_definitions_keeps_order() {
+        let block = |name: &str, light_emission| BlockDefinition {
+            name: name.to_string(),
+            light_emission,
+            ..Default::default()
+        };
+        let mut defs = vec![block("air", 0), block("voxel:grass", 0), block("dirt", 0)];
+        merge_block_definitions(&mut defs, vec![block("marble", 3), block("grass", 7)]);
+
+        let merged = defs
+            .iter()
+            .map(|def| (def.name.as_str(), def.light_emission))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            merged,
+            [("air", 0), ("grass", 7), ("dirt", 0), ("marble", 3)]
+        );
+    }
+}
@@ -1,10 +1,10 @@
-use std::{collections::HashMap, path::Path};
+use std::collections::HashMap;
 
 use anyhow::Context;
 use glam::UVec2;
 use serde::Deserialize;
 
-use crate::math::rectangle::URectangle;
+use crate::{assets::resource_packs::ResourcePacks, math::rectangle::URectangle};
 
 pub enum FontGlyph {
     Whitespace {
@@ -66,12 +66,13 @@ pub struct FontDefinition {
     glyphs: FontGlyphsDefinition,
 }
 
-pub fn load_font(folder: &Path, font_name: &str) -> anyhow::Result<Font> {
-    let definition_path = folder.join(format!("{font_name}.ron"));
-    let definition_data =
-        std::fs::read_to_string(&definition_path).context("Failed to read font definition file")?;
+/// Loads a font from the `fonts` directory of the last resource pack that has its definition
+pub fn load_font(resource_packs: &ResourcePacks, font_name: &str) -> anyhow::Result<Font> {
+    let definition_data = resource_packs
+        .read(&format!("fonts/{font_name}.ron"))
+        .context("Failed to read font definition file")?;
     let definition: FontDefinition =
-        ron::from_str(&definition_data).context("Failed to parse font definition file")?;
+        ron::de::from_bytes(&definition_data).context("Failed to parse font definition file")?;
 
     let glyphs = match definition.glyphs {
         FontGlyphsDefinition::FixedSizeAtlas {
@@ -79,14 +80,10 @@ pub fn load_font(folder: &Path, font_name: &str) -> anyhow::Result<Font> {
             symbols,
             overrides,
         } => {
-            let image_path = folder.join(format!("{}.png", font_name));
-            let texture = image::open(&image_path)
-                .with_context(|| {
-                    format!(
-                        "Failed to open font texture image at {}",
-                        image_path.display()
-                    )
-                })?
+            let image_path = format!("fonts/{}.png", font_name);
+            let image_data = resource_packs.read(&image_path)?;
+            let texture = image::load_from_memory(&image_data)
+                .with_context(|| format!("Failed to decode font texture image {}", image_path))?
                 .to_rgba8();
 
             let mut glyphs = HashMap::new();
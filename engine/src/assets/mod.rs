@@ -1,3 +1,8 @@
+pub mod asset_watcher;
+pub mod block_models;
+pub mod block_orientation;
 pub mod blocks;
 pub mod fonts;
+pub mod resource_packs;
+pub mod texture_variants;
 pub mod world_textures;
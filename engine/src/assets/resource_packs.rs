@@ -0,0 +1,223 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::formats::zip::ZipArchive;
+
+/// Built-in assets, always at the bottom of the resource pack stack
+const BUILTIN_ASSETS_DIRECTORY: &str = "assets";
+/// Directory of the resource packs that can be enabled in the engine config, as directories or zip archives
+pub const RESOURCE_PACKS_DIRECTORY: &str = "resource_packs";
+
+enum ResourcePackSource {
+    Directory,
+    Zip(ZipArchive),
+}
+
+/// Directory or zip archive laid out like the built-in assets, e.g. with `textures/dirt.png` and `defs/blocks.ron`.
+pub struct ResourcePack {
+    pub name: String,
+    path: PathBuf,
+    source: ResourcePackSource,
+}
+
+impl ResourcePack {
+    /// Opens a pack from a directory, or from a file ending in `.zip`
+    pub fn open(name: impl Into<String>, path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let source = if path.is_dir() {
+            ResourcePackSource::Directory
+        } else if path.extension().is_some_and(|extension| extension == "zip") {
+            ResourcePackSource::Zip(ZipArchive::load(&path)?)
+        } else {
+            anyhow::bail!(
+                "Resource pack {} is neither a directory nor a zip archive",
+                path.display()
+            );
+        };
+
+        Ok(ResourcePack {
+            name: name.into(),
+            path,
+            source,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_archive(&self) -> bool {
+        matches!(self.source, ResourcePackSource::Zip(_))
+    }
+
+    /// Reads an asset by its path in the pack, like `textures/dirt.png`. Returns None if the pack doesn't have it.
+    pub fn read(&self, asset_path: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match &self.source {
+            ResourcePackSource::Directory => {
+                let path = self.path.join(asset_path);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                std::fs::read(&path)
+                    .map(Some)
+                    .with_context(|| format!("Failed to read {}", path.display()))
+            }
+            ResourcePackSource::Zip(archive) => archive
+                .read(asset_path)
+                .with_context(|| format!("Failed to read {} from {}", asset_path, self.name)),
+        }
+    }
+}
+
+/// Ordered stack of resource packs, where assets in later packs override the same assets in earlier ones.
+pub struct ResourcePacks {
+    /// Lowest priority first, starting with the built-in assets
+    packs: Vec<ResourcePack>,
+}
+
+impl ResourcePacks {
+    /// Only the built-in assets
+    pub fn builtin() -> Self {
+        Self::open(&[])
+    }
+
+    /// The built-in assets with the named packs from `RESOURCE_PACKS_DIRECTORY` on top, in order.
+    /// Packs that can't be opened are left out, so a pack removed from disk doesn't prevent starting the game.
+    pub fn open(names: &[String]) -> Self {
+        let builtin_path = PathBuf::from(BUILTIN_ASSETS_DIRECTORY);
+        if !builtin_path.exists() {
+            log::error!(
+                "Assets root path '{}' does not exist - check your working directory!",
+                builtin_path.display()
+            );
+        }
+
+        let mut packs = vec![ResourcePack {
+            name: "Built-in".to_string(),
+            path: builtin_path,
+            source: ResourcePackSource::Directory,
+        }];
+        for name in names {
+            match ResourcePack::open(name, Path::new(RESOURCE_PACKS_DIRECTORY).join(name)) {
+                Ok(pack) => packs.push(pack),
+                Err(err) => log::error!("Skipping resource pack '{}': {:?}", name, err),
+            }
+        }
+
+        ResourcePacks { packs }
+    }
+
+    pub fn from_packs(packs: Vec<ResourcePack>) -> Self {
+        ResourcePacks { packs }
+    }
+
+    /// Opens the same packs again, picking up changes to zip archives, which are read into memory
+    pub fn reopen(&self) -> Self {
+        let packs = self
+            .packs
+            .iter()
+            .filter_map(|pack| match &pack.source {
+                ResourcePackSource::Directory => Some(ResourcePack {
+                    name: pack.name.clone(),
+                    path: pack.path.clone(),
+                    source: ResourcePackSource::Directory,
+                }),
+                ResourcePackSource::Zip(_) => ResourcePack::open(&pack.name, &pack.path)
+                    .inspect_err(|err| {
+                        log::error!("Skipping resource pack '{}': {:?}", pack.name, err)
+                    })
+                    .ok(),
+            })
+            .collect();
+        ResourcePacks { packs }
+    }
+
+    /// Packs from the lowest priority to the highest
+    pub fn packs(&self) -> &[ResourcePack] {
+        &self.packs
+    }
+
+    /// Reads an asset from the last pack that has it
+    pub fn read(&self, asset_path: &str) -> anyhow::Result<Vec<u8>> {
+        for pack in self.packs.iter().rev() {
+            if let Some(data) = pack.read(asset_path)? {
+                return Ok(data);
+            }
+        }
+        anyhow::bail!("{} isn't in any resource pack", asset_path)
+    }
+
+    /// Reads an asset from every pack that has it, from the lowest priority to the highest.
+    /// Used for assets like blocks.ron that are merged instead of replaced.
+    pub fn read_all(&self, asset_path: &str) -> anyhow::Result<Vec<(&ResourcePack, Vec<u8>)>> {
+        let mut layers = Vec::new();
+        for pack in &self.packs {
+            if let Some(data) = pack.read(asset_path)? {
+                layers.push((pack, data));
+            }
+        }
+        Ok(layers)
+    }
+}
+
+impl Default for ResourcePacks {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Names of the directories and zip archives in `RESOURCE_PACKS_DIRECTORY`, sorted alphabetically
+pub fn available_resource_packs() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(RESOURCE_PACKS_DIRECTORY) else {
+        return Vec::new();
+    };
+
+    let mut names = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir() || path.extension().is_some_and(|extension| extension == "zip")
+        })
+        .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_later_packs_override_earlier_ones() {
+        let root = TestDir::new("resource_packs_test");
+        root.write("base/textures/dirt.png", "base dirt");
+        root.write("base/textures/gold.png", "base gold");
+        root.write("base/defs/blocks.ron", "base");
+        root.write("overlay/textures/dirt.png", "overlay dirt");
+        root.write("overlay/defs/blocks.ron", "overlay");
+
+        let packs = ResourcePacks::from_packs(vec![
+            ResourcePack::open("base", root.path().join("base")).unwrap(),
+            ResourcePack::open("overlay", root.path().join("overlay")).unwrap(),
+        ]);
+        assert_eq!(packs.read("textures/dirt.png").unwrap(), b"overlay dirt");
+        assert_eq!(packs.read("textures/gold.png").unwrap(), b"base gold");
+        assert!(packs.read("textures/grass.png").is_err());
+
+        let layers = packs.read_all("defs/blocks.ron").unwrap();
+        let names = layers
+            .iter()
+            .map(|(pack, data)| (pack.name.as_str(), data.as_slice()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                ("base", b"base".as_slice()),
+                ("overlay", b"overlay".as_slice())
+            ]
+        );
+    }
+}
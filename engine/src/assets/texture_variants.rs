@@ -0,0 +1,247 @@
+use serde::Deserialize;
+
+use crate::{
+    assets::world_textures::WorldTextureHandle,
+    voxels::{coord::WorldPos, face::Face},
+};
+
+/// Texture of a block face in blocks.ron: a single path, a list of variants or an animation.
+/// Variants are written as anonymous structs: `[(texture: "grass.png", weight: 3, rotate: true)]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FaceTextureDefinition {
+    Path(String),
+    Variants(Vec<TextureVariantDefinition>),
+    Animated(AnimatedTextureDefinition),
+}
+
+/// One of the textures a face can randomly pick from
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextureVariantDefinition {
+    pub texture: String,
+    /// Relative chance of picking this variant
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Randomly turn the texture in quarter turns
+    #[serde(default)]
+    pub rotate: bool,
+    /// Randomly mirror the texture
+    #[serde(default)]
+    pub mirror: bool,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Texture animated from a vertical strip of frames: `(texture: "water.png", frame_time: 0.1, interpolate: true)`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnimatedTextureDefinition {
+    pub texture: String,
+    /// Seconds per frame
+    pub frame_time: f32,
+    #[serde(default)]
+    pub interpolate: bool,
+}
+
+/// Loaded variant of a face texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureVariant {
+    pub texture: WorldTextureHandle,
+    pub weight: u32,
+    pub rotate: bool,
+    pub mirror: bool,
+}
+
+/// Texture used for one side of a block type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaceTexture {
+    Single(WorldTextureHandle),
+    /// Weighted variants, picked from a hash of the voxel position so they stay the same between remeshes
+    Variants(Box<[TextureVariant]>),
+    /// First of `CONNECTED_TILE_COUNT` consecutive tiles, picked by which neighbours on the face plane are the same block
+    Connected(WorldTextureHandle),
+}
+
+/// Texture picked for a single voxel face. Faces are only merged when they pick the same texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SelectedTexture {
+    pub index: u16,
+    /// Counter-clockwise quarter turns (0-3)
+    pub rotation: u8,
+    /// Mirrored horizontally before it's turned
+    pub mirrored: bool,
+}
+
+/// Neighbours of a face on its plane that connected textures connect to, as steps along the face's tangent and bitangent.
+/// They go clockwise from the neighbour above the face, so each corner sits between the two edges it touches.
+pub const CONNECTED_NEIGHBORS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// Connected textures have a tile for every distinct combination of connected edges and corners.
+/// Corners only matter when both edges next to them are connected, which leaves 47 of the 256 combinations.
+pub const CONNECTED_TILE_COUNT: usize = 47;
+
+/// Keeps only the corners of a neighbour mask that are surrounded by connected edges
+const fn significant_connections(mask: u8) -> u8 {
+    let mut result = mask & 0b0101_0101;
+    let mut corner = 1;
+    while corner < 8 {
+        let before = 1 << (corner - 1);
+        let after = 1 << ((corner + 1) % 8);
+        if mask & (1 << corner) != 0 && mask & before != 0 && mask & after != 0 {
+            result |= 1 << corner;
+        }
+        corner += 2;
+    }
+    result
+}
+
+/// Tile of each neighbour mask. Tiles are numbered by their significant masks in ascending order.
+const CONNECTED_TILES: [u8; 256] = {
+    let mut tiles = [0; 256];
+    let mut next = 0;
+    let mut mask = 0;
+    while mask < 256 {
+        if significant_connections(mask as u8) == mask as u8 {
+            tiles[mask] = next;
+            next += 1;
+        }
+        mask += 1;
+    }
+    let mut mask = 0;
+    while mask < 256 {
+        tiles[mask] = tiles[significant_connections(mask as u8) as usize];
+        mask += 1;
+    }
+    tiles
+};
+
+/// Tile of a connected texture for the given neighbours, see `CONNECTED_NEIGHBORS` for the bit order
+pub fn connected_tile(neighbors: u8) -> u8 {
+    CONNECTED_TILES[neighbors as usize]
+}
+
+impl FaceTexture {
+    /// Texture used where there's no voxel to pick a variant for, like block previews.
+    /// For connected textures, this is the tile of a block without connected neighbours.
+    pub fn first(&self) -> WorldTextureHandle {
+        match self {
+            FaceTexture::Single(handle) | FaceTexture::Connected(handle) => *handle,
+            FaceTexture::Variants(variants) => variants[0].texture,
+        }
+    }
+
+    /// Picks the texture for a voxel face. `neighbors` is only called for connected textures.
+    pub fn select(
+        &self,
+        pos: WorldPos,
+        face: Face,
+        neighbors: impl FnOnce() -> u8,
+    ) -> SelectedTexture {
+        match self {
+            FaceTexture::Single(handle) => SelectedTexture {
+                index: handle.0,
+                ..Default::default()
+            },
+            FaceTexture::Variants(variants) => {
+                let hash = hash_position(pos);
+                let total = variants.iter().map(|variant| variant.weight).sum::<u32>();
+                let mut roll = (hash >> 8) % total.max(1);
+                let variant = variants
+                    .iter()
+                    .find(|variant| {
+                        let found = roll < variant.weight;
+                        roll = roll.saturating_sub(variant.weight);
+                        found
+                    })
+                    .unwrap_or(&variants[0]);
+
+                // Each face turns on its own, so neighbouring faces of the same voxel don't line up
+                let transform = hash >> (face as u32 * 3);
+                SelectedTexture {
+                    index: variant.texture.0,
+                    rotation: if variant.rotate {
+                        (transform & 0x3) as u8
+                    } else {
+                        0
+                    },
+                    mirrored: variant.mirror && transform & 0x4 != 0,
+                }
+            }
+            FaceTexture::Connected(first) => SelectedTexture {
+                index: first.0 + connected_tile(neighbors()) as u16,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Spreads neighbouring positions over unrelated values, so variants don't form visible patterns
+fn hash_position(pos: WorldPos) -> u32 {
+    let mut hash = (pos.0.x as u32).wrapping_mul(0x8da6_b343)
+        ^ (pos.0.y as u32).wrapping_mul(0xd816_3841)
+        ^ (pos.0.z as u32).wrapping_mul(0xcb1a_b31f);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb_352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846c_a68b);
+    hash ^ (hash >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+
+    #[test]
+    fn test_connected_tiles_ignore_corners_without_edges() {
+        let tiles = (0..=255u8).map(connected_tile).collect::<Vec<_>>();
+        assert_eq!(
+            tiles.iter().max().map(|max| *max as usize + 1),
+            Some(CONNECTED_TILE_COUNT)
+        );
+
+        // A lone corner doesn't change the tile, but a corner between two connected edges does
+        assert_eq!(connected_tile(0b0000_0010), connected_tile(0));
+        assert_ne!(connected_tile(0b0000_0111), connected_tile(0b0000_0101));
+        assert_eq!(
+            connected_tile(0b1111_1111) as usize,
+            CONNECTED_TILE_COUNT - 1
+        );
+    }
+
+    #[test]
+    fn test_variants_are_weighted_and_stable() {
+        let variant = |texture, weight| TextureVariant {
+            texture: WorldTextureHandle(texture),
+            weight,
+            rotate: true,
+            mirror: false,
+        };
+        let texture = FaceTexture::Variants(Box::new([variant(1, 3), variant(2, 1)]));
+
+        let mut counts = [0; 3];
+        for x in 0..64 {
+            for z in 0..64 {
+                let pos = WorldPos(IVec3::new(x, 5, z));
+                let selected = texture.select(pos, Face::Top, || unreachable!());
+                assert_eq!(selected, texture.select(pos, Face::Top, || 0));
+                assert!(!selected.mirrored && selected.rotation < 4);
+                counts[selected.index as usize] += 1;
+            }
+        }
+
+        // Roughly three to one, with some slack for the hash
+        assert!(counts[1] > counts[2] * 2 && counts[1] < counts[2] * 4);
+    }
+}
@@ -1,46 +1,69 @@
-use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::Context;
 use image::RgbaImage;
 use serde::Deserialize;
 
-#[derive(Debug, Clone, Copy)]
+use crate::assets::resource_packs::ResourcePacks;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct WorldTextureHandle(pub u16);
 
 impl WorldTextureHandle {
     pub const ERROR: Self = WorldTextureHandle(0);
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum TextureTransparency {
     Opaque = 0,
     AlphaCutout,
     AlphaBlend,
 }
 
+/// Frames of an animated texture are consecutive textures, starting from the one that has the animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureAnimation {
+    pub frame_count: u16,
+    /// How long each frame is shown, in seconds
+    pub frame_time_s: f32,
+    /// Blend each frame into the next one instead of switching abruptly
+    pub interpolate: bool,
+}
+
 pub struct TextureImage {
     pub data: RgbaImage,
     pub transparency: TextureTransparency,
+    pub animation: Option<TextureAnimation>,
+    /// Greyscale texture that is multiplied with the biome tint of its column
+    pub tinted: bool,
 }
 
 pub struct WorldTextures {
     // TODO: Do we have to keep textures in both RAM and VRAM?
     // I guess with Minecraft-style textures that doesn't really matter
     pub textures: Vec<TextureImage>,
-    base_path: std::path::PathBuf,
+    /// Textures are read from the `textures` directory of the packs
+    resource_packs: Arc<ResourcePacks>,
 }
 
 impl WorldTextures {
+    /// Loads textures from the built-in assets only
     pub fn new() -> Self {
+        Self::with_resource_packs(Arc::new(ResourcePacks::builtin()))
+    }
+
+    pub fn with_resource_packs(resource_packs: Arc<ResourcePacks>) -> Self {
         let mut world_textures = WorldTextures {
             textures: Vec::new(),
-            base_path: PathBuf::from("assets").join("textures"),
+            resource_packs,
         };
 
         let invalid_texture = generate_invalid_texture_checkerboard();
         world_textures.allocate(TextureImage {
             data: invalid_texture,
             transparency: TextureTransparency::Opaque,
+            animation: None,
+            tinted: false,
         });
         world_textures
     }
@@ -57,17 +80,95 @@ impl WorldTextures {
         path: &str,
         transparency: TextureTransparency,
     ) -> anyhow::Result<WorldTextureHandle> {
-        let path = self.base_path.join(path);
-        let texture = Self::load_texture(&path)?;
+        let texture = self.load_texture(path)?;
         Ok(self.allocate(TextureImage {
             data: texture,
             transparency,
+            animation: None,
+            tinted: false,
         }))
     }
 
-    fn load_texture(path: &Path) -> anyhow::Result<RgbaImage> {
-        let image = image::open(path)
-            .with_context(|| format!("Failed to open texture image at {}", path.display()))?;
+    /// Loads a vertical strip of square tiles into consecutive textures, and returns the first one.
+    /// Fails unless the strip has exactly `tile_count` tiles.
+    pub fn load_strip_and_allocate(
+        &mut self,
+        path: &str,
+        transparency: TextureTransparency,
+        tile_count: usize,
+    ) -> anyhow::Result<WorldTextureHandle> {
+        let strip = self.load_texture(path)?;
+        anyhow::ensure!(
+            strip.width() > 0 && strip.height() == strip.width() * tile_count as u32,
+            "Texture strip {} must have {} square tiles stacked vertically",
+            path,
+            tile_count
+        );
+        Ok(self.allocate_strip(&strip, transparency, None))
+    }
+
+    /// Loads an animation from a vertical strip of square frames, and returns its first frame.
+    pub fn load_animation_and_allocate(
+        &mut self,
+        path: &str,
+        transparency: TextureTransparency,
+        frame_time_s: f32,
+        interpolate: bool,
+    ) -> anyhow::Result<WorldTextureHandle> {
+        let strip = self.load_texture(path)?;
+        anyhow::ensure!(
+            strip.width() > 0 && strip.height() % strip.width() == 0,
+            "Animated texture {} must have square frames stacked vertically",
+            path
+        );
+        anyhow::ensure!(
+            frame_time_s > 0.0,
+            "Animated texture {} must have a positive frame time",
+            path
+        );
+
+        let animation = TextureAnimation {
+            frame_count: (strip.height() / strip.width()) as u16,
+            frame_time_s,
+            interpolate,
+        };
+        Ok(self.allocate_strip(&strip, transparency, Some(animation)))
+    }
+
+    /// Splits a strip into consecutive textures. The animation is only stored on the first one.
+    fn allocate_strip(
+        &mut self,
+        strip: &RgbaImage,
+        transparency: TextureTransparency,
+        animation: Option<TextureAnimation>,
+    ) -> WorldTextureHandle {
+        let tile_size = strip.width();
+        let first = WorldTextureHandle(self.textures.len() as u16);
+        for tile in 0..strip.height() / tile_size {
+            let data = image::imageops::crop_imm(strip, 0, tile * tile_size, tile_size, tile_size)
+                .to_image();
+            self.allocate(TextureImage {
+                data,
+                transparency,
+                animation: if tile == 0 { animation } else { None },
+                tinted: false,
+            });
+        }
+        first
+    }
+
+    /// Tints every texture allocated since `first`, see `TextureImage::tinted`
+    pub fn set_tinted_from(&mut self, first: WorldTextureHandle) {
+        for texture in &mut self.textures[first.0 as usize..] {
+            texture.tinted = true;
+        }
+    }
+
+    /// Loads a texture from the last resource pack that has it
+    fn load_texture(&self, path: &str) -> anyhow::Result<RgbaImage> {
+        let data = self.resource_packs.read(&format!("textures/{}", path))?;
+        let image = image::load_from_memory(&data)
+            .with_context(|| format!("Failed to decode texture image {}", path))?;
         Ok(image.to_rgba8())
     }
 }
@@ -94,3 +195,78 @@ fn generate_invalid_texture_checkerboard() -> RgbaImage {
     }
     img
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{assets::resource_packs::ResourcePack, test_dir::TestDir};
+
+    use super::*;
+
+    /// Textures from a pack in a temporary directory, with a (name, width, height) image for each strip.
+    /// Returns the directory too, which has to outlive the loading.
+    fn textures_with_strips(test: &str, strips: &[(&str, u32, u32)]) -> (WorldTextures, TestDir) {
+        let root = TestDir::new(&format!("world_textures_{}", test));
+        std::fs::create_dir_all(root.path().join("textures")).unwrap();
+        for (name, width, height) in strips {
+            RgbaImage::from_fn(*width, *height, |_, y| image::Rgba([y as u8, 0, 0, 255]))
+                .save(root.path().join("textures").join(name))
+                .unwrap();
+        }
+
+        let pack = ResourcePack::open("test", root.path()).unwrap();
+        let textures =
+            WorldTextures::with_resource_packs(Arc::new(ResourcePacks::from_packs(vec![pack])));
+        (textures, root)
+    }
+
+    #[test]
+    fn test_animation_allocates_a_texture_per_frame() {
+        let (mut textures, _root) = textures_with_strips("frames", &[("water.png", 4, 12)]);
+        let first = textures
+            .load_animation_and_allocate("water.png", TextureTransparency::AlphaBlend, 0.25, true)
+            .unwrap();
+
+        assert_eq!(first, WorldTextureHandle(1));
+        assert_eq!(textures.textures.len(), 4);
+        assert_eq!(
+            textures.textures[1].animation,
+            Some(TextureAnimation {
+                frame_count: 3,
+                frame_time_s: 0.25,
+                interpolate: true,
+            })
+        );
+        for (frame, texture) in textures.textures[1..].iter().enumerate() {
+            assert_eq!(texture.data.dimensions(), (4, 4));
+            assert_eq!(texture.data.get_pixel(0, 0)[0], frame as u8 * 4);
+            assert_eq!(texture.transparency, TextureTransparency::AlphaBlend);
+        }
+        assert!(textures.textures[2..].iter().all(|t| t.animation.is_none()));
+    }
+
+    #[test]
+    fn test_animation_rejects_invalid_strips() {
+        let (mut textures, _root) = textures_with_strips(
+            "invalid",
+            &[
+                ("uneven.png", 4, 10),
+                ("wide.png", 8, 4),
+                ("lava.png", 4, 8),
+            ],
+        );
+        let mut load = |path, frame_time_s| {
+            textures.load_animation_and_allocate(
+                path,
+                TextureTransparency::Opaque,
+                frame_time_s,
+                false,
+            )
+        };
+
+        assert!(load("uneven.png", 0.5).is_err());
+        assert!(load("wide.png", 0.5).is_err());
+        assert!(load("lava.png", 0.0).is_err());
+        assert!(load("lava.png", -1.0).is_err());
+        assert!(load("lava.png", 0.5).is_ok());
+    }
+}
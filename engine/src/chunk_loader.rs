@@ -5,12 +5,13 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crossbeam_channel::{Receiver, Sender, select};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, select};
 use glam::IVec3;
 
 use crate::{
-    assets::blocks::BlockDatabaseSlim,
+    assets::blocks::{BlockDatabaseSlim, SharedBlockDatabase},
     camera::Camera,
+    lighting::light_engine::LightEngine,
     limits::{LOAD_DISTANCE, UNLOAD_DISTANCE},
     loader_job_queue::{JobPriority, JobType, LoaderJobQueue},
     mesh_generation::{
@@ -19,6 +20,7 @@ use crate::{
         },
         greedy_mesher::GreedyMesher,
     },
+    persistence::world_storage::WorldStorage,
     visibility::generate_desired_chunk_offsets,
     voxels::{
         chunk::{
@@ -28,7 +30,7 @@ use crate::{
         face::Face,
     },
     world::WorldChunks,
-    worldgen::WorldGenerator,
+    worldgen::{ChunkClimate, WorldGenerator},
 };
 
 #[derive(Debug)]
@@ -43,11 +45,27 @@ pub enum ChunkLoaderEvent<T: IChunkRenderState> {
     WorldReset,
 }
 
+/// Changes to which chunks hold voxel data, for world systems that follow them like fluids
+#[derive(Debug)]
+pub enum ChunkDataEvent {
+    /// The chunk's voxels have been generated or loaded from disk
+    Inserted(ChunkPos),
+    Unloaded(Vec<ChunkPos>),
+    /// Every chunk has been removed, see `ChunkLoaderEvent::WorldReset`
+    Cleared,
+}
+
 // Used by the main thread to communicate with the chunk loader thread
 pub enum ChunkLoaderCommand {
+    /// Stops the chunk loader. Dirty chunks are saved before the loader thread exits.
     Shutdown,
+    /// Enqueues chunks in the StaleMesh state for meshing.
+    Remesh(Vec<ChunkHandle>),
 }
 
+/// How often the autosave thread writes dirty chunks to disk.
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
 // Used by chunk loader workers to communicate back to the chunk loader
 pub enum ChunkWorkerEvent {
     /// The chunk is ready to be meshed (all neighbors are present). No further checks are needed.
@@ -55,6 +73,8 @@ pub enum ChunkWorkerEvent {
     /// The chunk _might_ be ready for meshing, but the loader should verify its neighbors first.
     /// This is used to retry meshing when a neighbor was missing or in an invalid state when meshing was first attempted.
     PotentiallyReadyForMeshing(ChunkHandle),
+    /// Lighting changed in chunks that were already meshed. The chunks have been moved to StaleMesh.
+    Remesh(Vec<ChunkHandle>),
 }
 
 pub trait WorldAccess<T: IChunkRenderState>: Send + Sync {
@@ -66,23 +86,53 @@ pub trait WorldAccess<T: IChunkRenderState>: Send + Sync {
     fn create_mesh_input(
         &self,
         pos: ChunkPos,
+        block_database: &BlockDatabaseSlim,
     ) -> Result<Option<ChunkMeshGeneratorInput>, MeshGeneratorInputError>;
-    /// Inserts voxel data to the chunk and updates its state and neighbor mask.
-    /// Also propagates neighbor-ready bits to neighboring chunks.
+    /// Inserts voxel data to the chunk, lights it and updates its state and neighbor mask.
+    /// The chunk is expected to be either Generating or LoadingFromDisk.
+    /// Also propagates neighbor-ready bits to neighboring chunks, and requests remeshing of neighbors whose light changed.
     /// Return true if the chunk the data was inserted successfully, false if the chunk was not found (likely unloaded).
     fn insert_chunk_data_and_update_neighbor_masks(
         &self,
         chunk: &ChunkHandle,
         data: ChunkData,
+        climate: ChunkClimate,
+        light_engine: &LightEngine,
         sender: &Sender<ChunkWorkerEvent>,
     ) -> bool;
+    /// Stores a newly uploaded render state as pending. The renderer applies it once the upload has been flushed.
     fn insert_render_state(&self, pos: ChunkPos, render_state: T);
     /// Unloads and removes the given chunk positions from the world map.
+    /// Dirty chunks are saved to `storage` before they are dropped.
     /// Returns the positions that were actually removed.
-    fn unload_chunks(&self, positions: &[ChunkPos]) -> Vec<ChunkPos>;
-    fn unload_chunks_outside_distance(&self, center: ChunkPos, distance: u32) -> Vec<ChunkPos>;
+    fn unload_chunks(
+        &self,
+        positions: &[ChunkPos],
+        storage: Option<&WorldStorage>,
+    ) -> Vec<ChunkPos>;
+    fn unload_chunks_outside_distance(
+        &self,
+        center: ChunkPos,
+        distance: u32,
+        storage: Option<&WorldStorage>,
+    ) -> Vec<ChunkPos>;
     /// Unloads all chunks and clears the world map. Intended for large teleports.
-    fn clear_all_chunks(&self);
+    fn clear_all_chunks(&self, storage: Option<&WorldStorage>);
+    /// Saves all dirty chunks to `storage`. Returns the number of chunks written.
+    fn save_dirty_chunks(&self, storage: &WorldStorage) -> usize;
+}
+
+fn save_chunk_before_unload<T: IChunkRenderState>(
+    storage: Option<&WorldStorage>,
+    chunk: &Chunk<T>,
+) {
+    let Some(storage) = storage else {
+        return;
+    };
+
+    if let Err(err) = storage.save_chunk_if_dirty(chunk) {
+        log::error!("Failed to save chunk before unloading: {:?}", err);
+    }
 }
 
 impl<T: IChunkRenderState> WorldAccess<T> for WorldChunks<T> {
@@ -118,23 +168,35 @@ impl<T: IChunkRenderState> WorldAccess<T> for WorldChunks<T> {
     fn create_mesh_input(
         &self,
         pos: ChunkPos,
+        block_database: &BlockDatabaseSlim,
     ) -> Result<Option<ChunkMeshGeneratorInput>, MeshGeneratorInputError> {
-        ChunkMeshGeneratorInput::try_from_map(self, pos)
+        ChunkMeshGeneratorInput::try_from_map(self, pos, block_database)
     }
 
     fn insert_chunk_data_and_update_neighbor_masks(
         &self,
         chunk: &ChunkHandle,
         data: ChunkData,
+        climate: ChunkClimate,
+        light_engine: &LightEngine,
         sender: &Sender<ChunkWorkerEvent>,
     ) -> bool {
         if let Some(mut existing) = self.get_mut(&chunk.pos) {
             existing.data = Some(data);
+            existing.climate = Some(climate);
         } else {
             // Chunk was unloaded before data could be inserted, ignore
             return false;
         }
 
+        // Light the chunk before any neighbor sees it as suitable for meshing
+        let light_changes = light_engine.light_new_chunk(self, chunk.pos);
+        let mut relit_chunks = Vec::new();
+        light_changes.invalidate_meshes(self, &mut relit_chunks);
+        if !relit_chunks.is_empty() {
+            sender.send(ChunkWorkerEvent::Remesh(relit_chunks)).unwrap();
+        }
+
         // Mark this chunk as generated for every neighboring chunk
         let mut neighbor_bits = 0u8;
         for direction in Face::all().iter().copied() {
@@ -165,7 +227,9 @@ impl<T: IChunkRenderState> WorldAccess<T> for WorldChunks<T> {
             }
         }
 
-        if chunk.try_transition(ChunkState::Generating, ChunkState::Loaded) {
+        if chunk.try_transition(ChunkState::Generating, ChunkState::Loaded)
+            || chunk.try_transition(ChunkState::LoadingFromDisk, ChunkState::Loaded)
+        {
             let ready_for_meshing = chunk.neighbor_state.set_neighbor_bits(neighbor_bits);
             if ready_for_meshing {
                 sender
@@ -184,15 +248,26 @@ impl<T: IChunkRenderState> WorldAccess<T> for WorldChunks<T> {
                 // Chunk is about to be unloaded, don't insert render state
                 return;
             }
-            chunk.render_state = Some(render_state);
+            chunk.pending_render_state = Some(render_state);
         }
     }
 
-    fn unload_chunks(&self, positions: &[ChunkPos]) -> Vec<ChunkPos> {
+    fn unload_chunks(
+        &self,
+        positions: &[ChunkPos],
+        storage: Option<&WorldStorage>,
+    ) -> Vec<ChunkPos> {
         let mut removed = Vec::new();
 
         for pos in positions {
-            if let Some((pos, chunk)) = self.remove(pos) {
+            // Save while the entry is still locked, so anything that finds the chunk missing
+            // from the map can rely on storage being up to date
+            let removed_chunk = self.remove_if(pos, |_, chunk| {
+                save_chunk_before_unload(storage, chunk);
+                true
+            });
+
+            if let Some((pos, chunk)) = removed_chunk {
                 chunk.unload();
                 removed.push(pos);
             }
@@ -217,13 +292,19 @@ impl<T: IChunkRenderState> WorldAccess<T> for WorldChunks<T> {
         removed
     }
 
-    fn unload_chunks_outside_distance(&self, center: ChunkPos, distance: u32) -> Vec<ChunkPos> {
+    fn unload_chunks_outside_distance(
+        &self,
+        center: ChunkPos,
+        distance: u32,
+        storage: Option<&WorldStorage>,
+    ) -> Vec<ChunkPos> {
         let mut removed = Vec::new();
 
         self.retain(|pos, chunk| {
             let dist = pos.0.chebyshev_distance(center.0);
             let should_keep = dist <= distance;
             if !should_keep {
+                save_chunk_before_unload(storage, chunk);
                 chunk.unload();
                 removed.push(*pos);
             }
@@ -249,14 +330,40 @@ impl<T: IChunkRenderState> WorldAccess<T> for WorldChunks<T> {
         removed
     }
 
-    fn clear_all_chunks(&self) {
-        // Mark all chunks as unloaded first so any outstanding handles quickly stop doing work.
-        for entry in self.iter() {
-            entry.value().unload();
+    fn clear_all_chunks(&self, storage: Option<&WorldStorage>) {
+        // Chunks are saved and removed under the same lock, so edits can't slip in between.
+        // No neighbor updates needed.
+        self.retain(|_, chunk| {
+            save_chunk_before_unload(storage, chunk);
+            chunk.unload();
+            false
+        });
+    }
+
+    fn save_dirty_chunks(&self, storage: &WorldStorage) -> usize {
+        // Collect positions first, so we don't hold every shard lock while writing to disk
+        let dirty_positions = self
+            .iter()
+            .filter(|entry| entry.value().is_dirty())
+            .map(|entry| *entry.key())
+            .collect::<Vec<_>>();
+
+        let mut saved = 0;
+        for pos in dirty_positions {
+            // The chunk is saved while holding its entry, so edits and unloads can't interleave with the write.
+            // If the chunk was unloaded in the meantime, it was saved during unload.
+            let Some(chunk) = self.get(&pos) else {
+                continue;
+            };
+
+            match storage.save_chunk_if_dirty(chunk.value()) {
+                Ok(true) => saved += 1,
+                Ok(false) => {}
+                Err(err) => log::error!("{:?}", err),
+            }
         }
 
-        // Then drop everything from the map. No neighbor updates needed.
-        self.clear();
+        saved
     }
 }
 
@@ -264,6 +371,7 @@ pub struct ChunkLoaderHandle<T: IChunkRenderState> {
     pub command_sender: Sender<ChunkLoaderCommand>,
     pub event_receiver: Receiver<ChunkLoaderEvent<T>>,
     pub camera_moved_sender: Sender<()>,
+    pub chunk_data_events: Receiver<ChunkDataEvent>,
     pub _thread_handle: JoinHandle<()>,
     pub camera: Arc<RwLock<Camera>>,
 }
@@ -272,22 +380,46 @@ impl<T: IChunkRenderState> ChunkLoaderHandle<T> {
     pub fn notify_camera_moved(&self) {
         let _ = self.camera_moved_sender.try_send(());
     }
+
+    /// Enqueues chunks for remeshing. The chunks should have been moved to StaleMesh
+    /// with `ChunkHandle::invalidate_mesh` or `ChunkHandle::finish_meshing`.
+    pub fn remesh(&self, chunks: Vec<ChunkHandle>) {
+        if chunks.is_empty() {
+            return;
+        }
+
+        let _ = self.command_sender.send(ChunkLoaderCommand::Remesh(chunks));
+    }
+
+    /// Stops the chunk loader and blocks until dirty chunks have been saved.
+    pub fn shutdown(self) {
+        let _ = self.command_sender.send(ChunkLoaderCommand::Shutdown);
+        if self._thread_handle.join().is_err() {
+            log::error!("Chunk loader thread panicked during shutdown");
+        }
+    }
 }
 
 /// Manages coordination for chunk loading/meshing.
 /// Heavy camera-driven load/unload work runs on a dedicated thread so this coordinator
 /// can stay responsive to worker events (notably ReadyForMeshing).
-pub struct ChunkLoader {
+pub struct ChunkLoader<T: IChunkRenderState> {
     command_receiver: Receiver<ChunkLoaderCommand>,
     worker_event_receiver: Receiver<ChunkWorkerEvent>,
     job_queue: Arc<LoaderJobQueue>,
     camera: Arc<RwLock<Camera>>,
+    world_access: Arc<dyn WorldAccess<T>>,
+    world_storage: Option<Arc<WorldStorage>>,
+    camera_thread: JoinHandle<()>,
+    autosave_thread: Option<JoinHandle<()>>,
 }
 
-impl ChunkLoader {
-    pub fn start<T: IChunkRenderState>(
+impl<T: IChunkRenderState> ChunkLoader<T> {
+    pub fn start(
         world_generator: Box<dyn WorldGenerator>,
-        block_database: Arc<BlockDatabaseSlim>,
+        world_storage: Option<Arc<WorldStorage>>,
+        block_database: SharedBlockDatabase,
+        light_engine: Arc<LightEngine>,
         world_access: Arc<dyn WorldAccess<T>>,
         render_context: T::Context,
     ) -> ChunkLoaderHandle<T> {
@@ -296,6 +428,7 @@ impl ChunkLoader {
         let (camera_moved_sender, camera_moved_receiver) = crossbeam_channel::bounded(1);
         let (worker_event_sender, worker_event_receiver) = crossbeam_channel::unbounded();
         let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+        let (chunk_data_sender, chunk_data_events) = crossbeam_channel::unbounded();
 
         let camera = Arc::new(RwLock::new(Camera::default()));
         let camera_clone = camera.clone();
@@ -308,15 +441,20 @@ impl ChunkLoader {
 
                 let (camera_shutdown_sender, camera_shutdown_receiver) =
                     crossbeam_channel::bounded::<()>(1);
+                let (autosave_shutdown_sender, autosave_shutdown_receiver) =
+                    crossbeam_channel::bounded::<()>(1);
 
                 let worker_pool = ChunkLoaderWorkerPool::new(
                     // TODO: use num_cpus crate
                     16,
                     worker_event_sender,
                     event_sender.clone(),
+                    chunk_data_sender.clone(),
                     job_queue.clone(),
                     world_generator,
+                    world_storage.clone(),
                     block_database,
+                    light_engine,
                     world_access.clone(),
                     render_context,
                 );
@@ -327,13 +465,16 @@ impl ChunkLoader {
                     let job_queue = job_queue.clone();
                     let camera = camera_clone.clone();
                     let world_access = world_access.clone();
+                    let world_storage = world_storage.clone();
                     let event_sender = event_sender.clone();
                     std::thread::Builder::new()
                         .name("Chunk loader camera".to_string())
                         .spawn(move || {
                             let mut camera_worker = ChunkLoaderCameraWorker {
                                 event_sender,
+                                chunk_data_sender,
                                 world_access,
+                                world_storage,
                                 camera_moved_receiver,
                                 desired_generation_offsets: offsets,
                                 job_queue,
@@ -355,17 +496,40 @@ impl ChunkLoader {
                         .unwrap()
                 };
 
+                // Periodically writes dirty chunks to disk, so edits survive crashes.
+                let autosave_thread = world_storage.clone().map(|world_storage| {
+                    let world_access = world_access.clone();
+                    std::thread::Builder::new()
+                        .name("Chunk autosave".to_string())
+                        .spawn(move || {
+                            // Runs until a shutdown signal is received or the sender is dropped
+                            while let Err(RecvTimeoutError::Timeout) =
+                                autosave_shutdown_receiver.recv_timeout(AUTOSAVE_INTERVAL)
+                            {
+                                let saved = world_access.save_dirty_chunks(&world_storage);
+                                if saved > 0 {
+                                    log::info!("Autosaved {} chunks", saved);
+                                }
+                            }
+                        })
+                        .unwrap()
+                });
+
                 let mut chunk_loader = ChunkLoader {
                     command_receiver,
                     worker_event_receiver,
                     job_queue,
                     camera: camera_clone,
+                    world_access,
+                    world_storage,
+                    camera_thread,
+                    autosave_thread,
                 };
 
                 drop(worker_pool);
-                drop(camera_thread);
 
-                chunk_loader.run(camera_shutdown_sender)
+                chunk_loader.run(camera_shutdown_sender, autosave_shutdown_sender);
+                chunk_loader.shutdown();
             })
             .unwrap();
 
@@ -373,6 +537,7 @@ impl ChunkLoader {
             command_sender,
             event_receiver,
             camera_moved_sender,
+            chunk_data_events,
             _thread_handle: thread,
             camera,
         }
@@ -390,15 +555,29 @@ impl ChunkLoader {
         }
     }
 
-    fn run(&mut self, camera_shutdown_sender: Sender<()>) {
+    fn enqueue_stale_meshes(&self, chunks: Vec<ChunkHandle>) {
+        for chunk in chunks {
+            if chunk.try_transition(ChunkState::StaleMesh, ChunkState::InMeshingQueue) {
+                let priority = self.get_priority_for_job(chunk.pos, JobType::Meshing);
+                self.job_queue
+                    .push(ChunkLoaderJob::GenerateMesh(chunk), priority);
+            }
+        }
+    }
+
+    fn run(&mut self, camera_shutdown_sender: Sender<()>, autosave_shutdown_sender: Sender<()>) {
         loop {
             select! {
                 recv(self.command_receiver) -> command => {
                     match command {
                         Err(_) | Ok(ChunkLoaderCommand::Shutdown) => {
                             let _ = camera_shutdown_sender.try_send(());
+                            let _ = autosave_shutdown_sender.try_send(());
                             break;
                         },
+                        Ok(ChunkLoaderCommand::Remesh(chunks)) => {
+                            self.enqueue_stale_meshes(chunks);
+                        }
                     }
                 }
                 recv(self.worker_event_receiver) -> event => {
@@ -417,6 +596,9 @@ impl ChunkLoader {
                                 self.job_queue.push(ChunkLoaderJob::GenerateMesh(chunk), priority);
                             }
                         }
+                        Ok(ChunkWorkerEvent::Remesh(chunks)) => {
+                            self.enqueue_stale_meshes(chunks);
+                        }
                         Err(_) => {
                             // Channel closed, should not happen
                             break;
@@ -426,11 +608,32 @@ impl ChunkLoader {
             }
         }
     }
+
+    /// Waits for the camera and autosave threads to stop, then does a final blocking save.
+    fn shutdown(self) {
+        let _ = self.camera_thread.join();
+        if let Some(autosave_thread) = self.autosave_thread {
+            let _ = autosave_thread.join();
+        }
+
+        let Some(world_storage) = self.world_storage else {
+            return;
+        };
+
+        let saved = self.world_access.save_dirty_chunks(&world_storage);
+        if let Err(err) = world_storage.flush() {
+            log::error!("Failed to flush world storage: {:?}", err);
+        }
+
+        log::info!("Saved {} chunks on shutdown", saved);
+    }
 }
 
 struct ChunkLoaderCameraWorker<T: IChunkRenderState> {
     event_sender: Sender<ChunkLoaderEvent<T>>,
+    chunk_data_sender: Sender<ChunkDataEvent>,
     world_access: Arc<dyn WorldAccess<T>>,
+    world_storage: Option<Arc<WorldStorage>>,
     camera_moved_receiver: Receiver<()>,
     desired_generation_offsets: Vec<IVec3>,
     job_queue: Arc<LoaderJobQueue>,
@@ -571,7 +774,8 @@ impl<T: IChunkRenderState> ChunkLoaderCameraWorker<T> {
         // Unload chunks that are now out of range.
         if no_overlap_teleport {
             let removed_jobs = self.job_queue.clear();
-            self.world_access.clear_all_chunks();
+            self.world_access
+                .clear_all_chunks(self.world_storage.as_deref());
 
             log::info!(
                 "Large teleport detected ({}) chunks: cleared world and {} queued jobs",
@@ -579,6 +783,7 @@ impl<T: IChunkRenderState> ChunkLoaderCameraWorker<T> {
                 removed_jobs
             );
 
+            let _ = self.chunk_data_sender.send(ChunkDataEvent::Cleared);
             self.event_sender
                 .send(ChunkLoaderEvent::WorldReset)
                 .unwrap();
@@ -592,13 +797,20 @@ impl<T: IChunkRenderState> ChunkLoaderCameraWorker<T> {
                     -1,
                     |this, pos| this.boundary_slab_batch.push(pos),
                 );
-                self.world_access.unload_chunks(&self.boundary_slab_batch)
+                self.world_access
+                    .unload_chunks(&self.boundary_slab_batch, self.world_storage.as_deref())
             } else {
                 // Fallback for large jumps/teleports: scan the whole map.
-                self.world_access
-                    .unload_chunks_outside_distance(current_chunk_pos, UNLOAD_DISTANCE as u32)
+                self.world_access.unload_chunks_outside_distance(
+                    current_chunk_pos,
+                    UNLOAD_DISTANCE as u32,
+                    self.world_storage.as_deref(),
+                )
             };
 
+            let _ = self
+                .chunk_data_sender
+                .send(ChunkDataEvent::Unloaded(unloaded.clone()));
             self.event_sender
                 .send(ChunkLoaderEvent::ChunksUnloaded(unloaded))
                 .unwrap();
@@ -680,9 +892,12 @@ impl ChunkLoaderWorkerPool {
         num_workers: usize,
         worker_event_sender: Sender<ChunkWorkerEvent>,
         loader_event_sender: Sender<ChunkLoaderEvent<T>>,
+        chunk_data_sender: Sender<ChunkDataEvent>,
         job_queue: Arc<LoaderJobQueue>,
         world_generator: Arc<dyn WorldGenerator>,
-        block_database: Arc<BlockDatabaseSlim>,
+        world_storage: Option<Arc<WorldStorage>>,
+        block_database: SharedBlockDatabase,
+        light_engine: Arc<LightEngine>,
         chunk_access: Arc<dyn WorldAccess<T>>,
         render_context: T::Context,
     ) -> Self {
@@ -690,11 +905,14 @@ impl ChunkLoaderWorkerPool {
 
         for _ in 0..num_workers {
             let world_generator = world_generator.clone();
+            let world_storage = world_storage.clone();
             let block_database = block_database.clone();
+            let light_engine = light_engine.clone();
             let chunk_access = chunk_access.clone();
             let render_context = render_context.clone();
             let worker_event_sender = worker_event_sender.clone();
             let loader_event_sender = loader_event_sender.clone();
+            let chunk_data_sender = chunk_data_sender.clone();
             let job_queue = job_queue.clone();
 
             let handle = std::thread::Builder::new()
@@ -702,12 +920,15 @@ impl ChunkLoaderWorkerPool {
                 .spawn(move || {
                     let mut worker = ChunkLoaderWorker::new(
                         world_generator,
+                        world_storage,
                         block_database,
+                        light_engine,
                         chunk_access,
                         render_context,
                         job_queue,
                         worker_event_sender,
                         loader_event_sender,
+                        chunk_data_sender,
                     );
 
                     worker.process_jobs();
@@ -723,6 +944,7 @@ impl ChunkLoaderWorkerPool {
 
 #[derive(Clone)]
 pub enum ChunkLoaderJob {
+    /// Loads the chunk from disk, or generates it if it has never been saved.
     GenerateChunk(ChunkHandle),
     GenerateMesh(ChunkHandle),
 }
@@ -744,54 +966,118 @@ pub struct ChunkMeshUpdate {
 
 struct ChunkLoaderWorker<T: IChunkRenderState> {
     world_generator: Arc<dyn WorldGenerator>,
+    world_storage: Option<Arc<WorldStorage>>,
+    block_database: SharedBlockDatabase,
+    /// Rebuilt when the shared block database is replaced
     mesh_generator: Arc<GreedyMesher>,
+    light_engine: Arc<LightEngine>,
     chunk_access: Arc<dyn WorldAccess<T>>,
     render_context: T::Context,
     job_queue: Arc<LoaderJobQueue>,
     job_notifications: Receiver<()>,
     event_sender: Sender<ChunkWorkerEvent>,
     loader_event_sender: Sender<ChunkLoaderEvent<T>>,
+    chunk_data_sender: Sender<ChunkDataEvent>,
     pending_chunks: Vec<ChunkMeshUpdate>,
     last_flush: Instant,
 }
 
 impl<T: IChunkRenderState> ChunkLoaderWorker<T> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         world_generator: Arc<dyn WorldGenerator>,
-        block_database: Arc<BlockDatabaseSlim>,
+        world_storage: Option<Arc<WorldStorage>>,
+        block_database: SharedBlockDatabase,
+        light_engine: Arc<LightEngine>,
         chunk_access: Arc<dyn WorldAccess<T>>,
         render_context: T::Context,
         job_queue: Arc<LoaderJobQueue>,
         event_sender: Sender<ChunkWorkerEvent>,
         loader_event_sender: Sender<ChunkLoaderEvent<T>>,
+        chunk_data_sender: Sender<ChunkDataEvent>,
     ) -> Self {
-        let mesh_generator = Arc::new(GreedyMesher::new(block_database));
+        let mesh_generator = Arc::new(GreedyMesher::new(block_database.load()));
         let job_notifications = job_queue.subscribe();
 
         ChunkLoaderWorker {
             world_generator,
+            world_storage,
+            block_database,
             mesh_generator,
+            light_engine,
             chunk_access,
             render_context,
             job_queue,
             job_notifications,
             event_sender,
             loader_event_sender,
+            chunk_data_sender,
             pending_chunks: Vec::new(),
             last_flush: Instant::now(),
         }
     }
 
     fn generate_chunk(&mut self, chunk: ChunkHandle) {
-        if !chunk.try_transition(ChunkState::InGenerationQueue, ChunkState::Generating) {
+        if let Some(world_storage) = &self.world_storage {
+            if !chunk.try_transition(ChunkState::InGenerationQueue, ChunkState::LoadingFromDisk) {
+                // Chunk has likely been unloaded while in the generation queue, ignore
+                return;
+            }
+
+            match world_storage.load_chunk(chunk.pos) {
+                Ok(Some(data)) => {
+                    self.insert_chunk_data(&chunk, data);
+                    return;
+                }
+                Ok(None) => {
+                    // Chunk has never been saved, generate it
+                }
+                Err(err) => {
+                    log::error!(
+                        "Failed to load chunk {:?} from disk, regenerating it: {:?}",
+                        chunk.pos,
+                        err
+                    );
+                }
+            }
+
+            if !chunk.try_transition(ChunkState::LoadingFromDisk, ChunkState::Generating) {
+                return;
+            }
+        } else if !chunk.try_transition(ChunkState::InGenerationQueue, ChunkState::Generating) {
             // Chunk has likely been unloaded while in the generation queue, ignore
             return;
         }
 
         let data = self.world_generator.generate_chunk(chunk.pos);
-        let _ = self
+        self.insert_chunk_data(&chunk, data);
+    }
+
+    fn insert_chunk_data(&self, chunk: &ChunkHandle, data: ChunkData) {
+        // Climate isn't saved, it's generated again from the seed
+        let climate = self.world_generator.generate_climate(chunk.pos);
+        let inserted = self
             .chunk_access
-            .insert_chunk_data_and_update_neighbor_masks(&chunk, data, &self.event_sender);
+            .insert_chunk_data_and_update_neighbor_masks(
+                chunk,
+                data,
+                climate,
+                &self.light_engine,
+                &self.event_sender,
+            );
+        if inserted {
+            let _ = self
+                .chunk_data_sender
+                .send(ChunkDataEvent::Inserted(chunk.pos));
+        }
+    }
+
+    /// Switches to the current block database if it has been replaced since the last mesh.
+    fn update_mesh_generator(&mut self) {
+        let block_database = self.block_database.load();
+        if !std::ptr::eq(self.mesh_generator.block_database(), &*block_database) {
+            self.mesh_generator = Arc::new(GreedyMesher::new(block_database));
+        }
     }
 
     fn generate_mesh(&mut self, chunk: ChunkHandle) {
@@ -800,7 +1086,12 @@ impl<T: IChunkRenderState> ChunkLoaderWorker<T> {
             return;
         }
 
-        let input = match self.chunk_access.create_mesh_input(chunk.pos) {
+        self.update_mesh_generator();
+
+        let input = match self
+            .chunk_access
+            .create_mesh_input(chunk.pos, self.mesh_generator.block_database())
+        {
             Ok(input) => input,
             Err(err) => {
                 // We failed to create mesh input, handle the error
@@ -1,12 +1,29 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
 use crate::config::config_manager::Config;
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct EngineConfig {}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// Directory containing the region files of the world
+    pub save_directory: PathBuf,
+    /// Resource packs in `resource_packs/` applied on top of the built-in assets, later ones taking precedence
+    pub resource_packs: Vec<String>,
+}
 
 impl Config for EngineConfig {
     fn get_path() -> &'static str {
         "engine.ron"
     }
 }
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            save_directory: PathBuf::from("saves/world"),
+            resource_packs: Vec::new(),
+        }
+    }
+}
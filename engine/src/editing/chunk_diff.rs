@@ -0,0 +1,152 @@
+use crate::voxels::{
+    chunk::ChunkData,
+    coord::{ChunkPos, LocalPos},
+    face::Face,
+    packed_chunk::PackedChunk,
+    voxel::Voxel,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct VoxelChange {
+    /// Index of the voxel within the chunk, in YZX order
+    index: u16,
+    /// Palette index of the voxel before the edit
+    before: u16,
+    /// Palette index of the voxel after the edit
+    after: u16,
+}
+
+/// Which side of a diff to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSide {
+    Before,
+    After,
+}
+
+/// Voxels changed within a single chunk by an edit.
+///
+/// Diffs only refer to the chunk by position and store voxel values, not references to chunk storage,
+/// so they can be applied to a chunk that has been unloaded and reloaded since the edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkDiff {
+    pub position: ChunkPos,
+    /// Every distinct voxel appearing on either side of the diff
+    palette: Vec<Voxel>,
+    changes: Vec<VoxelChange>,
+}
+
+impl ChunkDiff {
+    pub fn new(position: ChunkPos) -> Self {
+        ChunkDiff {
+            position,
+            palette: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    fn palette_index(&mut self, voxel: Voxel) -> u16 {
+        if let Some(index) = self.palette.iter().position(|v| *v == voxel) {
+            return index as u16;
+        }
+
+        self.palette.push(voxel);
+        (self.palette.len() - 1) as u16
+    }
+
+    /// Records a change of a single voxel. If the same voxel changes several times, every change
+    /// should be recorded in order.
+    pub fn record(&mut self, pos: LocalPos, before: Voxel, after: Voxel) {
+        let change = VoxelChange {
+            index: PackedChunk::coord_to_index(pos) as u16,
+            before: self.palette_index(before),
+            after: self.palette_index(after),
+        };
+        self.changes.push(change);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of changed voxels
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Iterates over the changed voxels and their values on the given side of the diff.
+    pub fn iter(&self, side: DiffSide) -> impl DoubleEndedIterator<Item = (LocalPos, Voxel)> + '_ {
+        self.changes.iter().map(move |change| {
+            let palette_index = match side {
+                DiffSide::Before => change.before,
+                DiffSide::After => change.after,
+            };
+            (
+                PackedChunk::index_to_coord(change.index as usize),
+                self.palette[palette_index as usize],
+            )
+        })
+    }
+
+    /// Writes one side of the diff into chunk data.
+    pub fn apply_to(&self, data: &mut ChunkData, side: DiffSide) {
+        match side {
+            DiffSide::After => {
+                for (pos, voxel) in self.iter(side) {
+                    data.set_voxel(pos, voxel);
+                }
+            }
+            // Reverting in reverse order restores the original value of voxels that changed more than once
+            DiffSide::Before => {
+                for (pos, voxel) in self.iter(side).rev() {
+                    data.set_voxel(pos, voxel);
+                }
+            }
+        }
+    }
+
+    /// Bitmask of the chunk faces (indexed by `Face`) that have changed voxels on them.
+    /// Neighbours across these faces need to be remeshed.
+    pub fn touched_faces(&self) -> u8 {
+        let mut mask = 0u8;
+        for change in &self.changes {
+            let pos = PackedChunk::index_to_coord(change.index as usize);
+            for face in Face::all().iter().copied() {
+                if pos.is_on_face(face) {
+                    mask |= 1 << (face as u8);
+                }
+            }
+        }
+        mask
+    }
+
+    pub fn approximate_size(&self) -> usize {
+        size_of::<Self>()
+            + self.palette.capacity() * size_of::<Voxel>()
+            + self.changes.capacity() * size_of::<VoxelChange>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_round_trip() {
+        let mut data = ChunkData::solid(Voxel::AIR);
+        data.set_voxel(LocalPos::new(1, 1, 1), Voxel::DIRT);
+
+        let mut diff = ChunkDiff::new(ChunkPos::new(0, 0, 0));
+        diff.record(LocalPos::new(1, 1, 1), Voxel::DIRT, Voxel::GOLD);
+        diff.record(LocalPos::new(2, 3, 4), Voxel::AIR, Voxel::GOLD);
+        diff.record(LocalPos::new(1, 1, 1), Voxel::GOLD, Voxel::GRASS);
+        assert_eq!(diff.len(), 3);
+
+        diff.apply_to(&mut data, DiffSide::After);
+        assert_eq!(data.get_voxel(LocalPos::new(1, 1, 1)), Some(Voxel::GRASS));
+        assert_eq!(data.get_voxel(LocalPos::new(2, 3, 4)), Some(Voxel::GOLD));
+
+        diff.apply_to(&mut data, DiffSide::Before);
+        assert_eq!(data.get_voxel(LocalPos::new(1, 1, 1)), Some(Voxel::DIRT));
+        assert_eq!(data.get_voxel(LocalPos::new(2, 3, 4)), Some(Voxel::AIR));
+    }
+}
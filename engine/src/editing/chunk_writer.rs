@@ -0,0 +1,61 @@
+use crate::{
+    editing::chunk_diff::ChunkDiff,
+    voxels::{
+        chunk::ChunkData,
+        coord::{ChunkPos, LocalPos},
+        voxel::Voxel,
+    },
+};
+
+/// Writes voxels directly into a chunk's storage while recording every change into a diff.
+/// Handed out by the world while it holds the chunk's lock.
+pub struct ChunkWriter<'a> {
+    data: &'a mut ChunkData,
+    diff: &'a mut ChunkDiff,
+}
+
+impl<'a> ChunkWriter<'a> {
+    pub fn new(data: &'a mut ChunkData, diff: &'a mut ChunkDiff) -> Self {
+        ChunkWriter { data, diff }
+    }
+
+    pub fn position(&self) -> ChunkPos {
+        self.diff.position
+    }
+
+    pub fn get_voxel(&self, pos: LocalPos) -> Option<Voxel> {
+        self.data.get_voxel(pos)
+    }
+
+    /// Returns true if the voxel changed.
+    pub fn set_voxel(&mut self, pos: LocalPos, voxel: Voxel) -> bool {
+        let Some(previous) = self.data.get_voxel(pos) else {
+            return false;
+        };
+
+        if previous == voxel {
+            return false;
+        }
+
+        self.data.set_voxel(pos, voxel);
+        self.diff.record(pos, previous, voxel);
+        true
+    }
+
+    /// Sets every voxel in the chunk, replacing its storage with a solid chunk.
+    /// Returns true if any voxel changed.
+    pub fn fill(&mut self, voxel: Voxel) -> bool {
+        let mut changed = false;
+        for (pos, previous) in self.data.iter_voxels() {
+            if previous != voxel {
+                self.diff.record(pos, previous, voxel);
+                changed = true;
+            }
+        }
+
+        if changed {
+            *self.data = ChunkData::solid(voxel);
+        }
+        changed
+    }
+}
@@ -0,0 +1,204 @@
+use glam::IVec3;
+
+use crate::{
+    editing::shape::WorldBox,
+    math::axis::Axis,
+    voxels::{chunk::IChunkRenderState, coord::WorldPos, voxel::Voxel},
+    world::World,
+};
+
+/// A box of voxels copied out of the world, which can be transformed and pasted elsewhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clipboard {
+    size: IVec3,
+    /// Voxels in YZX order
+    voxels: Vec<Voxel>,
+}
+
+impl Clipboard {
+    pub fn new(size: IVec3, voxels: Vec<Voxel>) -> Self {
+        assert!(
+            size.cmpgt(IVec3::ZERO).all(),
+            "Invalid clipboard size {}",
+            size
+        );
+        assert_eq!(
+            voxels.len(),
+            (size.x * size.y * size.z) as usize,
+            "Clipboard voxel count doesn't match its size"
+        );
+
+        Clipboard { size, voxels }
+    }
+
+    /// Copies the voxels in the box. Voxels in chunks that aren't loaded are copied as air.
+    pub fn copy<T: IChunkRenderState>(world: &World<T>, region: WorldBox) -> Self {
+        let size = region.size();
+        let mut clipboard = Clipboard::new(size, vec![Voxel::AIR; region.volume()]);
+
+        for chunk_pos in region.iter_chunk_positions() {
+            let Some(chunk) = world.chunks.get(&chunk_pos) else {
+                continue;
+            };
+            let Some(data) = &chunk.data else {
+                continue;
+            };
+            let Some(chunk_region) = region.intersection(&WorldBox::from_chunk(chunk_pos)) else {
+                continue;
+            };
+
+            for pos in chunk_region.iter() {
+                if let Some(voxel) = data.get_voxel(pos.to_local_pos()) {
+                    clipboard.set(pos.0 - region.min.0, voxel);
+                }
+            }
+        }
+
+        clipboard
+    }
+
+    pub fn size(&self) -> IVec3 {
+        self.size
+    }
+
+    pub fn voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    fn index(&self, pos: IVec3) -> usize {
+        debug_assert!(pos.cmpge(IVec3::ZERO).all() && pos.cmplt(self.size).all());
+        (pos.y * self.size.z * self.size.x + pos.z * self.size.x + pos.x) as usize
+    }
+
+    pub fn get(&self, pos: IVec3) -> Voxel {
+        self.voxels[self.index(pos)]
+    }
+
+    pub fn set(&mut self, pos: IVec3, voxel: Voxel) {
+        let index = self.index(pos);
+        self.voxels[index] = voxel;
+    }
+
+    fn positions(&self) -> impl Iterator<Item = IVec3> + use<> {
+        WorldBox::new(WorldPos(IVec3::ZERO), WorldPos(self.size - IVec3::ONE))
+            .iter()
+            .map(|pos| pos.0)
+    }
+
+    /// Builds a new clipboard of the given size by moving every voxel to `transform(pos)`.
+    fn transformed(&self, size: IVec3, transform: impl Fn(IVec3) -> IVec3) -> Clipboard {
+        let mut result = Clipboard::new(size, vec![Voxel::AIR; self.voxels.len()]);
+        for pos in self.positions() {
+            result.set(transform(pos), self.get(pos));
+        }
+        result
+    }
+
+    /// Rotates the contents by 90° counterclockwise around the axis (when looking from the positive end
+    /// of the axis towards the origin), `quarter_turns` times. Negative values rotate clockwise.
+    pub fn rotate(&self, axis: Axis, quarter_turns: i32) -> Clipboard {
+        // The two axes perpendicular to the rotation axis, in right-handed order
+        let (u, v) = match axis {
+            Axis::X => (1, 2),
+            Axis::Y => (2, 0),
+            Axis::Z => (0, 1),
+        };
+
+        let mut result = self.clone();
+        for _ in 0..quarter_turns.rem_euclid(4) {
+            let mut size = result.size;
+            size[u] = result.size[v];
+            size[v] = result.size[u];
+
+            let old_size = result.size;
+            result = result.transformed(size, |pos| {
+                // (u, v) -> (-v, u), shifted back into the positive range
+                let mut rotated = pos;
+                rotated[u] = old_size[v] - 1 - pos[v];
+                rotated[v] = pos[u];
+                rotated
+            });
+        }
+
+        result
+    }
+
+    /// Mirrors the contents along the axis.
+    pub fn mirror(&self, axis: Axis) -> Clipboard {
+        let axis = axis as usize;
+        self.transformed(self.size, |pos| {
+            let mut mirrored = pos;
+            mirrored[axis] = self.size[axis] - 1 - pos[axis];
+            mirrored
+        })
+    }
+
+    /// Pastes the clipboard with its minimum corner at `origin`, as a single undo step.
+    /// Air is only pasted if `include_air` is set, so pasted structures can be merged with existing terrain.
+    /// Returns the number of voxels that changed.
+    pub fn paste<T: IChunkRenderState + Send + Sync + 'static>(
+        &self,
+        world: &World<T>,
+        origin: WorldPos,
+        include_air: bool,
+    ) -> usize {
+        let region = WorldBox::new(origin, origin + WorldPos(self.size - IVec3::ONE));
+
+        world.edit_chunks(region.iter_chunk_positions(), |writer| {
+            let Some(chunk_region) = region.intersection(&WorldBox::from_chunk(writer.position()))
+            else {
+                return;
+            };
+
+            for pos in chunk_region.iter() {
+                let voxel = self.get(pos.0 - origin.0);
+                if include_air || voxel != Voxel::AIR {
+                    writer.set_voxel(pos.to_local_pos(), voxel);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3x1x2 clipboard with a different voxel in every cell
+    fn numbered_clipboard() -> Clipboard {
+        let voxels = (1..=6).map(Voxel::from_type).collect();
+        Clipboard::new(IVec3::new(3, 1, 2), voxels)
+    }
+
+    #[test]
+    fn test_rotate_around_y() {
+        let clipboard = numbered_clipboard();
+        let rotated = clipboard.rotate(Axis::Y, 1);
+        assert_eq!(rotated.size(), IVec3::new(2, 1, 3));
+
+        // +X rotates towards -Z when turning counterclockwise around +Y
+        assert_eq!(
+            rotated.get(IVec3::new(0, 0, 0)),
+            clipboard.get(IVec3::new(2, 0, 0))
+        );
+        assert_eq!(
+            rotated.get(IVec3::new(1, 0, 2)),
+            clipboard.get(IVec3::new(0, 0, 1))
+        );
+
+        assert_eq!(clipboard.rotate(Axis::Y, 4), clipboard);
+        assert_eq!(clipboard.rotate(Axis::Y, -1), clipboard.rotate(Axis::Y, 3));
+    }
+
+    #[test]
+    fn test_mirror() {
+        let clipboard = numbered_clipboard();
+        let mirrored = clipboard.mirror(Axis::X);
+        assert_eq!(mirrored.size(), clipboard.size());
+        assert_eq!(
+            mirrored.get(IVec3::new(0, 0, 1)),
+            clipboard.get(IVec3::new(2, 0, 1))
+        );
+        assert_eq!(mirrored.mirror(Axis::X), clipboard);
+    }
+}
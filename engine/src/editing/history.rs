@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+
+use crate::editing::chunk_diff::ChunkDiff;
+
+/// Default number of transactions kept in the undo history
+pub const DEFAULT_HISTORY_LIMIT: usize = 64;
+
+/// A group of edits that is undone and redone as a single step.
+/// Contains at most one diff per chunk.
+#[derive(Debug, Clone, Default)]
+pub struct EditTransaction {
+    pub chunks: Vec<ChunkDiff>,
+}
+
+impl EditTransaction {
+    pub fn is_empty(&self) -> bool {
+        self.chunks.iter().all(|diff| diff.is_empty())
+    }
+
+    /// Total number of changed voxels
+    pub fn voxel_count(&self) -> usize {
+        self.chunks.iter().map(|diff| diff.len()).sum()
+    }
+}
+
+/// Bounded undo/redo stacks of edit transactions.
+pub struct EditHistory {
+    undo_stack: VecDeque<EditTransaction>,
+    redo_stack: Vec<EditTransaction>,
+    limit: usize,
+}
+
+impl EditHistory {
+    pub fn new(limit: usize) -> Self {
+        EditHistory {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Records a newly committed transaction. Clears the redo stack, and drops the oldest
+    /// transaction if the history is full.
+    pub fn push(&mut self, transaction: EditTransaction) {
+        if transaction.is_empty() || self.limit == 0 {
+            return;
+        }
+
+        self.redo_stack.clear();
+        self.push_undo(transaction);
+    }
+
+    fn push_undo(&mut self, transaction: EditTransaction) {
+        if self.undo_stack.len() >= self.limit {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(transaction);
+    }
+
+    /// Takes the most recent transaction for undoing.
+    /// It should be handed back with `finish_undo` once applied, or `cancel_undo` if applying failed.
+    pub fn begin_undo(&mut self) -> Option<EditTransaction> {
+        self.undo_stack.pop_back()
+    }
+
+    pub fn finish_undo(&mut self, transaction: EditTransaction) {
+        self.redo_stack.push(transaction);
+    }
+
+    pub fn cancel_undo(&mut self, transaction: EditTransaction) {
+        self.undo_stack.push_back(transaction);
+    }
+
+    /// Takes the most recently undone transaction for redoing.
+    /// It should be handed back with `finish_redo` once applied, or `cancel_redo` if applying failed.
+    pub fn begin_redo(&mut self) -> Option<EditTransaction> {
+        self.redo_stack.pop()
+    }
+
+    pub fn finish_redo(&mut self, transaction: EditTransaction) {
+        self.push_undo(transaction);
+    }
+
+    pub fn cancel_redo(&mut self, transaction: EditTransaction) {
+        self.redo_stack.push(transaction);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxels::{
+        coord::{ChunkPos, LocalPos},
+        voxel::Voxel,
+    };
+
+    fn transaction(x: i32) -> EditTransaction {
+        let mut diff = ChunkDiff::new(ChunkPos::new(x, 0, 0));
+        diff.record(LocalPos::new(0, 0, 0), Voxel::AIR, Voxel::DIRT);
+        EditTransaction { chunks: vec![diff] }
+    }
+
+    #[test]
+    fn test_history_is_bounded_and_redo_is_cleared() {
+        let mut history = EditHistory::new(2);
+        history.push(transaction(0));
+        history.push(transaction(1));
+        history.push(transaction(2));
+
+        let undone = history.begin_undo().unwrap();
+        assert_eq!(undone.chunks[0].position, ChunkPos::new(2, 0, 0));
+        history.finish_undo(undone);
+
+        let undone = history.begin_undo().unwrap();
+        assert_eq!(undone.chunks[0].position, ChunkPos::new(1, 0, 0));
+        history.finish_undo(undone);
+
+        // The oldest transaction was dropped when the limit was reached
+        assert!(!history.can_undo());
+        assert!(history.can_redo());
+
+        history.push(transaction(3));
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod chunk_diff;
+pub mod chunk_writer;
+pub mod clipboard;
+pub mod history;
+pub mod region_ops;
+pub mod shape;
+pub mod world_edit;
@@ -0,0 +1,95 @@
+use crate::{
+    assets::blocks::BlockTypeId,
+    editing::shape::{Shape, WorldBox},
+    voxels::{chunk::IChunkRenderState, coord::WorldPos, voxel::Voxel},
+    world::World,
+};
+
+/// Runs `op` for every voxel of the shape and writes the voxel it returns, if any.
+/// Each affected chunk is locked and written once, and the whole operation is recorded as a single undo step.
+/// Returns the number of voxels that changed.
+fn apply_to_shape<T: IChunkRenderState + Send + Sync + 'static>(
+    world: &World<T>,
+    shape: &Shape,
+    mut op: impl FnMut(WorldPos, Voxel) -> Option<Voxel>,
+) -> usize {
+    let bounds = shape.bounds();
+
+    world.edit_chunks(bounds.iter_chunk_positions(), |writer| {
+        let chunk_box = WorldBox::from_chunk(writer.position());
+        let Some(region) = bounds.intersection(&chunk_box) else {
+            return;
+        };
+
+        for pos in region.iter() {
+            if !shape.contains(pos) {
+                continue;
+            }
+
+            let local_pos = pos.to_local_pos();
+            let Some(current) = writer.get_voxel(local_pos) else {
+                continue;
+            };
+
+            if let Some(voxel) = op(pos, current) {
+                writer.set_voxel(local_pos, voxel);
+            }
+        }
+    })
+}
+
+/// Sets every voxel of the shape.
+pub fn fill<T: IChunkRenderState + Send + Sync + 'static>(
+    world: &World<T>,
+    shape: &Shape,
+    voxel: Voxel,
+) -> usize {
+    let bounds = shape.bounds();
+
+    world.edit_chunks(bounds.iter_chunk_positions(), |writer| {
+        let chunk_box = WorldBox::from_chunk(writer.position());
+
+        // Chunks entirely inside the shape are replaced wholesale
+        if shape.contains_box(&chunk_box) {
+            writer.fill(voxel);
+            return;
+        }
+
+        let Some(region) = bounds.intersection(&chunk_box) else {
+            return;
+        };
+
+        for pos in region.iter() {
+            if shape.contains(pos) {
+                writer.set_voxel(pos.to_local_pos(), voxel);
+            }
+        }
+    })
+}
+
+/// Replaces every voxel of the given block type within the shape, regardless of its metadata.
+pub fn replace<T: IChunkRenderState + Send + Sync + 'static>(
+    world: &World<T>,
+    shape: &Shape,
+    from: BlockTypeId,
+    to: Voxel,
+) -> usize {
+    apply_to_shape(world, shape, |_, current| {
+        (current.block_type_id() == from).then_some(to)
+    })
+}
+
+/// Sets the surface of the shape to `voxel` and clears its interior.
+pub fn hollow<T: IChunkRenderState + Send + Sync + 'static>(
+    world: &World<T>,
+    shape: &Shape,
+    voxel: Voxel,
+) -> usize {
+    apply_to_shape(world, shape, |pos, _| {
+        if shape.is_on_surface(pos) {
+            Some(voxel)
+        } else {
+            Some(Voxel::AIR)
+        }
+    })
+}
@@ -0,0 +1,214 @@
+use glam::{IVec3, Vec3};
+
+use crate::{
+    math::axis::Axis,
+    voxels::{
+        chunk::CHUNK_SIZE,
+        coord::{ChunkPos, WorldPos},
+        face::Face,
+    },
+};
+
+/// An axis-aligned box of voxels. Both corners are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldBox {
+    pub min: WorldPos,
+    pub max: WorldPos,
+}
+
+impl WorldBox {
+    pub fn new(corner1: WorldPos, corner2: WorldPos) -> Self {
+        WorldBox {
+            min: WorldPos(corner1.0.min(corner2.0)),
+            max: WorldPos(corner1.0.max(corner2.0)),
+        }
+    }
+
+    pub fn from_chunk(chunk_pos: ChunkPos) -> Self {
+        let origin = chunk_pos.origin();
+        WorldBox {
+            min: origin,
+            max: origin + WorldPos(IVec3::splat(CHUNK_SIZE as i32 - 1)),
+        }
+    }
+
+    pub fn size(&self) -> IVec3 {
+        self.max.0 - self.min.0 + IVec3::ONE
+    }
+
+    pub fn volume(&self) -> usize {
+        let size = self.size().as_u64vec3();
+        (size.x * size.y * size.z) as usize
+    }
+
+    pub fn contains(&self, pos: WorldPos) -> bool {
+        pos.0.cmpge(self.min.0).all() && pos.0.cmple(self.max.0).all()
+    }
+
+    pub fn intersection(&self, other: &WorldBox) -> Option<WorldBox> {
+        let min = self.min.0.max(other.min.0);
+        let max = self.max.0.min(other.max.0);
+        min.cmple(max).all().then_some(WorldBox {
+            min: WorldPos(min),
+            max: WorldPos(max),
+        })
+    }
+
+    /// Iterates over every position in the box in YZX order.
+    pub fn iter(&self) -> impl Iterator<Item = WorldPos> + use<> {
+        let (min, max) = (self.min.0, self.max.0);
+        (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| WorldPos::new(x, y, z)))
+        })
+    }
+
+    /// Iterates over the positions of every chunk overlapping the box.
+    pub fn iter_chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + use<> {
+        let chunks = WorldBox {
+            min: WorldPos(self.min.to_chunk_pos().0),
+            max: WorldPos(self.max.to_chunk_pos().0),
+        };
+        chunks.iter().map(|pos| ChunkPos(pos.0))
+    }
+}
+
+/// A set of voxels targeted by a region operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Box(WorldBox),
+    /// Voxels whose centers are within `radius` of the center of the `center` voxel
+    Sphere {
+        center: WorldPos,
+        radius: f32,
+    },
+    /// A cylinder standing on the `base` voxel, extending `height` voxels along `axis`
+    Cylinder {
+        base: WorldPos,
+        axis: Axis,
+        radius: f32,
+        height: u32,
+    },
+}
+
+impl Shape {
+    /// The smallest box containing every voxel of the shape
+    pub fn bounds(&self) -> WorldBox {
+        match *self {
+            Shape::Box(world_box) => world_box,
+            Shape::Sphere { center, radius } => {
+                let extent = IVec3::splat(radius.floor() as i32);
+                WorldBox::new(center - WorldPos(extent), center + WorldPos(extent))
+            }
+            Shape::Cylinder {
+                base,
+                axis,
+                radius,
+                height,
+            } => {
+                let axis_vector = axis.as_unit_vector();
+                let cross_section = (IVec3::ONE - axis_vector) * radius.floor() as i32;
+                let top = axis_vector * (height.max(1) as i32 - 1);
+                WorldBox::new(
+                    base - WorldPos(cross_section),
+                    base + WorldPos(cross_section + top),
+                )
+            }
+        }
+    }
+
+    pub fn contains(&self, pos: WorldPos) -> bool {
+        match *self {
+            Shape::Box(world_box) => world_box.contains(pos),
+            Shape::Sphere { center, radius } => {
+                (pos.0 - center.0).as_vec3().length_squared() <= radius * radius
+            }
+            Shape::Cylinder {
+                base,
+                axis,
+                radius,
+                height,
+            } => {
+                let offset = pos.0 - base.0;
+                let along_axis = offset[axis as usize];
+                let radial = offset.as_vec3() * (Vec3::ONE - axis.as_unit_vector().as_vec3());
+
+                (0..height as i32).contains(&along_axis)
+                    && radial.length_squared() <= radius * radius
+            }
+        }
+    }
+
+    /// Returns true if every voxel of the box is part of the shape.
+    /// All shapes are convex, so it's enough to check the corners.
+    pub fn contains_box(&self, world_box: &WorldBox) -> bool {
+        let (min, max) = (world_box.min.0, world_box.max.0);
+        (0..8).all(|corner: i32| {
+            let pick = IVec3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let pos = min + (max - min) * pick;
+            self.contains(WorldPos(pos))
+        })
+    }
+
+    /// Returns true if the voxel is part of the shape but at least one of its neighbours isn't.
+    pub fn is_on_surface(&self, pos: WorldPos) -> bool {
+        self.contains(pos)
+            && Face::all()
+                .iter()
+                .any(|face| !self.contains(pos + WorldPos(face.to_ivec3())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_box_chunks() {
+        let world_box = WorldBox::new(WorldPos::new(20, 0, -1), WorldPos::new(-1, 15, 0));
+        assert_eq!(world_box.size(), IVec3::new(22, 16, 2));
+        assert_eq!(world_box.iter().count(), world_box.volume());
+
+        let chunks = world_box.iter_chunk_positions().collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 3 * 2);
+        assert!(chunks.contains(&ChunkPos::new(-1, 0, -1)));
+        assert!(chunks.contains(&ChunkPos::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn test_shapes_fit_in_bounds() {
+        let shapes = [
+            Shape::Sphere {
+                center: WorldPos::new(3, -2, 7),
+                radius: 4.5,
+            },
+            Shape::Cylinder {
+                base: WorldPos::new(0, 0, 0),
+                axis: Axis::X,
+                radius: 2.0,
+                height: 5,
+            },
+        ];
+
+        for shape in shapes {
+            let bounds = shape.bounds();
+            let grown = WorldBox::new(
+                bounds.min - WorldPos(IVec3::ONE),
+                bounds.max + WorldPos(IVec3::ONE),
+            );
+            for pos in grown.iter() {
+                if shape.contains(pos) {
+                    assert!(
+                        bounds.contains(pos),
+                        "{:?} outside bounds of {:?}",
+                        pos,
+                        shape
+                    );
+                }
+            }
+        }
+
+        let sphere = shapes[0];
+        assert!(sphere.is_on_surface(WorldPos::new(3, 2, 7)));
+        assert!(!sphere.is_on_surface(WorldPos::new(3, -2, 7)));
+    }
+}
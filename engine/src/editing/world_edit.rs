@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::{
+    fluids::fluid_simulation::FluidView,
+    voxels::{
+        chunk::{CHUNK_VOLUME, IChunkRenderState},
+        coord::{ChunkPos, LocalPos, WorldPos},
+        packed_chunk::PackedChunk,
+        voxel::Voxel,
+    },
+    world::World,
+};
+
+/// Voxels written to a single chunk during a transaction, indexed in YZX order.
+struct PendingChunkEdit {
+    voxels: Box<[Option<Voxel>]>,
+}
+
+impl PendingChunkEdit {
+    fn new() -> Self {
+        PendingChunkEdit {
+            voxels: vec![None; CHUNK_VOLUME].into_boxed_slice(),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (LocalPos, Voxel)> + '_ {
+        self.voxels
+            .iter()
+            .enumerate()
+            .filter_map(|(index, voxel)| Some((PackedChunk::index_to_coord(index), (*voxel)?)))
+    }
+}
+
+/// Groups many voxel writes into a single undoable transaction.
+///
+/// Writes are buffered per chunk and only applied to the world on `commit`, so each chunk is locked
+/// and remeshed once regardless of how many of its voxels were changed.
+/// Dropping the edit without committing discards it.
+pub struct WorldEdit<'a, T: IChunkRenderState> {
+    world: &'a World<T>,
+    chunks: HashMap<ChunkPos, PendingChunkEdit, ahash::RandomState>,
+}
+
+impl<'a, T: IChunkRenderState + Send + Sync + 'static> WorldEdit<'a, T> {
+    pub fn new(world: &'a World<T>) -> Self {
+        WorldEdit {
+            world,
+            chunks: HashMap::default(),
+        }
+    }
+
+    pub fn set_voxel(&mut self, position: WorldPos, voxel: Voxel) {
+        let index = PackedChunk::coord_to_index(position.to_local_pos());
+        self.chunks
+            .entry(position.to_chunk_pos())
+            .or_insert_with(PendingChunkEdit::new)
+            .voxels[index] = Some(voxel);
+    }
+
+    /// Returns the voxel at the position, including writes made in this transaction.
+    pub fn get_voxel(&self, position: WorldPos) -> Option<Voxel> {
+        let pending = self
+            .chunks
+            .get(&position.to_chunk_pos())
+            .and_then(|chunk| chunk.voxels[PackedChunk::coord_to_index(position.to_local_pos())]);
+
+        pending.or_else(|| self.world.get_voxel(position))
+    }
+
+    /// Number of chunks with pending writes
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Applies all writes to the world and records them in the world's undo history.
+    /// Writes to chunks that aren't loaded are ignored, like with `World::set_voxel`.
+    /// Returns the number of voxels that actually changed.
+    pub fn commit(self) -> usize {
+        let chunks = &self.chunks;
+        self.world.edit_chunks(chunks.keys().copied(), |writer| {
+            for (pos, voxel) in chunks[&writer.position()].iter() {
+                writer.set_voxel(pos, voxel);
+            }
+        })
+    }
+
+    /// Applies all writes to the world without recording them in the undo history.
+    /// Returns the number of voxels that actually changed.
+    pub fn commit_without_history(self) -> usize {
+        let chunks = &self.chunks;
+        self.world
+            .write_chunks(chunks.keys().copied(), |writer| {
+                for (pos, voxel) in chunks[&writer.position()].iter() {
+                    writer.set_voxel(pos, voxel);
+                }
+            })
+            .voxel_count()
+    }
+}
+
+impl<T: IChunkRenderState + Send + Sync + 'static> FluidView for WorldEdit<'_, T> {
+    fn get_voxel(&self, pos: WorldPos) -> Option<Voxel> {
+        WorldEdit::get_voxel(self, pos)
+    }
+
+    fn set_voxel(&mut self, pos: WorldPos, voxel: Voxel) {
+        WorldEdit::set_voxel(self, pos, voxel);
+    }
+}
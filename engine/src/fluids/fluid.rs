@@ -0,0 +1,99 @@
+use serde::Deserialize;
+
+use crate::assets::block_models::MODEL_GRID_SIZE;
+
+/// Fluids can't flow further than this from a source, since levels are stored in 3 bits of the voxel metadata
+pub const MAX_FLOW_DISTANCE: u8 = 7;
+
+/// Height of a source's surface in model grid units. Only falling fluid fills its whole voxel.
+const SOURCE_HEIGHT: u8 = MODEL_GRID_SIZE - 2;
+
+const FALLING_BIT: u8 = 1 << 3;
+
+/// Makes a block a fluid, in blocks.ron.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct FluidDefinition {
+    /// How many voxels the fluid flows sideways from a source (1-7)
+    pub flow_distance: u8,
+    /// Game updates between each step of the flow
+    pub tick_delay: u32,
+}
+
+/// State of a fluid voxel, stored in its metadata.
+/// Sources are 0, flowing fluid stores its distance from a source (1-7), and falling fluid sets the top bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FluidLevel {
+    Source,
+    Flowing(u8),
+    /// Fluid with more of the same fluid above it, or falling into the voxel below it
+    Falling,
+}
+
+impl FluidLevel {
+    pub fn from_metadata(metadata: u8) -> Self {
+        if metadata & FALLING_BIT != 0 {
+            FluidLevel::Falling
+        } else if metadata == 0 {
+            FluidLevel::Source
+        } else {
+            FluidLevel::Flowing(metadata)
+        }
+    }
+
+    pub fn to_metadata(self) -> u8 {
+        match self {
+            FluidLevel::Source => 0,
+            FluidLevel::Flowing(distance) => distance.clamp(1, MAX_FLOW_DISTANCE),
+            FluidLevel::Falling => FALLING_BIT,
+        }
+    }
+
+    /// Distance from the fluid feeding this voxel. Fluid only replaces fluid with a greater distance.
+    pub fn distance(self) -> u8 {
+        match self {
+            FluidLevel::Source | FluidLevel::Falling => 0,
+            FluidLevel::Flowing(distance) => distance,
+        }
+    }
+
+    /// Height of the fluid's surface in model grid units (1-16)
+    pub fn height(self, fluid: &FluidDefinition) -> u8 {
+        match self {
+            FluidLevel::Source => SOURCE_HEIGHT,
+            FluidLevel::Falling => MODEL_GRID_SIZE,
+            FluidLevel::Flowing(distance) => {
+                let steps = fluid.flow_distance.max(distance) + 1;
+                (SOURCE_HEIGHT * (steps - distance) / steps).max(1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_round_trip_and_get_lower() {
+        let water = FluidDefinition {
+            flow_distance: 7,
+            tick_delay: 5,
+        };
+        for level in [
+            FluidLevel::Source,
+            FluidLevel::Flowing(1),
+            FluidLevel::Flowing(7),
+            FluidLevel::Falling,
+        ] {
+            assert_eq!(FluidLevel::from_metadata(level.to_metadata()), level);
+        }
+
+        let heights = (1..=7)
+            .map(|distance| FluidLevel::Flowing(distance).height(&water))
+            .collect::<Vec<_>>();
+        assert!(heights.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(FluidLevel::Source.height(&water) > heights[0]);
+        assert!(heights[6] >= 1);
+        assert_eq!(FluidLevel::Falling.height(&water), MODEL_GRID_SIZE);
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use glam::IVec3;
+
+use crate::{
+    assets::blocks::BlockDatabaseSlim,
+    fluids::fluid::{FluidDefinition, FluidLevel},
+    voxels::{
+        coord::{ChunkPos, WorldPos},
+        face::Face,
+        voxel::Voxel,
+    },
+};
+
+/// Limits the work done in a single game update. Updates over the limit run in the next one.
+const MAX_UPDATES_PER_TICK: usize = 4096;
+
+const HORIZONTAL_FACES: [Face; 4] = [Face::Left, Face::Right, Face::Front, Face::Back];
+
+/// How blocks interact with fluids.
+pub trait FluidRules {
+    fn fluid(&self, voxel: Voxel) -> Option<&FluidDefinition>;
+    /// Whether fluid can flow into the voxel, destroying it. Fluids never replace other fluids.
+    fn is_replaceable_by_fluid(&self, voxel: Voxel) -> bool;
+}
+
+impl FluidRules for BlockDatabaseSlim {
+    fn fluid(&self, voxel: Voxel) -> Option<&FluidDefinition> {
+        self.properties(voxel).fluid.as_ref()
+    }
+
+    fn is_replaceable_by_fluid(&self, voxel: Voxel) -> bool {
+        let properties = self.properties(voxel);
+        properties.replaceable && properties.fluid.is_none()
+    }
+}
+
+/// Voxels the simulation reads and writes. Writes must be visible to later reads during the same tick.
+pub trait FluidView {
+    /// Returns None if the position isn't loaded. Fluids don't update next to unloaded voxels,
+    /// since they can't tell whether they're being fed or have somewhere to flow.
+    fn get_voxel(&self, pos: WorldPos) -> Option<Voxel>;
+    fn set_voxel(&mut self, pos: WorldPos, voxel: Voxel);
+}
+
+/// Spreads fluids with scheduled updates.
+/// Changing a voxel schedules updates for the fluids at and around it, which run after the fluid's `tick_delay`.
+/// An update settles a flowing fluid to the level fed by its neighbours, and then spreads it down or sideways.
+/// Positions are in world space, so fluids flow across chunk borders.
+/// Updates next to chunks that aren't loaded wait for them, see `chunk_loaded`.
+#[derive(Default)]
+pub struct FluidSimulation {
+    tick: u64,
+    /// Positions to update, by the tick they're due
+    scheduled: BTreeMap<u64, Vec<WorldPos>>,
+    /// Every position in `scheduled` or `waiting`, so each voxel is only scheduled once
+    pending: HashSet<WorldPos>,
+    /// Positions whose update reads a voxel in a chunk that isn't loaded, by that chunk
+    waiting: HashMap<ChunkPos, Vec<WorldPos>>,
+}
+
+impl FluidSimulation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of scheduled fluid updates, not counting the ones waiting for chunks to load
+    pub fn pending_updates(&self) -> usize {
+        self.pending.len() - self.waiting.values().map(Vec::len).sum::<usize>()
+    }
+
+    /// Schedules updates for the fluids at and next to voxels that have changed.
+    pub fn voxels_changed(
+        &mut self,
+        rules: &impl FluidRules,
+        get_voxel: impl Fn(WorldPos) -> Option<Voxel>,
+        positions: impl IntoIterator<Item = WorldPos>,
+    ) {
+        for pos in positions {
+            let neighbors = Face::all().map(|face| pos + WorldPos(face.to_ivec3()));
+            for target in std::iter::once(pos).chain(neighbors) {
+                self.schedule(rules, &get_voxel, target);
+            }
+        }
+    }
+
+    /// Schedules the fluids of a chunk whose voxels have just been inserted, and the updates that were
+    /// waiting for it. Fluids are saved mid-flow, so this resumes them when their chunk is loaded again.
+    pub fn chunk_loaded(
+        &mut self,
+        rules: &impl FluidRules,
+        get_voxel: impl Fn(WorldPos) -> Option<Voxel>,
+        chunk_pos: ChunkPos,
+        fluids: impl IntoIterator<Item = WorldPos>,
+    ) {
+        for pos in self.waiting.remove(&chunk_pos).unwrap_or_default() {
+            self.pending.remove(&pos);
+            self.schedule(rules, &get_voxel, pos);
+        }
+
+        for pos in fluids {
+            self.schedule(rules, &get_voxel, pos);
+        }
+    }
+
+    /// Forgets the updates waiting in unloaded chunks. Their fluids are scheduled again by `chunk_loaded`.
+    pub fn chunks_unloaded(&mut self, chunks: &[ChunkPos]) {
+        for &chunk_pos in chunks {
+            // Updates only wait for the chunks around their own
+            for z in -1..=1 {
+                for y in -1..=1 {
+                    for x in -1..=1 {
+                        let blocking = ChunkPos(chunk_pos.0 + IVec3::new(x, y, z));
+                        let Some(positions) = self.waiting.get_mut(&blocking) else {
+                            continue;
+                        };
+                        positions.retain(|pos| {
+                            let unloaded = pos.to_chunk_pos() == chunk_pos;
+                            if unloaded {
+                                self.pending.remove(pos);
+                            }
+                            !unloaded
+                        });
+                        if positions.is_empty() {
+                            self.waiting.remove(&blocking);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drops every scheduled and waiting update, after all chunks have been removed
+    pub fn clear(&mut self) {
+        self.scheduled.clear();
+        self.pending.clear();
+        self.waiting.clear();
+    }
+
+    fn schedule(
+        &mut self,
+        rules: &impl FluidRules,
+        get_voxel: impl Fn(WorldPos) -> Option<Voxel>,
+        pos: WorldPos,
+    ) {
+        if self.pending.contains(&pos) {
+            return;
+        }
+        let Some(fluid) = get_voxel(pos).and_then(|voxel| rules.fluid(voxel)) else {
+            return;
+        };
+
+        let due = self.tick + fluid.tick_delay.max(1) as u64;
+        self.scheduled.entry(due).or_default().push(pos);
+        self.pending.insert(pos);
+    }
+
+    /// Advances the simulation by one game update, and runs the updates that are due.
+    /// Returns the positions that changed. Once the changes have been applied to the world,
+    /// they should be passed to `voxels_changed` to keep the fluid flowing.
+    pub fn tick(&mut self, view: &mut impl FluidView, rules: &impl FluidRules) -> Vec<WorldPos> {
+        self.tick += 1;
+
+        let mut due = Vec::new();
+        while due.len() < MAX_UPDATES_PER_TICK {
+            let Some(mut entry) = self.scheduled.first_entry() else {
+                break;
+            };
+            if *entry.key() > self.tick {
+                break;
+            }
+
+            let positions = entry.get_mut();
+            let count = positions.len().min(MAX_UPDATES_PER_TICK - due.len());
+            due.extend(positions.drain(..count));
+            if positions.is_empty() {
+                entry.remove();
+            }
+        }
+
+        let mut changed = Vec::new();
+        for pos in due {
+            // Fluids in unloaded chunks are scheduled again by `chunk_loaded`
+            if view.get_voxel(pos).is_none() {
+                self.pending.remove(&pos);
+                continue;
+            }
+            if let Some(unloaded) = neighborhood(pos).find(|read| view.get_voxel(*read).is_none()) {
+                self.waiting
+                    .entry(unloaded.to_chunk_pos())
+                    .or_default()
+                    .push(pos);
+                continue;
+            }
+
+            self.pending.remove(&pos);
+            update_fluid(view, rules, pos, &mut changed);
+        }
+        changed
+    }
+}
+
+/// Voxels an update of the fluid at `pos` reads: its neighbours, and the voxels below its horizontal neighbours
+fn neighborhood(pos: WorldPos) -> impl Iterator<Item = WorldPos> {
+    let below = WorldPos(Face::Bottom.to_ivec3());
+    let neighbors = Face::all().map(|face| pos + WorldPos(face.to_ivec3()));
+    let below_neighbors = HORIZONTAL_FACES.map(|face| pos + WorldPos(face.to_ivec3()) + below);
+    neighbors.into_iter().chain(below_neighbors)
+}
+
+/// Updates a fluid whose `neighborhood` is loaded
+fn update_fluid<V: FluidView>(
+    view: &mut V,
+    rules: &impl FluidRules,
+    pos: WorldPos,
+    changed: &mut Vec<WorldPos>,
+) {
+    let Some(voxel) = view.get_voxel(pos) else {
+        return;
+    };
+    let Some(fluid) = rules.fluid(voxel).copied() else {
+        return;
+    };
+    let block_type = voxel.block_type();
+    let mut write = |view: &mut V, pos: WorldPos, voxel: Voxel| {
+        view.set_voxel(pos, voxel);
+        changed.push(pos);
+    };
+
+    // Sources stay put, everything else needs to be fed by a neighbour or it dries up
+    let mut level = FluidLevel::from_metadata(voxel.metadata());
+    if level != FluidLevel::Source {
+        match fed_level(view, rules, pos, block_type, &fluid) {
+            None => {
+                write(view, pos, Voxel::AIR);
+                return;
+            }
+            Some(fed) if fed != level => {
+                write(view, pos, fluid_voxel(block_type, fed));
+                level = fed;
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Fluid falls if it can, and only spreads sideways once it has landed
+    if flows_down(view, rules, pos, block_type) {
+        let below = pos + WorldPos(Face::Bottom.to_ivec3());
+        if view
+            .get_voxel(below)
+            .is_some_and(|target| can_flow_into(rules, target, block_type, FluidLevel::Falling))
+        {
+            write(view, below, fluid_voxel(block_type, FluidLevel::Falling));
+        }
+        return;
+    }
+
+    let distance = level.distance() + 1;
+    if distance > fluid.flow_distance {
+        return;
+    }
+    let spread = FluidLevel::Flowing(distance);
+    for face in HORIZONTAL_FACES {
+        let neighbor = pos + WorldPos(face.to_ivec3());
+        if view
+            .get_voxel(neighbor)
+            .is_some_and(|target| can_flow_into(rules, target, block_type, spread))
+        {
+            write(view, neighbor, fluid_voxel(block_type, spread));
+        }
+    }
+}
+
+/// Level a flowing fluid should have, or None if nothing feeds it anymore.
+/// Fluid above makes it fall, and otherwise it's one step further than its nearest neighbour that has landed.
+fn fed_level(
+    view: &impl FluidView,
+    rules: &impl FluidRules,
+    pos: WorldPos,
+    block_type: u16,
+    fluid: &FluidDefinition,
+) -> Option<FluidLevel> {
+    let above = pos + WorldPos(Face::Top.to_ivec3());
+    if view
+        .get_voxel(above)
+        .is_some_and(|voxel| voxel.block_type() == block_type)
+    {
+        return Some(FluidLevel::Falling);
+    }
+
+    let nearest = HORIZONTAL_FACES
+        .iter()
+        .filter_map(|face| {
+            let neighbor = pos + WorldPos(face.to_ivec3());
+            let voxel = view.get_voxel(neighbor)?;
+            let feeds =
+                voxel.block_type() == block_type && !flows_down(view, rules, neighbor, block_type);
+            feeds.then(|| FluidLevel::from_metadata(voxel.metadata()).distance())
+        })
+        .min()?;
+
+    let distance = nearest + 1;
+    (distance <= fluid.flow_distance).then_some(FluidLevel::Flowing(distance))
+}
+
+/// Whether the fluid at `pos` can fall into the voxel below it, or is already falling through it.
+/// Sources resting on sources spread sideways, like the surface of a lake.
+fn flows_down(
+    view: &impl FluidView,
+    rules: &impl FluidRules,
+    pos: WorldPos,
+    block_type: u16,
+) -> bool {
+    let below = pos + WorldPos(Face::Bottom.to_ivec3());
+    view.get_voxel(below).is_some_and(|voxel| {
+        rules.is_replaceable_by_fluid(voxel)
+            || (voxel.block_type() == block_type
+                && FluidLevel::from_metadata(voxel.metadata()) != FluidLevel::Source)
+    })
+}
+
+/// Fluid replaces replaceable blocks, and the same fluid further from its source.
+fn can_flow_into(
+    rules: &impl FluidRules,
+    target: Voxel,
+    block_type: u16,
+    level: FluidLevel,
+) -> bool {
+    if rules.is_replaceable_by_fluid(target) {
+        return true;
+    }
+    if target.block_type() != block_type {
+        return false;
+    }
+    match FluidLevel::from_metadata(target.metadata()) {
+        FluidLevel::Source => false,
+        target_level => level.distance() < target_level.distance(),
+    }
+}
+
+fn fluid_voxel(block_type: u16, level: FluidLevel) -> Voxel {
+    Voxel::from_type_metadata(block_type, level.to_metadata())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const WATER: u16 = 5;
+    const WATER_FLUID: FluidDefinition = FluidDefinition {
+        flow_distance: 3,
+        tick_delay: 2,
+    };
+
+    /// Air is replaceable, and everything else is solid
+    struct TestRules;
+
+    impl FluidRules for TestRules {
+        fn fluid(&self, voxel: Voxel) -> Option<&FluidDefinition> {
+            (voxel.block_type() == WATER).then_some(&WATER_FLUID)
+        }
+
+        fn is_replaceable_by_fluid(&self, voxel: Voxel) -> bool {
+            voxel.is_air()
+        }
+    }
+
+    /// Bounded view of air, with floors at y = -1 and y = -3. There's a hole in the upper floor at x = 2, z = 0.
+    #[derive(Default)]
+    struct TestView {
+        voxels: HashMap<WorldPos, Voxel>,
+        unloaded_chunk: Option<ChunkPos>,
+    }
+
+    impl FluidView for TestView {
+        fn get_voxel(&self, pos: WorldPos) -> Option<Voxel> {
+            if pos.0.abs().max_element() > 40 || Some(pos.to_chunk_pos()) == self.unloaded_chunk {
+                return None;
+            }
+            if let Some(voxel) = self.voxels.get(&pos) {
+                return Some(*voxel);
+            }
+            let hole = pos == WorldPos::new(2, -1, 0);
+            let floor = (pos.0.y == -1 && !hole) || pos.0.y == -3;
+            Some(if floor { Voxel::DIRT } else { Voxel::AIR })
+        }
+
+        fn set_voxel(&mut self, pos: WorldPos, voxel: Voxel) {
+            self.voxels.insert(pos, voxel);
+        }
+    }
+
+    fn run_until_settled(simulation: &mut FluidSimulation, view: &mut TestView) {
+        for _ in 0..1000 {
+            let changed = simulation.tick(view, &TestRules);
+            simulation.voxels_changed(&TestRules, |pos| view.get_voxel(pos), changed);
+            if simulation.pending_updates() == 0 {
+                return;
+            }
+        }
+        panic!("Fluid didn't settle");
+    }
+
+    #[test]
+    fn test_fluid_spreads_falls_and_dries_up() {
+        let mut simulation = FluidSimulation::new();
+        let mut view = TestView::default();
+        let source = WorldPos::new(0, 0, 0);
+        view.set_voxel(source, Voxel::from_type(WATER));
+        simulation.voxels_changed(&TestRules, |pos| view.get_voxel(pos), [source]);
+        run_until_settled(&mut simulation, &mut view);
+
+        let level = |view: &TestView, x, y, z| {
+            view.get_voxel(WorldPos::new(x, y, z))
+                .filter(|voxel| voxel.block_type() == WATER)
+                .map(|voxel| FluidLevel::from_metadata(voxel.metadata()))
+        };
+        assert_eq!(level(&view, -1, 0, 0), Some(FluidLevel::Flowing(1)));
+        assert_eq!(level(&view, -3, 0, 0), Some(FluidLevel::Flowing(3)));
+        assert_eq!(level(&view, -4, 0, 0), None);
+        assert_eq!(level(&view, -1, 0, 1), Some(FluidLevel::Flowing(2)));
+
+        // Water pours through the hole instead of spreading past it, and spreads again on the lower floor
+        assert_eq!(level(&view, 2, 0, 0), Some(FluidLevel::Flowing(2)));
+        assert_eq!(level(&view, 3, 0, 0), None);
+        assert_eq!(level(&view, 2, -1, 0), Some(FluidLevel::Falling));
+        assert_eq!(level(&view, 2, -2, 0), Some(FluidLevel::Falling));
+        assert_eq!(level(&view, 3, -2, 0), Some(FluidLevel::Flowing(1)));
+        assert_eq!(level(&view, 5, -2, 0), Some(FluidLevel::Flowing(3)));
+
+        view.set_voxel(source, Voxel::AIR);
+        simulation.voxels_changed(&TestRules, |pos| view.get_voxel(pos), [source]);
+        run_until_settled(&mut simulation, &mut view);
+
+        assert!(view.voxels.values().all(|voxel| voxel.is_air()));
+    }
+
+    #[test]
+    fn test_fluid_waits_for_unloaded_chunks() {
+        let level = |view: &TestView, x| {
+            view.get_voxel(WorldPos::new(x, 0, 5))
+                .filter(|voxel| voxel.block_type() == WATER)
+                .map(|voxel| FluidLevel::from_metadata(voxel.metadata()))
+        };
+
+        // Water flowing towards an unloaded chunk stops at its border, but doesn't give up
+        let mut simulation = FluidSimulation::new();
+        let mut view = TestView {
+            unloaded_chunk: Some(ChunkPos::new(1, 0, 0)),
+            ..Default::default()
+        };
+        let source = WorldPos::new(14, 0, 5);
+        view.set_voxel(source, Voxel::from_type(WATER));
+        simulation.voxels_changed(&TestRules, |pos| view.get_voxel(pos), [source]);
+        run_until_settled(&mut simulation, &mut view);
+        assert_eq!(level(&view, 15), Some(FluidLevel::Flowing(1)));
+
+        view.unloaded_chunk = None;
+        simulation.chunk_loaded(
+            &TestRules,
+            |pos| view.get_voxel(pos),
+            ChunkPos::new(1, 0, 0),
+            [],
+        );
+        run_until_settled(&mut simulation, &mut view);
+        assert_eq!(level(&view, 16), Some(FluidLevel::Flowing(2)));
+        assert_eq!(level(&view, 17), Some(FluidLevel::Flowing(3)));
+
+        // Flowing water loaded without the chunk of its source isn't dried up, and resumes once it's loaded
+        let mut simulation = FluidSimulation::new();
+        view.unloaded_chunk = Some(ChunkPos::new(0, 0, 0));
+        view.voxels.remove(&WorldPos::new(17, 0, 5));
+        let loaded_fluids = [WorldPos::new(16, 0, 5)];
+        simulation.chunk_loaded(
+            &TestRules,
+            |pos| view.get_voxel(pos),
+            ChunkPos::new(1, 0, 0),
+            loaded_fluids,
+        );
+        run_until_settled(&mut simulation, &mut view);
+        assert_eq!(level(&view, 16), Some(FluidLevel::Flowing(2)));
+        assert_eq!(level(&view, 17), None);
+
+        view.unloaded_chunk = None;
+        simulation.chunk_loaded(
+            &TestRules,
+            |pos| view.get_voxel(pos),
+            ChunkPos::new(0, 0, 0),
+            [source, WorldPos::new(15, 0, 5)],
+        );
+        run_until_settled(&mut simulation, &mut view);
+        assert_eq!(level(&view, 17), Some(FluidLevel::Flowing(3)));
+    }
+
+    #[test]
+    fn test_unloading_drops_waiting_updates() {
+        let mut simulation = FluidSimulation::new();
+        let mut view = TestView {
+            unloaded_chunk: Some(ChunkPos::new(1, 0, 0)),
+            ..Default::default()
+        };
+        let source = WorldPos::new(14, 0, 5);
+        view.set_voxel(source, Voxel::from_type(WATER));
+        simulation.voxels_changed(&TestRules, |pos| view.get_voxel(pos), [source]);
+        run_until_settled(&mut simulation, &mut view);
+        assert_eq!(simulation.waiting.len(), 1);
+        assert!(!simulation.pending.is_empty());
+
+        // Updates waiting next to the chunk that's still loaded are kept
+        simulation.chunks_unloaded(&[ChunkPos::new(0, 1, 0)]);
+        assert!(!simulation.pending.is_empty());
+
+        simulation.chunks_unloaded(&[ChunkPos::new(0, 0, 0)]);
+        assert!(simulation.waiting.is_empty());
+        assert!(simulation.pending.is_empty());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod fluid;
+pub mod fluid_simulation;
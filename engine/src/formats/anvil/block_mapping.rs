@@ -0,0 +1,119 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::blocks::BlockDatabase,
+    formats::{
+        nbt::{NbtCompound, NbtTag},
+        schematic::resolve_block_name,
+    },
+    voxels::voxel::Voxel,
+};
+
+/// Block states that are always imported as air unless the mapping says otherwise
+const AIR_BLOCK_STATES: [&str; 3] = ["minecraft:air", "minecraft:cave_air", "minecraft:void_air"];
+
+/// Maps Minecraft block states to blocks. Loaded from a RON file like:
+///
+/// ```ron
+/// (
+///     blocks: {
+///         "minecraft:grass_block": "grass",
+///         "minecraft:oak_log[axis=y]": "tree",
+///         "minecraft:oak_log": "tree[meta=1]",
+///     },
+///     default: Some("dirt"),
+/// )
+/// ```
+///
+/// Keys are either a block name, or a block name followed by all of its properties in alphabetical order.
+/// Values are block names, optionally with a `meta` property that sets the voxel metadata.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnvilBlockMapping {
+    pub blocks: BTreeMap<String, String>,
+    /// Block used for states that aren't listed. If None, those blocks become air.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl AnvilBlockMapping {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read block mapping {}", path.display()))?;
+        ron::from_str(&data)
+            .with_context(|| format!("Failed to parse block mapping {}", path.display()))
+    }
+
+    /// Resolves all block names in the mapping against the block database.
+    pub fn resolve(&self, block_database: &BlockDatabase) -> anyhow::Result<BlockStateResolver> {
+        let find_block = |name: &str| -> anyhow::Result<Voxel> {
+            match resolve_block_name(block_database, name) {
+                Some(voxel) => Ok(voxel),
+                None => bail!("Unknown block '{}' in block mapping", name),
+            }
+        };
+
+        let mut states = AIR_BLOCK_STATES
+            .iter()
+            .map(|name| (name.to_string(), Voxel::AIR))
+            .collect::<HashMap<_, _>>();
+        for (state, name) in &self.blocks {
+            states.insert(state.clone(), find_block(name)?);
+        }
+
+        Ok(BlockStateResolver {
+            states,
+            default: self.default.as_deref().map(find_block).transpose()?,
+            unknown_states: HashSet::new(),
+        })
+    }
+}
+
+/// Translates block states to voxels, remembering states without a mapping so they can be reported once.
+pub struct BlockStateResolver {
+    states: HashMap<String, Voxel>,
+    default: Option<Voxel>,
+    unknown_states: HashSet<String>,
+}
+
+impl BlockStateResolver {
+    /// Resolves a block state palette entry, which has a `Name` and optional `Properties`.
+    pub fn resolve(&mut self, state: &NbtCompound) -> anyhow::Result<Voxel> {
+        let name = state.get_string("Name")?;
+
+        let mut key = name.to_string();
+        if let Ok(properties) = state.get_compound("Properties") {
+            // Compound entries are sorted, so the key doesn't depend on the order in the file
+            let properties = properties
+                .0
+                .iter()
+                .filter_map(|(key, value)| match value {
+                    NbtTag::String(value) => Some(format!("{}={}", key, value)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if !properties.is_empty() {
+                key = format!("{}[{}]", name, properties.join(","));
+            }
+        }
+
+        if let Some(voxel) = self.states.get(&key).or_else(|| self.states.get(name)) {
+            return Ok(*voxel);
+        }
+
+        if self.unknown_states.insert(name.to_string()) {
+            log::debug!("No mapping for block state {}", key);
+        }
+        Ok(self.default.unwrap_or(Voxel::AIR))
+    }
+
+    /// Names of blocks that were encountered without a mapping
+    pub fn unknown_states(&self) -> impl Iterator<Item = &str> {
+        self.unknown_states.iter().map(String::as_str)
+    }
+}
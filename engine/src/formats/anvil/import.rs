@@ -0,0 +1,377 @@
+use std::path::Path;
+
+use anyhow::{Context, bail, ensure};
+use glam::IVec2;
+
+use crate::{
+    assets::blocks::BlockDatabase,
+    formats::{
+        anvil::{
+            block_mapping::{AnvilBlockMapping, BlockStateResolver},
+            region::{AnvilRegion, parse_region_file_name},
+        },
+        nbt::{NbtCompound, NbtTag},
+    },
+    persistence::world_storage::WorldStorage,
+    voxels::{
+        chunk::{CHUNK_SIZE, CHUNK_VOLUME, ChunkData},
+        coord::ChunkPos,
+        voxel::Voxel,
+    },
+};
+
+/// First data version (20w17a) where block state indices no longer span multiple longs
+const NON_SPANNING_DATA_VERSION: i32 = 2529;
+/// First data version (1.13) with block state palettes
+const PALETTE_DATA_VERSION: i32 = 1451;
+
+// Minecraft sections are the same size as our chunks, so they can be converted one to one
+const _: () = assert!(CHUNK_SIZE == 16);
+
+/// Converts every section of a chunk column into chunk data.
+/// Sections without block states are returned as air, so they aren't filled in by the world generator.
+pub fn convert_chunk(
+    chunk: &NbtCompound,
+    resolver: &mut BlockStateResolver,
+) -> anyhow::Result<Vec<(ChunkPos, ChunkData)>> {
+    let data_version = chunk.get_int::<i32>("DataVersion").unwrap_or(0);
+    ensure!(
+        data_version >= PALETTE_DATA_VERSION,
+        "Chunks from before Minecraft 1.13 (data version {}) are not supported",
+        data_version
+    );
+
+    // Before 1.18 everything was nested in a "Level" compound, and the section fields had different names
+    let (level, sections_tag) = match chunk.get("Level") {
+        Some(NbtTag::Compound(level)) => (level, "Sections"),
+        _ => (chunk, "sections"),
+    };
+
+    let x = level.get_int::<i32>("xPos")?;
+    let z = level.get_int::<i32>("zPos")?;
+
+    let mut chunks = Vec::new();
+    for section in level.get_list(sections_tag)? {
+        let NbtTag::Compound(section) = section else {
+            bail!("Chunk section is not a compound");
+        };
+
+        let y = section.get_int::<i8>("Y")? as i32;
+        let (palette, data) = match section.get("block_states") {
+            Some(NbtTag::Compound(states)) => (states.get_list("palette"), states.get("data")),
+            _ => (section.get_list("Palette"), section.get("BlockStates")),
+        };
+
+        // Sections with only lighting data have no palette
+        let Ok(palette) = palette else {
+            chunks.push((ChunkPos::new(x, y, z), ChunkData::solid(Voxel::AIR)));
+            continue;
+        };
+
+        let data = match data {
+            Some(NbtTag::LongArray(data)) => data.as_slice(),
+            Some(_) => bail!("Block states of section {} are not a long array", y),
+            None => &[],
+        };
+
+        let data = convert_section(palette, data, data_version, resolver)
+            .with_context(|| format!("Failed to convert section {} of chunk {}, {}", y, x, z))?;
+        chunks.push((ChunkPos::new(x, y, z), data));
+    }
+
+    Ok(chunks)
+}
+
+fn convert_section(
+    palette: &[NbtTag],
+    data: &[i64],
+    data_version: i32,
+    resolver: &mut BlockStateResolver,
+) -> anyhow::Result<ChunkData> {
+    let palette = palette
+        .iter()
+        .map(|state| match state {
+            NbtTag::Compound(state) => resolver.resolve(state),
+            _ => bail!("Block state is not a compound"),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    match palette.as_slice() {
+        [] => bail!("Section has an empty palette"),
+        [voxel] => return Ok(ChunkData::solid(*voxel)),
+        _ => {}
+    }
+
+    let indices = unpack_block_states(data, palette.len(), data_version)?;
+    let voxels = indices
+        .iter()
+        .map(|index| {
+            palette
+                .get(*index as usize)
+                .copied()
+                .with_context(|| format!("Block state index {} is out of range", index))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Both formats store voxels in YZX order
+    Ok(ChunkData::from(voxels.as_slice()))
+}
+
+/// Unpacks the palette indices of a section.
+fn unpack_block_states(
+    data: &[i64],
+    palette_len: usize,
+    data_version: i32,
+) -> anyhow::Result<Vec<u16>> {
+    let bits = (usize::BITS - (palette_len - 1).leading_zeros()).max(4) as usize;
+    ensure!(bits <= 16, "Section palette is too large");
+    let mask = (1u64 << bits) - 1;
+
+    let spanning = data_version < NON_SPANNING_DATA_VERSION;
+    let expected_len = if spanning {
+        (CHUNK_VOLUME * bits).div_ceil(64)
+    } else {
+        CHUNK_VOLUME.div_ceil(64 / bits)
+    };
+    ensure!(
+        data.len() == expected_len,
+        "Expected {} longs of block states for {} bits per block, got {}",
+        expected_len,
+        bits,
+        data.len()
+    );
+
+    let indices = (0..CHUNK_VOLUME)
+        .map(|i| {
+            let value = if spanning {
+                let bit = i * bits;
+                let (word, offset) = (bit / 64, bit % 64);
+                let mut value = data[word] as u64 >> offset;
+                if offset + bits > 64 {
+                    value |= (data[word + 1] as u64) << (64 - offset);
+                }
+                value
+            } else {
+                let per_word = 64 / bits;
+                data[i / per_word] as u64 >> ((i % per_word) * bits)
+            };
+            (value & mask) as u16
+        })
+        .collect();
+
+    Ok(indices)
+}
+
+/// Converts every chunk in a region file. Chunks that fail to convert are logged and skipped.
+pub fn import_region(
+    path: &Path,
+    resolver: &mut BlockStateResolver,
+) -> anyhow::Result<Vec<(ChunkPos, ChunkData)>> {
+    let region = AnvilRegion::load(path)?;
+
+    let mut chunks = Vec::new();
+    for index in region.chunk_indices() {
+        let result = region.read_chunk(index).and_then(|chunk| match chunk {
+            Some(chunk) => convert_chunk(&chunk, resolver),
+            None => Ok(Vec::new()),
+        });
+
+        match result {
+            Ok(columns) => chunks.extend(columns),
+            Err(err) => log::warn!("Skipping chunk {} of {}: {:#}", index, path.display(), err),
+        }
+    }
+
+    Ok(chunks)
+}
+
+fn region_paths(region_dir: &Path) -> anyhow::Result<Vec<(IVec2, std::path::PathBuf)>> {
+    let mut paths = std::fs::read_dir(region_dir)
+        .with_context(|| format!("Failed to read region directory {}", region_dir.display()))?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            Some((parse_region_file_name(&path)?, path))
+        })
+        .collect::<Vec<_>>();
+    paths.sort_by_key(|(pos, _)| (pos.y, pos.x));
+    Ok(paths)
+}
+
+fn log_unknown_states(resolver: &BlockStateResolver) {
+    let mut unknown = resolver.unknown_states().collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        unknown.sort_unstable();
+        log::warn!("Blocks without a mapping: {}", unknown.join(", "));
+    }
+}
+
+/// Converts every region in a Minecraft world's `region` directory, e.g. for `World::from_chunks`.
+pub fn import_anvil_world(
+    region_dir: &Path,
+    mapping: &AnvilBlockMapping,
+    block_database: &BlockDatabase,
+) -> anyhow::Result<Vec<(ChunkPos, ChunkData)>> {
+    let mut resolver = mapping.resolve(block_database)?;
+
+    let mut chunks = Vec::new();
+    for (_, path) in region_paths(region_dir)? {
+        chunks.extend(import_region(&path, &mut resolver)?);
+    }
+
+    log_unknown_states(&resolver);
+    Ok(chunks)
+}
+
+/// Converts every region in a Minecraft world's `region` directory straight into world storage,
+/// one region at a time so large maps don't have to fit in memory. Returns the number of chunks written.
+pub fn import_anvil_world_to_storage(
+    region_dir: &Path,
+    mapping: &AnvilBlockMapping,
+    block_database: &BlockDatabase,
+    storage: &WorldStorage,
+) -> anyhow::Result<usize> {
+    let mut resolver = mapping.resolve(block_database)?;
+
+    let mut count = 0;
+    for (_, path) in region_paths(region_dir)? {
+        for (pos, data) in import_region(&path, &mut resolver)? {
+            storage.save_chunk(pos, &data)?;
+            count += 1;
+        }
+    }
+
+    storage.flush()?;
+    log_unknown_states(&resolver);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxels::coord::LocalPos;
+
+    fn block_state(name: &str, properties: &[(&str, &str)]) -> NbtTag {
+        let mut state = NbtCompound::new();
+        state.insert("Name", NbtTag::String(name.to_string()));
+        if !properties.is_empty() {
+            let mut props = NbtCompound::new();
+            for (key, value) in properties {
+                props.insert(*key, NbtTag::String(value.to_string()));
+            }
+            state.insert("Properties", NbtTag::Compound(props));
+        }
+        NbtTag::Compound(state)
+    }
+
+    fn test_resolver() -> BlockStateResolver {
+        let db = BlockDatabase::from_names(&["air", "stone", "log"]);
+
+        let mapping = AnvilBlockMapping {
+            blocks: [
+                ("minecraft:stone", "stone"),
+                ("minecraft:oak_log", "log"),
+                ("minecraft:oak_log[axis=x]", "log[meta=1]"),
+            ]
+            .into_iter()
+            .map(|(state, name)| (state.to_string(), name.to_string()))
+            .collect(),
+            default: None,
+        };
+        mapping.resolve(&db).unwrap()
+    }
+
+    #[test]
+    fn test_unpack_block_states() {
+        // 5 bits per index: 12 indices per long without spanning, 64 longs exactly with spanning
+        let palette_len = 17;
+        let indices = (0..CHUNK_VOLUME)
+            .map(|i| (i % 17) as u64)
+            .collect::<Vec<_>>();
+
+        let mut non_spanning = vec![0i64; CHUNK_VOLUME.div_ceil(12)];
+        let mut spanning = vec![0u64; 5 * 64];
+        for (i, index) in indices.iter().enumerate() {
+            non_spanning[i / 12] |= (index << ((i % 12) * 5)) as i64;
+
+            let bit = i * 5;
+            spanning[bit / 64] |= index << (bit % 64);
+            if bit % 64 > 59 {
+                spanning[bit / 64 + 1] |= index >> (64 - bit % 64);
+            }
+        }
+        let spanning = spanning.iter().map(|v| *v as i64).collect::<Vec<_>>();
+
+        let expected = indices.iter().map(|i| *i as u16).collect::<Vec<_>>();
+        assert_eq!(
+            unpack_block_states(&non_spanning, palette_len, 3465).unwrap(),
+            expected
+        );
+        assert_eq!(
+            unpack_block_states(&spanning, palette_len, 1976).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_convert_chunk() {
+        let mut resolver = test_resolver();
+
+        // 4 bits per index, 16 indices per long. Put stone in the first column of the bottom row,
+        // and an x-axis log at the end of the section.
+        let mut data = vec![0i64; CHUNK_VOLUME / 16];
+        data[0] = 1;
+        data[CHUNK_VOLUME / 16 - 1] = 2 << 60;
+
+        let mut block_states = NbtCompound::new();
+        block_states.insert(
+            "palette",
+            NbtTag::List(vec![
+                block_state("minecraft:cave_air", &[]),
+                block_state("minecraft:stone", &[]),
+                block_state("minecraft:oak_log", &[("axis", "x")]),
+            ]),
+        );
+        block_states.insert("data", NbtTag::LongArray(data));
+
+        let mut mixed = NbtCompound::new();
+        mixed.insert("Y", NbtTag::Byte(-1));
+        mixed.insert("block_states", NbtTag::Compound(block_states));
+
+        let mut solid_states = NbtCompound::new();
+        solid_states.insert(
+            "palette",
+            NbtTag::List(vec![block_state("minecraft:oak_log", &[("axis", "y")])]),
+        );
+        let mut solid = NbtCompound::new();
+        solid.insert("Y", NbtTag::Byte(0));
+        solid.insert("block_states", NbtTag::Compound(solid_states));
+
+        let mut chunk = NbtCompound::new();
+        chunk.insert("DataVersion", NbtTag::Int(3465));
+        chunk.insert("xPos", NbtTag::Int(3));
+        chunk.insert("zPos", NbtTag::Int(-2));
+        chunk.insert(
+            "sections",
+            NbtTag::List(vec![NbtTag::Compound(mixed), NbtTag::Compound(solid)]),
+        );
+
+        let chunks = convert_chunk(&chunk, &mut resolver).unwrap();
+        assert_eq!(chunks.len(), 2);
+
+        let (pos, data) = &chunks[0];
+        assert_eq!(*pos, ChunkPos::new(3, -1, -2));
+        assert_eq!(
+            data.get_voxel(LocalPos::new(0, 0, 0)),
+            Some(Voxel::from_type(1))
+        );
+        assert_eq!(data.get_voxel(LocalPos::new(1, 0, 0)), Some(Voxel::AIR));
+        assert_eq!(
+            data.get_voxel(LocalPos::new(15, 15, 15)),
+            Some(Voxel::from_type_metadata(2, 1))
+        );
+
+        let (pos, data) = &chunks[1];
+        assert_eq!(*pos, ChunkPos::new(3, 0, -2));
+        assert!(matches!(data, ChunkData::Solid(voxel) if *voxel == Voxel::from_type(2)));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod block_mapping;
+pub mod import;
+pub mod region;
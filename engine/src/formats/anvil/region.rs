@@ -0,0 +1,183 @@
+//! Reading Minecraft's Anvil region files (`r.<x>.<z>.mca`).
+//! See https://minecraft.wiki/w/Region_file_format
+
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail, ensure};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use glam::IVec2;
+
+use crate::formats::nbt::{NbtCompound, read_nbt};
+
+/// Number of chunk columns per region along X and Z
+pub const ANVIL_REGION_SIZE: i32 = 32;
+pub const ANVIL_REGION_CHUNKS: usize = (ANVIL_REGION_SIZE * ANVIL_REGION_SIZE) as usize;
+
+const SECTOR_SIZE: usize = 4096;
+// Chunk locations followed by timestamps, one sector each
+const HEADER_SIZE: usize = SECTOR_SIZE * 2;
+
+const COMPRESSION_GZIP: u8 = 1;
+const COMPRESSION_ZLIB: u8 = 2;
+const COMPRESSION_NONE: u8 = 3;
+/// Set when the chunk was too large for the region and is stored in a separate `c.<x>.<z>.mcc` file
+const COMPRESSION_EXTERNAL_FLAG: u8 = 0x80;
+
+/// An Anvil region file, read fully into memory.
+pub struct AnvilRegion {
+    /// Region coordinates on the X and Z axes
+    pub position: IVec2,
+    /// Directory containing the region, used to find external chunk files
+    directory: Option<PathBuf>,
+    bytes: Vec<u8>,
+}
+
+impl AnvilRegion {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let position = parse_region_file_name(path)
+            .with_context(|| format!("{} is not named like a region file", path.display()))?;
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read region file {}", path.display()))?;
+
+        let mut region = Self::parse(position, bytes)?;
+        region.directory = path.parent().map(Path::to_path_buf);
+        Ok(region)
+    }
+
+    pub fn parse(position: IVec2, bytes: Vec<u8>) -> anyhow::Result<Self> {
+        // Files of regions without any chunks may be completely empty
+        ensure!(
+            bytes.is_empty() || bytes.len() >= HEADER_SIZE,
+            "Region file is too short to contain a header"
+        );
+
+        Ok(AnvilRegion {
+            position,
+            directory: None,
+            bytes,
+        })
+    }
+
+    /// Returns the (offset, size) of a chunk's data in bytes, or None if the chunk hasn't been generated.
+    fn chunk_location(&self, index: usize) -> Option<(usize, usize)> {
+        let entry = self.bytes.get(index * 4..index * 4 + 4)?;
+        let offset = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]) as usize;
+        let sectors = entry[3] as usize;
+
+        (offset != 0 && sectors != 0).then_some((offset * SECTOR_SIZE, sectors * SECTOR_SIZE))
+    }
+
+    /// Indices of all chunk columns stored in the region. The index of a column is `x + z * 32`, in local coordinates.
+    pub fn chunk_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..ANVIL_REGION_CHUNKS).filter(|index| self.chunk_location(*index).is_some())
+    }
+
+    /// Reads and decompresses the NBT data of a chunk column. Returns None if the chunk hasn't been generated.
+    pub fn read_chunk(&self, index: usize) -> anyhow::Result<Option<NbtCompound>> {
+        let Some((offset, _)) = self.chunk_location(index) else {
+            return Ok(None);
+        };
+
+        let header = self
+            .bytes
+            .get(offset..offset + 5)
+            .context("Chunk offset is past the end of the region file")?;
+        let length = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let compression = header[4];
+
+        let external_data;
+        let data = if compression & COMPRESSION_EXTERNAL_FLAG != 0 {
+            external_data = self.read_external_chunk(index)?;
+            external_data.as_slice()
+        } else {
+            ensure!(length >= 1, "Chunk {} has an invalid length", index);
+            self.bytes
+                .get(offset + 5..offset + 4 + length)
+                .with_context(|| format!("Chunk {} extends past the end of the region", index))?
+        };
+
+        let mut decompressed = Vec::new();
+        match compression & !COMPRESSION_EXTERNAL_FLAG {
+            COMPRESSION_GZIP => {
+                GzDecoder::new(data).read_to_end(&mut decompressed)?;
+            }
+            COMPRESSION_ZLIB => {
+                ZlibDecoder::new(data).read_to_end(&mut decompressed)?;
+            }
+            COMPRESSION_NONE => decompressed.extend_from_slice(data),
+            other => bail!(
+                "Chunk {} uses unsupported compression type {}",
+                index,
+                other
+            ),
+        }
+
+        let (_, chunk) = read_nbt(&decompressed)?;
+        Ok(Some(chunk))
+    }
+
+    fn read_external_chunk(&self, index: usize) -> anyhow::Result<Vec<u8>> {
+        let directory = self
+            .directory
+            .as_ref()
+            .context("External chunks can only be read from regions loaded from disk")?;
+
+        let x = self.position.x * ANVIL_REGION_SIZE + (index as i32 % ANVIL_REGION_SIZE);
+        let z = self.position.y * ANVIL_REGION_SIZE + (index as i32 / ANVIL_REGION_SIZE);
+        let path = directory.join(format!("c.{}.{}.mcc", x, z));
+        std::fs::read(&path)
+            .with_context(|| format!("Failed to read external chunk {}", path.display()))
+    }
+}
+
+/// Parses the region coordinates from a file name like `r.-1.2.mca`.
+pub fn parse_region_file_name(path: &Path) -> Option<IVec2> {
+    let name = path.file_name()?.to_str()?;
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    parts.next().is_none().then_some(IVec2::new(x, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::ZlibEncoder};
+
+    use super::*;
+    use crate::formats::nbt::{NbtTag, write_nbt};
+
+    #[test]
+    fn test_read_zlib_chunk() {
+        let mut chunk = NbtCompound::new();
+        chunk.insert("xPos", NbtTag::Int(33));
+
+        let mut nbt = Vec::new();
+        write_nbt("", &chunk, &mut nbt);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&nbt).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // Chunk at local (1, 0) stored in the first sector after the header
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        bytes[4..8].copy_from_slice(&[0, 0, 2, 1]);
+        bytes.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+        bytes.push(COMPRESSION_ZLIB);
+        bytes.extend_from_slice(&compressed);
+        bytes.resize(HEADER_SIZE + SECTOR_SIZE, 0);
+
+        let region = AnvilRegion::parse(IVec2::new(1, 0), bytes).unwrap();
+        assert_eq!(region.chunk_indices().collect::<Vec<_>>(), [1]);
+        assert_eq!(region.read_chunk(1).unwrap(), Some(chunk));
+        assert_eq!(region.read_chunk(0).unwrap(), None);
+
+        assert_eq!(
+            parse_region_file_name(Path::new("world/region/r.-1.2.mca")),
+            Some(IVec2::new(-1, 2))
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use glam::{UVec2, Vec2};
+use image::{RgbaImage, imageops::FilterType};
+
+use crate::{assets::world_textures::WorldTextures, worldgen::Climate};
+
+/// All world textures packed into a single image, laid out in a grid in texture index order.
+/// Textures smaller than the largest one are scaled up with nearest neighbour filtering.
+/// Tinted textures are baked with the temperate tint, since exported meshes don't carry the climate.
+pub struct TextureAtlas {
+    pub image: RgbaImage,
+    pub tile_size: UVec2,
+    columns: u32,
+}
+
+impl TextureAtlas {
+    pub fn from_world_textures(world_textures: &WorldTextures) -> Self {
+        let tile_size = world_textures
+            .textures
+            .iter()
+            .map(|texture| UVec2::from(texture.data.dimensions()))
+            .fold(UVec2::ONE, UVec2::max);
+
+        let count = world_textures.textures.len().max(1) as u32;
+        // Aim for a roughly square atlas
+        let columns = (count as f32).sqrt().ceil() as u32;
+        let rows = count.div_ceil(columns);
+
+        let mut image = RgbaImage::new(columns * tile_size.x, rows * tile_size.y);
+        for (index, texture) in world_textures.textures.iter().enumerate() {
+            let mut tile = if UVec2::from(texture.data.dimensions()) == tile_size {
+                texture.data.clone()
+            } else {
+                image::imageops::resize(
+                    &texture.data,
+                    tile_size.x,
+                    tile_size.y,
+                    FilterType::Nearest,
+                )
+            };
+            if texture.tinted {
+                let tint = Climate::TEMPERATE.tint();
+                for pixel in tile.pixels_mut() {
+                    for (channel, tint) in pixel.0.iter_mut().zip(tint.to_array()) {
+                        *channel = (*channel as f32 * tint).round() as u8;
+                    }
+                }
+            }
+
+            let origin = Self::tile_origin(columns, index as u16) * tile_size;
+            image::imageops::replace(&mut image, &tile, origin.x as i64, origin.y as i64);
+        }
+
+        TextureAtlas {
+            image,
+            tile_size,
+            columns,
+        }
+    }
+
+    fn tile_origin(columns: u32, texture_index: u16) -> UVec2 {
+        let index = texture_index as u32;
+        UVec2::new(index % columns, index / columns)
+    }
+
+    /// Maps a UV within a single texture (0-1, Y down) to a UV within the atlas.
+    pub fn map_uv(&self, texture_index: u16, uv: Vec2) -> Vec2 {
+        let atlas_size = UVec2::new(self.image.width(), self.image.height()).as_vec2();
+        let origin = (Self::tile_origin(self.columns, texture_index) * self.tile_size).as_vec2();
+        (origin + uv * self.tile_size.as_vec2()) / atlas_size
+    }
+}
@@ -0,0 +1,347 @@
+use glam::{IVec3, U8Vec2, Vec2, Vec3};
+
+use crate::{
+    assets::block_models::{CrossDiagonal, MODEL_GRID_SIZE},
+    formats::mesh_export::atlas::TextureAtlas,
+    mesh_generation::chunk_mesh::{ChunkMeshData, FaceShape, PackedVoxelFace, VoxelFace},
+    voxels::{chunk::CHUNK_SIZE, face::Face},
+};
+
+// Same ambient occlusion curve as world_geo_draw.wesl
+const AO_TO_FACTOR: [f32; 4] = [0.0, 0.5, 0.75, 1.0];
+const MAX_AO_DARKENING: f32 = 0.8;
+
+/// Triangle list with the vertex attributes needed by DCC tools.
+#[derive(Debug, Default, Clone)]
+pub struct ExportPrimitive {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    /// UVs within the texture atlas, with (0, 0) at the top left
+    pub uvs: Vec<Vec2>,
+    /// Brightness from ambient occlusion and light, 0-1
+    pub colors: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl ExportPrimitive {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns the minimum and maximum vertex positions, or None if the primitive is empty.
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let first = *self.positions.first()?;
+        Some(
+            self.positions
+                .iter()
+                .fold((first, first), |(min, max), p| (min.min(*p), max.max(*p))),
+        )
+    }
+}
+
+/// Meshes of a set of chunks, ready to be written to a file.
+///
+/// Positions are converted from the engine's left-handed coordinates to the right-handed,
+/// Y-up coordinates used by glTF and OBJ by negating Z.
+#[derive(Debug, Default, Clone)]
+pub struct ExportMesh {
+    pub opaque: ExportPrimitive,
+    pub alpha_cutout: ExportPrimitive,
+    pub alpha_blend: ExportPrimitive,
+}
+
+impl ExportMesh {
+    /// Unpacks chunk meshes into triangles. `origin` is subtracted from every position.
+    ///
+    /// Greedy meshing merges faces that repeat the same texture, which an atlas can't represent.
+    /// Merged faces are split back into one quad per voxel.
+    pub fn from_chunk_meshes<'a>(
+        meshes: impl IntoIterator<Item = &'a ChunkMeshData>,
+        atlas: &TextureAtlas,
+        origin: IVec3,
+    ) -> Self {
+        let mut mesh = ExportMesh::default();
+        for chunk_mesh in meshes {
+            let chunk_origin = chunk_mesh.position.0 * CHUNK_SIZE as i32 - origin;
+            for face in &chunk_mesh.opaque_faces {
+                add_face(&mut mesh.opaque, face, chunk_origin, atlas);
+            }
+            for face in &chunk_mesh.alpha_cutout_faces {
+                add_face(&mut mesh.alpha_cutout, face, chunk_origin, atlas);
+            }
+            for face in &chunk_mesh.alpha_blend_faces {
+                add_face(&mut mesh.alpha_blend, face, chunk_origin, atlas);
+            }
+        }
+        mesh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.opaque.is_empty() && self.alpha_cutout.is_empty() && self.alpha_blend.is_empty()
+    }
+}
+
+/// Tangent and bitangent of each face, matching world_geo_draw.wesl
+fn face_axes(face: Face) -> (Vec3, Vec3) {
+    match face {
+        Face::Top | Face::Bottom => (Vec3::Z, Vec3::X),
+        Face::Left | Face::Right => (Vec3::Z, Vec3::Y),
+        Face::Front | Face::Back => (Vec3::X, Vec3::Y),
+    }
+}
+
+/// Corner index of each quad vertex, as selected in world_geo_draw.wesl
+fn vertex_corner(vertex_index: usize, face: &VoxelFace) -> usize {
+    let base = (vertex_index + face.flip_diagonal as usize) % 4;
+    let swap_winding = matches!(face.face_direction, Face::Top | Face::Left | Face::Front);
+    match base {
+        1 if swap_winding => 3,
+        3 if swap_winding => 1,
+        _ => base,
+    }
+}
+
+const QUAD_CORNERS: [Vec2; 4] = [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(1.0, 0.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(0.0, 1.0),
+];
+
+// Same index order as the quad index buffer in the world geometry pass
+const QUAD_INDICES: [usize; 6] = [0, 3, 1, 3, 2, 1];
+
+fn to_export_space(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, v.y, -v.z)
+}
+
+fn add_face(
+    primitive: &mut ExportPrimitive,
+    packed: &PackedVoxelFace,
+    chunk_origin: IVec3,
+    atlas: &TextureAtlas,
+) {
+    let face = packed.unpack();
+    if let Some(shape) = face.shape {
+        add_shaped_face(primitive, &face, shape, chunk_origin, atlas);
+        return;
+    }
+
+    let normal = face.face_direction.to_ivec3().as_vec3();
+    let (tangent, bitangent) = face_axes(face.face_direction);
+    let base = (chunk_origin + face.position.as_ivec3()).as_vec3() + normal.max(Vec3::ZERO);
+    let export_normal = to_export_space(normal);
+
+    let corners = std::array::from_fn::<_, 4, _>(|vertex| vertex_corner(vertex, &face));
+    let U8Vec2 {
+        x: width,
+        y: height,
+    } = face.size;
+
+    for tile_y in 0..height {
+        for tile_x in 0..width {
+            let first_vertex = primitive.positions.len() as u32;
+            let tile = Vec2::new(tile_x as f32, tile_y as f32);
+
+            for corner in corners {
+                let corner_uv = QUAD_CORNERS[corner];
+                let local = tangent * (tile.x + corner_uv.x) + bitangent * (tile.y + corner_uv.y);
+                primitive.positions.push(to_export_space(base + local));
+                primitive.normals.push(export_normal);
+
+                // Texture space Y points down
+                let uv = Vec2::new(corner_uv.x, 1.0 - corner_uv.y);
+                let uv = orient_uv(uv, &face);
+                primitive.uvs.push(atlas.map_uv(face.texture_index, uv));
+
+                // Every voxel in a merged face has the same ambient occlusion and light
+                let ao = AO_TO_FACTOR[face.ambient_occlusion[corner] as usize];
+                let light = face.light[corner].brightness();
+                primitive
+                    .colors
+                    .push((1.0 - MAX_AO_DARKENING * ao * ao) * light);
+            }
+
+            add_quad_indices(primitive, first_vertex, export_normal);
+        }
+    }
+}
+
+/// Adds a single quad for a block model face, which covers only part of a voxel.
+fn add_shaped_face(
+    primitive: &mut ExportPrimitive,
+    face: &VoxelFace,
+    shape: FaceShape,
+    chunk_origin: IVec3,
+    atlas: &TextureAtlas,
+) {
+    let grid = MODEL_GRID_SIZE as f32;
+    let voxel_origin = (chunk_origin + face.position.as_ivec3()).as_vec3();
+    let size = face.size.as_vec2() / grid;
+
+    // Origin, tangent and bitangent span the whole quad
+    let (origin, tangent, bitangent, normal, uv_offset) = match shape {
+        FaceShape::Inset { offset, inset } => {
+            let normal = face.face_direction.to_ivec3().as_vec3();
+            let (tangent, bitangent) = face_axes(face.face_direction);
+            let offset = offset.as_vec2() / grid;
+            let origin = voxel_origin + normal.max(Vec3::ZERO) - normal * (inset as f32 / grid)
+                + tangent * offset.x
+                + bitangent * offset.y;
+            (origin, tangent * size.x, bitangent * size.y, normal, offset)
+        }
+        FaceShape::Diagonal(diagonal) => {
+            let (origin, tangent) = match diagonal {
+                CrossDiagonal::Rising => (Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0)),
+                CrossDiagonal::Falling => (Vec3::Z, Vec3::new(1.0, 0.0, -1.0)),
+            };
+            let normal = tangent.cross(Vec3::Y).normalize();
+            (voxel_origin + origin, tangent, Vec3::Y, normal, Vec2::ZERO)
+        }
+        FaceShape::Sloped { .. } => {
+            let normal = face.face_direction.to_ivec3().as_vec3();
+            let (tangent, bitangent) = face_axes(face.face_direction);
+            let origin = voxel_origin + normal.max(Vec3::ZERO);
+            (origin, tangent, bitangent, normal, Vec2::ZERO)
+        }
+    };
+    let drops = match shape {
+        FaceShape::Sloped { drops } => drops.map(|drop| drop as f32 / grid),
+        _ => [0.0; 4],
+    };
+
+    let export_normal = to_export_space(normal);
+    let first_vertex = primitive.positions.len() as u32;
+    for vertex in 0..4 {
+        let corner = vertex_corner(vertex, face);
+        let corner_uv = QUAD_CORNERS[corner];
+        // Sloped faces lower their corners, and crop the texture to match
+        let drop = drops[corner];
+        let local = tangent * corner_uv.x + bitangent * corner_uv.y - Vec3::Y * drop;
+        primitive.positions.push(to_export_space(origin + local));
+        primitive.normals.push(export_normal);
+
+        // Texture space Y points down
+        let mut uv = uv_offset + corner_uv * size;
+        uv.y -= bitangent.y * drop;
+        primitive.uvs.push(atlas.map_uv(
+            face.texture_index,
+            orient_uv(Vec2::new(uv.x, 1.0 - uv.y), face),
+        ));
+
+        // Shaped faces have no ambient occlusion
+        primitive.colors.push(face.light[0].brightness());
+    }
+
+    add_quad_indices(primitive, first_vertex, export_normal);
+}
+
+/// Mirrors and turns texture coordinates within a tile around its center,
+/// see `VoxelFace::texture_mirrored` and `VoxelFace::texture_rotation`.
+fn orient_uv(uv: Vec2, face: &VoxelFace) -> Vec2 {
+    let uv = if face.texture_mirrored {
+        Vec2::new(1.0 - uv.x, uv.y)
+    } else {
+        uv
+    };
+    match face.texture_rotation % 4 {
+        1 => Vec2::new(1.0 - uv.y, uv.x),
+        2 => Vec2::new(1.0 - uv.x, 1.0 - uv.y),
+        3 => Vec2::new(uv.y, 1.0 - uv.x),
+        _ => uv,
+    }
+}
+
+/// Adds the two triangles of the quad starting at `first_vertex`.
+fn add_quad_indices(primitive: &mut ExportPrimitive, first_vertex: u32, export_normal: Vec3) {
+    // Converting to right-handed coordinates mirrors the winding, so pick the order that faces outwards
+    let p = &primitive.positions[first_vertex as usize..];
+    let triangle_normal =
+        (p[QUAD_INDICES[1]] - p[QUAD_INDICES[0]]).cross(p[QUAD_INDICES[2]] - p[QUAD_INDICES[0]]);
+    let reverse = triangle_normal.dot(export_normal) < 0.0;
+
+    for triangle in QUAD_INDICES.chunks_exact(3) {
+        let triangle = if reverse {
+            [triangle[0], triangle[2], triangle[1]]
+        } else {
+            [triangle[0], triangle[1], triangle[2]]
+        };
+        primitive
+            .indices
+            .extend(triangle.map(|index| first_vertex + index as u32));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::U8Vec3;
+
+    use super::*;
+    use crate::{
+        assets::world_textures::WorldTextures,
+        lighting::chunk_light::{MAX_LIGHT, PackedLight},
+        voxels::coord::ChunkPos,
+    };
+
+    fn mesh_with_face(face_direction: Face, size: U8Vec2) -> ChunkMeshData {
+        let mut mesh = ChunkMeshData::from_position(ChunkPos::new(1, 0, 0));
+        mesh.opaque_faces.push(PackedVoxelFace::from(VoxelFace {
+            position: U8Vec3::new(2, 3, 4),
+            face_direction,
+            size,
+            ambient_occlusion: [0, 1, 2, 3],
+            light: [PackedLight::new(MAX_LIGHT, 0); 4],
+            flip_diagonal: true,
+            texture_index: 0,
+            texture_rotation: 0,
+            texture_mirrored: false,
+            full_bright: false,
+            shape: None,
+        }));
+        mesh
+    }
+
+    #[test]
+    fn test_faces_are_split_and_face_outwards() {
+        let atlas = TextureAtlas::from_world_textures(&WorldTextures::new());
+
+        for face in Face::all() {
+            let chunk_mesh = mesh_with_face(face, U8Vec2::new(3, 2));
+            let mesh = ExportMesh::from_chunk_meshes([&chunk_mesh], &atlas, IVec3::ZERO);
+            let primitive = &mesh.opaque;
+
+            // One quad per voxel
+            assert_eq!(primitive.vertex_count(), 6 * 4);
+            assert_eq!(primitive.indices.len(), 6 * 6);
+
+            let expected_normal = to_export_space(face.to_ivec3().as_vec3());
+            for triangle in primitive.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| primitive.positions[triangle[i] as usize]);
+                let normal = (b - a).cross(c - a).normalize();
+                assert!(
+                    normal.abs_diff_eq(expected_normal, 1e-5),
+                    "{:?} has a triangle facing {}",
+                    face,
+                    normal
+                );
+            }
+
+            // Every vertex lies on the plane of the face
+            let plane = to_export_space(
+                IVec3::new(18, 3, 4).as_vec3() + face.to_ivec3().as_vec3().max(Vec3::ZERO),
+            );
+            for position in &primitive.positions {
+                assert_eq!(
+                    (*position - plane).dot(expected_normal),
+                    0.0,
+                    "{:?} has a vertex off its plane",
+                    face
+                );
+            }
+        }
+    }
+}
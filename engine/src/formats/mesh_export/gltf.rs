@@ -0,0 +1,291 @@
+//! Writing binary glTF 2.0 (`.glb`) files.
+//! See https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html
+
+use std::{io::Cursor, path::Path};
+
+use anyhow::Context;
+use glam::{Vec2, Vec3};
+use serde_json::{Value, json};
+
+use crate::formats::mesh_export::{
+    atlas::TextureAtlas,
+    geometry::{ExportMesh, ExportPrimitive},
+};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const FILTER_NEAREST: u32 = 9728;
+const WRAP_CLAMP_TO_EDGE: u32 = 33071;
+
+/// Accumulates the binary buffer along with the buffer views and accessors describing it
+#[derive(Default)]
+struct GlbBuilder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GlbBuilder {
+    fn add_buffer_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(bytes);
+        self.buffer.resize(self.buffer.len().next_multiple_of(4), 0);
+
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    fn add_accessor(
+        &mut self,
+        bytes: &[u8],
+        target: u32,
+        component_type: u32,
+        count: usize,
+        accessor_type: &str,
+    ) -> usize {
+        let view = self.add_buffer_view(bytes, Some(target));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": component_type,
+            "count": count,
+            "type": accessor_type,
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn add_primitive(&mut self, primitive: &ExportPrimitive, material: usize) -> Value {
+        let vec3s = |values: &[Vec3]| {
+            values
+                .iter()
+                .flat_map(|v| v.to_array())
+                .flat_map(f32::to_le_bytes)
+                .collect::<Vec<_>>()
+        };
+        let vec2s = |values: &[Vec2]| {
+            values
+                .iter()
+                .flat_map(|v| v.to_array())
+                .flat_map(f32::to_le_bytes)
+                .collect::<Vec<_>>()
+        };
+
+        let count = primitive.vertex_count();
+        let position = self.add_accessor(
+            &vec3s(&primitive.positions),
+            TARGET_ARRAY_BUFFER,
+            COMPONENT_FLOAT,
+            count,
+            "VEC3",
+        );
+        // Positions are the only attribute that requires bounds
+        if let Some((min, max)) = primitive.bounds() {
+            self.accessors[position]["min"] = json!(min.to_array());
+            self.accessors[position]["max"] = json!(max.to_array());
+        }
+
+        let normal = self.add_accessor(
+            &vec3s(&primitive.normals),
+            TARGET_ARRAY_BUFFER,
+            COMPONENT_FLOAT,
+            count,
+            "VEC3",
+        );
+        let uv = self.add_accessor(
+            &vec2s(&primitive.uvs),
+            TARGET_ARRAY_BUFFER,
+            COMPONENT_FLOAT,
+            count,
+            "VEC2",
+        );
+        let colors = primitive
+            .colors
+            .iter()
+            .map(|c| Vec3::splat(*c))
+            .collect::<Vec<_>>();
+        let color = self.add_accessor(
+            &vec3s(&colors),
+            TARGET_ARRAY_BUFFER,
+            COMPONENT_FLOAT,
+            count,
+            "VEC3",
+        );
+        let indices = self.add_accessor(
+            bytemuck::cast_slice(&primitive.indices),
+            TARGET_ELEMENT_ARRAY_BUFFER,
+            COMPONENT_UNSIGNED_INT,
+            primitive.indices.len(),
+            "SCALAR",
+        );
+
+        json!({
+            "attributes": {
+                "POSITION": position,
+                "NORMAL": normal,
+                "TEXCOORD_0": uv,
+                "COLOR_0": color,
+            },
+            "indices": indices,
+            "material": material,
+        })
+    }
+}
+
+/// Serializes the mesh as a binary glTF file with the texture atlas embedded.
+pub fn write_glb(mesh: &ExportMesh, atlas: &TextureAtlas) -> anyhow::Result<Vec<u8>> {
+    let mut builder = GlbBuilder::default();
+
+    let mut primitives = Vec::new();
+    if !mesh.opaque.is_empty() {
+        primitives.push(builder.add_primitive(&mesh.opaque, 0));
+    }
+    if !mesh.alpha_cutout.is_empty() {
+        primitives.push(builder.add_primitive(&mesh.alpha_cutout, 1));
+    }
+    if !mesh.alpha_blend.is_empty() {
+        primitives.push(builder.add_primitive(&mesh.alpha_blend, 2));
+    }
+
+    let mut png = Vec::new();
+    atlas
+        .image
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode texture atlas")?;
+    let image_view = builder.add_buffer_view(&png, None);
+
+    let base_color = json!({
+        "baseColorTexture": { "index": 0 },
+        "metallicFactor": 0.0,
+        "roughnessFactor": 1.0,
+    });
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "voxel-engine" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "world" }],
+        "meshes": [{ "primitives": primitives }],
+        "materials": [
+            { "name": "opaque", "pbrMetallicRoughness": base_color },
+            {
+                "name": "alpha_cutout",
+                "pbrMetallicRoughness": base_color,
+                "alphaMode": "MASK",
+                "alphaCutoff": 0.5,
+                "doubleSided": true,
+            },
+            {
+                "name": "alpha_blend",
+                "pbrMetallicRoughness": base_color,
+                "alphaMode": "BLEND",
+                "doubleSided": true,
+            },
+        ],
+        "textures": [{ "source": 0, "sampler": 0 }],
+        "samplers": [{
+            "magFilter": FILTER_NEAREST,
+            "minFilter": FILTER_NEAREST,
+            "wrapS": WRAP_CLAMP_TO_EDGE,
+            "wrapT": WRAP_CLAMP_TO_EDGE,
+        }],
+        "images": [{ "bufferView": image_view, "mimeType": "image/png" }],
+        "buffers": [{ "byteLength": builder.buffer.len() }],
+        "bufferViews": builder.buffer_views,
+        "accessors": builder.accessors,
+    });
+
+    let mut json = serde_json::to_vec(&document)?;
+    // Chunks must be 4-byte aligned, the JSON chunk is padded with spaces
+    json.resize(json.len().next_multiple_of(4), b' ');
+
+    let total_length = 12 + 8 + json.len() + 8 + builder.buffer.len();
+    let mut out = Vec::with_capacity(total_length);
+    out.extend_from_slice(GLB_MAGIC);
+    out.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    out.extend_from_slice(&(total_length as u32).to_le_bytes());
+
+    out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    out.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+    out.extend_from_slice(&json);
+
+    out.extend_from_slice(&(builder.buffer.len() as u32).to_le_bytes());
+    out.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+    out.extend_from_slice(&builder.buffer);
+
+    Ok(out)
+}
+
+pub fn save_glb(mesh: &ExportMesh, atlas: &TextureAtlas, path: &Path) -> anyhow::Result<()> {
+    std::fs::write(path, write_glb(mesh, atlas)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+    use crate::{
+        assets::world_textures::WorldTextures,
+        lighting::chunk_light::{MAX_LIGHT, PackedLight},
+        mesh_generation::chunk_mesh::{ChunkMeshData, PackedVoxelFace, VoxelFace},
+        voxels::{coord::ChunkPos, face::Face},
+    };
+
+    #[test]
+    fn test_glb_layout() {
+        let mut chunk_mesh = ChunkMeshData::from_position(ChunkPos::new(0, 0, 0));
+        chunk_mesh
+            .alpha_cutout_faces
+            .push(PackedVoxelFace::from(VoxelFace {
+                position: glam::U8Vec3::ZERO,
+                face_direction: Face::Top,
+                size: glam::U8Vec2::ONE,
+                ambient_occlusion: [0; 4],
+                light: [PackedLight::new(MAX_LIGHT, 0); 4],
+                flip_diagonal: false,
+                texture_index: 0,
+                texture_rotation: 0,
+                texture_mirrored: false,
+                full_bright: false,
+                shape: None,
+            }));
+
+        let atlas = TextureAtlas::from_world_textures(&WorldTextures::new());
+        let mesh = ExportMesh::from_chunk_meshes([&chunk_mesh], &atlas, IVec3::ZERO);
+        let bytes = write_glb(&mesh, &atlas).unwrap();
+
+        assert_eq!(&bytes[0..4], GLB_MAGIC);
+        let total_length = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        assert_eq!(total_length, bytes.len());
+
+        let json_length = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        assert_eq!(json_length % 4, 0);
+        let document: Value = serde_json::from_slice(&bytes[20..20 + json_length]).unwrap();
+
+        // Only the cutout primitive has faces
+        let primitives = document["meshes"][0]["primitives"].as_array().unwrap();
+        assert_eq!(primitives.len(), 1);
+        assert_eq!(primitives[0]["material"], 1);
+
+        let position = &document["accessors"]
+            [primitives[0]["attributes"]["POSITION"].as_u64().unwrap() as usize];
+        assert_eq!(position["count"], 4);
+        assert_eq!(position["max"], json!([1.0, 1.0, 0.0]));
+    }
+}
@@ -0,0 +1,153 @@
+//! Exporting world geometry for use in DCC tools like Blender.
+//! Runs the same greedy mesher as the renderer, but doesn't need a GPU.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::bail;
+use rayon::prelude::*;
+
+use crate::{
+    assets::blocks::{BlockDatabase, BlockDatabaseSlim, SharedBlockDatabase},
+    editing::shape::WorldBox,
+    formats::mesh_export::{
+        atlas::TextureAtlas, geometry::ExportMesh, gltf::save_glb, obj::write_obj,
+    },
+    lighting::light_engine::LightEngine,
+    mesh_generation::{
+        chunk_mesh::ChunkMeshData, chunk_mesh_generator_input::ChunkMeshGeneratorInput,
+        greedy_mesher::GreedyMesher,
+    },
+    persistence::world_storage::WorldStorage,
+    voxels::{
+        chunk::{CHUNK_SIZE, Chunk, ChunkData},
+        coord::ChunkPos,
+        face::Face,
+        voxel::Voxel,
+    },
+    world::WorldChunks,
+    worldgen::WorldGenerator,
+};
+
+pub mod atlas;
+pub mod geometry;
+pub mod gltf;
+pub mod obj;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshExportFormat {
+    Obj,
+    Glb,
+}
+
+impl MeshExportFormat {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("obj") => Ok(MeshExportFormat::Obj),
+            Some(ext) if ext.eq_ignore_ascii_case("glb") => Ok(MeshExportFormat::Glb),
+            _ => bail!(
+                "Unsupported mesh format for {}, expected .obj or .glb",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Meshes the chunks at `positions`. `chunks` should also contain their neighbours,
+/// missing neighbours are treated as air.
+pub fn mesh_chunks(
+    chunks: Vec<(ChunkPos, ChunkData)>,
+    positions: &[ChunkPos],
+    block_database: Arc<BlockDatabaseSlim>,
+) -> Vec<ChunkMeshData> {
+    let world: WorldChunks<()> = chunks
+        .into_iter()
+        .map(|(pos, data)| (pos, Chunk::from_data(pos, data)))
+        .collect();
+
+    for pos in positions {
+        for face in Face::all() {
+            let neighbor = pos.get_neighbor(face);
+            world
+                .entry(neighbor)
+                .or_insert_with(|| Chunk::from_data(neighbor, ChunkData::solid(Voxel::AIR)));
+        }
+    }
+
+    // Light from the top down, so fewer chunks start out assuming open sky above them
+    let light_engine = LightEngine::new(SharedBlockDatabase::new(block_database.clone()));
+    let mut lighting_order = world.iter().map(|chunk| *chunk.key()).collect::<Vec<_>>();
+    lighting_order.sort_by_key(|pos| std::cmp::Reverse(pos.y()));
+    for pos in lighting_order {
+        light_engine.light_new_chunk(&world, pos);
+    }
+
+    let mesher = GreedyMesher::new(block_database.clone());
+    positions
+        .par_iter()
+        .filter_map(|pos| {
+            match ChunkMeshGeneratorInput::try_from_map(&world, *pos, &block_database) {
+                Ok(input) => input.map(|input| mesher.generate_mesh(&input)),
+                Err(err) => {
+                    log::warn!("Failed to mesh chunk {:?}: {}", pos, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Loads the chunks needed to mesh `positions`, including their neighbours.
+/// Chunks that haven't been saved are generated.
+pub fn load_chunks_for_meshing(
+    storage: Option<&WorldStorage>,
+    generator: &dyn WorldGenerator,
+    positions: &[ChunkPos],
+) -> anyhow::Result<Vec<(ChunkPos, ChunkData)>> {
+    let mut required = positions.to_vec();
+    for pos in positions {
+        required.extend(Face::all().map(|face| pos.get_neighbor(face)));
+    }
+    required.sort_unstable_by_key(|pos| pos.0.to_array());
+    required.dedup();
+
+    required
+        .into_par_iter()
+        .map(|pos| {
+            let saved = match storage {
+                Some(storage) => storage.load_chunk(pos)?,
+                None => None,
+            };
+            let data = saved.unwrap_or_else(|| generator.generate_chunk(pos));
+            Ok((pos, data))
+        })
+        .collect()
+}
+
+/// Exports every chunk overlapping the region as an OBJ or binary glTF file, depending on the extension of `path`.
+/// Whole chunks are exported, with the minimum corner of the first chunk at the origin.
+pub fn export_region_mesh(
+    storage: Option<&WorldStorage>,
+    generator: &dyn WorldGenerator,
+    block_database: &BlockDatabase,
+    region: WorldBox,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let format = MeshExportFormat::from_path(path)?;
+
+    let positions = region.iter_chunk_positions().collect::<Vec<_>>();
+    let chunks = load_chunks_for_meshing(storage, generator, &positions)?;
+    let slim_database = Arc::new(BlockDatabaseSlim::from_block_database(block_database));
+    let meshes = mesh_chunks(chunks, &positions, slim_database);
+
+    let atlas = TextureAtlas::from_world_textures(&block_database.world_textures);
+    let origin = region.min.to_chunk_pos().0 * CHUNK_SIZE as i32;
+    let mesh = ExportMesh::from_chunk_meshes(&meshes, &atlas, origin);
+    if mesh.is_empty() {
+        log::warn!("Region {:?} has no visible faces", region);
+    }
+
+    match format {
+        MeshExportFormat::Obj => write_obj(&mesh, &atlas, path),
+        MeshExportFormat::Glb => save_glb(&mesh, &atlas, path),
+    }
+}
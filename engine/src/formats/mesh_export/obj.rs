@@ -0,0 +1,98 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+
+use crate::formats::mesh_export::{
+    atlas::TextureAtlas,
+    geometry::{ExportMesh, ExportPrimitive},
+};
+
+/// Writes the mesh as a Wavefront OBJ file, along with a `.mtl` material library and a `.png` texture atlas.
+/// Ambient occlusion is written as vertex colours, using the `v x y z r g b` extension supported by most tools.
+pub fn write_obj(mesh: &ExportMesh, atlas: &TextureAtlas, path: &Path) -> anyhow::Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let atlas_path = path.with_extension("png");
+    let file_name = |path: &Path| path.file_name().unwrap().to_string_lossy().into_owned();
+
+    atlas
+        .image
+        .save(&atlas_path)
+        .with_context(|| format!("Failed to write texture atlas {}", atlas_path.display()))?;
+
+    let atlas_name = file_name(&atlas_path);
+    let mut mtl = BufWriter::new(
+        File::create(&mtl_path)
+            .with_context(|| format!("Failed to create {}", mtl_path.display()))?,
+    );
+    writeln!(mtl, "newmtl opaque")?;
+    writeln!(mtl, "Kd 1 1 1")?;
+    writeln!(mtl, "map_Kd {}", atlas_name)?;
+    writeln!(mtl)?;
+    writeln!(mtl, "newmtl alpha_cutout")?;
+    writeln!(mtl, "Kd 1 1 1")?;
+    writeln!(mtl, "map_Kd {}", atlas_name)?;
+    writeln!(mtl, "map_d {}", atlas_name)?;
+    writeln!(mtl)?;
+    writeln!(mtl, "newmtl alpha_blend")?;
+    writeln!(mtl, "Kd 1 1 1")?;
+    writeln!(mtl, "map_Kd {}", atlas_name)?;
+    writeln!(mtl, "map_d {}", atlas_name)?;
+    mtl.flush()?;
+
+    let mut obj = BufWriter::new(
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+    );
+    writeln!(obj, "mtllib {}", file_name(&mtl_path))?;
+
+    let mut first_vertex = 1;
+    for (name, primitive) in [
+        ("opaque", &mesh.opaque),
+        ("alpha_cutout", &mesh.alpha_cutout),
+        ("alpha_blend", &mesh.alpha_blend),
+    ] {
+        if primitive.is_empty() {
+            continue;
+        }
+
+        writeln!(obj, "o {}", name)?;
+        writeln!(obj, "usemtl {}", name)?;
+        write_primitive(&mut obj, primitive, first_vertex)?;
+        first_vertex += primitive.vertex_count();
+    }
+
+    obj.flush()
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn write_primitive(
+    out: &mut impl Write,
+    primitive: &ExportPrimitive,
+    first_vertex: usize,
+) -> anyhow::Result<()> {
+    for (position, color) in primitive.positions.iter().zip(&primitive.colors) {
+        writeln!(
+            out,
+            "v {} {} {} {} {} {}",
+            position.x, position.y, position.z, color, color, color
+        )?;
+    }
+    for uv in &primitive.uvs {
+        // OBJ texture coordinates have their origin at the bottom left
+        writeln!(out, "vt {} {}", uv.x, 1.0 - uv.y)?;
+    }
+    for normal in &primitive.normals {
+        writeln!(out, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+    }
+
+    // Positions, UVs and normals share indices
+    for triangle in primitive.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize + first_vertex);
+        writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+
+    Ok(())
+}
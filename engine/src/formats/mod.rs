@@ -0,0 +1,6 @@
+pub mod anvil;
+pub mod mesh_export;
+pub mod nbt;
+pub mod schematic;
+pub mod vox;
+pub mod zip;
@@ -0,0 +1,362 @@
+//! Minimal reader and writer for Minecraft's Named Binary Tag format.
+//! See https://minecraft.wiki/w/NBT_format
+
+use std::{collections::BTreeMap, io::Read};
+
+use anyhow::{Context, bail};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+/// Guards against stack overflows from maliciously nested data
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtTag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// All elements should have the same type
+    List(Vec<NbtTag>),
+    Compound(NbtCompound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl NbtTag {
+    fn tag_type(&self) -> u8 {
+        match self {
+            NbtTag::Byte(_) => TAG_BYTE,
+            NbtTag::Short(_) => TAG_SHORT,
+            NbtTag::Int(_) => TAG_INT,
+            NbtTag::Long(_) => TAG_LONG,
+            NbtTag::Float(_) => TAG_FLOAT,
+            NbtTag::Double(_) => TAG_DOUBLE,
+            NbtTag::ByteArray(_) => TAG_BYTE_ARRAY,
+            NbtTag::String(_) => TAG_STRING,
+            NbtTag::List(_) => TAG_LIST,
+            NbtTag::Compound(_) => TAG_COMPOUND,
+            NbtTag::IntArray(_) => TAG_INT_ARRAY,
+            NbtTag::LongArray(_) => TAG_LONG_ARRAY,
+        }
+    }
+
+    /// Returns the value of any integer tag, widened to i64
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            NbtTag::Byte(v) => Some(*v as i64),
+            NbtTag::Short(v) => Some(*v as i64),
+            NbtTag::Int(v) => Some(*v as i64),
+            NbtTag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NbtCompound(pub BTreeMap<String, NbtTag>);
+
+impl NbtCompound {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NbtTag> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, tag: NbtTag) {
+        self.0.insert(name.into(), tag);
+    }
+
+    fn get_required(&self, name: &str) -> anyhow::Result<&NbtTag> {
+        self.get(name)
+            .with_context(|| format!("Missing NBT tag '{}'", name))
+    }
+
+    /// Returns the value of an integer tag of any width, checking that it fits in `T`.
+    pub fn get_int<T: TryFrom<i64>>(&self, name: &str) -> anyhow::Result<T> {
+        let value = self
+            .get_required(name)?
+            .as_i64()
+            .with_context(|| format!("NBT tag '{}' is not an integer", name))?;
+        T::try_from(value).map_err(|_| anyhow::anyhow!("NBT tag '{}' is out of range", name))
+    }
+
+    pub fn get_string(&self, name: &str) -> anyhow::Result<&str> {
+        match self.get_required(name)? {
+            NbtTag::String(value) => Ok(value),
+            _ => bail!("NBT tag '{}' is not a string", name),
+        }
+    }
+
+    pub fn get_compound(&self, name: &str) -> anyhow::Result<&NbtCompound> {
+        match self.get_required(name)? {
+            NbtTag::Compound(value) => Ok(value),
+            _ => bail!("NBT tag '{}' is not a compound", name),
+        }
+    }
+
+    pub fn get_list(&self, name: &str) -> anyhow::Result<&[NbtTag]> {
+        match self.get_required(name)? {
+            NbtTag::List(value) => Ok(value),
+            _ => bail!("NBT tag '{}' is not a list", name),
+        }
+    }
+
+    pub fn get_byte_array(&self, name: &str) -> anyhow::Result<&[i8]> {
+        match self.get_required(name)? {
+            NbtTag::ByteArray(value) => Ok(value),
+            _ => bail!("NBT tag '{}' is not a byte array", name),
+        }
+    }
+
+    pub fn get_int_array(&self, name: &str) -> anyhow::Result<&[i32]> {
+        match self.get_required(name)? {
+            NbtTag::IntArray(value) => Ok(value),
+            _ => bail!("NBT tag '{}' is not an int array", name),
+        }
+    }
+
+    pub fn get_long_array(&self, name: &str) -> anyhow::Result<&[i64]> {
+        match self.get_required(name)? {
+            NbtTag::LongArray(value) => Ok(value),
+            _ => bail!("NBT tag '{}' is not a long array", name),
+        }
+    }
+}
+
+/// Reads an uncompressed NBT document, returning the name and contents of the root compound.
+pub fn read_nbt(bytes: &[u8]) -> anyhow::Result<(String, NbtCompound)> {
+    let mut reader = NbtReader { bytes };
+    if reader.read_u8()? != TAG_COMPOUND {
+        bail!("NBT root tag is not a compound");
+    }
+
+    let name = reader.read_string()?;
+    let root = reader.read_compound(0)?;
+    Ok((name, root))
+}
+
+pub fn read_gzip_nbt(bytes: &[u8]) -> anyhow::Result<(String, NbtCompound)> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut decompressed)
+        .context("Failed to decompress NBT data")?;
+    read_nbt(&decompressed)
+}
+
+/// Writes an uncompressed NBT document with the given root compound.
+pub fn write_nbt(name: &str, root: &NbtCompound, out: &mut Vec<u8>) {
+    out.push(TAG_COMPOUND);
+    write_string(name, out);
+    write_compound(root, out);
+}
+
+pub fn write_gzip_nbt(name: &str, root: &NbtCompound) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    write_nbt(name, root, &mut bytes);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    std::io::Write::write_all(&mut encoder, &bytes)?;
+    Ok(encoder.finish()?)
+}
+
+fn write_string(value: &str, out: &mut Vec<u8>) {
+    // NBT uses Java's modified UTF-8, which only differs from UTF-8 for null and supplementary characters
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_compound(compound: &NbtCompound, out: &mut Vec<u8>) {
+    for (name, tag) in &compound.0 {
+        out.push(tag.tag_type());
+        write_string(name, out);
+        write_payload(tag, out);
+    }
+    out.push(TAG_END);
+}
+
+fn write_payload(tag: &NbtTag, out: &mut Vec<u8>) {
+    match tag {
+        NbtTag::Byte(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtTag::Short(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtTag::Int(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtTag::Long(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtTag::Float(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtTag::Double(v) => out.extend_from_slice(&v.to_be_bytes()),
+        NbtTag::ByteArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            out.extend(values.iter().map(|v| *v as u8));
+        }
+        NbtTag::String(value) => write_string(value, out),
+        NbtTag::List(values) => {
+            out.push(values.first().map_or(TAG_END, NbtTag::tag_type));
+            out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for value in values {
+                write_payload(value, out);
+            }
+        }
+        NbtTag::Compound(compound) => write_compound(compound, out),
+        NbtTag::IntArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for v in values {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+        NbtTag::LongArray(values) => {
+            out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for v in values {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+    }
+}
+
+struct NbtReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> NbtReader<'a> {
+    fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let Some((head, rest)) = self.bytes.split_at_checked(len) else {
+            bail!("Unexpected end of NBT data");
+        };
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_length(&mut self) -> anyhow::Result<usize> {
+        let len = i32::from_be_bytes(self.read_array()?);
+        usize::try_from(len).with_context(|| format!("Invalid NBT length {}", len))
+    }
+
+    fn read_string(&mut self) -> anyhow::Result<String> {
+        let len = u16::from_be_bytes(self.read_array()?) as usize;
+        Ok(String::from_utf8_lossy(self.read_bytes(len)?).into_owned())
+    }
+
+    fn read_compound(&mut self, depth: usize) -> anyhow::Result<NbtCompound> {
+        let mut compound = NbtCompound::new();
+        loop {
+            let tag_type = self.read_u8()?;
+            if tag_type == TAG_END {
+                return Ok(compound);
+            }
+
+            let name = self.read_string()?;
+            let tag = self
+                .read_payload(tag_type, depth + 1)
+                .with_context(|| format!("Failed to read NBT tag '{}'", name))?;
+            compound.insert(name, tag);
+        }
+    }
+
+    fn read_payload(&mut self, tag_type: u8, depth: usize) -> anyhow::Result<NbtTag> {
+        if depth > MAX_DEPTH {
+            bail!("NBT data is nested too deeply");
+        }
+
+        let tag = match tag_type {
+            TAG_BYTE => NbtTag::Byte(i8::from_be_bytes(self.read_array()?)),
+            TAG_SHORT => NbtTag::Short(i16::from_be_bytes(self.read_array()?)),
+            TAG_INT => NbtTag::Int(i32::from_be_bytes(self.read_array()?)),
+            TAG_LONG => NbtTag::Long(i64::from_be_bytes(self.read_array()?)),
+            TAG_FLOAT => NbtTag::Float(f32::from_be_bytes(self.read_array()?)),
+            TAG_DOUBLE => NbtTag::Double(f64::from_be_bytes(self.read_array()?)),
+            TAG_BYTE_ARRAY => {
+                let len = self.read_length()?;
+                NbtTag::ByteArray(self.read_bytes(len)?.iter().map(|b| *b as i8).collect())
+            }
+            TAG_STRING => NbtTag::String(self.read_string()?),
+            TAG_LIST => {
+                let element_type = self.read_u8()?;
+                let len = self.read_length()?;
+                if element_type == TAG_END && len > 0 {
+                    bail!("Non-empty NBT list without an element type");
+                }
+                let values = (0..len)
+                    .map(|_| self.read_payload(element_type, depth + 1))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                NbtTag::List(values)
+            }
+            TAG_COMPOUND => NbtTag::Compound(self.read_compound(depth)?),
+            TAG_INT_ARRAY => {
+                let len = self.read_length()?;
+                let bytes = self.read_bytes(len.checked_mul(4).context("NBT array too long")?)?;
+                NbtTag::IntArray(
+                    bytes
+                        .chunks_exact(4)
+                        .map(|b| i32::from_be_bytes(b.try_into().unwrap()))
+                        .collect(),
+                )
+            }
+            TAG_LONG_ARRAY => {
+                let len = self.read_length()?;
+                let bytes = self.read_bytes(len.checked_mul(8).context("NBT array too long")?)?;
+                NbtTag::LongArray(
+                    bytes
+                        .chunks_exact(8)
+                        .map(|b| i64::from_be_bytes(b.try_into().unwrap()))
+                        .collect(),
+                )
+            }
+            other => bail!("Unknown NBT tag type {}", other),
+        };
+
+        Ok(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nbt_round_trip() {
+        let mut inner = NbtCompound::new();
+        inner.insert("name", NbtTag::String("minecraft:stone".to_string()));
+        inner.insert("data", NbtTag::LongArray(vec![-1, 0, i64::MAX]));
+
+        let mut root = NbtCompound::new();
+        root.insert("Width", NbtTag::Short(300));
+        root.insert("Offset", NbtTag::IntArray(vec![1, -2, 3]));
+        root.insert("Bytes", NbtTag::ByteArray(vec![-128, 0, 127]));
+        root.insert("Empty", NbtTag::List(vec![]));
+        root.insert("Sections", NbtTag::List(vec![NbtTag::Compound(inner)]));
+        root.insert("Scale", NbtTag::Double(0.5));
+
+        let bytes = write_gzip_nbt("Schematic", &root).unwrap();
+        let (name, decoded) = read_gzip_nbt(&bytes).unwrap();
+
+        assert_eq!(name, "Schematic");
+        assert_eq!(decoded, root);
+        assert_eq!(decoded.get_int::<u16>("Width").unwrap(), 300);
+        assert!(decoded.get_int::<u8>("Width").is_err());
+    }
+}
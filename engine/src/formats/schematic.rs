@@ -0,0 +1,417 @@
+//! Sponge schematics (`.schem`), as used by WorldEdit and most other Minecraft-like tools.
+//! See https://github.com/SpongePowered/Schematic-Specification
+//!
+//! Blocks are stored as indices into a palette of block names. Names are matched against
+//! `BlockDatabaseEntry::name`, and voxel metadata is stored as a `meta` block state property,
+//! e.g. `stairs[meta=3]`.
+
+use std::path::Path;
+
+use anyhow::{Context, bail, ensure};
+use glam::IVec3;
+
+use crate::{
+    assets::blocks::BlockDatabase,
+    editing::{clipboard::Clipboard, shape::WorldBox},
+    formats::nbt::{NbtCompound, NbtTag, read_gzip_nbt, write_gzip_nbt},
+    voxels::{chunk::IChunkRenderState, coord::WorldPos, packed_chunk::Palette, voxel::Voxel},
+    world::World,
+};
+
+const WRITTEN_VERSION: i32 = 3;
+/// Minecraft 1.20.1. Required by the format, but only meaningful for Minecraft's own blocks.
+const DATA_VERSION: i32 = 3465;
+const METADATA_PROPERTY: &str = "meta";
+
+#[derive(Debug, Clone, Default)]
+pub struct SchematicImportOptions {
+    /// Block used for names that aren't in the block database. If None, those voxels become air.
+    pub fallback: Option<String>,
+}
+
+/// A schematic with its block names still unresolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    pub size: IVec3,
+    /// Suggested offset from the paste position to the minimum corner
+    pub offset: IVec3,
+    /// Block name for each palette index
+    pub palette: Vec<String>,
+    /// Palette index of every block in YZX order, the same order used by `Clipboard`
+    pub blocks: Vec<u32>,
+}
+
+impl Schematic {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read schematic {}", path.display()))?;
+        Self::parse(&bytes).with_context(|| format!("Failed to parse schematic {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, self.write()?)
+            .with_context(|| format!("Failed to write schematic {}", path.display()))
+    }
+
+    /// Parses a gzip-compressed schematic. Versions 1 to 3 are supported.
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let (_, root) = read_gzip_nbt(bytes)?;
+
+        // Version 3 wraps everything in a "Schematic" compound and moves the blocks into their own container
+        let (schematic, blocks) = match root.get("Schematic") {
+            Some(NbtTag::Compound(schematic)) => (schematic, schematic.get_compound("Blocks")?),
+            _ => (&root, &root),
+        };
+
+        let version = schematic.get_int::<i32>("Version")?;
+        ensure!(
+            (1..=3).contains(&version),
+            "Unsupported schematic version {}",
+            version
+        );
+
+        let size = IVec3::new(
+            schematic.get_int::<u16>("Width")? as i32,
+            schematic.get_int::<u16>("Height")? as i32,
+            schematic.get_int::<u16>("Length")? as i32,
+        );
+
+        let offset = match schematic.get("Offset") {
+            Some(NbtTag::IntArray(offset)) if offset.len() == 3 => {
+                IVec3::new(offset[0], offset[1], offset[2])
+            }
+            Some(_) => bail!("Schematic offset should have 3 components"),
+            None => IVec3::ZERO,
+        };
+
+        let (palette_tag, data_tag) = if version == 3 {
+            ("Palette", "Data")
+        } else {
+            ("Palette", "BlockData")
+        };
+
+        // Indices must cover the palette without holes, so each one has to be unique and below its length
+        let entries = &blocks.get_compound(palette_tag)?.0;
+        let mut palette = vec![None; entries.len()];
+        for (name, index) in entries {
+            let index = index
+                .as_i64()
+                .and_then(|index| usize::try_from(index).ok())
+                .with_context(|| format!("Invalid palette index for '{}'", name))?;
+            let slot = palette.get_mut(index).with_context(|| {
+                format!(
+                    "Palette index {} of '{}' leaves holes in a palette of {} blocks",
+                    index,
+                    name,
+                    entries.len()
+                )
+            })?;
+            ensure!(
+                slot.is_none(),
+                "Palette index {} is used by more than one block",
+                index
+            );
+            *slot = Some(name.clone());
+        }
+        let palette: Vec<String> = palette.into_iter().flatten().collect();
+
+        let volume = size.as_uvec3().element_product() as usize;
+        let blocks = decode_varints(blocks.get_byte_array(data_tag)?, volume)?;
+        for &index in &blocks {
+            ensure!(
+                (index as usize) < palette.len(),
+                "Block data refers to missing palette index {}",
+                index
+            );
+        }
+
+        Ok(Schematic {
+            size,
+            offset,
+            palette,
+            blocks,
+        })
+    }
+
+    /// Serializes the schematic as a gzip-compressed version 3 schematic.
+    pub fn write(&self) -> anyhow::Result<Vec<u8>> {
+        let dimension = |value: i32| -> anyhow::Result<NbtTag> {
+            let value = u16::try_from(value).context("Schematic is too large")?;
+            Ok(NbtTag::Short(value as i16))
+        };
+
+        let mut palette = NbtCompound::new();
+        for (index, name) in self.palette.iter().enumerate() {
+            palette.insert(name.clone(), NbtTag::Int(index as i32));
+        }
+
+        let mut blocks = NbtCompound::new();
+        blocks.insert("Palette", NbtTag::Compound(palette));
+        blocks.insert("Data", NbtTag::ByteArray(encode_varints(&self.blocks)));
+        blocks.insert("BlockEntities", NbtTag::List(Vec::new()));
+
+        let mut schematic = NbtCompound::new();
+        schematic.insert("Version", NbtTag::Int(WRITTEN_VERSION));
+        schematic.insert("DataVersion", NbtTag::Int(DATA_VERSION));
+        schematic.insert("Width", dimension(self.size.x)?);
+        schematic.insert("Height", dimension(self.size.y)?);
+        schematic.insert("Length", dimension(self.size.z)?);
+        schematic.insert("Offset", NbtTag::IntArray(self.offset.to_array().to_vec()));
+        schematic.insert("Blocks", NbtTag::Compound(blocks));
+
+        let mut root = NbtCompound::new();
+        root.insert("Schematic", NbtTag::Compound(schematic));
+        write_gzip_nbt("", &root)
+    }
+
+    /// Builds a schematic from a clipboard. The schematic palette is built the same way as a chunk palette,
+    /// so air is always index 0 and every distinct voxel, including its metadata, gets its own entry.
+    pub fn from_clipboard(
+        clipboard: &Clipboard,
+        block_database: &BlockDatabase,
+    ) -> anyhow::Result<Self> {
+        let mut palette = Palette::new();
+        palette.ensure_voxel_type(Voxel::AIR);
+
+        let blocks = clipboard
+            .voxels()
+            .iter()
+            .map(|voxel| palette.ensure_voxel_type(*voxel) as u32)
+            .collect();
+
+        let palette = palette
+            .voxel_types
+            .iter()
+            .map(|voxel| {
+                let block = block_database
+                    .get_by_id(voxel.block_type_id())
+                    .with_context(|| format!("Unknown block type {:?}", voxel.block_type_id()))?;
+                Ok(format_block_name(&block.name, voxel.metadata()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Schematic {
+            size: clipboard.size(),
+            offset: IVec3::ZERO,
+            palette,
+            blocks,
+        })
+    }
+
+    /// Resolves block names and converts the schematic into a clipboard.
+    pub fn to_clipboard(
+        &self,
+        block_database: &BlockDatabase,
+        options: &SchematicImportOptions,
+    ) -> anyhow::Result<Clipboard> {
+        let fallback = match &options.fallback {
+            Some(name) => find_block(block_database, name)
+                .with_context(|| format!("Unknown fallback block '{}'", name))?,
+            None => Voxel::AIR,
+        };
+
+        let palette = Palette::from_voxel_type_iterator(self.palette.iter().map(|name| {
+            resolve_block_name(block_database, name).unwrap_or_else(|| {
+                log::warn!("Unknown block '{}' in schematic", name);
+                fallback
+            })
+        }));
+
+        let voxels = self
+            .blocks
+            .iter()
+            .map(|index| palette.get_voxel_type(*index as usize).unwrap_or(fallback))
+            .collect();
+
+        Ok(Clipboard::new(self.size, voxels))
+    }
+}
+
+fn format_block_name(name: &str, metadata: u8) -> String {
+    if metadata == 0 {
+        name.to_string()
+    } else {
+        format!("{}[{}={}]", name, METADATA_PROPERTY, metadata)
+    }
+}
+
+fn find_block(block_database: &BlockDatabase, name: &str) -> Option<Voxel> {
+    block_database
+        .get_by_name(name)
+        .map(|block| Voxel::from_type(block.id.0))
+}
+
+/// Resolves a name like `minecraft:oak_stairs[facing=east,meta=2]`.
+/// Names are matched exactly first, and then without their namespace, which makes them use the default namespace.
+/// Only the `meta` property is used.
+pub(crate) fn resolve_block_name(block_database: &BlockDatabase, name: &str) -> Option<Voxel> {
+    let (base, properties) = match name.split_once('[') {
+        Some((base, properties)) => (base, properties.strip_suffix(']').unwrap_or(properties)),
+        None => (name, ""),
+    };
+
+    let voxel = find_block(block_database, base).or_else(|| {
+        let (_, path) = base.split_once(':')?;
+        find_block(block_database, path)
+    })?;
+
+    let metadata = properties
+        .split(',')
+        .filter_map(|property| property.split_once('='))
+        .find(|(key, _)| key.trim() == METADATA_PROPERTY)
+        .and_then(|(_, value)| value.trim().parse::<u8>().ok())
+        .filter(|metadata| *metadata < 16)
+        .unwrap_or(0);
+
+    Some(Voxel::from_type_metadata(voxel.block_type_id().0, metadata))
+}
+
+fn encode_varints(values: &[u32]) -> Vec<i8> {
+    let mut out = Vec::with_capacity(values.len());
+    for &value in values {
+        let mut value = value;
+        while value >= 0x80 {
+            out.push((value as u8 & 0x7F | 0x80) as i8);
+            value >>= 7;
+        }
+        out.push(value as i8);
+    }
+    out
+}
+
+fn decode_varints(bytes: &[i8], count: usize) -> anyhow::Result<Vec<u32>> {
+    let mut values = Vec::with_capacity(count);
+    let mut bytes = bytes.iter().map(|b| *b as u8);
+
+    while values.len() < count {
+        let mut value = 0u32;
+        let mut shift = 0;
+        loop {
+            let byte = bytes.next().context("Block data ended early")?;
+            ensure!(shift < 32, "Block data contains an invalid varint");
+            value |= ((byte & 0x7F) as u32) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        values.push(value);
+    }
+
+    Ok(values)
+}
+
+/// Imports a schematic into the world as a single undo step, with its minimum corner at `origin`.
+/// Returns the number of voxels that changed.
+pub fn import_schematic<T: IChunkRenderState + Send + Sync + 'static>(
+    world: &World<T>,
+    path: &Path,
+    block_database: &BlockDatabase,
+    options: &SchematicImportOptions,
+    origin: WorldPos,
+) -> anyhow::Result<usize> {
+    let schematic = Schematic::load(path)?;
+    let clipboard = schematic.to_clipboard(block_database, options)?;
+    Ok(clipboard.paste(world, origin, true))
+}
+
+/// Exports the voxels in the box to a schematic.
+pub fn export_schematic<T: IChunkRenderState>(
+    world: &World<T>,
+    region: WorldBox,
+    block_database: &BlockDatabase,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let clipboard = Clipboard::copy(world, region);
+    Schematic::from_clipboard(&clipboard, block_database)?.save(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schematic_round_trip() {
+        let db = BlockDatabase::from_names(&["air", "stone", "stairs"]);
+
+        // Large enough to need multi-byte varints
+        let size = IVec3::new(20, 3, 5);
+        let mut clipboard = Clipboard::new(size, vec![Voxel::AIR; 300]);
+        clipboard.set(IVec3::new(0, 0, 0), Voxel::from_type(1));
+        clipboard.set(IVec3::new(19, 2, 4), Voxel::from_type_metadata(2, 3));
+        clipboard.set(IVec3::new(5, 1, 2), Voxel::from_type_metadata(2, 15));
+
+        let schematic = Schematic::from_clipboard(&clipboard, &db).unwrap();
+        assert_eq!(
+            schematic.palette,
+            [
+                "voxel:air",
+                "voxel:stone",
+                "voxel:stairs[meta=15]",
+                "voxel:stairs[meta=3]"
+            ]
+        );
+
+        let parsed = Schematic::parse(&schematic.write().unwrap()).unwrap();
+        assert_eq!(parsed, schematic);
+
+        let imported = parsed
+            .to_clipboard(&db, &SchematicImportOptions::default())
+            .unwrap();
+        assert_eq!(imported, clipboard);
+    }
+
+    #[test]
+    fn test_unknown_names_use_fallback() {
+        let db = BlockDatabase::from_names(&["air", "stone", "stairs"]);
+        let schematic = Schematic {
+            size: IVec3::new(3, 1, 1),
+            offset: IVec3::ZERO,
+            palette: vec![
+                "minecraft:air".to_string(),
+                "minecraft:stone".to_string(),
+                "minecraft:diamond_block".to_string(),
+            ],
+            blocks: vec![0, 1, 2],
+        };
+
+        let options = SchematicImportOptions {
+            fallback: Some("stairs".to_string()),
+        };
+        let clipboard = schematic.to_clipboard(&db, &options).unwrap();
+        assert_eq!(
+            clipboard.voxels(),
+            [Voxel::AIR, Voxel::from_type(1), Voxel::from_type(2)]
+        );
+    }
+
+    #[test]
+    fn test_reject_palette_holes() {
+        let schematic = Schematic {
+            size: IVec3::new(2, 1, 1),
+            offset: IVec3::ZERO,
+            palette: vec!["voxel:air".to_string(), "voxel:stone".to_string()],
+            blocks: vec![0, 1],
+        };
+
+        // Rewrites the palette index of stone and parses the result
+        let parse_with_stone_at = |index: i32| {
+            let (_, mut root) = read_gzip_nbt(&schematic.write().unwrap()).unwrap();
+            let Some(NbtTag::Compound(root_schematic)) = root.0.get_mut("Schematic") else {
+                unreachable!()
+            };
+            let Some(NbtTag::Compound(blocks)) = root_schematic.0.get_mut("Blocks") else {
+                unreachable!()
+            };
+            let Some(NbtTag::Compound(palette)) = blocks.0.get_mut("Palette") else {
+                unreachable!()
+            };
+            palette.insert("voxel:stone", NbtTag::Int(index));
+            Schematic::parse(&write_gzip_nbt("", &root).unwrap())
+        };
+
+        assert_eq!(parse_with_stone_at(1).unwrap(), schematic);
+        assert!(parse_with_stone_at(2).is_err());
+        assert!(parse_with_stone_at(0).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, bail};
+use glam::IVec3;
+
+use crate::{
+    assets::blocks::{BlockDatabase, BlockTypeId},
+    editing::{clipboard::Clipboard, shape::WorldBox},
+    formats::vox::{
+        file::{MAX_MODEL_SIZE, VoxFile, VoxModel, VoxNode, VoxRotation, VoxTransform},
+        import::world_to_vox,
+        palette_mapping::VoxPaletteMapping,
+    },
+    voxels::{chunk::IChunkRenderState, voxel::Voxel},
+    world::World,
+};
+
+/// Colour used for blocks without a texture
+const MISSING_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Returns the colour used for a block when exporting, the average of its side texture.
+pub fn block_color(block_database: &BlockDatabase, id: BlockTypeId) -> [u8; 4] {
+    let texture = block_database
+        .get_by_id(id)
+        .and_then(|block| block.texture_indices.as_ref())
+        .and_then(|indices| {
+            block_database
+                .world_textures
+                .textures
+                .get(indices.side.first().0 as usize)
+        });
+
+    let Some(texture) = texture else {
+        return MISSING_COLOR;
+    };
+
+    let mut sum = [0u64; 3];
+    let mut count = 0u64;
+    for pixel in texture.data.pixels() {
+        // Skip transparent pixels so cutout textures like leaves don't get darkened
+        if pixel[3] == 0 {
+            continue;
+        }
+        for (channel, total) in sum.iter_mut().enumerate() {
+            *total += pixel[channel] as u64;
+        }
+        count += 1;
+    }
+
+    if count == 0 {
+        return MISSING_COLOR;
+    }
+
+    [
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+        255,
+    ]
+}
+
+/// Converts a clipboard into a vox scene, along with a palette mapping that imports it back as the same blocks.
+/// Clipboards larger than the maximum model size are split into several models.
+/// Voxel metadata isn't preserved.
+pub fn clipboard_to_vox(
+    clipboard: &Clipboard,
+    block_database: &BlockDatabase,
+) -> anyhow::Result<(VoxFile, VoxPaletteMapping)> {
+    let mut palette = Box::new([[0u8; 4]; 256]);
+    let mut color_indices = HashMap::<BlockTypeId, u8>::new();
+    let mut mapping = VoxPaletteMapping::default();
+
+    let size = clipboard.size();
+    let vox_size = world_to_vox(size).abs();
+    let tile_counts = (vox_size + IVec3::splat(MAX_MODEL_SIZE - 1)) / MAX_MODEL_SIZE;
+    let tile_index = |tile: IVec3| {
+        (tile.z * tile_counts.y * tile_counts.x + tile.y * tile_counts.x + tile.x) as usize
+    };
+
+    let mut models = (0..tile_counts.element_product())
+        .map(|i| {
+            let tile = IVec3::new(
+                i % tile_counts.x,
+                (i / tile_counts.x) % tile_counts.y,
+                i / (tile_counts.x * tile_counts.y),
+            );
+            VoxModel {
+                size: (vox_size - tile * MAX_MODEL_SIZE).min(IVec3::splat(MAX_MODEL_SIZE)),
+                voxels: Vec::new(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for (index, voxel) in clipboard.voxels().iter().enumerate() {
+        if *voxel == Voxel::AIR {
+            continue;
+        }
+
+        let id = voxel.block_type_id();
+        let color_index = match color_indices.get(&id) {
+            Some(color_index) => *color_index,
+            None => {
+                if color_indices.len() >= 255 {
+                    bail!("Too many block types to fit in a vox palette");
+                }
+                let color_index = color_indices.len() as u8 + 1;
+                color_indices.insert(id, color_index);
+                palette[color_index as usize - 1] = block_color(block_database, id);
+
+                let name = block_database
+                    .get_by_id(id)
+                    .map(|block| block.name.clone())
+                    .with_context(|| format!("Unknown block type {:?}", id))?;
+                mapping.colors.insert(color_index, name);
+                color_index
+            }
+        };
+
+        // Clipboards are in YZX order
+        let pos = IVec3::new(
+            index as i32 % size.x,
+            index as i32 / (size.x * size.z),
+            (index as i32 / size.x) % size.z,
+        );
+        // Shift the rotated position back into the positive range
+        let vox_pos = world_to_vox(pos) + IVec3::new(0, size.z - 1, 0);
+
+        let tile = vox_pos / MAX_MODEL_SIZE;
+        let local = (vox_pos - tile * MAX_MODEL_SIZE).as_u8vec3();
+        models[tile_index(tile)]
+            .voxels
+            .push((local.to_array(), color_index));
+    }
+
+    // Root transform -> group -> a transform and a shape for every model
+    let mut nodes = HashMap::new();
+    nodes.insert(
+        0,
+        VoxNode::Transform {
+            transform: VoxTransform::IDENTITY,
+            child: 1,
+            hidden: false,
+        },
+    );
+
+    let mut group_children = Vec::new();
+    for (i, model) in models.iter().enumerate() {
+        let i = i as i32;
+        let tile = IVec3::new(
+            i % tile_counts.x,
+            (i / tile_counts.x) % tile_counts.y,
+            i / (tile_counts.x * tile_counts.y),
+        );
+        let transform_id = 2 + i * 2;
+
+        nodes.insert(
+            transform_id,
+            VoxNode::Transform {
+                transform: VoxTransform {
+                    rotation: VoxRotation::IDENTITY,
+                    // Transforms place the center of the model
+                    translation: tile * MAX_MODEL_SIZE + model.size / 2,
+                },
+                child: transform_id + 1,
+                hidden: false,
+            },
+        );
+        nodes.insert(transform_id + 1, VoxNode::Shape { models: vec![i] });
+        group_children.push(transform_id);
+    }
+    nodes.insert(
+        1,
+        VoxNode::Group {
+            children: group_children,
+        },
+    );
+
+    let file = VoxFile {
+        models,
+        palette: Some(palette),
+        nodes,
+    };
+    Ok((file, mapping))
+}
+
+/// Exports the voxels in the box to a `.vox` file.
+/// A palette mapping for importing the file back is written next to it, with a `.ron` extension.
+pub fn export_vox<T: IChunkRenderState>(
+    world: &World<T>,
+    region: WorldBox,
+    block_database: &BlockDatabase,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let clipboard = Clipboard::copy(world, region);
+    let (file, mapping) = clipboard_to_vox(&clipboard, block_database)?;
+
+    let mut bytes = Vec::new();
+    file.write(&mut bytes);
+    std::fs::write(path, bytes)
+        .with_context(|| format!("Failed to write vox file {}", path.display()))?;
+
+    mapping.save(&path.with_extension("ron"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::vox::import::vox_to_clipboard;
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let db = BlockDatabase::from_names(&["air", "stone", "wood"]);
+
+        let mut clipboard = Clipboard::new(IVec3::new(3, 4, 2), vec![Voxel::AIR; 24]);
+        clipboard.set(IVec3::new(0, 0, 0), Voxel::from_type(1));
+        clipboard.set(IVec3::new(2, 3, 1), Voxel::from_type(2));
+        clipboard.set(IVec3::new(1, 2, 0), Voxel::from_type(2));
+
+        let (file, mapping) = clipboard_to_vox(&clipboard, &db).unwrap();
+        assert_eq!(file.models.len(), 1);
+        assert_eq!(file.models[0].size, IVec3::new(3, 2, 4));
+
+        let mut bytes = Vec::new();
+        file.write(&mut bytes);
+        let file = VoxFile::parse(&bytes).unwrap();
+
+        let palette = mapping.resolve(&db).unwrap();
+        let imported = vox_to_clipboard(&file, &palette).unwrap().unwrap();
+        assert_eq!(imported, clipboard);
+    }
+}
@@ -0,0 +1,497 @@
+//! Reading and writing the MagicaVoxel `.vox` format.
+//! See https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt
+//! and https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox-extension.txt
+
+use std::collections::HashMap;
+
+use anyhow::{Context, bail, ensure};
+use glam::IVec3;
+
+const VOX_MAGIC: [u8; 4] = *b"VOX ";
+const VOX_VERSION: i32 = 200;
+
+/// Maximum size of a single model along each axis
+pub const MAX_MODEL_SIZE: i32 = 256;
+
+/// A single model. Coordinates are in MagicaVoxel space, where Z is up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxModel {
+    pub size: IVec3,
+    /// Position and colour index (1-255) of each voxel
+    pub voxels: Vec<([u8; 3], u8)>,
+}
+
+/// A rotation matrix where each row has a single non-zero entry of ±1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxRotation {
+    /// Column of the non-zero entry in each row
+    columns: [usize; 3],
+    signs: [i32; 3],
+}
+
+impl VoxRotation {
+    pub const IDENTITY: VoxRotation = VoxRotation {
+        columns: [0, 1, 2],
+        signs: [1, 1, 1],
+    };
+
+    /// Decodes the packed `_r` attribute of a transform frame.
+    pub fn from_byte(byte: u8) -> anyhow::Result<Self> {
+        let first = (byte & 0b11) as usize;
+        let second = ((byte >> 2) & 0b11) as usize;
+        ensure!(
+            first < 3 && second < 3 && first != second,
+            "Invalid rotation {:#b}",
+            byte
+        );
+
+        let sign = |bit: u8| if byte & (1 << bit) != 0 { -1 } else { 1 };
+        Ok(VoxRotation {
+            columns: [first, second, 3 - first - second],
+            signs: [sign(4), sign(5), sign(6)],
+        })
+    }
+
+    pub fn to_byte(self) -> u8 {
+        let mut byte = self.columns[0] as u8 | (self.columns[1] as u8) << 2;
+        for (row, sign) in self.signs.iter().enumerate() {
+            if *sign < 0 {
+                byte |= 1 << (4 + row);
+            }
+        }
+        byte
+    }
+
+    pub fn apply(&self, v: IVec3) -> IVec3 {
+        IVec3::new(
+            self.signs[0] * v[self.columns[0]],
+            self.signs[1] * v[self.columns[1]],
+            self.signs[2] * v[self.columns[2]],
+        )
+    }
+
+    /// Returns the rotation equivalent to applying `other` first and then `self`.
+    pub fn then(&self, other: &VoxRotation) -> VoxRotation {
+        let mut columns = [0; 3];
+        let mut signs = [0; 3];
+        for row in 0..3 {
+            let inner_row = self.columns[row];
+            columns[row] = other.columns[inner_row];
+            signs[row] = self.signs[row] * other.signs[inner_row];
+        }
+        VoxRotation { columns, signs }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxTransform {
+    pub rotation: VoxRotation,
+    pub translation: IVec3,
+}
+
+impl VoxTransform {
+    pub const IDENTITY: VoxTransform = VoxTransform {
+        rotation: VoxRotation::IDENTITY,
+        translation: IVec3::ZERO,
+    };
+
+    pub fn apply(&self, v: IVec3) -> IVec3 {
+        self.rotation.apply(v) + self.translation
+    }
+
+    /// Returns the transform equivalent to applying `child` first and then `self`.
+    pub fn then(&self, child: &VoxTransform) -> VoxTransform {
+        VoxTransform {
+            rotation: self.rotation.then(&child.rotation),
+            translation: self.apply(child.translation),
+        }
+    }
+}
+
+/// A node of the scene graph introduced in version 150 of the format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoxNode {
+    Transform {
+        transform: VoxTransform,
+        child: i32,
+        hidden: bool,
+    },
+    Group {
+        children: Vec<i32>,
+    },
+    Shape {
+        models: Vec<i32>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    /// RGBA colours. Colour index `i` in the models refers to `palette[i - 1]`.
+    pub palette: Option<Box<[[u8; 4]; 256]>>,
+    /// Scene graph nodes by ID. The root node is 0. Older files don't have a scene graph.
+    pub nodes: HashMap<i32, VoxNode>,
+}
+
+impl VoxFile {
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read vox file {}", path.display()))?;
+        Self::parse(&bytes).with_context(|| format!("Failed to parse vox file {}", path.display()))
+    }
+
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = VoxReader::new(bytes);
+        ensure!(reader.read_array()? == VOX_MAGIC, "Not a vox file");
+        let _version = reader.read_i32()?;
+
+        let main = reader.read_chunk()?;
+        ensure!(&main.id == b"MAIN", "Expected MAIN chunk");
+
+        let mut file = VoxFile {
+            models: Vec::new(),
+            palette: None,
+            nodes: HashMap::new(),
+        };
+        let mut pending_size = None;
+
+        let mut children = VoxReader::new(main.children);
+        while !children.is_empty() {
+            let chunk = children.read_chunk()?;
+            let mut content = VoxReader::new(chunk.content);
+
+            match &chunk.id {
+                b"SIZE" => {
+                    pending_size = Some(IVec3::new(
+                        content.read_i32()?,
+                        content.read_i32()?,
+                        content.read_i32()?,
+                    ));
+                }
+                b"XYZI" => {
+                    let size = pending_size.take().context("XYZI chunk without SIZE")?;
+                    let count = content.read_count()?;
+                    let voxels = (0..count)
+                        .map(|_| {
+                            let [x, y, z, color] = content.read_array()?;
+                            Ok(([x, y, z], color))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    file.models.push(VoxModel { size, voxels });
+                }
+                b"RGBA" => {
+                    let mut palette = Box::new([[0u8; 4]; 256]);
+                    for color in palette.iter_mut() {
+                        *color = content.read_array()?;
+                    }
+                    file.palette = Some(palette);
+                }
+                b"nTRN" => {
+                    let id = content.read_i32()?;
+                    let attributes = content.read_dict()?;
+                    let child = content.read_i32()?;
+                    let _reserved = content.read_i32()?;
+                    let _layer = content.read_i32()?;
+                    let frame_count = content.read_count()?;
+                    let frames = (0..frame_count)
+                        .map(|_| content.read_dict())
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    // Animated transforms aren't supported, so only the first frame is used
+                    let transform = match frames.first() {
+                        Some(frame) => parse_frame(frame)?,
+                        None => VoxTransform::IDENTITY,
+                    };
+
+                    let hidden = attributes.get("_hidden").is_some_and(|v| v == "1");
+                    file.nodes.insert(
+                        id,
+                        VoxNode::Transform {
+                            transform,
+                            child,
+                            hidden,
+                        },
+                    );
+                }
+                b"nGRP" => {
+                    let id = content.read_i32()?;
+                    let _attributes = content.read_dict()?;
+                    let count = content.read_count()?;
+                    let children = (0..count)
+                        .map(|_| content.read_i32())
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    file.nodes.insert(id, VoxNode::Group { children });
+                }
+                b"nSHP" => {
+                    let id = content.read_i32()?;
+                    let _attributes = content.read_dict()?;
+                    let count = content.read_count()?;
+                    let models = (0..count)
+                        .map(|_| {
+                            let model = content.read_i32()?;
+                            let _model_attributes = content.read_dict()?;
+                            Ok(model)
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    file.nodes.insert(id, VoxNode::Shape { models });
+                }
+                // Materials, layers, cameras etc. aren't needed
+                _ => {}
+            }
+        }
+
+        Ok(file)
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let mut children = Vec::new();
+
+        for model in &self.models {
+            let mut size = Vec::new();
+            for axis in model.size.to_array() {
+                write_i32(&mut size, axis);
+            }
+            write_chunk(&mut children, b"SIZE", &size);
+
+            let mut xyzi = Vec::with_capacity(4 + model.voxels.len() * 4);
+            write_i32(&mut xyzi, model.voxels.len() as i32);
+            for ([x, y, z], color) in &model.voxels {
+                xyzi.extend_from_slice(&[*x, *y, *z, *color]);
+            }
+            write_chunk(&mut children, b"XYZI", &xyzi);
+        }
+
+        let mut node_ids = self.nodes.keys().copied().collect::<Vec<_>>();
+        node_ids.sort_unstable();
+        for id in node_ids {
+            let mut content = Vec::new();
+            write_i32(&mut content, id);
+
+            match &self.nodes[&id] {
+                VoxNode::Transform {
+                    transform,
+                    child,
+                    hidden,
+                } => {
+                    let attributes = if *hidden {
+                        vec![("_hidden", "1".to_string())]
+                    } else {
+                        vec![]
+                    };
+                    write_dict(&mut content, &attributes);
+                    write_i32(&mut content, *child);
+                    write_i32(&mut content, -1);
+                    write_i32(&mut content, -1);
+                    write_i32(&mut content, 1);
+
+                    let t = transform.translation;
+                    let frame = [
+                        ("_r", transform.rotation.to_byte().to_string()),
+                        ("_t", format!("{} {} {}", t.x, t.y, t.z)),
+                    ];
+                    write_dict(&mut content, &frame);
+                    write_chunk(&mut children, b"nTRN", &content);
+                }
+                VoxNode::Group { children: nodes } => {
+                    write_dict(&mut content, &[]);
+                    write_i32(&mut content, nodes.len() as i32);
+                    for node in nodes {
+                        write_i32(&mut content, *node);
+                    }
+                    write_chunk(&mut children, b"nGRP", &content);
+                }
+                VoxNode::Shape { models } => {
+                    write_dict(&mut content, &[]);
+                    write_i32(&mut content, models.len() as i32);
+                    for model in models {
+                        write_i32(&mut content, *model);
+                        write_dict(&mut content, &[]);
+                    }
+                    write_chunk(&mut children, b"nSHP", &content);
+                }
+            }
+        }
+
+        if let Some(palette) = &self.palette {
+            write_chunk(&mut children, b"RGBA", palette.as_flattened());
+        }
+
+        out.extend_from_slice(&VOX_MAGIC);
+        write_i32(out, VOX_VERSION);
+        out.extend_from_slice(b"MAIN");
+        write_i32(out, 0);
+        write_i32(out, children.len() as i32);
+        out.extend_from_slice(&children);
+    }
+}
+
+fn parse_frame(frame: &HashMap<String, String>) -> anyhow::Result<VoxTransform> {
+    let rotation = match frame.get("_r") {
+        Some(r) => VoxRotation::from_byte(r.parse().context("Invalid rotation")?)?,
+        None => VoxRotation::IDENTITY,
+    };
+
+    let translation = match frame.get("_t") {
+        Some(t) => {
+            let components = t
+                .split_whitespace()
+                .map(|c| c.parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("Invalid translation '{}'", t))?;
+            ensure!(components.len() == 3, "Invalid translation '{}'", t);
+            IVec3::from_slice(&components)
+        }
+        None => IVec3::ZERO,
+    };
+
+    Ok(VoxTransform {
+        rotation,
+        translation,
+    })
+}
+
+fn write_i32(out: &mut Vec<u8>, value: i32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    write_i32(out, content.len() as i32);
+    write_i32(out, 0);
+    out.extend_from_slice(content);
+}
+
+fn write_dict(out: &mut Vec<u8>, entries: &[(&str, String)]) {
+    write_i32(out, entries.len() as i32);
+    for (key, value) in entries {
+        for string in [*key, value.as_str()] {
+            write_i32(out, string.len() as i32);
+            out.extend_from_slice(string.as_bytes());
+        }
+    }
+}
+
+struct RawChunk<'a> {
+    id: [u8; 4],
+    content: &'a [u8],
+    children: &'a [u8],
+}
+
+struct VoxReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> VoxReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        VoxReader { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let Some((head, rest)) = self.bytes.split_at_checked(len) else {
+            bail!("Unexpected end of vox data");
+        };
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    fn read_i32(&mut self) -> anyhow::Result<i32> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    /// Reads a non-negative element count
+    fn read_count(&mut self) -> anyhow::Result<usize> {
+        let count = self.read_i32()?;
+        usize::try_from(count).with_context(|| format!("Invalid count {}", count))
+    }
+
+    fn read_string(&mut self) -> anyhow::Result<String> {
+        let len = self.read_count()?;
+        let bytes = self.read_bytes(len)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn read_dict(&mut self) -> anyhow::Result<HashMap<String, String>> {
+        let count = self.read_count()?;
+        (0..count)
+            .map(|_| Ok((self.read_string()?, self.read_string()?)))
+            .collect()
+    }
+
+    fn read_chunk(&mut self) -> anyhow::Result<RawChunk<'a>> {
+        let id = self.read_array()?;
+        let content_len = self.read_count()?;
+        let children_len = self.read_count()?;
+        Ok(RawChunk {
+            id,
+            content: self.read_bytes(content_len)?,
+            children: self.read_bytes(children_len)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_byte_round_trip() {
+        for byte in 0..128u8 {
+            if let Ok(rotation) = VoxRotation::from_byte(byte) {
+                assert_eq!(rotation.to_byte(), byte);
+            }
+        }
+
+        // 90° around Z: X -> Y, Y -> -X
+        let rotation = VoxRotation::from_byte(0b0010001).unwrap();
+        assert_eq!(rotation.apply(IVec3::X), IVec3::Y);
+        assert_eq!(rotation.apply(IVec3::Y), -IVec3::X);
+        assert_eq!(rotation.then(&rotation).apply(IVec3::X), -IVec3::X);
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            0,
+            VoxNode::Transform {
+                transform: VoxTransform::IDENTITY,
+                child: 1,
+                hidden: false,
+            },
+        );
+        nodes.insert(1, VoxNode::Group { children: vec![2] });
+        nodes.insert(
+            2,
+            VoxNode::Transform {
+                transform: VoxTransform {
+                    rotation: VoxRotation::from_byte(0b0010001).unwrap(),
+                    translation: IVec3::new(1, -2, 3),
+                },
+                child: 3,
+                hidden: true,
+            },
+        );
+        nodes.insert(3, VoxNode::Shape { models: vec![0] });
+
+        let file = VoxFile {
+            models: vec![VoxModel {
+                size: IVec3::new(2, 3, 4),
+                voxels: vec![([0, 0, 0], 1), ([1, 2, 3], 255)],
+            }],
+            palette: Some(Box::new([[1, 2, 3, 255]; 256])),
+            nodes,
+        };
+
+        let mut bytes = Vec::new();
+        file.write(&mut bytes);
+        assert_eq!(VoxFile::parse(&bytes).unwrap(), file);
+    }
+}
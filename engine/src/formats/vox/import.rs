@@ -0,0 +1,162 @@
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use glam::IVec3;
+
+use crate::{
+    assets::blocks::BlockDatabase,
+    editing::clipboard::Clipboard,
+    formats::vox::{
+        file::{VoxFile, VoxModel, VoxNode, VoxTransform},
+        palette_mapping::VoxPaletteMapping,
+    },
+    voxels::{chunk::IChunkRenderState, coord::WorldPos, voxel::Voxel},
+    world::World,
+};
+
+/// Guards against cycles in malformed scene graphs
+const MAX_SCENE_DEPTH: usize = 64;
+
+/// Converts MagicaVoxel's Z-up coordinates to the engine's Y-up coordinates.
+/// This is a rotation rather than a swap of Y and Z, so models aren't mirrored.
+pub fn vox_to_world(v: IVec3) -> IVec3 {
+    IVec3::new(v.x, v.z, -v.y)
+}
+
+pub fn world_to_vox(v: IVec3) -> IVec3 {
+    IVec3::new(v.x, -v.z, v.y)
+}
+
+impl VoxFile {
+    /// Returns the position and colour index of every visible voxel in the scene, in MagicaVoxel coordinates.
+    /// Files without a scene graph place every model at the origin.
+    pub fn bake(&self) -> anyhow::Result<Vec<(IVec3, u8)>> {
+        let mut voxels = Vec::new();
+
+        if self.nodes.is_empty() {
+            for model in &self.models {
+                voxels.extend(
+                    model
+                        .voxels
+                        .iter()
+                        .map(|(pos, color)| (to_ivec3(*pos), *color)),
+                );
+            }
+        } else {
+            self.bake_node(0, VoxTransform::IDENTITY, 0, &mut voxels)?;
+        }
+
+        Ok(voxels)
+    }
+
+    fn bake_node(
+        &self,
+        node_id: i32,
+        transform: VoxTransform,
+        depth: usize,
+        out: &mut Vec<(IVec3, u8)>,
+    ) -> anyhow::Result<()> {
+        if depth > MAX_SCENE_DEPTH {
+            bail!("Scene graph is too deep or contains a cycle");
+        }
+
+        let node = self
+            .nodes
+            .get(&node_id)
+            .with_context(|| format!("Missing scene node {}", node_id))?;
+
+        match node {
+            VoxNode::Transform {
+                transform: local,
+                child,
+                hidden,
+            } => {
+                if !hidden {
+                    self.bake_node(*child, transform.then(local), depth + 1, out)?;
+                }
+            }
+            VoxNode::Group { children } => {
+                for child in children {
+                    self.bake_node(*child, transform, depth + 1, out)?;
+                }
+            }
+            VoxNode::Shape { models } => {
+                for model_id in models {
+                    let model = usize::try_from(*model_id)
+                        .ok()
+                        .and_then(|id| self.models.get(id))
+                        .with_context(|| format!("Missing model {}", model_id))?;
+                    bake_model(model, &transform, out);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn to_ivec3(pos: [u8; 3]) -> IVec3 {
+    IVec3::new(pos[0] as i32, pos[1] as i32, pos[2] as i32)
+}
+
+fn bake_model(model: &VoxModel, transform: &VoxTransform, out: &mut Vec<(IVec3, u8)>) {
+    // Transforms place the center of the model, rounded down
+    let pivot = model.size / 2;
+    out.extend(
+        model
+            .voxels
+            .iter()
+            .map(|(pos, color)| (transform.apply(to_ivec3(*pos) - pivot), *color)),
+    );
+}
+
+/// Converts the scene into a clipboard in engine coordinates, using `palette` to pick the voxel for each colour index.
+/// Returns None if none of the voxels map to a block.
+pub fn vox_to_clipboard(
+    file: &VoxFile,
+    palette: &[Option<Voxel>; 256],
+) -> anyhow::Result<Option<Clipboard>> {
+    let voxels = file
+        .bake()?
+        .into_iter()
+        .filter_map(|(pos, color)| Some((vox_to_world(pos), palette[color as usize]?)))
+        .collect::<Vec<_>>();
+
+    let Some(min) = voxels.iter().map(|(pos, _)| *pos).reduce(IVec3::min) else {
+        return Ok(None);
+    };
+    let max = voxels.iter().map(|(pos, _)| *pos).fold(min, IVec3::max);
+
+    let size = max - min + IVec3::ONE;
+    let volume = size.as_u64vec3().element_product();
+    if volume > u32::MAX as u64 {
+        bail!("Scene is too large to import ({} voxels)", size);
+    }
+
+    let mut clipboard = Clipboard::new(size, vec![Voxel::AIR; volume as usize]);
+    for (pos, voxel) in voxels {
+        clipboard.set(pos - min, voxel);
+    }
+
+    Ok(Some(clipboard))
+}
+
+/// Imports a `.vox` file into the world as a single undo step, with the minimum corner of the scene at `origin`.
+/// Returns the number of voxels that changed.
+pub fn import_vox<T: IChunkRenderState + Send + Sync + 'static>(
+    world: &World<T>,
+    path: &Path,
+    mapping: &VoxPaletteMapping,
+    block_database: &BlockDatabase,
+    origin: WorldPos,
+) -> anyhow::Result<usize> {
+    let file = VoxFile::load(path)?;
+    let palette = mapping.resolve(block_database)?;
+
+    let Some(clipboard) = vox_to_clipboard(&file, &palette)? else {
+        log::warn!("{} has no voxels mapped to blocks", path.display());
+        return Ok(0);
+    };
+
+    Ok(clipboard.paste(world, origin, false))
+}
@@ -0,0 +1,4 @@
+pub mod export;
+pub mod file;
+pub mod import;
+pub mod palette_mapping;
@@ -0,0 +1,70 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{assets::blocks::BlockDatabase, voxels::voxel::Voxel};
+
+/// Maps MagicaVoxel colour indices to blocks. Loaded from a RON file like:
+///
+/// ```ron
+/// (
+///     colors: {
+///         1: "grass",
+///         2: "dirt",
+///     },
+///     default: Some("gold"),
+/// )
+/// ```
+///
+/// Colour indices are the 1-255 indices shown in MagicaVoxel's palette.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoxPaletteMapping {
+    /// Block name for each colour index
+    pub colors: BTreeMap<u8, String>,
+    /// Block used for colours that aren't listed. If None, those voxels are skipped.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl VoxPaletteMapping {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read palette mapping {}", path.display()))?;
+        ron::from_str(&data)
+            .with_context(|| format!("Failed to parse palette mapping {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .context("Failed to serialize palette mapping")?;
+        std::fs::write(path, data)
+            .with_context(|| format!("Failed to write palette mapping {}", path.display()))
+    }
+
+    /// Resolves block names, returning the voxel for each colour index. Index 0 is never used by models.
+    pub fn resolve(
+        &self,
+        block_database: &BlockDatabase,
+    ) -> anyhow::Result<Box<[Option<Voxel>; 256]>> {
+        let find_block = |name: &str| -> anyhow::Result<Voxel> {
+            match block_database.get_by_name(name) {
+                Some(block) => Ok(Voxel::from_type(block.id.0)),
+                None => bail!("Unknown block '{}' in palette mapping", name),
+            }
+        };
+
+        let default = self.default.as_deref().map(find_block).transpose()?;
+        let mut voxels = Box::new([default; 256]);
+        voxels[0] = None;
+
+        for (index, name) in &self.colors {
+            if *index == 0 {
+                bail!("Colour index 0 is not used by MagicaVoxel, indices start at 1");
+            }
+            voxels[*index as usize] = Some(find_block(name)?);
+        }
+
+        Ok(voxels)
+    }
+}
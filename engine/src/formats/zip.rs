@@ -0,0 +1,269 @@
+//! Minimal reader for zip archives, supporting stored and deflated entries without zip64 or encryption.
+//! See https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+
+use std::{collections::HashMap, io::Read, path::Path};
+
+use anyhow::{Context, bail, ensure};
+use flate2::{Crc, read::DeflateDecoder};
+
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const LOCAL_HEADER_SIZE: usize = 30;
+/// The end of central directory record is followed by a comment of up to this many bytes
+const MAX_COMMENT_SIZE: usize = u16::MAX as usize;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+const FLAG_ENCRYPTED: u16 = 0x1;
+/// Sizes in headers can't be trusted, so at most this much is reserved before decompressing
+const MAX_PREALLOCATED_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy)]
+struct ZipEntry {
+    method: u16,
+    flags: u16,
+    crc32: u32,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+}
+
+/// A zip archive, read fully into memory. Entries are decompressed when they're read.
+pub struct ZipArchive {
+    bytes: Vec<u8>,
+    /// Files by their path in the archive, directories are left out
+    entries: HashMap<String, ZipEntry>,
+}
+
+impl ZipArchive {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read zip archive {}", path.display()))?;
+        Self::parse(bytes)
+            .with_context(|| format!("Failed to parse zip archive {}", path.display()))
+    }
+
+    pub fn parse(bytes: Vec<u8>) -> anyhow::Result<Self> {
+        let end = find_end_of_central_directory(&bytes)
+            .context("Missing the end of central directory record")?;
+        let entry_count = read_u16(&bytes, end + 10)? as usize;
+        let directory_offset = read_u32(&bytes, end + 16)? as usize;
+
+        let mut entries = HashMap::with_capacity(entry_count);
+        let mut offset = directory_offset;
+        for _ in 0..entry_count {
+            ensure!(
+                read_u32(&bytes, offset)? == CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+                "Invalid central directory header at offset {}",
+                offset
+            );
+
+            let entry = ZipEntry {
+                flags: read_u16(&bytes, offset + 8)?,
+                method: read_u16(&bytes, offset + 10)?,
+                crc32: read_u32(&bytes, offset + 16)?,
+                compressed_size: read_u32(&bytes, offset + 20)? as usize,
+                uncompressed_size: read_u32(&bytes, offset + 24)? as usize,
+                local_header_offset: read_u32(&bytes, offset + 42)? as usize,
+            };
+            let name_length = read_u16(&bytes, offset + 28)? as usize;
+            let extra_length = read_u16(&bytes, offset + 30)? as usize;
+            let comment_length = read_u16(&bytes, offset + 32)? as usize;
+
+            let name_start = offset + CENTRAL_DIRECTORY_HEADER_SIZE;
+            let name = bytes
+                .get(name_start..name_start + name_length)
+                .context("Entry name extends past the end of the archive")?;
+            let name = String::from_utf8_lossy(name).replace('\\', "/");
+
+            if !name.ends_with('/') {
+                entries.insert(name, entry);
+            }
+            offset = name_start + name_length + extra_length + comment_length;
+        }
+
+        Ok(ZipArchive { bytes, entries })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Paths of all files in the archive, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Reads and decompresses a file. Returns None if the archive doesn't contain it.
+    pub fn read(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.get(name) else {
+            return Ok(None);
+        };
+
+        ensure!(
+            entry.flags & FLAG_ENCRYPTED == 0,
+            "{} is encrypted, which isn't supported",
+            name
+        );
+        ensure!(
+            read_u32(&self.bytes, entry.local_header_offset)? == LOCAL_HEADER_SIGNATURE,
+            "Invalid local header for {}",
+            name
+        );
+
+        // The local header repeats the name, but its extra field may differ from the central directory
+        let name_length = read_u16(&self.bytes, entry.local_header_offset + 26)? as usize;
+        let extra_length = read_u16(&self.bytes, entry.local_header_offset + 28)? as usize;
+        let data_start = entry.local_header_offset + LOCAL_HEADER_SIZE + name_length + extra_length;
+        let data = self
+            .bytes
+            .get(data_start..data_start + entry.compressed_size)
+            .with_context(|| format!("{} extends past the end of the archive", name))?;
+
+        let mut decompressed =
+            Vec::with_capacity(entry.uncompressed_size.min(MAX_PREALLOCATED_SIZE));
+        match entry.method {
+            METHOD_STORED => decompressed.extend_from_slice(data),
+            METHOD_DEFLATED => {
+                // One byte past the expected size is enough to fail the size check below
+                DeflateDecoder::new(data)
+                    .take(entry.uncompressed_size as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .with_context(|| format!("Failed to decompress {}", name))?;
+            }
+            other => bail!("{} uses unsupported compression method {}", name, other),
+        }
+
+        let mut crc = Crc::new();
+        crc.update(&decompressed);
+        ensure!(
+            decompressed.len() == entry.uncompressed_size && crc.sum() == entry.crc32,
+            "{} is corrupted",
+            name
+        );
+
+        Ok(Some(decompressed))
+    }
+}
+
+/// Searches backwards past the archive comment for the end of central directory record
+fn find_end_of_central_directory(bytes: &[u8]) -> Option<usize> {
+    let last = bytes.len().checked_sub(END_OF_CENTRAL_DIRECTORY_SIZE)?;
+    let first = last.saturating_sub(MAX_COMMENT_SIZE);
+    (first..=last)
+        .rev()
+        .find(|offset| read_u32(bytes, *offset).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> anyhow::Result<u16> {
+    let data = bytes
+        .get(offset..offset + 2)
+        .context("Unexpected end of zip archive")?;
+    Ok(u16::from_le_bytes([data[0], data[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let data = bytes
+        .get(offset..offset + 4)
+        .context("Unexpected end of zip archive")?;
+    Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::DeflateEncoder};
+
+    use super::*;
+
+    /// Writes an archive with the given (name, contents, deflate) entries
+    fn write_zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut directory = Vec::new();
+        for (name, contents, deflate) in files {
+            let mut crc = Crc::new();
+            crc.update(contents);
+            let (method, data) = if *deflate {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(contents).unwrap();
+                (METHOD_DEFLATED, encoder.finish().unwrap())
+            } else {
+                (METHOD_STORED, contents.to_vec())
+            };
+
+            let mut sizes = Vec::new();
+            sizes.extend_from_slice(&method.to_le_bytes());
+            sizes.extend_from_slice(&[0; 4]);
+            sizes.extend_from_slice(&crc.sum().to_le_bytes());
+            sizes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            sizes.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            sizes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            sizes.extend_from_slice(&[0; 2]);
+
+            directory.extend_from_slice(&CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes());
+            directory.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            directory.extend_from_slice(&sizes);
+            directory.extend_from_slice(&[0; 10]);
+            directory.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+
+            bytes.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+            bytes.extend_from_slice(&[20, 0, 0, 0]);
+            bytes.extend_from_slice(&sizes);
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&data);
+        }
+
+        let directory_offset = bytes.len() as u32;
+        bytes.extend_from_slice(&directory);
+        bytes.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&directory_offset.to_le_bytes());
+        bytes.extend_from_slice(&[0; 2]);
+        bytes
+    }
+
+    #[test]
+    fn test_read_stored_and_deflated_entries() {
+        let text = b"voxel ".repeat(100);
+        let bytes = write_zip(&[
+            ("defs/", b"", false),
+            ("defs/blocks.ron", &text, true),
+            ("textures/dirt.png", b"not really a png", false),
+        ]);
+
+        let archive = ZipArchive::parse(bytes).unwrap();
+        assert!(archive.contains("textures/dirt.png"));
+        assert!(!archive.contains("defs/"));
+        assert_eq!(archive.read("defs/blocks.ron").unwrap(), Some(text));
+        assert_eq!(
+            archive.read("textures/dirt.png").unwrap().as_deref(),
+            Some(b"not really a png".as_slice())
+        );
+        assert_eq!(archive.read("textures/grass.png").unwrap(), None);
+
+        assert!(ZipArchive::parse(b"definitely not a zip".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_reject_entries_larger_than_their_header() {
+        let mut bytes = write_zip(&[("big.bin", &[7; 4096], true)]);
+        // Shrink the uncompressed size in the local and central directory headers, so decompressing overshoots it
+        let end = bytes.len() - END_OF_CENTRAL_DIRECTORY_SIZE;
+        let directory = read_u32(&bytes, end + 16).unwrap() as usize;
+        for offset in [22, directory + 24] {
+            bytes[offset..offset + 4].copy_from_slice(&16u32.to_le_bytes());
+        }
+
+        let archive = ZipArchive::parse(bytes).unwrap();
+        assert!(archive.read("big.bin").is_err());
+    }
+}
@@ -8,7 +8,10 @@ use rapier3d::prelude::{
     PhysicsPipeline, RigidBodyBuilder, RigidBodyHandle, RigidBodySet,
 };
 
-use crate::voxels::{chunk::ChunkData, coord::ChunkPos};
+use crate::{
+    assets::blocks::BlockDatabaseSlim,
+    voxels::{chunk::ChunkData, coord::ChunkPos},
+};
 
 pub struct PhysicsWorld {
     colliders: ColliderSet,
@@ -71,8 +74,13 @@ impl PhysicsWorld {
             .insert_with_parent(collider, rigid_body_handle, &mut self.rigid_bodies);
     }
 
-    pub fn add_chunk(&mut self, chunk_pos: ChunkPos, chunk: &ChunkData) {
-        let collider = create_chunk_collider(chunk_pos, chunk);
+    pub fn add_chunk(
+        &mut self,
+        chunk_pos: ChunkPos,
+        chunk: &ChunkData,
+        block_database: &BlockDatabaseSlim,
+    ) {
+        let collider = create_chunk_collider(chunk_pos, chunk, block_database);
         let rigid_body = RigidBodyBuilder::fixed().build();
         let rigid_body_handle = self.rigid_bodies.insert(rigid_body);
         let collider_handle =
@@ -105,11 +113,15 @@ impl PhysicsWorld {
     }
 }
 
-fn create_chunk_collider(chunk_pos: ChunkPos, chunk: &ChunkData) -> Collider {
+fn create_chunk_collider(
+    chunk_pos: ChunkPos,
+    chunk: &ChunkData,
+    block_database: &BlockDatabaseSlim,
+) -> Collider {
     let points = chunk
         .iter_voxels()
         .filter_map(|(local_pos, voxel)| -> Option<Point<i32>> {
-            if voxel.is_solid() {
+            if block_database.properties(voxel).collidable {
                 Some(local_pos.0.as_ivec3().into())
             } else {
                 None
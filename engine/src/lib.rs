@@ -1,7 +1,7 @@
-use std::{path::PathBuf, sync::Arc};
+use std::sync::Arc;
 
 use crate::{
-    assets::{blocks::BlockDatabase, fonts::load_font},
+    assets::{blocks::BlockDatabase, fonts::load_font, resource_packs::ResourcePacks},
     config::{
         config_manager::{Config, ConfigManager},
         engine_config::EngineConfig,
@@ -16,14 +16,21 @@ pub mod assets;
 pub mod camera;
 pub mod chunk_loader;
 pub mod config;
+pub mod editing;
+pub mod fluids;
+pub mod formats;
 pub mod game_loop;
 pub mod gameplay;
+pub mod lighting;
 pub mod limits;
 pub mod loader_job_queue;
 pub mod math;
 pub mod memory;
 pub mod mesh_generation;
+pub mod persistence;
 pub mod player;
+#[cfg(test)]
+mod test_dir;
 pub mod visibility;
 pub mod voxels;
 pub mod world;
@@ -49,10 +56,14 @@ impl<T: IChunkRenderState> EngineContext<T> {
 pub fn init_engine<T: IChunkRenderState>() -> anyhow::Result<EngineContext<T>> {
     let config = EngineConfig::create_manager()?;
 
+    let resource_packs = Arc::new(ResourcePacks::open(
+        &config.get().read().unwrap().resource_packs,
+    ));
+
     // TODO: This does nothing, this is just here to ensure the font loading system works
-    load_font(PathBuf::from("assets/fonts").as_path(), "custom").expect("Failed to load font");
+    load_font(&resource_packs, "custom").expect("Failed to load font");
 
-    let mut block_database = BlockDatabase::new();
+    let mut block_database = BlockDatabase::with_resource_packs(resource_packs);
     block_database
         .load_all_blocks()
         .expect("Failed to load block definitions");
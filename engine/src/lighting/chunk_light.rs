@@ -0,0 +1,210 @@
+use crate::voxels::{
+    chunk::{CHUNK_SIZE, CHUNK_VOLUME},
+    coord::LocalPos,
+    face::Face,
+};
+
+/// Highest light level. Sky light at this level travels straight down without attenuation.
+pub const MAX_LIGHT: u8 = 15;
+
+pub const LIGHT_LAYER_AREA: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightChannel {
+    /// Light coming from the open sky above
+    Sky,
+    /// Light emitted by blocks
+    Block,
+}
+
+impl LightChannel {
+    pub const fn all() -> [LightChannel; 2] {
+        [LightChannel::Sky, LightChannel::Block]
+    }
+}
+
+/// Sky and block light packed into a single byte, sky light in the high nibble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackedLight(pub u8);
+
+impl PackedLight {
+    pub const DARK: PackedLight = PackedLight(0);
+
+    pub const fn new(sky: u8, block: u8) -> Self {
+        PackedLight((sky << 4) | (block & 0x0F))
+    }
+
+    #[inline(always)]
+    pub const fn sky(self) -> u8 {
+        self.0 >> 4
+    }
+
+    #[inline(always)]
+    pub const fn block(self) -> u8 {
+        self.0 & 0x0F
+    }
+
+    #[inline(always)]
+    pub const fn get(self, channel: LightChannel) -> u8 {
+        match channel {
+            LightChannel::Sky => self.sky(),
+            LightChannel::Block => self.block(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, channel: LightChannel, level: u8) {
+        debug_assert!(level <= MAX_LIGHT);
+        *self = match channel {
+            LightChannel::Sky => PackedLight::new(level, self.block()),
+            LightChannel::Block => PackedLight::new(self.sky(), level),
+        };
+    }
+
+    /// Brighter of the two channels
+    #[inline(always)]
+    pub const fn max_level(self) -> u8 {
+        if self.sky() > self.block() {
+            self.sky()
+        } else {
+            self.block()
+        }
+    }
+
+    /// Brightness factor used for shading, from almost black at level 0 to 1.0 at full light.
+    /// Matches `light_brightness` in `world_geo_draw.wesl`.
+    pub fn brightness(self) -> f32 {
+        LIGHT_FALLOFF.powi((MAX_LIGHT - self.max_level()) as i32)
+    }
+}
+
+/// Brightness ratio between two adjacent light levels
+pub const LIGHT_FALLOFF: f32 = 0.8;
+
+enum LightLevels {
+    /// Every voxel has the same light, which is the case for most fully underground or open air chunks
+    Uniform(PackedLight),
+    /// Light of every voxel in YZX order
+    Full(Box<[PackedLight; CHUNK_VOLUME]>),
+}
+
+/// Light levels of every voxel in a chunk. Light isn't persisted, it's recomputed whenever a chunk is loaded.
+pub struct ChunkLight {
+    levels: LightLevels,
+    /// Set when the chunk above wasn't lit when sky light was computed, so the sky was assumed to be open.
+    /// The assumption is corrected once the chunk above is lit.
+    pub(crate) assumes_open_sky: bool,
+}
+
+impl ChunkLight {
+    pub fn uniform(light: PackedLight) -> Self {
+        ChunkLight {
+            levels: LightLevels::Uniform(light),
+            assumes_open_sky: false,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self::uniform(PackedLight::DARK)
+    }
+
+    pub fn is_uniform(&self) -> bool {
+        matches!(self.levels, LightLevels::Uniform(_))
+    }
+
+    #[inline(always)]
+    pub fn get_packed(&self, pos: LocalPos) -> PackedLight {
+        match &self.levels {
+            LightLevels::Uniform(light) => *light,
+            LightLevels::Full(levels) => levels[pos.to_chunk_data_index()],
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, pos: LocalPos, channel: LightChannel) -> u8 {
+        self.get_packed(pos).get(channel)
+    }
+
+    pub fn set(&mut self, pos: LocalPos, channel: LightChannel, level: u8) {
+        if let LightLevels::Uniform(light) = self.levels {
+            if light.get(channel) == level {
+                return;
+            }
+            self.levels = LightLevels::Full(Box::new([light; CHUNK_VOLUME]));
+        }
+
+        let LightLevels::Full(levels) = &mut self.levels else {
+            unreachable!();
+        };
+        levels[pos.to_chunk_data_index()].set(channel, level);
+    }
+
+    /// Copies the light of every voxel in YZX order.
+    pub fn copy_to(&self, target: &mut [PackedLight; CHUNK_VOLUME]) {
+        match &self.levels {
+            LightLevels::Uniform(light) => target.fill(*light),
+            LightLevels::Full(levels) => target.copy_from_slice(levels.as_slice()),
+        }
+    }
+
+    /// Copies the light of the voxel layer on the given face of the chunk.
+    /// Layers are indexed the same way as `Border`, see `light_layer_pos`.
+    pub fn copy_layer(&self, face: Face) -> [PackedLight; LIGHT_LAYER_AREA] {
+        let mut layer = [PackedLight::DARK; LIGHT_LAYER_AREA];
+        match &self.levels {
+            LightLevels::Uniform(light) => layer.fill(*light),
+            LightLevels::Full(levels) => {
+                for (index, light) in layer.iter_mut().enumerate() {
+                    *light = levels[light_layer_pos(face, index).to_chunk_data_index()];
+                }
+            }
+        }
+        layer
+    }
+
+    pub fn approximate_size(&self) -> usize {
+        match &self.levels {
+            LightLevels::Uniform(_) => size_of::<Self>(),
+            LightLevels::Full(_) => size_of::<Self>() + CHUNK_VOLUME,
+        }
+    }
+}
+
+/// Position of a voxel in the layer on the given face of a chunk.
+/// The index runs over the two axes parallel to the face, the same way as in `Border`.
+pub fn light_layer_pos(face: Face, index: usize) -> LocalPos {
+    let a = (index % CHUNK_SIZE as usize) as u8;
+    let b = (index / CHUNK_SIZE as usize) as u8;
+    match face {
+        Face::Top => LocalPos::new(a, CHUNK_SIZE - 1, b),
+        Face::Bottom => LocalPos::new(a, 0, b),
+        Face::Left => LocalPos::new(0, a, b),
+        Face::Right => LocalPos::new(CHUNK_SIZE - 1, a, b),
+        Face::Front => LocalPos::new(a, b, CHUNK_SIZE - 1),
+        Face::Back => LocalPos::new(a, b, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_light_expands_on_write() {
+        let mut light = ChunkLight::uniform(PackedLight::new(MAX_LIGHT, 0));
+        let pos = LocalPos::new(3, 15, 7);
+
+        // Writing the same value keeps the chunk uniform
+        light.set(pos, LightChannel::Sky, MAX_LIGHT);
+        assert!(light.is_uniform());
+
+        light.set(pos, LightChannel::Block, 9);
+        assert!(!light.is_uniform());
+        assert_eq!(light.get_packed(pos), PackedLight::new(MAX_LIGHT, 9));
+        assert_eq!(light.get(LocalPos::new(0, 0, 0), LightChannel::Block), 0);
+
+        let top = light.copy_layer(Face::Top);
+        assert_eq!(top[7 * CHUNK_SIZE as usize + 3].block(), 9);
+        assert_eq!(light_layer_pos(Face::Top, 7 * CHUNK_SIZE as usize + 3), pos);
+    }
+}
@@ -0,0 +1,562 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::{
+    assets::blocks::SharedBlockDatabase,
+    lighting::{
+        chunk_light::{
+            ChunkLight, LIGHT_LAYER_AREA, LightChannel, MAX_LIGHT, PackedLight, light_layer_pos,
+        },
+        propagation::{LightPropagator, LightRules, LightView, spread_level},
+    },
+    voxels::{
+        chunk::{ChunkData, ChunkHandle, IChunkRenderState},
+        coord::{ChunkPos, LocalPos, WorldPos},
+        face::Face,
+        voxel::Voxel,
+    },
+    world::WorldChunks,
+};
+
+/// Computes and updates light for the chunks of a world.
+///
+/// Light is only ever changed while holding the propagator lock, which serializes lighting across
+/// the loader workers and edits. Chunk entries are locked one at a time, so lighting can't deadlock with
+/// anything holding a chunk entry.
+pub struct LightEngine {
+    rules: SharedBlockDatabase,
+    propagator: Mutex<LightPropagator>,
+}
+
+impl LightEngine {
+    pub fn new(rules: SharedBlockDatabase) -> Self {
+        LightEngine {
+            rules,
+            propagator: Mutex::default(),
+        }
+    }
+
+    /// Lights a chunk whose voxel data was just inserted, and spreads its light into lit neighbours.
+    /// Should be called before the chunk is announced to its neighbours as ready for meshing.
+    pub fn light_new_chunk<T: IChunkRenderState>(
+        &self,
+        chunks: &WorldChunks<T>,
+        pos: ChunkPos,
+    ) -> LightChanges {
+        let mut propagator = self.propagator.lock().unwrap();
+        light_new_chunk(chunks, pos, &mut propagator, &*self.rules.load())
+    }
+
+    /// Updates the light around voxels that have been edited.
+    /// Voxels in chunks that aren't loaded or lit yet are ignored, they are lit once loading finishes.
+    pub fn update_voxels<T: IChunkRenderState>(
+        &self,
+        chunks: &WorldChunks<T>,
+        positions: impl IntoIterator<Item = WorldPos>,
+    ) -> LightChanges {
+        let mut propagator = self.propagator.lock().unwrap();
+        update_voxels(chunks, positions, &mut propagator, &*self.rules.load())
+    }
+}
+
+/// Chunks whose light changed, and which of their faces had changes on them.
+#[derive(Debug, Default)]
+pub struct LightChanges {
+    touched_faces: HashMap<ChunkPos, u8, ahash::RandomState>,
+}
+
+impl LightChanges {
+    fn record(&mut self, chunk_pos: ChunkPos, local_pos: LocalPos) {
+        let mut mask = 0u8;
+        for face in Face::all() {
+            if local_pos.is_on_face(face) {
+                mask |= 1 << (face as u8);
+            }
+        }
+        *self.touched_faces.entry(chunk_pos).or_default() |= mask;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.touched_faces.is_empty()
+    }
+
+    pub fn contains(&self, pos: ChunkPos) -> bool {
+        self.touched_faces.contains_key(&pos)
+    }
+
+    /// Chunks whose light changed, and a bitmask of the faces (indexed by `Face`) with changed light on them.
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkPos, u8)> + '_ {
+        self.touched_faces.iter().map(|(pos, faces)| (*pos, *faces))
+    }
+
+    /// Invalidates the meshes of relit chunks, and of their neighbours across faces with changed light.
+    /// Chunks that need to be enqueued for meshing are added to `stale_chunks`.
+    pub fn invalidate_meshes<T: IChunkRenderState>(
+        &self,
+        chunks: &WorldChunks<T>,
+        stale_chunks: &mut Vec<ChunkHandle>,
+    ) {
+        let mut invalidate = |pos: ChunkPos| {
+            let Some(chunk) = chunks.get(&pos) else {
+                return;
+            };
+            let handle = chunk.handle();
+            drop(chunk);
+
+            // Only the first invalidation moves the chunk to StaleMesh, so chunks aren't added twice
+            if handle.invalidate_mesh() {
+                stale_chunks.push(handle);
+            }
+        };
+
+        for (pos, touched_faces) in self.iter() {
+            invalidate(pos);
+            for face in Face::all() {
+                if touched_faces & (1 << (face as u8)) != 0 {
+                    invalidate(pos.get_neighbor(face));
+                }
+            }
+        }
+    }
+}
+
+/// View of every loaded and lit chunk in the world. Each access locks a single chunk entry.
+struct WorldLightView<'a, T: IChunkRenderState> {
+    chunks: &'a WorldChunks<T>,
+    changes: LightChanges,
+}
+
+impl<T: IChunkRenderState> LightView for WorldLightView<'_, T> {
+    fn get(&self, pos: WorldPos, channel: LightChannel) -> Option<(Voxel, u8)> {
+        let chunk = self.chunks.get(&pos.to_chunk_pos())?;
+        let light = chunk.light.as_ref()?;
+        let local_pos = pos.to_local_pos();
+        let voxel = chunk.data.as_ref()?.get_voxel(local_pos)?;
+        Some((voxel, light.get(local_pos, channel)))
+    }
+
+    fn set(&mut self, pos: WorldPos, channel: LightChannel, level: u8) {
+        let chunk_pos = pos.to_chunk_pos();
+        let Some(mut chunk) = self.chunks.get_mut(&chunk_pos) else {
+            return;
+        };
+        let Some(light) = &mut chunk.light else {
+            return;
+        };
+
+        let local_pos = pos.to_local_pos();
+        light.set(local_pos, channel, level);
+        drop(chunk);
+        self.changes.record(chunk_pos, local_pos);
+    }
+}
+
+/// View of a single chunk that is being lit, before it's visible to the rest of the world.
+struct ChunkLightView<'a> {
+    pos: ChunkPos,
+    data: &'a ChunkData,
+    light: &'a mut ChunkLight,
+}
+
+impl LightView for ChunkLightView<'_> {
+    fn get(&self, pos: WorldPos, channel: LightChannel) -> Option<(Voxel, u8)> {
+        if pos.to_chunk_pos() != self.pos {
+            return None;
+        }
+        let local_pos = pos.to_local_pos();
+        let voxel = self.data.get_voxel(local_pos)?;
+        Some((voxel, self.light.get(local_pos, channel)))
+    }
+
+    fn set(&mut self, pos: WorldPos, channel: LightChannel, level: u8) {
+        self.light.set(pos.to_local_pos(), channel, level);
+    }
+}
+
+/// Light on the layer of a neighbouring chunk facing the chunk being lit
+struct NeighborLayer {
+    light: [PackedLight; LIGHT_LAYER_AREA],
+    assumes_open_sky: bool,
+}
+
+pub(crate) fn light_new_chunk<T: IChunkRenderState>(
+    chunks: &WorldChunks<T>,
+    pos: ChunkPos,
+    propagator: &mut LightPropagator,
+    rules: &impl LightRules,
+) -> LightChanges {
+    // Copy the neighbours' borders first, so only one chunk entry is locked at a time
+    let neighbors = Face::all().map(|face| {
+        let neighbor = chunks.get(&pos.get_neighbor(face))?;
+        let light = neighbor.light.as_ref()?;
+        Some(NeighborLayer {
+            light: light.copy_layer(face.opposite()),
+            assumes_open_sky: light.assumes_open_sky,
+        })
+    });
+
+    let own_layers = {
+        let Some(mut entry) = chunks.get_mut(&pos) else {
+            return LightChanges::default();
+        };
+        let chunk = &mut *entry;
+        let Some(data) = &chunk.data else {
+            return LightChanges::default();
+        };
+
+        let light = compute_chunk_light(pos, data, &neighbors, propagator, rules);
+        let own_layers = Face::all().map(|face| light.copy_layer(face));
+        chunk.light = Some(light);
+        own_layers
+    };
+
+    let mut view = WorldLightView {
+        chunks,
+        changes: LightChanges::default(),
+    };
+
+    for face in Face::all() {
+        let Some(neighbor) = &neighbors[face as usize] else {
+            continue;
+        };
+        let own_layer = &own_layers[face as usize];
+
+        // Spread our light across the border wherever it's brighter than what the neighbour has
+        for (index, (own, theirs)) in own_layer.iter().zip(&neighbor.light).enumerate() {
+            for channel in LightChannel::all() {
+                let level = spread_level(channel, own.get(channel), face);
+                if level > theirs.get(channel) {
+                    let local_pos = light_layer_pos(face, index);
+                    propagator
+                        .enqueue_spread(WorldPos::from_chunk_and_voxel(pos, local_pos), channel);
+                }
+            }
+        }
+
+        // The chunk below assumed it was under open sky, remove sky light we aren't letting through
+        if face == Face::Bottom && neighbor.assumes_open_sky {
+            let below = pos.get_neighbor(face);
+            if let Some(mut chunk) = chunks.get_mut(&below)
+                && let Some(light) = &mut chunk.light
+            {
+                light.assumes_open_sky = false;
+            }
+
+            for (index, (own, theirs)) in own_layer.iter().zip(&neighbor.light).enumerate() {
+                if theirs.sky() == MAX_LIGHT && own.sky() < MAX_LIGHT {
+                    let below_pos =
+                        WorldPos::from_chunk_and_voxel(below, light_layer_pos(Face::Top, index));
+                    view.set(below_pos, LightChannel::Sky, 0);
+                    propagator.enqueue_removal(below_pos, LightChannel::Sky, MAX_LIGHT);
+                }
+            }
+        }
+    }
+
+    propagator.propagate(&mut view, rules);
+    view.changes
+}
+
+/// Computes the light of a single chunk from its own voxels and the borders of its lit neighbours.
+/// If the chunk above isn't lit, its sky is assumed to be open.
+fn compute_chunk_light(
+    pos: ChunkPos,
+    data: &ChunkData,
+    neighbors: &[Option<NeighborLayer>; 6],
+    propagator: &mut LightPropagator,
+    rules: &impl LightRules,
+) -> ChunkLight {
+    let mut light = compute_local_light(pos, data, neighbors, propagator, rules);
+    light.assumes_open_sky = neighbors[Face::Top as usize].is_none();
+    light
+}
+
+fn compute_local_light(
+    pos: ChunkPos,
+    data: &ChunkData,
+    neighbors: &[Option<NeighborLayer>; 6],
+    propagator: &mut LightPropagator,
+    rules: &impl LightRules,
+) -> ChunkLight {
+    // Fast paths for chunks made of a single block, which are most of the world
+    if let ChunkData::Solid(voxel) = data {
+        if rules.is_opaque(*voxel) {
+            return ChunkLight::uniform(PackedLight::new(0, rules.emission(*voxel)));
+        }
+
+        let open_sky = match &neighbors[Face::Top as usize] {
+            Some(above) => above.light.iter().all(|light| light.sky() == MAX_LIGHT),
+            None => true,
+        };
+        let no_block_light = neighbors
+            .iter()
+            .flatten()
+            .all(|neighbor| neighbor.light.iter().all(|light| light.block() <= 1));
+
+        if open_sky && no_block_light && rules.emission(*voxel) == 0 {
+            return ChunkLight::uniform(PackedLight::new(MAX_LIGHT, 0));
+        }
+    }
+
+    let mut light = ChunkLight::dark();
+
+    let has_emitters = match data {
+        ChunkData::Solid(voxel) => rules.emission(*voxel) > 0,
+        ChunkData::Packed(packed) => packed
+            .palette
+            .voxel_types
+            .iter()
+            .any(|voxel| rules.emission(*voxel) > 0),
+    };
+    if has_emitters {
+        for (local_pos, voxel) in data.iter_voxels() {
+            let emission = rules.emission(voxel);
+            if emission > 0 {
+                light.set(local_pos, LightChannel::Block, emission);
+                propagator.enqueue_spread(
+                    WorldPos::from_chunk_and_voxel(pos, local_pos),
+                    LightChannel::Block,
+                );
+            }
+        }
+    }
+
+    // Light entering from neighbours, travelling in the direction opposite to the face
+    for face in Face::all() {
+        let neighbor = &neighbors[face as usize];
+        if neighbor.is_none() && face != Face::Top {
+            continue;
+        }
+
+        for index in 0..LIGHT_LAYER_AREA {
+            let local_pos = light_layer_pos(face, index);
+            let Some(voxel) = data.get_voxel(local_pos) else {
+                continue;
+            };
+            if rules.is_opaque(voxel) {
+                continue;
+            }
+
+            let incoming = match neighbor {
+                Some(neighbor) => neighbor.light[index],
+                None => PackedLight::new(MAX_LIGHT, 0),
+            };
+
+            let mut seeded = false;
+            for channel in LightChannel::all() {
+                let level = spread_level(channel, incoming.get(channel), face.opposite());
+                if level > light.get(local_pos, channel) {
+                    light.set(local_pos, channel, level);
+                    seeded = true;
+                }
+            }
+
+            if seeded {
+                let world_pos = WorldPos::from_chunk_and_voxel(pos, local_pos);
+                for channel in LightChannel::all() {
+                    propagator.enqueue_spread(world_pos, channel);
+                }
+            }
+        }
+    }
+
+    propagator.propagate(
+        &mut ChunkLightView {
+            pos,
+            data,
+            light: &mut light,
+        },
+        rules,
+    );
+
+    light
+}
+
+pub(crate) fn update_voxels<T: IChunkRenderState>(
+    chunks: &WorldChunks<T>,
+    positions: impl IntoIterator<Item = WorldPos>,
+    propagator: &mut LightPropagator,
+    rules: &impl LightRules,
+) -> LightChanges {
+    let mut view = WorldLightView {
+        chunks,
+        changes: LightChanges::default(),
+    };
+
+    for pos in positions {
+        propagator.enqueue_voxel_change(&mut view, rules, pos);
+
+        // Voxels opened up below an unlit chunk get the sky light it was assumed to let through
+        if assumes_open_sky_above(chunks, pos)
+            && let Some((voxel, _)) = view.get(pos, LightChannel::Sky)
+            && !rules.is_opaque(voxel)
+        {
+            view.set(pos, LightChannel::Sky, MAX_LIGHT);
+            propagator.enqueue_spread(pos, LightChannel::Sky);
+        }
+    }
+
+    propagator.propagate(&mut view, rules);
+    view.changes
+}
+
+fn assumes_open_sky_above<T: IChunkRenderState>(chunks: &WorldChunks<T>, pos: WorldPos) -> bool {
+    if !pos.to_local_pos().is_on_face(Face::Top) {
+        return false;
+    }
+
+    chunks
+        .get(&pos.to_chunk_pos())
+        .and_then(|chunk| chunk.light.as_ref().map(|light| light.assumes_open_sky))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lighting::propagation::tests::TestRules, voxels::chunk::Chunk};
+
+    fn insert_chunk(
+        chunks: &WorldChunks<()>,
+        propagator: &mut LightPropagator,
+        pos: ChunkPos,
+        data: ChunkData,
+    ) -> LightChanges {
+        chunks.insert(pos, Chunk::from_data(pos, data));
+        light_new_chunk(chunks, pos, propagator, &TestRules)
+    }
+
+    fn light_at(chunks: &WorldChunks<()>, pos: WorldPos, channel: LightChannel) -> u8 {
+        let chunk = chunks.get(&pos.to_chunk_pos()).unwrap();
+        chunk
+            .light
+            .as_ref()
+            .unwrap()
+            .get(pos.to_local_pos(), channel)
+    }
+
+    fn set_voxel(
+        chunks: &WorldChunks<()>,
+        propagator: &mut LightPropagator,
+        pos: WorldPos,
+        voxel: Voxel,
+    ) -> LightChanges {
+        chunks
+            .get_mut(&pos.to_chunk_pos())
+            .unwrap()
+            .set_voxel(pos.to_local_pos(), voxel);
+        update_voxels(chunks, [pos], propagator, &TestRules)
+    }
+
+    #[test]
+    fn test_overhang_casts_shadow() {
+        let chunks = WorldChunks::<()>::default();
+        let mut propagator = LightPropagator::new();
+
+        // A roof covering most of the chunk, open on the x = 15 edge
+        let mut data = ChunkData::solid(Voxel::AIR);
+        for x in 0..15 {
+            for z in 0..16 {
+                data.set_voxel(LocalPos::new(x, 10, z), Voxel::DIRT);
+            }
+        }
+        insert_chunk(&chunks, &mut propagator, ChunkPos::new(0, 0, 0), data);
+
+        let sky = |x, y, z| light_at(&chunks, WorldPos::new(x, y, z), LightChannel::Sky);
+        assert_eq!(sky(0, 15, 0), MAX_LIGHT);
+        assert_eq!(sky(15, 0, 0), MAX_LIGHT);
+        // Light falling past the roof edge spreads sideways underneath it
+        assert_eq!(sky(14, 5, 0), MAX_LIGHT - 1);
+        assert_eq!(sky(0, 5, 0), MAX_LIGHT - 15);
+    }
+
+    #[test]
+    fn test_light_crosses_chunk_borders() {
+        let chunks = WorldChunks::<()>::default();
+        let mut propagator = LightPropagator::new();
+
+        let mut data = ChunkData::solid(Voxel::AIR);
+        data.set_voxel(LocalPos::new(15, 0, 0), Voxel::GOLD);
+        insert_chunk(&chunks, &mut propagator, ChunkPos::new(0, 0, 0), data);
+
+        // The emitter lights up a neighbour loaded after it
+        insert_chunk(
+            &chunks,
+            &mut propagator,
+            ChunkPos::new(1, 0, 0),
+            ChunkData::solid(Voxel::AIR),
+        );
+        let block = |x, y, z| light_at(&chunks, WorldPos::new(x, y, z), LightChannel::Block);
+        assert_eq!(block(15, 0, 0), 14);
+        assert_eq!(block(18, 0, 0), 11);
+
+        // Removing it darkens both chunks
+        set_voxel(
+            &chunks,
+            &mut propagator,
+            WorldPos::new(15, 0, 0),
+            Voxel::AIR,
+        );
+        assert_eq!(block(15, 0, 0), 0);
+        assert_eq!(block(18, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_chunk_above_corrects_assumed_sky() {
+        let chunks = WorldChunks::<()>::default();
+        let mut propagator = LightPropagator::new();
+
+        // Lit before the chunk above, so it assumes open sky
+        insert_chunk(
+            &chunks,
+            &mut propagator,
+            ChunkPos::new(0, 0, 0),
+            ChunkData::solid(Voxel::AIR),
+        );
+        assert_eq!(
+            light_at(&chunks, WorldPos::new(5, 0, 5), LightChannel::Sky),
+            MAX_LIGHT
+        );
+
+        // A solid chunk above turns the air below into a closed, pitch-black cave
+        let changes = insert_chunk(
+            &chunks,
+            &mut propagator,
+            ChunkPos::new(0, 1, 0),
+            ChunkData::solid(Voxel::DIRT),
+        );
+        assert!(changes.contains(ChunkPos::new(0, 0, 0)));
+        assert_eq!(
+            light_at(&chunks, WorldPos::new(5, 0, 5), LightChannel::Sky),
+            0
+        );
+        assert!(
+            !chunks
+                .get(&ChunkPos::new(0, 0, 0))
+                .unwrap()
+                .light
+                .as_ref()
+                .unwrap()
+                .assumes_open_sky
+        );
+
+        // Digging a hole through lets the sky back in
+        let mut hole = Vec::new();
+        for y in 16..32 {
+            let pos = WorldPos::new(5, y, 5);
+            chunks
+                .get_mut(&pos.to_chunk_pos())
+                .unwrap()
+                .set_voxel(pos.to_local_pos(), Voxel::AIR);
+            hole.push(pos);
+        }
+        update_voxels(&chunks, hole, &mut propagator, &TestRules);
+        assert_eq!(
+            light_at(&chunks, WorldPos::new(5, 0, 5), LightChannel::Sky),
+            MAX_LIGHT
+        );
+        assert_eq!(
+            light_at(&chunks, WorldPos::new(7, 0, 5), LightChannel::Sky),
+            MAX_LIGHT - 2
+        );
+    }
+}
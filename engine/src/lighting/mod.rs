@@ -0,0 +1,3 @@
+pub mod chunk_light;
+pub mod light_engine;
+pub mod propagation;
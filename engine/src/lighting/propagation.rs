@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+
+use crate::{
+    assets::blocks::BlockDatabaseSlim,
+    lighting::chunk_light::{LightChannel, MAX_LIGHT},
+    voxels::{coord::WorldPos, face::Face, voxel::Voxel},
+};
+
+/// How blocks interact with light.
+pub trait LightRules {
+    /// Opaque blocks stop light, and are never lit themselves unless they emit light.
+    fn is_opaque(&self, voxel: Voxel) -> bool;
+    /// Block light level emitted by the voxel, 0 for most blocks.
+    fn emission(&self, voxel: Voxel) -> u8;
+}
+
+impl LightRules for BlockDatabaseSlim {
+    fn is_opaque(&self, voxel: Voxel) -> bool {
+        self.properties(voxel).opaque
+    }
+
+    fn emission(&self, voxel: Voxel) -> u8 {
+        self.properties(voxel).light_emission
+    }
+}
+
+/// Voxels and light levels the propagator is allowed to read and write.
+pub trait LightView {
+    /// Returns the voxel and its light on the given channel, or None if the position is outside the view.
+    fn get(&self, pos: WorldPos, channel: LightChannel) -> Option<(Voxel, u8)>;
+    fn set(&mut self, pos: WorldPos, channel: LightChannel, level: u8);
+}
+
+/// Level of light spreading from a voxel lit at `level` to its neighbour in the given direction.
+#[inline(always)]
+pub fn spread_level(channel: LightChannel, level: u8, direction: Face) -> u8 {
+    if channel == LightChannel::Sky && direction == Face::Bottom && level == MAX_LIGHT {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+/// Breadth-first flood fill of light, with separate queues for spreading and removing light.
+/// The queues are kept between runs to avoid reallocating them.
+#[derive(Default)]
+pub struct LightPropagator {
+    spread_queue: VecDeque<(WorldPos, LightChannel)>,
+    removal_queue: VecDeque<(WorldPos, LightChannel, u8)>,
+}
+
+impl LightPropagator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a lit voxel to spread its light to its neighbours.
+    pub fn enqueue_spread(&mut self, pos: WorldPos, channel: LightChannel) {
+        self.spread_queue.push_back((pos, channel));
+    }
+
+    /// Queues the removal of light that was at `level` before the voxel was darkened.
+    /// The voxel's own light must already have been cleared.
+    pub fn enqueue_removal(&mut self, pos: WorldPos, channel: LightChannel, level: u8) {
+        self.removal_queue.push_back((pos, channel, level));
+    }
+
+    /// Updates the light around a voxel that has changed.
+    /// The light it held is removed, and then refilled from its emission and neighbours.
+    pub fn enqueue_voxel_change(
+        &mut self,
+        view: &mut impl LightView,
+        rules: &impl LightRules,
+        pos: WorldPos,
+    ) {
+        for channel in LightChannel::all() {
+            let Some((voxel, level)) = view.get(pos, channel) else {
+                continue;
+            };
+
+            if level > 0 {
+                view.set(pos, channel, 0);
+                self.enqueue_removal(pos, channel, level);
+            }
+
+            let emission = match channel {
+                LightChannel::Sky => 0,
+                LightChannel::Block => rules.emission(voxel),
+            };
+            if emission > 0 {
+                view.set(pos, channel, emission);
+                self.enqueue_spread(pos, channel);
+            }
+
+            if !rules.is_opaque(voxel) {
+                for face in Face::all() {
+                    self.enqueue_spread(pos + WorldPos(face.to_ivec3()), channel);
+                }
+            }
+        }
+    }
+
+    /// Runs all queued removals, and then spreads light until every voxel in the view is consistent.
+    pub fn propagate(&mut self, view: &mut impl LightView, rules: &impl LightRules) {
+        while let Some((pos, channel, level)) = self.removal_queue.pop_front() {
+            for face in Face::all() {
+                let neighbor = pos + WorldPos(face.to_ivec3());
+                let Some((voxel, current)) = view.get(neighbor, channel) else {
+                    continue;
+                };
+
+                if current == 0 {
+                    continue;
+                }
+
+                // Light that could have come from the removed voxel is removed as well.
+                // Anything brighter has another source, and refills the removed area.
+                let dependent = current < level
+                    || (channel == LightChannel::Sky
+                        && face == Face::Bottom
+                        && level == MAX_LIGHT
+                        && current == MAX_LIGHT);
+
+                if dependent {
+                    let emission = match channel {
+                        LightChannel::Sky => 0,
+                        LightChannel::Block => rules.emission(voxel),
+                    };
+                    view.set(neighbor, channel, emission);
+                    self.enqueue_removal(neighbor, channel, current);
+                    if emission > 0 {
+                        self.enqueue_spread(neighbor, channel);
+                    }
+                } else {
+                    self.enqueue_spread(neighbor, channel);
+                }
+            }
+        }
+
+        while let Some((pos, channel)) = self.spread_queue.pop_front() {
+            let Some((_, level)) = view.get(pos, channel) else {
+                continue;
+            };
+
+            if level <= 1 && !(channel == LightChannel::Sky && level == MAX_LIGHT) {
+                continue;
+            }
+
+            for face in Face::all() {
+                let neighbor = pos + WorldPos(face.to_ivec3());
+                let Some((voxel, current)) = view.get(neighbor, channel) else {
+                    continue;
+                };
+
+                if rules.is_opaque(voxel) {
+                    continue;
+                }
+
+                let spread = spread_level(channel, level, face);
+                if spread > current {
+                    view.set(neighbor, channel, spread);
+                    self.enqueue_spread(neighbor, channel);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Treats every non-air voxel as opaque, and gold as a light source.
+    pub(crate) struct TestRules;
+
+    impl LightRules for TestRules {
+        fn is_opaque(&self, voxel: Voxel) -> bool {
+            !voxel.is_air()
+        }
+
+        fn emission(&self, voxel: Voxel) -> u8 {
+            if voxel == Voxel::GOLD { 14 } else { 0 }
+        }
+    }
+
+    /// Unbounded view of air, with a few solid voxels
+    #[derive(Default)]
+    struct SparseView {
+        voxels: HashMap<WorldPos, Voxel>,
+        light: HashMap<(WorldPos, LightChannel), u8>,
+        bounds: i32,
+    }
+
+    impl LightView for SparseView {
+        fn get(&self, pos: WorldPos, channel: LightChannel) -> Option<(Voxel, u8)> {
+            if pos.0.abs().max_element() > self.bounds {
+                return None;
+            }
+            let voxel = self.voxels.get(&pos).copied().unwrap_or(Voxel::AIR);
+            let level = self.light.get(&(pos, channel)).copied().unwrap_or(0);
+            Some((voxel, level))
+        }
+
+        fn set(&mut self, pos: WorldPos, channel: LightChannel, level: u8) {
+            self.light.insert((pos, channel), level);
+        }
+    }
+
+    #[test]
+    fn test_block_light_spreads_and_is_removed() {
+        let mut view = SparseView {
+            bounds: 20,
+            ..Default::default()
+        };
+        let mut propagator = LightPropagator::new();
+        let source = WorldPos::new(0, 0, 0);
+        let wall = WorldPos::new(1, 0, 0);
+
+        view.voxels.insert(wall, Voxel::DIRT);
+        view.voxels.insert(source, Voxel::GOLD);
+        propagator.enqueue_voxel_change(&mut view, &TestRules, source);
+        propagator.propagate(&mut view, &TestRules);
+
+        let block_light = |view: &SparseView, pos| view.get(pos, LightChannel::Block).unwrap().1;
+        assert_eq!(block_light(&view, source), 14);
+        assert_eq!(block_light(&view, WorldPos::new(-3, 0, 0)), 11);
+        assert_eq!(block_light(&view, wall), 0);
+        // Light goes around the wall
+        assert_eq!(block_light(&view, WorldPos::new(2, 0, 0)), 10);
+
+        view.voxels.remove(&source);
+        propagator.enqueue_voxel_change(&mut view, &TestRules, source);
+        propagator.propagate(&mut view, &TestRules);
+
+        assert!(view.light.values().all(|level| *level == 0));
+    }
+}
@@ -21,6 +21,11 @@ impl AABB {
     pub fn extent(&self) -> Vec3 {
         (self.max - self.min) * 0.5
     }
+
+    /// Returns true if the boxes overlap. Boxes that only touch don't count as intersecting.
+    pub fn intersects(&self, other: &AABB) -> bool {
+        self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
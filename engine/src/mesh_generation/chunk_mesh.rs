@@ -1,14 +1,20 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{U8Vec2, U8Vec3};
+use glam::{U8Vec2, U8Vec3, Vec3};
 
 use crate::{
+    assets::block_models::{CrossDiagonal, MODEL_GRID_SIZE},
+    lighting::chunk_light::PackedLight,
     math::aabb::AABB8,
     voxels::{coord::ChunkPos, face::Face},
+    worldgen::ChunkTint,
 };
 
+/// Version of the packed face encoding, stored in every face so the shader can reject stale data.
+pub const PACKED_FACE_VERSION: u8 = 5;
+
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
-/// A voxel face packed into 6 bytes (48 bits).
+/// A voxel face packed into 12 bytes (96 bits).
 /// Stored as a byte array to avoid padding & alignment issues.
 ///
 /// Layout:
@@ -16,7 +22,7 @@ use crate::{
 ///   - bits 0-3:   position.x (0-15)
 ///   - bits 4-7:   position.y (0-15)
 ///   - bits 8-11:  position.z (0-15)
-///   - bits 12-14: face_id (0-5)
+///   - bits 12-14: face_id (0-5, or 6-7 for the diagonals of shaped faces)
 ///   - bit 15:     flip_diagonal
 ///   - bits 16-19: width - 1 (0-15)
 ///   - bits 20-23: height - 1 (0-15)
@@ -24,9 +30,33 @@ use crate::{
 ///   - bits 26-27: AO bottom-right (0-3)
 ///   - bits 28-29: AO top-right (0-3)
 ///   - bits 30-31: AO top-left (0-3)
-/// - Bytes 4-5: Texture index (16 bits, little-endian)
+/// - Bytes 4-5: Texture (16 bits, little-endian)
+///   - bits 0-11:  texture index (0-4095)
+///   - bits 12-13: texture rotation, see `VoxelFace::texture_rotation`
+///   - bit 14:     texture mirrored, see `VoxelFace::texture_mirrored`
+///   - bit 15:     Reserved
+/// - Byte 6: Encoding version, see `PACKED_FACE_VERSION`
+/// - Byte 7: Flags
+///   - bit 0: full_bright
+///   - bit 1: shaped, see `FaceShape`
+///   - bits 2-5: inset of shaped faces (0-15)
+///   - bit 6: sloped, see `FaceShape::Sloped`
+///   - bit 7: Reserved
+/// - Bytes 8-11: Light of each corner as a `PackedLight` (sky in the high nibble, block in the low nibble)
+///   - byte 8:  bottom-left
+///   - byte 9:  bottom-right
+///   - byte 10: top-right
+///   - byte 11: top-left
+///
+/// Shaped faces measure their size in model grid units instead of voxels, and have no AO.
+/// Their AO bits store the offset of the face instead:
+///   - bits 24-27: offset along the width (0-15)
+///   - bits 28-31: offset along the height (0-15)
+///
+/// Sloped faces always cover their whole voxel face, so their size and AO bits store how far each corner
+/// is lowered instead, 4 bits each in the same corner order as the light.
 pub struct PackedVoxelFace {
-    bytes: [u8; 6],
+    bytes: [u8; 12],
 }
 
 pub struct VoxelFace {
@@ -36,45 +66,131 @@ pub struct VoxelFace {
     /// Ambient occlusion values for each vertex (0-3)
     /// Order: bottom-left, bottom-right, top-right, top-left
     pub ambient_occlusion: [u8; 4],
+    /// Smoothed light of each vertex, in the same order as `ambient_occlusion`
+    pub light: [PackedLight; 4],
     /// By default, the diagonal goes from bottom-left to top-right
     /// Set to true to flip the diagonal.
     /// This is used to get better looking ambient occlusion.
     pub flip_diagonal: bool,
     pub texture_index: u16,
+    /// Counter-clockwise quarter turns of the texture (0-3), used for rotated blocks like sideways logs
+    pub texture_rotation: u8,
+    /// Texture is mirrored horizontally before it's turned, used for random texture variants
+    pub texture_mirrored: bool,
+    /// Drawn at full brightness without AO or shading, used for light emitting blocks
+    pub full_bright: bool,
+    /// Placement of block model faces, which don't cover whole voxels. None for greedy meshed faces.
+    pub shape: Option<FaceShape>,
+}
+
+/// Placement of a block model face within its voxel, in model grid units.
+/// The size of a shaped face is in model grid units as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceShape {
+    /// Rectangle starting at `offset` on the voxel face, moved `inset` units towards the center of the voxel
+    Inset { offset: U8Vec2, inset: u8 },
+    /// Vertical plane across the whole voxel. The face direction isn't stored, and unpacks as `Face::Front`.
+    Diagonal(CrossDiagonal),
+    /// Whole voxel face with each corner lowered by a number of model grid units, used for the surfaces of fluids.
+    /// Order: bottom-left, bottom-right, top-right, top-left
+    Sloped { drops: [u8; 4] },
 }
 
+const SHAPED_FLAG: u8 = 1 << 1;
+const SLOPED_FLAG: u8 = 1 << 6;
+const DIAGONAL_FACE_IDS: [u32; 2] = [6, 7];
+
 impl From<VoxelFace> for PackedVoxelFace {
     fn from(value: VoxelFace) -> Self {
         let mut geometry = 0u32;
         geometry |= (value.position.x as u32) & 0xF;
         geometry |= ((value.position.y as u32) & 0xF) << 4;
         geometry |= ((value.position.z as u32) & 0xF) << 8;
-        geometry |= ((value.face_direction as u32) & 0x7) << 12;
+        let face_id = match value.shape {
+            Some(FaceShape::Diagonal(diagonal)) => DIAGONAL_FACE_IDS[diagonal as usize],
+            _ => value.face_direction as u32,
+        };
+        geometry |= (face_id & 0x7) << 12;
         if value.flip_diagonal {
             geometry |= 1 << 15;
         }
-        geometry |= ((value.size.x.saturating_sub(1) as u32) & 0xF) << 16;
-        geometry |= ((value.size.y.saturating_sub(1) as u32) & 0xF) << 20;
-        geometry |= ((value.ambient_occlusion[0] as u32) & 0x3) << 24;
-        geometry |= ((value.ambient_occlusion[1] as u32) & 0x3) << 26;
-        geometry |= ((value.ambient_occlusion[2] as u32) & 0x3) << 28;
-        geometry |= ((value.ambient_occlusion[3] as u32) & 0x3) << 30;
+        if !matches!(value.shape, Some(FaceShape::Sloped { .. })) {
+            geometry |= ((value.size.x.saturating_sub(1) as u32) & 0xF) << 16;
+            geometry |= ((value.size.y.saturating_sub(1) as u32) & 0xF) << 20;
+        }
 
-        let texture_index = value.texture_index;
+        let mut flags = value.full_bright as u8;
+        match value.shape {
+            None => {
+                geometry |= ((value.ambient_occlusion[0] as u32) & 0x3) << 24;
+                geometry |= ((value.ambient_occlusion[1] as u32) & 0x3) << 26;
+                geometry |= ((value.ambient_occlusion[2] as u32) & 0x3) << 28;
+                geometry |= ((value.ambient_occlusion[3] as u32) & 0x3) << 30;
+            }
+            Some(FaceShape::Inset { offset, inset }) => {
+                geometry |= ((offset.x as u32) & 0xF) << 24;
+                geometry |= ((offset.y as u32) & 0xF) << 28;
+                flags |= SHAPED_FLAG | ((inset & 0xF) << 2);
+            }
+            Some(FaceShape::Diagonal(_)) => flags |= SHAPED_FLAG,
+            Some(FaceShape::Sloped { drops }) => {
+                for (corner, drop) in drops.iter().enumerate() {
+                    geometry |= ((*drop as u32) & 0xF) << (16 + corner * 4);
+                }
+                flags |= SHAPED_FLAG | SLOPED_FLAG;
+            }
+        }
+
+        let texture = (value.texture_index & 0xFFF)
+            | (((value.texture_rotation as u16) & 0x3) << 12)
+            | ((value.texture_mirrored as u16) << 14);
 
-        let mut bytes = [0u8; 6];
+        let mut bytes = [0u8; 12];
         bytes[0..4].copy_from_slice(&geometry.to_le_bytes());
-        bytes[4..6].copy_from_slice(&texture_index.to_le_bytes());
+        bytes[4..6].copy_from_slice(&texture.to_le_bytes());
+        bytes[6] = PACKED_FACE_VERSION;
+        bytes[7] = flags;
+        for (byte, light) in bytes[8..12].iter_mut().zip(value.light) {
+            *byte = light.0;
+        }
         PackedVoxelFace { bytes }
     }
 }
 
 impl PackedVoxelFace {
-    /// Should only be used for debugging and tests.
+    pub fn version(&self) -> u8 {
+        self.bytes[6]
+    }
+
+    /// Center of the face relative to its chunk. Shaped faces use the center of their voxel.
+    pub fn center(&self) -> Vec3 {
+        let face = self.unpack();
+        let position = face.position.as_vec3();
+        if face.shape.is_some() {
+            return position + Vec3::splat(0.5);
+        }
+
+        let axis = face.face_direction.axis();
+        let depth = if face.face_direction.is_positive() {
+            1.0
+        } else {
+            0.0
+        };
+        let size = face.size.as_vec2() * 0.5;
+        position
+            + axis.u_axis().as_unit_vector().as_vec3() * size.x
+            + axis.v_axis().as_unit_vector().as_vec3() * size.y
+            + axis.as_unit_vector().as_vec3() * depth
+    }
+
+    /// Too slow for rendering, used for debugging, tests and mesh export.
     pub fn unpack(&self) -> VoxelFace {
         let geometry =
             u32::from_le_bytes([self.bytes[0], self.bytes[1], self.bytes[2], self.bytes[3]]);
-        let texture_index = u16::from_le_bytes([self.bytes[4], self.bytes[5]]);
+        let texture = u16::from_le_bytes([self.bytes[4], self.bytes[5]]);
+        let texture_index = texture & 0xFFF;
+        let texture_rotation = ((texture >> 12) & 0x3) as u8;
+        let texture_mirrored = (texture >> 14) & 0x1 != 0;
 
         let x = (geometry & 0xF) as u8;
         let y = ((geometry >> 4) & 0xF) as u8;
@@ -86,21 +202,62 @@ impl PackedVoxelFace {
 
         let width = ((geometry >> 16) & 0xF) as u8 + 1;
         let height = ((geometry >> 20) & 0xF) as u8 + 1;
-        let size = U8Vec2::new(width, height);
+        let mut size = U8Vec2::new(width, height);
+
+        let flags = self.bytes[7];
+        let full_bright = (flags & 0x1) != 0;
+        let shaped = (flags & SHAPED_FLAG) != 0;
+        let sloped = (flags & SLOPED_FLAG) != 0;
 
-        let ao0 = ((geometry >> 24) & 0x3) as u8;
-        let ao1 = ((geometry >> 26) & 0x3) as u8;
-        let ao2 = ((geometry >> 28) & 0x3) as u8;
-        let ao3 = ((geometry >> 30) & 0x3) as u8;
-        let ambient_occlusion = [ao0, ao1, ao2, ao3];
+        let (face_direction, shape, ambient_occlusion) = if shaped {
+            let shape = match face_id {
+                _ if sloped => {
+                    size = U8Vec2::splat(MODEL_GRID_SIZE);
+                    FaceShape::Sloped {
+                        drops: std::array::from_fn(|corner| {
+                            ((geometry >> (16 + corner * 4)) & 0xF) as u8
+                        }),
+                    }
+                }
+                6 => FaceShape::Diagonal(CrossDiagonal::Rising),
+                7 => FaceShape::Diagonal(CrossDiagonal::Falling),
+                _ => FaceShape::Inset {
+                    offset: U8Vec2::new(
+                        ((geometry >> 24) & 0xF) as u8,
+                        ((geometry >> 28) & 0xF) as u8,
+                    ),
+                    inset: (flags >> 2) & 0xF,
+                },
+            };
+            let face_direction = Face::try_from(face_id).unwrap_or(Face::Front);
+            (face_direction, Some(shape), [0; 4])
+        } else {
+            let ao0 = ((geometry >> 24) & 0x3) as u8;
+            let ao1 = ((geometry >> 26) & 0x3) as u8;
+            let ao2 = ((geometry >> 28) & 0x3) as u8;
+            let ao3 = ((geometry >> 30) & 0x3) as u8;
+            (Face::try_from(face_id).unwrap(), None, [ao0, ao1, ao2, ao3])
+        };
+
+        let light = [
+            PackedLight(self.bytes[8]),
+            PackedLight(self.bytes[9]),
+            PackedLight(self.bytes[10]),
+            PackedLight(self.bytes[11]),
+        ];
 
         VoxelFace {
             position,
-            face_direction: Face::try_from(face_id).unwrap(),
+            face_direction,
             size,
             ambient_occlusion,
+            light,
             flip_diagonal,
             texture_index,
+            texture_rotation,
+            texture_mirrored,
+            full_bright,
+            shape,
         }
     }
 }
@@ -111,6 +268,10 @@ pub struct ChunkMeshData {
     pub aabb: AABB8,
     pub opaque_faces: Vec<PackedVoxelFace>,
     pub alpha_cutout_faces: Vec<PackedVoxelFace>,
+    /// Faces with partially transparent textures. They're drawn last and need to be sorted back to front.
+    pub alpha_blend_faces: Vec<PackedVoxelFace>,
+    /// Biome tint, only included for chunks with tinted blocks
+    pub tint: Option<Box<ChunkTint>>,
 }
 
 impl ChunkMeshData {
@@ -124,10 +285,111 @@ impl ChunkMeshData {
             aabb: AABB8::new(U8Vec3::splat(0), U8Vec3::splat(15)),
             opaque_faces: Vec::new(),
             alpha_cutout_faces: Vec::new(),
+            alpha_blend_faces: Vec::new(),
+            tint: None,
         }
     }
 
     pub fn total_faces(&self) -> usize {
-        self.opaque_faces.len() + self.alpha_cutout_faces.len()
+        self.opaque_faces.len() + self.alpha_cutout_faces.len() + self.alpha_blend_faces.len()
+    }
+}
+
+/// Sorts faces so that the ones furthest from `eye` come first. `eye` is relative to the chunk of the faces.
+/// Faces are compared by their centers, which is enough for blended faces that don't intersect.
+pub fn sort_faces_back_to_front(faces: &mut [PackedVoxelFace], eye: Vec3) {
+    faces.sort_by_cached_key(|face| {
+        std::cmp::Reverse(face.center().distance_squared(eye).to_bits())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faces_are_sorted_back_to_front() {
+        let face = |x: u8, face_direction: Face| {
+            PackedVoxelFace::from(VoxelFace {
+                position: U8Vec3::new(x, 0, 0),
+                face_direction,
+                size: U8Vec2::new(1, 1),
+                ambient_occlusion: [0; 4],
+                light: [PackedLight::DARK; 4],
+                flip_diagonal: false,
+                texture_index: x as u16,
+                texture_rotation: 0,
+                texture_mirrored: false,
+                full_bright: false,
+                shape: None,
+            })
+        };
+        let mut faces = [
+            face(2, Face::Left),
+            face(2, Face::Right),
+            face(8, Face::Top),
+            face(0, Face::Right),
+        ];
+
+        sort_faces_back_to_front(&mut faces, Vec3::new(-1.0, 0.5, 0.5));
+        let order = faces
+            .iter()
+            .map(|f| {
+                let f = f.unpack();
+                (f.position.x, f.face_direction)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                (8, Face::Top),
+                (2, Face::Right),
+                (2, Face::Left),
+                (0, Face::Right)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shaped_faces_round_trip() {
+        let face = |shape| VoxelFace {
+            position: U8Vec3::new(3, 15, 7),
+            face_direction: Face::Right,
+            size: U8Vec2::new(16, 8),
+            ambient_occlusion: [0; 4],
+            light: [PackedLight::new(12, 3); 4],
+            flip_diagonal: false,
+            texture_index: 42,
+            texture_rotation: 3,
+            texture_mirrored: true,
+            full_bright: false,
+            shape,
+        };
+
+        let inset = Some(FaceShape::Inset {
+            offset: U8Vec2::new(0, 8),
+            inset: 15,
+        });
+        let unpacked = PackedVoxelFace::from(face(inset)).unpack();
+        assert_eq!(unpacked.shape, inset);
+        assert_eq!(unpacked.face_direction, Face::Right);
+        assert_eq!(unpacked.size, U8Vec2::new(16, 8));
+        assert_eq!(unpacked.position, U8Vec3::new(3, 15, 7));
+
+        let diagonal = Some(FaceShape::Diagonal(CrossDiagonal::Falling));
+        let unpacked = PackedVoxelFace::from(face(diagonal)).unpack();
+        assert_eq!(unpacked.shape, diagonal);
+        assert_eq!(unpacked.texture_index, 42);
+        assert_eq!(unpacked.texture_rotation, 3);
+        assert!(unpacked.texture_mirrored);
+        assert_eq!(unpacked.light, [PackedLight::new(12, 3); 4]);
+
+        let sloped = Some(FaceShape::Sloped {
+            drops: [0, 15, 2, 9],
+        });
+        let unpacked = PackedVoxelFace::from(face(sloped)).unpack();
+        assert_eq!(unpacked.shape, sloped);
+        assert_eq!(unpacked.face_direction, Face::Right);
+        assert_eq!(unpacked.size, U8Vec2::splat(MODEL_GRID_SIZE));
     }
 }
@@ -1,6 +1,8 @@
 use thiserror::Error;
 
 use crate::{
+    assets::blocks::BlockDatabaseSlim,
+    lighting::chunk_light::PackedLight,
     voxels::{
         border::Border,
         chunk::{ChunkState, IChunkRenderState},
@@ -10,6 +12,7 @@ use crate::{
         voxel::Voxel,
     },
     world::WorldChunks,
+    worldgen::{ChunkClimate, ChunkTint},
 };
 
 // Non-critical errors that can occur during meshing
@@ -49,6 +52,8 @@ pub struct ChunkMeshGeneratorInput {
     pub center: Box<UnpackedChunk>,
     // The neighbors of a chunk, in opposite face order (Bottom, Top, Right, Left, Back, Front)
     pub neighbors: Box<[Border; 6]>,
+    /// Biome tint of the chunk, blended with its horizontal neighbours
+    pub tint: ChunkTint,
 }
 
 impl ChunkMeshGeneratorInput {
@@ -66,12 +71,14 @@ impl ChunkMeshGeneratorInput {
             center: Box::new(center),
             center_pos,
             neighbors,
+            tint: ChunkTint::default(),
         }
     }
 
     pub fn try_from_map<T: IChunkRenderState>(
         chunks: &WorldChunks<T>,
         center_pos: ChunkPos,
+        block_database: &BlockDatabaseSlim,
     ) -> Result<Option<Self>, MeshGeneratorInputError> {
         let Some(chunk) = chunks.get(&center_pos) else {
             return Err(MeshGeneratorWarning::ChunkMissing {
@@ -100,6 +107,9 @@ impl ChunkMeshGeneratorInput {
             (Border::new(Face::Front)),
         ]);
 
+        let center_climate = chunk.climate.clone();
+        // Climates of the horizontal neighbors in the order -X, +X, -Z, +Z
+        let mut neighbor_climates: [Option<ChunkClimate>; 4] = Default::default();
         let mut neighbors_occlude = true;
 
         for (i, face) in Face::all().iter().enumerate() {
@@ -121,7 +131,17 @@ impl ChunkMeshGeneratorInput {
                 .into());
             }
 
-            neighbors[i].copy_from_chunk(&neighbor_chunk);
+            neighbors[i].copy_from_chunk(&neighbor_chunk, block_database);
+            let climate_index = match face {
+                Face::Left => Some(0),
+                Face::Right => Some(1),
+                Face::Back => Some(2),
+                Face::Front => Some(3),
+                Face::Top | Face::Bottom => None,
+            };
+            if let Some(index) = climate_index {
+                neighbor_climates[index] = neighbor_chunk.climate.clone();
+            }
             if neighbors_occlude && !neighbors[i].occludes {
                 neighbors_occlude = false;
             }
@@ -133,10 +153,16 @@ impl ChunkMeshGeneratorInput {
             return Ok(None);
         }
 
+        let tint = ChunkTint::blend(
+            center_climate.as_ref(),
+            neighbor_climates.each_ref().map(Option::as_ref),
+        );
+
         Ok(Some(ChunkMeshGeneratorInput {
             center,
             center_pos,
             neighbors,
+            tint,
         }))
     }
 
@@ -166,6 +192,43 @@ impl ChunkMeshGeneratorInput {
         neighbor_border.get_voxel(local_pos)
     }
 
+    /// Gets the light of a voxel in the center chunk or in one of the borders.
+    pub fn get_light(&self, world_pos: WorldPos) -> Option<PackedLight> {
+        let chunk_pos = world_pos.to_chunk_pos();
+        let local_pos = world_pos.to_local_pos();
+
+        if chunk_pos == self.center_pos {
+            return self.center.get_light(local_pos);
+        }
+
+        let face = Self::neighbor_face(chunk_pos - self.center_pos)?;
+        self.neighbors[face as usize].get_light(local_pos)
+    }
+
+    /// This should only be used in tests.
+    pub fn set_light(&mut self, world_pos: WorldPos, light: PackedLight) {
+        assert_eq!(
+            world_pos.to_chunk_pos(),
+            self.center_pos,
+            "Light can only be set in the center chunk"
+        );
+        let index = world_pos.to_local_pos().to_chunk_data_index();
+        self.center.light[index] = light;
+    }
+
+    /// Face of the center chunk shared with the neighbor at the given chunk offset.
+    fn neighbor_face(offset: ChunkPos) -> Option<Face> {
+        match (offset.0.x, offset.0.y, offset.0.z) {
+            (1, 0, 0) => Some(Face::Right),
+            (-1, 0, 0) => Some(Face::Left),
+            (0, 1, 0) => Some(Face::Top),
+            (0, -1, 0) => Some(Face::Bottom),
+            (0, 0, 1) => Some(Face::Front),
+            (0, 0, -1) => Some(Face::Back),
+            _ => None,
+        }
+    }
+
     /// This should only be used in tests.
     pub fn set_voxel(&mut self, world_pos: WorldPos, voxel: Voxel) {
         // Determine which chunk the world_pos belongs to
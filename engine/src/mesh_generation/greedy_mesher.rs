@@ -1,14 +1,21 @@
 use std::sync::Arc;
 
 use crate::{
-    assets::blocks::BlockDatabaseSlim,
+    assets::{
+        block_models::{FaceRect, MODEL_GRID_SIZE, ModelFace},
+        blocks::{BiomeTint, BlockDatabaseSlim},
+        texture_variants::{CONNECTED_NEIGHBORS, SelectedTexture},
+        world_textures::TextureTransparency,
+    },
+    fluids::fluid::{FluidDefinition, FluidLevel},
+    lighting::chunk_light::{MAX_LIGHT, PackedLight},
     math::{
         axis::Axis,
         basis::Basis,
         local_vec::{ConstructLocalVec3, LocalVec3},
     },
     mesh_generation::{
-        chunk_mesh::{ChunkMeshData, PackedVoxelFace, VoxelFace},
+        chunk_mesh::{ChunkMeshData, FaceShape, PackedVoxelFace, VoxelFace},
         chunk_mesh_generator_input::ChunkMeshGeneratorInput,
     },
     voxels::{
@@ -30,11 +37,33 @@ enum FaceDirection {
 enum MaskEntry {
     #[default]
     Empty,
-    VoxelFace {
-        voxel: Voxel,
-        direction: FaceDirection,
-        ao: [u8; 4],
-    },
+    VoxelFace(MaskFace),
+}
+
+/// Adjacent faces are merged into one quad when all of these match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MaskFace {
+    voxel: Voxel,
+    direction: FaceDirection,
+    shading: FaceShading,
+    /// Texture variant picked for the face's own voxel
+    texture: SelectedTexture,
+}
+
+/// Per-corner shading of a face.
+/// Order: bottom-left, bottom-right, top-right, top-left
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct FaceShading {
+    ao: [u8; 4],
+    light: [PackedLight; 4],
+}
+
+impl FaceShading {
+    /// Shading of light emitting blocks, which lets all of their faces merge regardless of their surroundings
+    const FULL_BRIGHT: FaceShading = FaceShading {
+        ao: [0; 4],
+        light: [PackedLight::new(MAX_LIGHT, MAX_LIGHT); 4],
+    };
 }
 
 pub struct GreedyMesher {
@@ -56,20 +85,63 @@ impl GreedyMesher {
         for d_axis in AXES {
             self.create_faces_for_axis(input, &mut chunk_mesh_data, d_axis);
         }
+        self.create_model_faces(input, &mut chunk_mesh_data);
         chunk_mesh_data.aabb = input.center.compute_aabb();
+        if self.has_tinted_blocks(input) && chunk_mesh_data.total_faces() > 0 {
+            chunk_mesh_data.tint = Some(Box::new(input.tint));
+        }
         chunk_mesh_data
     }
 
+    pub fn block_database(&self) -> &BlockDatabaseSlim {
+        &self.block_database
+    }
+
+    fn has_tinted_blocks(&self, input: &ChunkMeshGeneratorInput) -> bool {
+        input
+            .center
+            .voxels
+            .iter()
+            .any(|voxel| self.block_database.properties(*voxel).tint != BiomeTint::None)
+    }
+
     /// Gets the voxel at the given position. The position is provided as chunk-relative coordinates.
     /// If the position is out of bounds for the current chunk, it queries the world for the voxel instead.
     fn get_voxel(&self, input: &ChunkMeshGeneratorInput, offset: IVec3) -> Option<Voxel> {
         let world_pos = input.center_pos.origin() + WorldPos::from(offset);
-        let voxel = input.get_voxel(world_pos);
+        let voxel = input.get_voxel(world_pos)?;
+
+        // Treat invisible blocks like air as None
+        self.block_database
+            .properties(voxel)
+            .visible
+            .then_some(voxel)
+    }
+
+    /// Whether the `face` side of `voxel` is visible next to `neighbor`.
+    /// Faces are hidden by neighbors that cover them, and between transparent blocks of the same type.
+    fn is_face_exposed(&self, voxel: Voxel, neighbor: Option<Voxel>, face: Face) -> bool {
+        match neighbor {
+            None => true,
+            Some(neighbor) => {
+                !self
+                    .block_database
+                    .occludes(neighbor, face.opposite(), &FaceRect::FULL)
+                    && neighbor.block_type() != voxel.block_type()
+            }
+        }
+    }
 
-        // Treat AIR as None
-        match voxel {
-            None | Some(Voxel::AIR) => None,
-            Some(voxel) => Some(voxel),
+    /// Faces are drawn by the pipeline matching the transparency of their texture.
+    fn face_list<'a>(
+        &self,
+        mesh_data: &'a mut ChunkMeshData,
+        voxel: Voxel,
+    ) -> &'a mut Vec<PackedVoxelFace> {
+        match self.block_database.transparency(voxel) {
+            TextureTransparency::Opaque => &mut mesh_data.opaque_faces,
+            TextureTransparency::AlphaCutout => &mut mesh_data.alpha_cutout_faces,
+            TextureTransparency::AlphaBlend => &mut mesh_data.alpha_blend_faces,
         }
     }
 
@@ -80,68 +152,106 @@ impl GreedyMesher {
         d_axis: Axis,
     ) {
         let basis = Basis::new(d_axis.u_axis(), d_axis.v_axis(), d_axis);
-        let mut mask: Mask = [MaskEntry::Empty; MASK_SIZE];
+        // Transparent blocks can expose faces in both directions at once, so each direction gets its own mask
+        let mut positive_mask: Mask = [MaskEntry::Empty; MASK_SIZE];
+        let mut negative_mask: Mask = [MaskEntry::Empty; MASK_SIZE];
 
         // Iterate across the depth of the chunk along the current axis
         // Note that this is an inclusive range to handle the back faces of the last layer
         for depth in 0..=(CHUNK_SIZE as i32) {
-            self.create_mask_for_slice(&mut mask, input, basis, depth);
-            self.create_faces_at_depth(&mut mask, mesh_data, basis, depth);
+            let [has_positive, has_negative] = self.create_masks_for_slice(
+                &mut positive_mask,
+                &mut negative_mask,
+                input,
+                basis,
+                depth,
+            );
+            if has_positive {
+                self.create_faces_at_depth(&mut positive_mask, mesh_data, basis, depth);
+            }
+            if has_negative {
+                self.create_faces_at_depth(&mut negative_mask, mesh_data, basis, depth);
+            }
         }
     }
 
-    fn create_mask_for_slice(
+    /// Fills the masks of the faces on the slice between `depth - 1` and `depth`, in one pass over its voxels.
+    /// The back voxel's positive faces go in `positive_mask` and the front voxel's negative faces in `negative_mask`.
+    /// Returns whether each mask has any faces.
+    fn create_masks_for_slice(
         &self,
-        mask: &mut Mask,
+        positive_mask: &mut Mask,
+        negative_mask: &mut Mask,
         input: &ChunkMeshGeneratorInput,
         basis: Basis,
         depth: i32,
-    ) {
+    ) -> [bool; 2] {
         const N: i32 = CHUNK_SIZE as i32;
 
+        // Only generate faces for voxels that belong to the current chunk
+        let back_in_chunk = (0..N).contains(&(depth - 1));
+        let front_in_chunk = (0..N).contains(&depth);
+        let mut has_faces = [false; 2];
+
         for v in 0..N {
             for u in 0..N {
                 let pos = LocalVec3::from_uvd(u, v, depth, basis);
-                let front = pos.offset(0, 0, -1).to_world();
-                let back = pos.to_world();
-                let voxel_front = self.get_voxel(input, front);
-                let voxel_back = self.get_voxel(input, back);
-
-                let entry = match (voxel_front, voxel_back) {
-                    // Voxel's front face is exposed
-                    (Some(voxel), None) => {
-                        // Only generate if the voxel belongs to the current chunk
-                        if depth - 1 < 0 {
-                            MaskEntry::Empty
-                        } else {
-                            let ao = self.calculate_face_ao(input, pos, FaceDirection::Positive);
-                            MaskEntry::VoxelFace {
-                                voxel,
-                                direction: FaceDirection::Positive,
-                                ao,
-                            }
-                        }
-                    }
-                    // Voxel's back face is exposed
-                    (None, Some(voxel)) => {
-                        // Only generate if the voxel belongs to the current chunk
-                        if depth >= N {
-                            MaskEntry::Empty
-                        } else {
-                            let ao = self.calculate_face_ao(input, pos, FaceDirection::Negative);
-                            MaskEntry::VoxelFace {
-                                voxel,
-                                direction: FaceDirection::Negative,
-                                ao,
-                            }
-                        }
-                    }
-                    // Both faces of the voxel are either exposed or hidden, skip
-                    _ => MaskEntry::Empty,
+                let back = self.get_voxel(input, pos.offset(0, 0, -1).to_world());
+                let front = self.get_voxel(input, pos.to_world());
+
+                let index = (v * N + u) as usize;
+                positive_mask[index] = if back_in_chunk {
+                    self.mask_entry(input, pos, back, front, FaceDirection::Positive)
+                } else {
+                    MaskEntry::Empty
                 };
-                mask[(v * N + u) as usize] = entry;
+                negative_mask[index] = if front_in_chunk {
+                    self.mask_entry(input, pos, front, back, FaceDirection::Negative)
+                } else {
+                    MaskEntry::Empty
+                };
+                has_faces[0] |= positive_mask[index] != MaskEntry::Empty;
+                has_faces[1] |= negative_mask[index] != MaskEntry::Empty;
             }
         }
+        has_faces
+    }
+
+    /// Mask entry of the `direction` face of `owner`, which lies on the slice at `pos`
+    fn mask_entry(
+        &self,
+        input: &ChunkMeshGeneratorInput,
+        pos: LocalVec3<IVec3>,
+        owner: Option<Voxel>,
+        facing: Option<Voxel>,
+        direction: FaceDirection,
+    ) -> MaskEntry {
+        let Some(voxel) = owner else {
+            return MaskEntry::Empty;
+        };
+        // Shaped blocks and fluids emit their own faces, see `create_model_faces`
+        if !self.block_database.model(voxel).is_cube()
+            || self.block_database.properties(voxel).fluid.is_some()
+        {
+            return MaskEntry::Empty;
+        }
+
+        let face = face_from_axis(pos.basis.d, direction);
+        if !self.is_face_exposed(voxel, facing, face) {
+            return MaskEntry::Empty;
+        }
+
+        let owner_offset = match direction {
+            FaceDirection::Positive => -1,
+            FaceDirection::Negative => 0,
+        };
+        let owner_pos = pos.offset(0, 0, owner_offset).to_world();
+        MaskEntry::VoxelFace(MaskFace {
+            voxel,
+            direction,
+            shading: self.calculate_face_shading(input, pos, voxel, direction),
+            texture: self.face_texture(input, owner_pos, voxel, face),
+        })
     }
 
     fn create_faces_at_depth(
@@ -158,12 +268,7 @@ impl GreedyMesher {
         for v in 0..N {
             let mut u = 0;
             while u < N {
-                let entry @ MaskEntry::VoxelFace {
-                    voxel,
-                    direction,
-                    ao,
-                } = mask[index + u]
-                else {
+                let entry @ MaskEntry::VoxelFace(mask_face) = mask[index + u] else {
                     // The mask is empty here, skip
                     u += 1;
                     continue;
@@ -195,7 +300,7 @@ impl GreedyMesher {
                     }
                 }
 
-                let depth = match direction {
+                let depth = match mask_face.direction {
                     FaceDirection::Positive => depth - 1,
                     FaceDirection::Negative => depth,
                 };
@@ -206,9 +311,7 @@ impl GreedyMesher {
                     mesh_data,
                     origin,
                     (width as u8, height as u8).into(),
-                    voxel,
-                    direction,
-                    ao,
+                    mask_face,
                 );
 
                 // Zero out the mask entries we just consumed
@@ -230,24 +333,15 @@ impl GreedyMesher {
         chunk_mesh_data: &mut ChunkMeshData,
         origin: LocalVec3<U8Vec3>,
         size: U8Vec2,
-        voxel: Voxel,
-        direction: FaceDirection,
-        ao: [u8; 4],
+        mask_face: MaskFace,
     ) {
-        let face = match (origin.basis.d, direction) {
-            (Axis::Y, FaceDirection::Positive) => Face::Top,
-            (Axis::Y, FaceDirection::Negative) => Face::Bottom,
-            (Axis::X, FaceDirection::Negative) => Face::Left,
-            (Axis::X, FaceDirection::Positive) => Face::Right,
-            (Axis::Z, FaceDirection::Positive) => Face::Front,
-            (Axis::Z, FaceDirection::Negative) => Face::Back,
-        };
-
-        let texture_index = self
-            .block_database
-            .get_texture_indices(voxel.block_type_id())
-            .expect("Expected to find block definition")
-            .get_face_index(face);
+        let MaskFace {
+            voxel,
+            direction,
+            shading: FaceShading { ao, light },
+            texture,
+        } = mask_face;
+        let face = face_from_axis(origin.basis.d, direction);
 
         let diagonal = if (ao[0] + ao[2]) < (ao[1] + ao[3]) {
             FaceDiagonal::BottomLeftToTopRight
@@ -255,49 +349,392 @@ impl GreedyMesher {
             FaceDiagonal::TopLeftToBottomRight
         };
 
-        chunk_mesh_data
-            .opaque_faces
+        self.face_list(chunk_mesh_data, voxel)
             .push(PackedVoxelFace::from(VoxelFace {
                 position: origin.to_world(),
                 face_direction: face,
                 size,
                 ambient_occlusion: ao,
+                light,
                 flip_diagonal: diagonal == FaceDiagonal::TopLeftToBottomRight,
-                texture_index,
+                texture_index: texture.index,
+                texture_rotation: texture.rotation,
+                texture_mirrored: texture.mirrored,
+                full_bright: self.is_full_bright(voxel),
+                shape: None,
             }))
     }
 
-    fn calculate_face_ao(
+    /// Emits the faces of every voxel with a shaped model or a fluid.
+    /// They're never merged, since they don't line up with the faces of their neighbors.
+    fn create_model_faces(&self, input: &ChunkMeshGeneratorInput, mesh_data: &mut ChunkMeshData) {
+        const N: i32 = CHUNK_SIZE as i32;
+        for y in 0..N {
+            for z in 0..N {
+                for x in 0..N {
+                    let pos = IVec3::new(x, y, z);
+                    let Some(voxel) = self.get_voxel(input, pos) else {
+                        continue;
+                    };
+                    if let Some(fluid) = self.block_database.properties(voxel).fluid {
+                        self.add_fluid_faces(input, mesh_data, pos, voxel, &fluid);
+                        continue;
+                    }
+                    for model_face in self.block_database.model(voxel).faces() {
+                        self.add_model_face(input, mesh_data, pos, voxel, *model_face);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_model_face(
+        &self,
+        input: &ChunkMeshGeneratorInput,
+        mesh_data: &mut ChunkMeshData,
+        pos: IVec3,
+        voxel: Voxel,
+        model_face: ModelFace,
+    ) {
+        let (face, size, shape) = match model_face {
+            ModelFace::Box { face, rect, inset } => {
+                // Only faces on the voxel's boundary can be hidden by a neighbor
+                if inset == 0 {
+                    let neighbor = self.get_voxel(input, pos + face.to_ivec3());
+                    if neighbor.is_some_and(|neighbor| {
+                        self.block_database
+                            .occludes(neighbor, face.opposite(), &rect)
+                    }) {
+                        return;
+                    }
+                }
+                let shape = FaceShape::Inset {
+                    offset: rect.min,
+                    inset,
+                };
+                (face, rect.size(), shape)
+            }
+            ModelFace::Diagonal(diagonal) => (
+                Face::Front,
+                U8Vec2::splat(MODEL_GRID_SIZE),
+                FaceShape::Diagonal(diagonal),
+            ),
+        };
+
+        // Shaped faces are lit evenly. Light doesn't enter opaque blocks, so they use the light in front of the face.
+        let light = if self.is_full_bright(voxel) {
+            PackedLight::new(MAX_LIGHT, MAX_LIGHT)
+        } else {
+            let light_pos = match shape {
+                FaceShape::Inset { .. } if self.block_database.properties(voxel).opaque => {
+                    pos + face.to_ivec3()
+                }
+                _ => pos,
+            };
+            input
+                .get_light(input.center_pos.origin() + WorldPos::from(light_pos))
+                .unwrap_or_default()
+        };
+
+        let texture = self.face_texture(input, pos, voxel, face);
+
+        // The alpha cutout pipeline doesn't cull back faces, so diagonals are always drawn there to be visible from both sides
+        let faces = match shape {
+            FaceShape::Diagonal(_) => &mut mesh_data.alpha_cutout_faces,
+            FaceShape::Inset { .. } | FaceShape::Sloped { .. } => self.face_list(mesh_data, voxel),
+        };
+        faces.push(PackedVoxelFace::from(VoxelFace {
+            position: pos.as_u8vec3(),
+            face_direction: face,
+            size,
+            ambient_occlusion: [0; 4],
+            light: [light; 4],
+            flip_diagonal: false,
+            texture_index: texture.index,
+            texture_rotation: texture.rotation,
+            texture_mirrored: texture.mirrored,
+            full_bright: self.is_full_bright(voxel),
+            shape: Some(shape),
+        }));
+    }
+
+    /// Emits the faces of a fluid voxel, skipping the ones that touch the same fluid.
+    /// The top of the fluid slopes between the levels of the neighbouring fluid voxels.
+    fn add_fluid_faces(
+        &self,
+        input: &ChunkMeshGeneratorInput,
+        mesh_data: &mut ChunkMeshData,
+        pos: IVec3,
+        voxel: Voxel,
+        fluid: &FluidDefinition,
+    ) {
+        let is_same_fluid = |neighbor: Option<Voxel>| {
+            neighbor.is_some_and(|neighbor| neighbor.block_type() == voxel.block_type())
+        };
+        // Height of the surface at each corner of the top of the voxel, indexed by x and z
+        let corner_heights: [[u8; 2]; 2] = std::array::from_fn(|x| {
+            std::array::from_fn(|z| self.fluid_corner_height(input, pos, voxel, fluid, x, z))
+        });
+
+        let full_bright = self.is_full_bright(voxel);
+        let light = if full_bright {
+            PackedLight::new(MAX_LIGHT, MAX_LIGHT)
+        } else {
+            input
+                .get_light(input.center_pos.origin() + WorldPos::from(pos))
+                .unwrap_or_default()
+        };
+        for face in Face::all() {
+            let neighbor = self.get_voxel(input, pos + face.to_ivec3());
+            if is_same_fluid(neighbor) {
+                continue;
+            }
+
+            // Corners on the top of the voxel are lowered to the surface of the fluid
+            let axis = face.axis();
+            let drops = QUAD_CORNERS.map(|(u, v)| {
+                let mut corner = [0usize; 3];
+                corner[axis as usize] = face.is_positive() as usize;
+                corner[axis.u_axis() as usize] = u;
+                corner[axis.v_axis() as usize] = v;
+                if corner[1] == 1 {
+                    MODEL_GRID_SIZE - corner_heights[corner[0]][corner[2]]
+                } else {
+                    0
+                }
+            });
+
+            // A lowered surface can be seen below the block above it
+            let lowered = drops.iter().any(|drop| *drop > 0);
+            let occluded = neighbor.is_some_and(|neighbor| {
+                self.block_database
+                    .occludes(neighbor, face.opposite(), &FaceRect::FULL)
+            });
+            if occluded && !(face == Face::Top && lowered) {
+                continue;
+            }
+
+            let texture = self.face_texture(input, pos, voxel, face);
+            self.face_list(mesh_data, voxel)
+                .push(PackedVoxelFace::from(VoxelFace {
+                    position: pos.as_u8vec3(),
+                    face_direction: face,
+                    size: U8Vec2::splat(MODEL_GRID_SIZE),
+                    ambient_occlusion: [0; 4],
+                    light: [light; 4],
+                    flip_diagonal: false,
+                    texture_index: texture.index,
+                    texture_rotation: texture.rotation,
+                    texture_mirrored: texture.mirrored,
+                    full_bright,
+                    shape: Some(FaceShape::Sloped { drops }),
+                }));
+        }
+    }
+
+    /// Height of a fluid's surface at a corner of the top of its voxel, averaged over the four columns sharing the corner.
+    /// Fluid with more of the same fluid above it raises the corner to the top of the voxel.
+    fn fluid_corner_height(
+        &self,
+        input: &ChunkMeshGeneratorInput,
+        pos: IVec3,
+        voxel: Voxel,
+        fluid: &FluidDefinition,
+        x: usize,
+        z: usize,
+    ) -> u8 {
+        let same_fluid = |pos: IVec3| {
+            self.get_voxel(input, pos)
+                .filter(|neighbor| neighbor.block_type() == voxel.block_type())
+        };
+
+        let (mut total, mut count) = (0u32, 0u32);
+        for dx in [x as i32 - 1, x as i32] {
+            for dz in [z as i32 - 1, z as i32] {
+                let column = pos + IVec3::new(dx, 0, dz);
+                let Some(neighbor) = same_fluid(column) else {
+                    continue;
+                };
+                if same_fluid(column + IVec3::Y).is_some() {
+                    return MODEL_GRID_SIZE;
+                }
+                total += FluidLevel::from_metadata(neighbor.metadata()).height(fluid) as u32;
+                count += 1;
+            }
+        }
+
+        // The voxel's own column always counts, so there's at least one
+        (total / count.max(1)) as u8
+    }
+
+    /// Texture of the side of the voxel facing `face`, turned to match the block's rotation.
+    /// Variants are picked by the voxel's position, and connected textures by its neighbours.
+    fn face_texture(
+        &self,
+        input: &ChunkMeshGeneratorInput,
+        pos: IVec3,
+        voxel: Voxel,
+        face: Face,
+    ) -> SelectedTexture {
+        let rotation = self.block_database.rotation(voxel);
+        let world_pos = input.center_pos.origin() + WorldPos::from(pos);
+        let mut texture = self
+            .block_database
+            .get_texture_indices(voxel.block_type_id())
+            .expect("Expected to find block definition")
+            .get_face_texture(face, rotation)
+            .select(world_pos, face, || {
+                self.connected_neighbors(input, pos, voxel, face)
+            });
+        texture.rotation = (texture.rotation + rotation.texture_rotation(face)) % 4;
+        texture
+    }
+
+    /// Which neighbours on the plane of the face are the same block, in the order of `CONNECTED_NEIGHBORS`
+    fn connected_neighbors(
+        &self,
+        input: &ChunkMeshGeneratorInput,
+        pos: IVec3,
+        voxel: Voxel,
+        face: Face,
+    ) -> u8 {
+        let axis = face.axis();
+        let tangent = axis.u_axis().as_unit_vector();
+        let bitangent = axis.v_axis().as_unit_vector();
+        CONNECTED_NEIGHBORS
+            .iter()
+            .enumerate()
+            .filter(|(_, (u, v))| {
+                self.get_voxel(input, pos + tangent * *u + bitangent * *v)
+                    .is_some_and(|neighbor| neighbor.block_type() == voxel.block_type())
+            })
+            .fold(0, |mask, (bit, _)| mask | (1 << bit))
+    }
+
+    /// Light emitting blocks are drawn at full brightness.
+    fn is_full_bright(&self, voxel: Voxel) -> bool {
+        self.block_database.properties(voxel).light_emission > 0
+    }
+
+    /// Calculates the AO and smoothed light of each corner of a face.
+    /// Both are sampled from the layer of voxels in front of the face.
+    fn calculate_face_shading(
         &self,
         input: &ChunkMeshGeneratorInput,
         pos: LocalVec3<IVec3>,
+        voxel: Voxel,
         direction: FaceDirection,
-    ) -> [u8; 4] {
+    ) -> FaceShading {
+        if self.is_full_bright(voxel) {
+            return FaceShading::FULL_BRIGHT;
+        }
+
         let offset_d = match direction {
             FaceDirection::Positive => 0,
             FaceDirection::Negative => -1,
         };
 
-        let get_neighbor_voxel = |offset_u: i32, offset_v: i32| -> bool {
+        let get_neighbor = |offset_u: i32, offset_v: i32| -> (bool, Option<PackedLight>) {
             let local_pos = pos.offset(offset_u, offset_v, offset_d);
             let chunk_relative_world_pos = local_pos.to_world();
-            self.get_voxel(input, chunk_relative_world_pos).is_some()
+            let occluder = self
+                .get_voxel(input, chunk_relative_world_pos)
+                .is_some_and(|voxel| self.block_database.properties(voxel).opaque);
+            let world_pos = input.center_pos.origin() + WorldPos::from(chunk_relative_world_pos);
+            (occluder, input.get_light(world_pos))
         };
 
+        let center = get_neighbor(0, 0);
+        let bottom = get_neighbor(0, -1);
+        let top = get_neighbor(0, 1);
+        let left = get_neighbor(-1, 0);
+        let right = get_neighbor(1, 0);
+        let bottom_left = get_neighbor(-1, -1);
+        let bottom_right = get_neighbor(1, -1);
+        let top_right = get_neighbor(1, 1);
+        let top_left = get_neighbor(-1, 1);
+
         // Pack 8 neighbor samples into a single byte index
         // Bit layout: [top_left, top_right, bottom_right, bottom_left, right, left, top, bottom]
-        let index = (get_neighbor_voxel(0, -1) as usize)
-            | (get_neighbor_voxel(0, 1) as usize) << 1
-            | (get_neighbor_voxel(-1, 0) as usize) << 2
-            | (get_neighbor_voxel(1, 0) as usize) << 3
-            | (get_neighbor_voxel(-1, -1) as usize) << 4
-            | (get_neighbor_voxel(1, -1) as usize) << 5
-            | (get_neighbor_voxel(1, 1) as usize) << 6
-            | (get_neighbor_voxel(-1, 1) as usize) << 7;
-        AO_LOOKUP_TABLE[index]
+        let index = (bottom.0 as usize)
+            | (top.0 as usize) << 1
+            | (left.0 as usize) << 2
+            | (right.0 as usize) << 3
+            | (bottom_left.0 as usize) << 4
+            | (bottom_right.0 as usize) << 5
+            | (top_right.0 as usize) << 6
+            | (top_left.0 as usize) << 7;
+
+        let light = [
+            compute_corner_light(center, left, bottom, bottom_left),
+            compute_corner_light(center, right, bottom, bottom_right),
+            compute_corner_light(center, right, top, top_right),
+            compute_corner_light(center, left, top, top_left),
+        ];
+
+        FaceShading {
+            ao: AO_LOOKUP_TABLE[index],
+            light,
+        }
+    }
+}
+
+/// Corners of a quad along its tangent and bitangent, in the order used by light and AO
+const QUAD_CORNERS: [(usize, usize); 4] = [(0, 0), (1, 0), (1, 1), (0, 1)];
+
+/// Face of a voxel pointing along the given axis
+fn face_from_axis(axis: Axis, direction: FaceDirection) -> Face {
+    match (axis, direction) {
+        (Axis::Y, FaceDirection::Positive) => Face::Top,
+        (Axis::Y, FaceDirection::Negative) => Face::Bottom,
+        (Axis::X, FaceDirection::Negative) => Face::Left,
+        (Axis::X, FaceDirection::Positive) => Face::Right,
+        (Axis::Z, FaceDirection::Positive) => Face::Front,
+        (Axis::Z, FaceDirection::Negative) => Face::Back,
     }
 }
 
+/// Averages the light of the voxels touching a corner, skipping occluders.
+/// The diagonal is hidden from the corner when both sides are occluded, the same way as in AO.
+fn compute_corner_light(
+    center: (bool, Option<PackedLight>),
+    side1: (bool, Option<PackedLight>),
+    side2: (bool, Option<PackedLight>),
+    corner: (bool, Option<PackedLight>),
+) -> PackedLight {
+    let corner_visible = !(side1.0 && side2.0);
+    let samples = [
+        (center, true),
+        (side1, true),
+        (side2, true),
+        (corner, corner_visible),
+    ];
+
+    let mut sky = 0u32;
+    let mut block = 0u32;
+    let mut count = 0u32;
+    for ((occluder, light), visible) in samples {
+        let Some(light) = light else {
+            continue;
+        };
+        if occluder || !visible {
+            continue;
+        }
+        sky += light.sky() as u32;
+        block += light.block() as u32;
+        count += 1;
+    }
+
+    if count == 0 {
+        // The center is always in front of an exposed face, so this only happens with missing data
+        return center.1.unwrap_or_default();
+    }
+
+    // Round to the nearest level
+    let average = |sum: u32| ((sum + count / 2) / count) as u8;
+    PackedLight::new(average(sky), average(block))
+}
+
 /// Computes the AO value for a single corner given its two adjacent sides and diagonal neighbor.
 const fn compute_corner_ao(side1: bool, side2: bool, corner: bool) -> u8 {
     if side1 && side2 {
@@ -343,15 +780,94 @@ mod tests {
 
     use super::*;
     use crate::{
-        assets::{blocks::TextureIndices, world_textures::WorldTextureHandle},
+        assets::{
+            block_models::{BlockModel, BlockModelDefinition},
+            block_orientation::BlockOrientation,
+            blocks::{BlockProperties, BlockTypeId, TextureIndices},
+            texture_variants::{FaceTexture, TextureVariant},
+            world_textures::WorldTextureHandle,
+        },
         voxels::{coord::ChunkPos, voxel::Voxel},
     };
     use glam::{IVec3, U8Vec2, U8Vec3};
 
+    const STONE: BlockTypeId = BlockTypeId(1);
+    const LAMP: BlockTypeId = BlockTypeId(2);
+    const GLASS: BlockTypeId = BlockTypeId(3);
+    const SLAB: BlockTypeId = BlockTypeId(4);
+    const PLANT: BlockTypeId = BlockTypeId(5);
+    const WATER: BlockTypeId = BlockTypeId(6);
+    /// Picks texture 7 or 8 for each voxel
+    const VARIED: BlockTypeId = BlockTypeId(7);
+
+    /// Air followed by the blocks above, each with the texture index of its ID
     fn create_test_block_database() -> Arc<BlockDatabaseSlim> {
+        let texture = |id: BlockTypeId| TextureIndices::new_single(WorldTextureHandle(id.0));
         let mut db = BlockDatabaseSlim::new();
-        db.add_block(TextureIndices::new_single(WorldTextureHandle(0)));
-        db.add_block(TextureIndices::new_single(WorldTextureHandle(1)));
+        db.add_block_with_properties(
+            TextureIndices::new_single(WorldTextureHandle(0)),
+            BlockProperties::AIR,
+        );
+        db.add_block(texture(STONE));
+        db.add_block_with_properties(
+            texture(LAMP),
+            BlockProperties {
+                light_emission: 15,
+                ..BlockProperties::SOLID
+            },
+        );
+        db.add_block_with_properties(
+            texture(GLASS),
+            BlockProperties {
+                opaque: false,
+                ..BlockProperties::SOLID
+            },
+        );
+        let shaped = |db: &mut BlockDatabaseSlim, id, transparency, model| {
+            db.add_block_with_model(
+                texture(id),
+                BlockProperties {
+                    opaque: false,
+                    ..BlockProperties::SOLID
+                },
+                transparency,
+                BlockModel::from_definition(&model, BlockOrientation::Fixed).unwrap(),
+            )
+        };
+        shaped(
+            &mut db,
+            SLAB,
+            TextureTransparency::Opaque,
+            BlockModelDefinition::Slab,
+        );
+        shaped(
+            &mut db,
+            PLANT,
+            TextureTransparency::AlphaCutout,
+            BlockModelDefinition::Cross,
+        );
+        db.add_block_with_model(
+            texture(WATER),
+            BlockProperties {
+                fluid: Some(FluidDefinition {
+                    flow_distance: 7,
+                    tick_delay: 5,
+                }),
+                visible: true,
+                ..BlockProperties::AIR
+            },
+            TextureTransparency::AlphaBlend,
+            BlockModel::Cube,
+        );
+        let variant = |texture| TextureVariant {
+            texture: WorldTextureHandle(texture),
+            weight: 1,
+            rotate: false,
+            mirror: false,
+        };
+        db.add_block(TextureIndices::new_uniform(FaceTexture::Variants(
+            Box::new([variant(VARIED.0), variant(VARIED.0 + 1)]),
+        )));
         Arc::new(db)
     }
 
@@ -384,6 +900,7 @@ mod tests {
             assert_eq!(unpacked.size, U8Vec2::new(1, 1));
             assert_eq!(unpacked.texture_index, 1);
             assert_eq!(unpacked.ambient_occlusion, [0, 0, 0, 0]);
+            assert_eq!(unpacked.light, [PackedLight::new(MAX_LIGHT, 0); 4]);
             directions_found[unpacked.face_direction as usize] = true;
         }
         assert!(directions_found.iter().all(|&found| found));
@@ -442,5 +959,282 @@ mod tests {
         assert_eq!(back_face.size, U8Vec2::new(2, 1));
     }
 
+    #[test]
+    fn test_faces_with_different_light_are_not_merged() {
+        let db = create_test_block_database();
+        let mesher = GreedyMesher::new(db);
+        let center_pos = ChunkPos::new(0, 0, 0);
+        let mut input = ChunkMeshGeneratorInput::new_empty(center_pos);
+
+        let voxel = Voxel::from_type(1);
+        for x in 1..=3 {
+            input.set_voxel(
+                center_pos.origin() + WorldPos::from(IVec3::new(x, 1, 1)),
+                voxel,
+            );
+        }
+        // Darken the air above the last voxel
+        input.set_light(
+            center_pos.origin() + WorldPos::from(IVec3::new(3, 2, 1)),
+            PackedLight::new(0, 6),
+        );
+
+        let mesh = mesher.generate_mesh(&input);
+        let mut top_faces = mesh
+            .opaque_faces
+            .iter()
+            .map(|f| f.unpack())
+            .filter(|f| f.face_direction == Face::Top)
+            .collect::<Vec<_>>();
+        top_faces.sort_by_key(|f| f.position.x);
+
+        // The first top face is fully lit and the others are not, so nothing is merged
+        assert_eq!(top_faces.len(), 3);
+        assert_eq!(top_faces[0].light, [PackedLight::new(MAX_LIGHT, 0); 4]);
+        // Every corner of the last face averages the dark voxel with three fully lit ones
+        assert_eq!(top_faces[2].light, [PackedLight::new(11, 2); 4]);
+        assert!(top_faces.iter().all(|f| f.size == U8Vec2::new(1, 1)));
+    }
+
+    #[test]
+    fn test_light_emitting_faces_are_full_bright() {
+        let mesher = GreedyMesher::new(create_test_block_database());
+        let center_pos = ChunkPos::new(0, 0, 0);
+        let mut input = ChunkMeshGeneratorInput::new_empty(center_pos);
+
+        // A row of lamps on a stone floor, so they would normally get AO on their sides
+        for x in 1..=3 {
+            input.set_voxel(
+                center_pos.origin() + WorldPos::from(IVec3::new(x, 1, 1)),
+                Voxel::from_type(LAMP.0),
+            );
+            input.set_voxel(
+                center_pos.origin() + WorldPos::from(IVec3::new(x, 0, 1)),
+                Voxel::from_type(STONE.0),
+            );
+        }
+        input.set_light(
+            center_pos.origin() + WorldPos::from(IVec3::new(2, 2, 1)),
+            PackedLight::new(0, 3),
+        );
+
+        let mesh = mesher.generate_mesh(&input);
+        let lamp_faces = mesh
+            .opaque_faces
+            .iter()
+            .map(|f| f.unpack())
+            .filter(|f| f.texture_index == LAMP.0)
+            .collect::<Vec<_>>();
+
+        // Top, front and back are merged despite the different light above, plus the two ends
+        assert_eq!(lamp_faces.len(), 5);
+        for face in &lamp_faces {
+            assert!(face.full_bright);
+            assert_eq!(face.ambient_occlusion, [0; 4]);
+        }
+    }
+
+    #[test]
+    fn test_transparent_blocks_do_not_hide_neighbors() {
+        let mesher = GreedyMesher::new(create_test_block_database());
+        let center_pos = ChunkPos::new(0, 0, 0);
+        let mut input = ChunkMeshGeneratorInput::new_empty(center_pos);
+
+        // Stone with two glass blocks on top of it
+        let place = |input: &mut ChunkMeshGeneratorInput, y: i32, id: BlockTypeId| {
+            input.set_voxel(
+                center_pos.origin() + WorldPos::from(IVec3::new(1, y, 1)),
+                Voxel::from_type(id.0),
+            );
+        };
+        place(&mut input, 1, STONE);
+        place(&mut input, 2, GLASS);
+        place(&mut input, 3, GLASS);
+
+        let mesh = mesher.generate_mesh(&input);
+        let faces = mesh
+            .opaque_faces
+            .iter()
+            .map(|f| f.unpack())
+            .collect::<Vec<_>>();
+        let has_face = |texture_index: u16, y: u8, face: Face| {
+            faces.iter().any(|f| {
+                f.texture_index == texture_index && f.position.y == y && f.face_direction == face
+            })
+        };
+
+        // The stone is visible through the glass, but the glass doesn't draw faces against the stone
+        assert!(has_face(STONE.0, 1, Face::Top));
+        assert!(!has_face(GLASS.0, 2, Face::Bottom));
+        // Faces between blocks of the same transparent type are hidden
+        assert!(!has_face(GLASS.0, 2, Face::Top));
+        assert!(!has_face(GLASS.0, 3, Face::Bottom));
+        assert!(has_face(GLASS.0, 3, Face::Top));
+    }
+
+    #[test]
+    fn test_shaped_blocks_cull_by_coverage() {
+        let mesher = GreedyMesher::new(create_test_block_database());
+        let center_pos = ChunkPos::new(0, 0, 0);
+        let mut input = ChunkMeshGeneratorInput::new_empty(center_pos);
+
+        // A slab on top of stone, with stone next to it, and a plant next to the slab
+        let place = |input: &mut ChunkMeshGeneratorInput, x: i32, y: i32, id: BlockTypeId| {
+            input.set_voxel(
+                center_pos.origin() + WorldPos::from(IVec3::new(x, y, 1)),
+                Voxel::from_type(id.0),
+            );
+        };
+        place(&mut input, 1, 1, STONE);
+        place(&mut input, 1, 2, SLAB);
+        place(&mut input, 2, 2, STONE);
+        place(&mut input, 0, 2, PLANT);
+
+        let mesh = mesher.generate_mesh(&input);
+        let opaque = mesh
+            .opaque_faces
+            .iter()
+            .map(|f| f.unpack())
+            .collect::<Vec<_>>();
+        let has_face = |texture_index: u16, x: u8, y: u8, face: Face| {
+            opaque.iter().any(|f| {
+                f.texture_index == texture_index
+                    && f.position.x == x
+                    && f.position.y == y
+                    && f.face_direction == face
+            })
+        };
+
+        // The slab's bottom covers the stone below it, but its side only covers half of the stone next to it
+        assert!(!has_face(STONE.0, 1, 1, Face::Top));
+        assert!(has_face(STONE.0, 2, 2, Face::Left));
+        // The slab's sides next to stone are hidden, and its top is inset halfway down
+        assert!(!has_face(SLAB.0, 1, 2, Face::Right));
+        assert!(!has_face(SLAB.0, 1, 2, Face::Bottom));
+        let slab_faces = opaque
+            .iter()
+            .filter(|f| f.texture_index == SLAB.0)
+            .collect::<Vec<_>>();
+        assert_eq!(slab_faces.len(), 4);
+        let top = slab_faces
+            .iter()
+            .find(|f| f.face_direction == Face::Top)
+            .expect("Expected slab top");
+        assert_eq!(
+            top.shape,
+            Some(FaceShape::Inset {
+                offset: U8Vec2::ZERO,
+                inset: 8
+            })
+        );
+        assert_eq!(top.size, U8Vec2::splat(16));
+        // The plant doesn't cover anything, so the slab's left side is visible
+        assert!(has_face(SLAB.0, 1, 2, Face::Left));
+
+        // Both diagonals of the plant are drawn with alpha cutout
+        assert_eq!(mesh.alpha_cutout_faces.len(), 2);
+        assert!(
+            mesh.alpha_cutout_faces
+                .iter()
+                .all(|f| matches!(f.unpack().shape, Some(FaceShape::Diagonal(_))))
+        );
+    }
+
+    #[test]
+    fn test_fluid_surfaces_slope_between_levels() {
+        let mesher = GreedyMesher::new(create_test_block_database());
+        let center_pos = ChunkPos::new(0, 0, 0);
+        let mut input = ChunkMeshGeneratorInput::new_empty(center_pos);
+
+        // A source with flowing water next to it, and a column of water elsewhere
+        let place = |input: &mut ChunkMeshGeneratorInput, x: i32, y: i32, level: FluidLevel| {
+            input.set_voxel(
+                center_pos.origin() + WorldPos::from(IVec3::new(x, y, 1)),
+                Voxel::from_type_metadata(WATER.0, level.to_metadata()),
+            );
+        };
+        place(&mut input, 1, 1, FluidLevel::Source);
+        place(&mut input, 2, 1, FluidLevel::Flowing(2));
+        place(&mut input, 5, 1, FluidLevel::Falling);
+        place(&mut input, 5, 2, FluidLevel::Source);
+
+        let mesh = mesher.generate_mesh(&input);
+        assert!(mesh.opaque_faces.is_empty());
+        let faces = mesh
+            .alpha_blend_faces
+            .iter()
+            .map(|f| f.unpack())
+            .collect::<Vec<_>>();
+        let find = |x: u8, y: u8, face: Face| {
+            faces
+                .iter()
+                .find(|f| f.position == U8Vec3::new(x, y, 1) && f.face_direction == face)
+        };
+
+        // Faces between voxels of the same fluid are skipped
+        assert!(find(1, 1, Face::Right).is_none());
+        assert!(find(2, 1, Face::Left).is_none());
+        assert!(find(5, 1, Face::Top).is_none());
+        assert!(find(5, 2, Face::Bottom).is_none());
+        assert_eq!(faces.len(), 2 * 5 + 2 * 5);
+
+        // The source's top slopes down towards the flowing water, which is on the far edge along X
+        let top = find(1, 1, Face::Top).expect("Expected source top");
+        assert_eq!(
+            top.shape,
+            Some(FaceShape::Sloped {
+                drops: [2, 2, 4, 4]
+            })
+        );
+        // Water with more water above it fills its whole voxel
+        let side = find(5, 1, Face::Front).expect("Expected falling water side");
+        assert_eq!(side.shape, Some(FaceShape::Sloped { drops: [0; 4] }));
+    }
+
+    #[test]
+    fn test_faces_only_merge_with_the_same_variant() {
+        let db = create_test_block_database();
+        let mesher = GreedyMesher::new(db.clone());
+        let center_pos = ChunkPos::new(0, 0, 0);
+        let mut input = ChunkMeshGeneratorInput::new_empty(center_pos);
+
+        let row = (0..CHUNK_SIZE as i32)
+            .map(|x| center_pos.origin() + WorldPos::from(IVec3::new(x, 1, 1)))
+            .collect::<Vec<_>>();
+        for pos in &row {
+            input.set_voxel(*pos, Voxel::from_type(VARIED.0));
+        }
+
+        // Runs of the same variant along the row, as (first x, length, texture)
+        let variants = db.get_texture_indices(VARIED).unwrap();
+        let mut runs: Vec<(u8, u8, u16)> = Vec::new();
+        for (x, pos) in row.iter().enumerate() {
+            let texture = variants.top.select(*pos, Face::Top, || 0).index;
+            match runs.last_mut() {
+                Some((_, length, last)) if *last == texture => *length += 1,
+                _ => runs.push((x as u8, 1, texture)),
+            }
+        }
+        assert!(runs.len() > 1, "Expected the row to pick both variants");
+        assert!(runs.iter().any(|(_, length, _)| *length > 1));
+
+        let mesh = mesher.generate_mesh(&input);
+        let mut top_faces = mesh
+            .opaque_faces
+            .iter()
+            .map(|f| f.unpack())
+            .filter(|f| f.face_direction == Face::Top)
+            .map(|f| (f.position.x, f.size, f.texture_index))
+            .collect::<Vec<_>>();
+        top_faces.sort_by_key(|(x, _, _)| *x);
+
+        // Top faces along X are one voxel deep, and as wide as the run of their variant
+        let expected = runs
+            .iter()
+            .map(|(x, length, texture)| (*x, U8Vec2::new(1, *length), *texture))
+            .collect::<Vec<_>>();
+        assert_eq!(top_faces, expected);
+    }
+
     // TODO: Add more tests for AO correctness and complex shapes
 }
@@ -0,0 +1,196 @@
+use std::{borrow::Cow, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::blocks::BlockDatabase,
+    voxels::{chunk::ChunkData, voxel::Voxel},
+};
+
+/// Names of the blocks used by a saved world, indexed by the block IDs stored in its chunks.
+/// New blocks are only ever appended, so the stored IDs of existing blocks never change.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockIdTable {
+    pub names: Vec<String>,
+}
+
+impl BlockIdTable {
+    /// Loads the table, or returns None if the world doesn't have one yet.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read block ID table {}", path.display()))?;
+        ron::from_str(&data)
+            .with_context(|| format!("Failed to parse block ID table {}", path.display()))
+            .map(Some)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .context("Failed to serialize block ID table")?;
+        std::fs::write(path, data)
+            .with_context(|| format!("Failed to write block ID table {}", path.display()))
+    }
+}
+
+/// Converts block IDs between the ones stored in a world and the ones assigned by the block database.
+pub struct BlockIdRemap {
+    /// Runtime ID for each stored ID
+    to_runtime: Vec<u16>,
+    /// Stored ID for each runtime ID
+    to_storage: Vec<u16>,
+    identity: bool,
+}
+
+impl BlockIdRemap {
+    /// Builds the mapping, appending blocks the world hasn't seen before to the table.
+    /// Stored blocks that no longer exist are loaded as air.
+    pub fn new(table: &mut BlockIdTable, block_database: &BlockDatabase) -> Self {
+        let mut to_storage = Vec::new();
+        for block in block_database.iter_blocks() {
+            let stored_id = match table.names.iter().position(|name| *name == block.name) {
+                Some(index) => index,
+                None => {
+                    table.names.push(block.name.clone());
+                    table.names.len() - 1
+                }
+            };
+            to_storage.push(stored_id as u16);
+        }
+
+        let to_runtime = table
+            .names
+            .iter()
+            .map(|name| match block_database.get_by_name(name) {
+                Some(block) => block.id.0,
+                None => {
+                    log::warn!("Block '{}' no longer exists, loading it as air", name);
+                    Voxel::AIR.block_type()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let identity = to_runtime.len() == to_storage.len()
+            && to_runtime
+                .iter()
+                .enumerate()
+                .all(|(index, id)| *id as usize == index);
+
+        BlockIdRemap {
+            to_runtime,
+            to_storage,
+            identity,
+        }
+    }
+
+    /// Whether stored and runtime IDs are the same, which is the case until blocks are added or removed.
+    pub fn is_identity(&self) -> bool {
+        self.identity
+    }
+
+    /// Converts a chunk loaded from disk to runtime IDs.
+    pub fn to_runtime(&self, data: &mut ChunkData) {
+        if !self.identity {
+            remap_chunk(data, &self.to_runtime);
+        }
+    }
+
+    /// Converts a chunk to the IDs stored on disk.
+    pub fn to_storage<'a>(&self, data: &'a ChunkData) -> Cow<'a, ChunkData> {
+        if self.identity {
+            return Cow::Borrowed(data);
+        }
+
+        let mut data = data.clone();
+        remap_chunk(&mut data, &self.to_storage);
+        Cow::Owned(data)
+    }
+}
+
+/// Only the palette needs to be remapped, since packed voxels are indices into it.
+/// Unknown IDs become air.
+fn remap_chunk(data: &mut ChunkData, ids: &[u16]) {
+    let remap = |voxel: Voxel| match ids.get(voxel.block_type() as usize) {
+        Some(&id) if id != Voxel::AIR.block_type() => {
+            Voxel::from_type_metadata(id, voxel.metadata())
+        }
+        _ => Voxel::AIR,
+    };
+
+    match data {
+        ChunkData::Solid(voxel) => *voxel = remap(*voxel),
+        ChunkData::Packed(packed) => {
+            for voxel in packed.palette.voxel_types.iter_mut() {
+                *voxel = remap(*voxel);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxels::coord::LocalPos;
+
+    #[test]
+    fn test_removed_and_reordered_blocks_are_remapped() {
+        let mut table = BlockIdTable::default();
+        let remap = BlockIdRemap::new(
+            &mut table,
+            &BlockDatabase::from_names(&["air", "stone", "dirt", "wood"]),
+        );
+        assert!(remap.is_identity());
+        assert_eq!(
+            table.names,
+            ["voxel:air", "voxel:stone", "voxel:dirt", "voxel:wood"]
+        );
+
+        let mut data = ChunkData::solid(Voxel::AIR);
+        data.set_voxel(LocalPos::new(0, 0, 0), Voxel::from_type(1));
+        data.set_voxel(LocalPos::new(1, 0, 0), Voxel::from_type_metadata(2, 5));
+        data.set_voxel(LocalPos::new(2, 0, 0), Voxel::from_type(3));
+
+        // Stone is removed and a new block is added before wood
+        let remap = BlockIdRemap::new(
+            &mut table,
+            &BlockDatabase::from_names(&["air", "dirt", "glass", "wood"]),
+        );
+        assert!(!remap.is_identity());
+        assert_eq!(
+            table.names,
+            [
+                "voxel:air",
+                "voxel:stone",
+                "voxel:dirt",
+                "voxel:wood",
+                "voxel:glass"
+            ]
+        );
+
+        remap.to_runtime(&mut data);
+        assert_eq!(data.get_voxel(LocalPos::new(0, 0, 0)), Some(Voxel::AIR));
+        assert_eq!(
+            data.get_voxel(LocalPos::new(1, 0, 0)),
+            Some(Voxel::from_type_metadata(1, 5))
+        );
+        assert_eq!(
+            data.get_voxel(LocalPos::new(2, 0, 0)),
+            Some(Voxel::from_type(3))
+        );
+
+        data.set_voxel(LocalPos::new(3, 0, 0), Voxel::from_type(2));
+        let stored = remap.to_storage(&data);
+        assert_eq!(
+            stored.get_voxel(LocalPos::new(1, 0, 0)),
+            Some(Voxel::from_type_metadata(2, 5))
+        );
+        assert_eq!(
+            stored.get_voxel(LocalPos::new(3, 0, 0)),
+            Some(Voxel::from_type(4))
+        );
+    }
+}
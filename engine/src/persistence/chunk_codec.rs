@@ -0,0 +1,235 @@
+use anyhow::{Context, bail, ensure};
+
+use crate::voxels::{
+    chunk::{CHUNK_VOLUME, ChunkData},
+    packed_chunk::{PackedChunk, Palette},
+    voxel::Voxel,
+};
+
+const TAG_SOLID: u8 = 0;
+const TAG_PACKED: u8 = 1;
+
+/// Serializes chunk data into a compact little-endian binary representation.
+///
+/// Solid chunks are stored as a single voxel. Packed chunks store their palette and the packed
+/// u64 words as-is, so decoding doesn't need to repack anything.
+pub fn encode_chunk_data(data: &ChunkData, out: &mut Vec<u8>) {
+    match data {
+        ChunkData::Solid(voxel) => {
+            out.push(TAG_SOLID);
+            out.extend_from_slice(&voxel.into_bits().to_le_bytes());
+        }
+        ChunkData::Packed(packed) => {
+            out.reserve(10 + packed.palette.voxel_types.len() * 2 + packed.data.len() * 8);
+
+            out.push(TAG_PACKED);
+            out.extend_from_slice(&(packed.palette.voxel_types.len() as u32).to_le_bytes());
+            for voxel in &packed.palette.voxel_types {
+                out.extend_from_slice(&voxel.into_bits().to_le_bytes());
+            }
+
+            out.push(packed.bits_per_voxel);
+            out.extend_from_slice(&(packed.data.len() as u32).to_le_bytes());
+            for word in packed.data.iter() {
+                out.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+}
+
+pub fn decode_chunk_data(bytes: &[u8]) -> anyhow::Result<ChunkData> {
+    let mut reader = ByteReader::new(bytes);
+
+    let data = match reader.read_u8()? {
+        TAG_SOLID => ChunkData::Solid(Voxel::from_bits(reader.read_u16()?)),
+        TAG_PACKED => {
+            let palette_len = reader.read_u32()? as usize;
+            ensure!(
+                (1..=2usize.pow(16)).contains(&palette_len),
+                "Invalid palette length: {}",
+                palette_len
+            );
+
+            let voxel_types = (0..palette_len)
+                .map(|_| reader.read_u16().map(Voxel::from_bits))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .context("Failed to read palette")?;
+
+            let bits_per_voxel = reader.read_u8()?;
+            let palette = Palette { voxel_types };
+            // Packing picks the smallest width that fits the palette, which needn't be a power of two
+            ensure!(
+                bits_per_voxel <= 16 && bits_per_voxel as usize >= palette.get_packed_index_bits(),
+                "Invalid bits per voxel for a palette of {} entries: {}",
+                palette_len,
+                bits_per_voxel
+            );
+
+            let expected_len = if bits_per_voxel == 0 {
+                0
+            } else {
+                CHUNK_VOLUME.div_ceil(64 / bits_per_voxel as usize)
+            };
+            let data_len = reader.read_u32()? as usize;
+            ensure!(
+                data_len == expected_len,
+                "Expected {} data words for {} bits per voxel, got {}",
+                expected_len,
+                bits_per_voxel,
+                data_len
+            );
+
+            let data = (0..data_len)
+                .map(|_| reader.read_u64())
+                .collect::<anyhow::Result<Box<[u64]>>>()
+                .context("Failed to read voxel data")?;
+
+            let packed = PackedChunk {
+                palette,
+                data,
+                bits_per_voxel,
+                bit_mask: (1u64 << bits_per_voxel) - 1,
+            };
+            if let Some(index) = (0..CHUNK_VOLUME)
+                .map(|i| packed.get_packed_index(i) as usize)
+                .find(|&index| index >= palette_len)
+            {
+                bail!(
+                    "Voxel data refers to palette index {}, but the palette has {} entries",
+                    index,
+                    palette_len
+                );
+            }
+            ChunkData::Packed(packed)
+        }
+        tag => bail!("Unknown chunk data tag: {}", tag),
+    };
+
+    ensure!(
+        reader.is_empty(),
+        "{} trailing bytes after chunk data",
+        reader.remaining()
+    );
+
+    Ok(data)
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes }
+    }
+
+    fn read_array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let Some((head, rest)) = self.bytes.split_first_chunk::<N>() else {
+            bail!("Unexpected end of chunk data");
+        };
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(u8::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxels::coord::LocalPos;
+
+    fn round_trip(data: &ChunkData) -> ChunkData {
+        let mut bytes = Vec::new();
+        encode_chunk_data(data, &mut bytes);
+        decode_chunk_data(&bytes).expect("Failed to decode chunk data")
+    }
+
+    #[test]
+    fn test_solid_round_trip() {
+        let voxel = Voxel::from_type_metadata(3, 7);
+        let decoded = round_trip(&ChunkData::solid(voxel));
+
+        assert!(matches!(decoded, ChunkData::Solid(v) if v == voxel));
+    }
+
+    #[test]
+    fn test_packed_round_trip() {
+        let mut data = ChunkData::solid(Voxel::AIR);
+        data.set_voxel(LocalPos::new(0, 0, 0), Voxel::GRASS);
+        data.set_voxel(LocalPos::new(15, 15, 15), Voxel::DIRT);
+        data.set_voxel(LocalPos::new(3, 8, 12), Voxel::from_type_metadata(5, 2));
+
+        let decoded = round_trip(&data);
+
+        assert_eq!(decoded.bits_per_voxel(), data.bits_per_voxel());
+        for ((pos, expected), (_, actual)) in data.iter_voxels().zip(decoded.iter_voxels()) {
+            assert_eq!(expected, actual, "Voxel mismatch at {:?}", pos);
+        }
+    }
+
+    #[test]
+    fn test_truncated_data_is_rejected() {
+        let mut data = ChunkData::solid(Voxel::AIR);
+        data.set_voxel(LocalPos::new(1, 2, 3), Voxel::GOLD);
+
+        let mut bytes = Vec::new();
+        encode_chunk_data(&data, &mut bytes);
+        bytes.truncate(bytes.len() - 1);
+
+        assert!(decode_chunk_data(&bytes).is_err());
+    }
+
+    fn packed_bytes(palette_len: u32, bits_per_voxel: u8, word: u64) -> Vec<u8> {
+        let mut bytes = vec![TAG_PACKED];
+        bytes.extend_from_slice(&palette_len.to_le_bytes());
+        for i in 0..palette_len as u16 {
+            bytes.extend_from_slice(&Voxel::from_type(i).into_bits().to_le_bytes());
+        }
+        bytes.push(bits_per_voxel);
+        let data_len = match bits_per_voxel {
+            0 => 0,
+            bits => CHUNK_VOLUME.div_ceil(64 / bits as usize),
+        };
+        bytes.extend_from_slice(&(data_len as u32).to_le_bytes());
+        for _ in 0..data_len {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_invalid_packing_is_rejected() {
+        assert!(decode_chunk_data(&packed_bytes(3, 2, 0)).is_ok());
+        // Packing doesn't round widths up to a power of two
+        assert!(decode_chunk_data(&packed_bytes(5, 3, 0)).is_ok());
+
+        assert!(decode_chunk_data(&packed_bytes(0, 0, 0)).is_err());
+        assert!(decode_chunk_data(&packed_bytes(2, 0, 0)).is_err());
+        assert!(decode_chunk_data(&packed_bytes(5, 2, 0)).is_err());
+        // Every voxel points at index 3 of a 3 entry palette
+        assert!(decode_chunk_data(&packed_bytes(3, 2, u64::MAX)).is_err());
+    }
+}
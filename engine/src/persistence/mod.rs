@@ -0,0 +1,4 @@
+pub mod block_ids;
+pub mod chunk_codec;
+pub mod region_file;
+pub mod world_storage;
@@ -0,0 +1,275 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, ensure};
+use glam::IVec3;
+
+use crate::voxels::coord::ChunkPos;
+
+/// Number of chunks per region along each axis
+pub const REGION_SIZE: i32 = 32;
+// For fast division
+pub const REGION_SIZE_LOG2: i32 = 5;
+pub const REGION_VOLUME: usize = (REGION_SIZE as usize).pow(3);
+
+const REGION_MAGIC: [u8; 4] = *b"VXRG";
+const REGION_VERSION: u32 = 1;
+
+// Magic + version, followed by an (offset, length) pair for every chunk in the region
+const HEADER_PREFIX_SIZE: u64 = 8;
+const HEADER_ENTRY_SIZE: u64 = 8;
+const HEADER_SIZE: u64 = HEADER_PREFIX_SIZE + REGION_VOLUME as u64 * HEADER_ENTRY_SIZE;
+
+/// Chunk payloads are allocated in sectors, so a rewritten chunk can usually reuse its old slot.
+const SECTOR_SIZE: u64 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Coordinates identifying a region in region space (chunk coordinates divided by region size and floored)
+pub struct RegionPos(pub IVec3);
+
+impl RegionPos {
+    pub fn from_chunk_pos(pos: ChunkPos) -> Self {
+        RegionPos(pos.0 >> REGION_SIZE_LOG2)
+    }
+
+    /// Index of the chunk within its region, in YZX order.
+    pub fn chunk_index(pos: ChunkPos) -> usize {
+        let local = pos.0 & IVec3::splat(REGION_SIZE - 1);
+        let region_size = REGION_SIZE as usize;
+        local.y as usize * region_size * region_size
+            + local.z as usize * region_size
+            + local.x as usize
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("r.{}.{}.{}.region", self.0.x, self.0.y, self.0.z)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct RegionEntry {
+    /// Byte offset of the payload from the start of the file. 0 means the chunk hasn't been saved.
+    offset: u32,
+    length: u32,
+}
+
+impl RegionEntry {
+    fn is_present(&self) -> bool {
+        self.offset != 0
+    }
+
+    fn allocated_size(&self) -> u64 {
+        (self.length as u64).next_multiple_of(SECTOR_SIZE)
+    }
+}
+
+/// A single file containing up to `REGION_VOLUME` serialized chunks.
+///
+/// The file starts with a fixed-size header table mapping each chunk in the region to its payload.
+/// Payloads are appended to the end of the file, or written in place if the new payload fits in
+/// the previously allocated sectors.
+// TODO: Compact region files, space freed by growing chunks is never reclaimed
+pub struct RegionFile {
+    path: PathBuf,
+    file: File,
+    entries: Box<[RegionEntry]>,
+    end_offset: u64,
+}
+
+impl RegionFile {
+    /// Opens an existing region file. Returns None if the file doesn't exist.
+    pub fn open(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Failed to open region file {:?}", path))?;
+
+        let mut header = vec![0u8; HEADER_SIZE as usize];
+        file.read_exact(&mut header)
+            .with_context(|| format!("Failed to read region header from {:?}", path))?;
+
+        ensure!(
+            header[0..4] == REGION_MAGIC,
+            "{:?} is not a region file",
+            path
+        );
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        ensure!(
+            version == REGION_VERSION,
+            "Unsupported region file version {} in {:?}",
+            version,
+            path
+        );
+
+        let entries = header[HEADER_PREFIX_SIZE as usize..]
+            .chunks_exact(HEADER_ENTRY_SIZE as usize)
+            .map(|entry| RegionEntry {
+                offset: u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+                length: u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+            })
+            .collect::<Box<[_]>>();
+
+        let end_offset = file.metadata()?.len().next_multiple_of(SECTOR_SIZE);
+
+        Ok(Some(RegionFile {
+            path: path.to_path_buf(),
+            file,
+            entries,
+            end_offset,
+        }))
+    }
+
+    /// Opens a region file, creating an empty one if it doesn't exist yet.
+    pub fn open_or_create(path: &Path) -> anyhow::Result<Self> {
+        if let Some(region) = Self::open(path)? {
+            return Ok(region);
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .with_context(|| format!("Failed to create region file {:?}", path))?;
+
+        let mut header = vec![0u8; HEADER_SIZE as usize];
+        header[0..4].copy_from_slice(&REGION_MAGIC);
+        header[4..8].copy_from_slice(&REGION_VERSION.to_le_bytes());
+        file.write_all(&header)
+            .with_context(|| format!("Failed to write region header to {:?}", path))?;
+
+        Ok(RegionFile {
+            path: path.to_path_buf(),
+            file,
+            entries: vec![RegionEntry::default(); REGION_VOLUME].into_boxed_slice(),
+            end_offset: HEADER_SIZE.next_multiple_of(SECTOR_SIZE),
+        })
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.entries[index].is_present()
+    }
+
+    /// Reads the payload of the chunk at the given index, if it has been saved.
+    pub fn read(&mut self, index: usize) -> anyhow::Result<Option<Vec<u8>>> {
+        let entry = self.entries[index];
+        if !entry.is_present() {
+            return Ok(None);
+        }
+
+        let mut payload = vec![0u8; entry.length as usize];
+        self.file.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.file
+            .read_exact(&mut payload)
+            .with_context(|| format!("Failed to read chunk {} from {:?}", index, self.path))?;
+
+        Ok(Some(payload))
+    }
+
+    /// Writes the payload of the chunk at the given index, replacing any previous payload.
+    pub fn write(&mut self, index: usize, payload: &[u8]) -> anyhow::Result<()> {
+        let previous = self.entries[index];
+        let required_size = (payload.len() as u64).next_multiple_of(SECTOR_SIZE);
+
+        let offset = if previous.is_present() && required_size <= previous.allocated_size() {
+            previous.offset as u64
+        } else {
+            let offset = self.end_offset;
+            self.end_offset += required_size;
+            offset
+        };
+
+        ensure!(
+            offset + payload.len() as u64 <= u32::MAX as u64,
+            "Region file {:?} is full",
+            self.path
+        );
+
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file
+            .write_all(payload)
+            .with_context(|| format!("Failed to write chunk {} to {:?}", index, self.path))?;
+
+        // Only update the header once the payload is in place
+        let entry = RegionEntry {
+            offset: offset as u32,
+            length: payload.len() as u32,
+        };
+        let mut entry_bytes = [0u8; HEADER_ENTRY_SIZE as usize];
+        entry_bytes[0..4].copy_from_slice(&entry.offset.to_le_bytes());
+        entry_bytes[4..8].copy_from_slice(&entry.length.to_le_bytes());
+
+        self.file.seek(SeekFrom::Start(
+            HEADER_PREFIX_SIZE + index as u64 * HEADER_ENTRY_SIZE,
+        ))?;
+        self.file.write_all(&entry_bytes)?;
+        self.entries[index] = entry;
+
+        Ok(())
+    }
+
+    /// Flushes all pending writes to disk.
+    pub fn sync(&mut self) -> anyhow::Result<()> {
+        self.file
+            .sync_data()
+            .with_context(|| format!("Failed to sync region file {:?}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_region_pos_from_chunk_pos() {
+        assert_eq!(
+            RegionPos::from_chunk_pos(ChunkPos::new(0, 31, -1)),
+            RegionPos(IVec3::new(0, 0, -1))
+        );
+        assert_eq!(
+            RegionPos::from_chunk_pos(ChunkPos::new(32, -32, -33)),
+            RegionPos(IVec3::new(1, -1, -2))
+        );
+
+        assert_eq!(RegionPos::chunk_index(ChunkPos::new(0, 0, 0)), 0);
+        assert_eq!(
+            RegionPos::chunk_index(ChunkPos::new(-1, -1, -1)),
+            REGION_VOLUME - 1
+        );
+        assert_eq!(RegionPos::chunk_index(ChunkPos::new(33, 0, 0)), 1);
+    }
+
+    #[test]
+    fn test_region_file_write_and_read() {
+        let dir = TestDir::new("region_test");
+        let path = dir.path().join("test.region");
+
+        {
+            let mut region = RegionFile::open_or_create(&path).unwrap();
+            assert_eq!(region.read(5).unwrap(), None);
+
+            region.write(5, &[1, 2, 3]).unwrap();
+            region.write(7, &[4; 300]).unwrap();
+            // Smaller payload reuses the existing slot, larger one is moved to the end
+            region.write(5, &[9, 9]).unwrap();
+            region.write(7, &[5; 600]).unwrap();
+            region.sync().unwrap();
+        }
+
+        let mut region = RegionFile::open(&path)
+            .unwrap()
+            .expect("Region should exist");
+        assert_eq!(region.read(5).unwrap(), Some(vec![9, 9]));
+        assert_eq!(region.read(7).unwrap(), Some(vec![5; 600]));
+        assert!(!region.contains(6));
+    }
+}
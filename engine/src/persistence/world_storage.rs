@@ -0,0 +1,193 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use anyhow::Context;
+use dashmap::DashMap;
+
+use crate::{
+    assets::blocks::BlockDatabase,
+    persistence::{
+        block_ids::{BlockIdRemap, BlockIdTable},
+        chunk_codec::{decode_chunk_data, encode_chunk_data},
+        region_file::{RegionFile, RegionPos},
+    },
+    voxels::{
+        chunk::{Chunk, ChunkData, IChunkRenderState},
+        coord::ChunkPos,
+    },
+};
+
+/// Region-file backed chunk storage for a single world.
+/// Shared between the chunk loader workers, so all methods take `&self`.
+pub struct WorldStorage {
+    root: PathBuf,
+    // TODO: Close region files that haven't been used in a while
+    regions: DashMap<RegionPos, Arc<Mutex<RegionFile>>, ahash::RandomState>,
+    /// Converts between the block IDs saved in the world and the ones of the loaded blocks
+    block_ids: RwLock<BlockIdRemap>,
+}
+
+const BLOCK_ID_TABLE_FILE: &str = "block_ids.ron";
+
+impl WorldStorage {
+    /// Opens the world stored in the given directory, creating the directory if needed.
+    /// The world's block ID table is updated with any blocks it hasn't seen before.
+    /// Worlds saved before the table existed are assumed to use the current block IDs.
+    pub fn open(root: impl Into<PathBuf>, block_database: &BlockDatabase) -> anyhow::Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create world directory {:?}", root))?;
+
+        let block_ids = load_block_ids(&root, block_database)?;
+
+        Ok(WorldStorage {
+            root,
+            regions: DashMap::default(),
+            block_ids: RwLock::new(block_ids),
+        })
+    }
+
+    /// Adds blocks that were added by reloading the block definitions to the world's block ID table.
+    pub fn update_block_ids(&self, block_database: &BlockDatabase) -> anyhow::Result<()> {
+        let block_ids = load_block_ids(&self.root, block_database)?;
+        *self.block_ids.write().unwrap() = block_ids;
+        Ok(())
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn region_path(&self, region_pos: RegionPos) -> PathBuf {
+        self.root.join(region_pos.file_name())
+    }
+
+    /// Returns the region file for the given position, or None if it hasn't been created yet.
+    fn get_region(&self, region_pos: RegionPos) -> anyhow::Result<Option<Arc<Mutex<RegionFile>>>> {
+        if let Some(region) = self.regions.get(&region_pos) {
+            return Ok(Some(region.clone()));
+        }
+
+        let path = self.region_path(region_pos);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let region = self
+            .regions
+            .entry(region_pos)
+            .or_try_insert_with(|| {
+                RegionFile::open(&path)?
+                    .map(|region| Arc::new(Mutex::new(region)))
+                    .with_context(|| format!("Region file {:?} disappeared", path))
+            })?
+            .clone();
+
+        Ok(Some(region))
+    }
+
+    fn get_or_create_region(
+        &self,
+        region_pos: RegionPos,
+    ) -> anyhow::Result<Arc<Mutex<RegionFile>>> {
+        let path = self.region_path(region_pos);
+        let region = self
+            .regions
+            .entry(region_pos)
+            .or_try_insert_with(|| {
+                RegionFile::open_or_create(&path).map(|region| Arc::new(Mutex::new(region)))
+            })?
+            .clone();
+
+        Ok(region)
+    }
+
+    /// Loads a chunk from disk. Returns None if the chunk has never been saved.
+    pub fn load_chunk(&self, pos: ChunkPos) -> anyhow::Result<Option<ChunkData>> {
+        let Some(region) = self.get_region(RegionPos::from_chunk_pos(pos))? else {
+            return Ok(None);
+        };
+
+        let payload = region.lock().unwrap().read(RegionPos::chunk_index(pos))?;
+
+        let Some(payload) = payload else {
+            return Ok(None);
+        };
+
+        let mut data = decode_chunk_data(&payload)
+            .with_context(|| format!("Failed to decode chunk {:?}", pos))?;
+        self.block_ids.read().unwrap().to_runtime(&mut data);
+        Ok(Some(data))
+    }
+
+    pub fn save_chunk(&self, pos: ChunkPos, data: &ChunkData) -> anyhow::Result<()> {
+        let mut payload = Vec::new();
+        encode_chunk_data(
+            &self.block_ids.read().unwrap().to_storage(data),
+            &mut payload,
+        );
+
+        let region = self.get_or_create_region(RegionPos::from_chunk_pos(pos))?;
+        region
+            .lock()
+            .unwrap()
+            .write(RegionPos::chunk_index(pos), &payload)
+    }
+
+    /// Saves the chunk if it has unsaved changes, and clears its dirty flag.
+    /// Returns true if the chunk was written.
+    pub fn save_chunk_if_dirty<T: IChunkRenderState>(
+        &self,
+        chunk: &Chunk<T>,
+    ) -> anyhow::Result<bool> {
+        let Some(data) = &chunk.data else {
+            return Ok(false);
+        };
+
+        if !chunk.take_dirty() {
+            return Ok(false);
+        }
+
+        if let Err(err) = self.save_chunk(chunk.position, data) {
+            // Keep the chunk dirty so the next save attempt retries it
+            chunk.mark_dirty();
+            return Err(err).with_context(|| format!("Failed to save chunk {:?}", chunk.position));
+        }
+
+        Ok(true)
+    }
+
+    /// Syncs all open region files to disk.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        let regions = self
+            .regions
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<_>>();
+
+        for region in regions {
+            region.lock().unwrap().sync()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads the world's block ID table, adding any new blocks to it.
+fn load_block_ids(root: &Path, block_database: &BlockDatabase) -> anyhow::Result<BlockIdRemap> {
+    let table_path = root.join(BLOCK_ID_TABLE_FILE);
+    let loaded_table = BlockIdTable::load(&table_path)?;
+    let mut table = loaded_table.clone().unwrap_or_default();
+    let block_ids = BlockIdRemap::new(&mut table, block_database);
+    if loaded_table.as_ref() != Some(&table) {
+        table.save(&table_path)?;
+    }
+
+    if !block_ids.is_identity() {
+        log::info!("Block IDs of the world differ from the loaded blocks, remapping chunks");
+    }
+
+    Ok(block_ids)
+}
@@ -1,7 +1,13 @@
 use glam::Vec3;
 use splines::{Interpolation, Key, Spline};
 
-use crate::{camera::Camera, game_loop::GameLoopTime};
+use crate::{camera::Camera, game_loop::GameLoopTime, math::aabb::AABB};
+
+/// Width of the player's collision box along X and Z
+pub const PLAYER_WIDTH: f32 = 0.6;
+pub const PLAYER_HEIGHT: f32 = 1.8;
+/// Height of the camera above the player's feet
+pub const PLAYER_EYE_HEIGHT: f32 = 1.62;
 
 #[allow(unused)]
 enum CameraMode {
@@ -101,6 +107,16 @@ impl Player {
         self.camera.target = target;
     }
 
+    /// Collision box of the player, placed so that the camera is at eye height.
+    pub fn bounds(&self) -> AABB {
+        let feet = self.camera.eye - Vec3::new(0.0, PLAYER_EYE_HEIGHT, 0.0);
+        let half_width = PLAYER_WIDTH * 0.5;
+        AABB::new(
+            feet - Vec3::new(half_width, 0.0, half_width),
+            feet + Vec3::new(half_width, PLAYER_HEIGHT, half_width),
+        )
+    }
+
     pub fn before_render(&mut self, resolution: glam::Vec2) {
         self.camera.update_matrices(resolution);
     }
@@ -0,0 +1,38 @@
+//! Temporary directories for tests that read and write files
+
+use std::path::{Path, PathBuf};
+
+/// Empty directory under the system temp directory, removed with everything in it when dropped,
+/// so failing tests don't leave files behind.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// `name` keeps tests that run at the same time apart, so it should be unique to the test
+    pub fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("voxel_engine_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TestDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a file at a path relative to the directory, creating its parent directories
+    pub fn write(&self, relative_path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path.join(relative_path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -1,10 +1,14 @@
 use std::hint::unreachable_unchecked;
 
-use crate::voxels::{
-    chunk::{CHUNK_SIZE, Chunk, ChunkData, IChunkRenderState},
-    coord::LocalPos,
-    face::Face,
-    voxel::Voxel,
+use crate::{
+    assets::blocks::BlockDatabaseSlim,
+    lighting::chunk_light::{MAX_LIGHT, PackedLight},
+    voxels::{
+        chunk::{CHUNK_SIZE, Chunk, ChunkData, IChunkRenderState},
+        coord::LocalPos,
+        face::Face,
+        voxel::Voxel,
+    },
 };
 
 const BORDER_VOLUME: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
@@ -12,9 +16,11 @@ const BORDER_VOLUME: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
 // Represents the border voxels of a chunk. Used for meshing with neighboring chunks.
 pub struct Border {
     orientation: Face,
-    /// Set to true if all voxels in this border are non-transparent
+    /// Set to true if all voxels in this border are opaque
     pub occludes: bool,
     voxels: [Voxel; BORDER_VOLUME],
+    /// Light of the border voxels, fully lit by the sky unless copied from a lit chunk
+    light: [PackedLight; BORDER_VOLUME],
 }
 
 impl Border {
@@ -23,10 +29,15 @@ impl Border {
             orientation,
             occludes: false,
             voxels: [Voxel::AIR; BORDER_VOLUME],
+            light: [PackedLight::new(MAX_LIGHT, 0); BORDER_VOLUME],
         }
     }
 
-    pub fn copy_from_chunk<T: IChunkRenderState>(&mut self, chunk: &Chunk<T>) {
+    pub fn copy_from_chunk<T: IChunkRenderState>(
+        &mut self,
+        chunk: &Chunk<T>,
+        block_database: &BlockDatabaseSlim,
+    ) {
         let Some(chunk_data) = chunk.data.as_ref() else {
             panic!(
                 "Tried to copy border from chunk at position {:?} which has no data",
@@ -37,7 +48,7 @@ impl Border {
         match chunk_data {
             ChunkData::Solid(voxel) => {
                 self.voxels.fill(*voxel);
-                self.occludes = !voxel.is_transparent();
+                self.occludes = block_database.properties(*voxel).opaque;
             }
             ChunkData::Packed(packed) => {
                 let mut occludes = true;
@@ -56,7 +67,7 @@ impl Border {
                         if let Some(voxel) = packed.get_voxel(local_pos) {
                             let target_index = (y as usize) * (CHUNK_SIZE as usize) + (x as usize);
                             self.voxels[target_index] = voxel;
-                            if occludes && voxel.is_transparent() {
+                            if occludes && !block_database.properties(voxel).opaque {
                                 occludes = false;
                             }
                         } else {
@@ -67,9 +78,14 @@ impl Border {
                 self.occludes = occludes;
             }
         }
+
+        if let Some(light) = chunk.light.as_ref() {
+            self.light = light.copy_layer(self.orientation);
+        }
     }
 
-    pub fn get_voxel(&self, pos: LocalPos) -> Option<Voxel> {
+    /// Index of the position in the border, or None if it isn't on the border plane.
+    fn border_index(&self, pos: LocalPos) -> Option<usize> {
         // Check if the position is on the border plane
         let is_on_border = match self.orientation {
             Face::Top => pos.y() == CHUNK_SIZE - 1,
@@ -91,8 +107,15 @@ impl Border {
             Face::Front | Face::Back => (pos.x(), pos.y()),
         };
 
-        let index = (y as usize) * (CHUNK_SIZE as usize) + (x as usize);
-        Some(self.voxels[index])
+        Some((y as usize) * (CHUNK_SIZE as usize) + (x as usize))
+    }
+
+    pub fn get_voxel(&self, pos: LocalPos) -> Option<Voxel> {
+        self.border_index(pos).map(|index| self.voxels[index])
+    }
+
+    pub fn get_light(&self, pos: LocalPos) -> Option<PackedLight> {
+        self.border_index(pos).map(|index| self.light[index])
     }
 
     // This only exists for testing purposes
@@ -3,13 +3,14 @@ use std::{
     mem::size_of,
     sync::{
         Arc,
-        atomic::{AtomicU8, Ordering},
+        atomic::{AtomicBool, AtomicU8, Ordering},
     },
 };
 
 use crossbeam::atomic::AtomicCell;
 
 use crate::{
+    lighting::chunk_light::ChunkLight,
     mesh_generation::chunk_mesh::ChunkMeshData,
     voxels::{
         coord::{ChunkPos, LocalPos},
@@ -19,6 +20,7 @@ use crate::{
         voxel::Voxel,
     },
     world_stats::CHUNKS_BY_STATE,
+    worldgen::ChunkClimate,
 };
 
 pub const CHUNK_SIZE: u8 = 16;
@@ -27,6 +29,7 @@ pub const CHUNK_SIZE_LOG2: i32 = 4;
 
 pub const CHUNK_VOLUME: usize = (CHUNK_SIZE as usize).pow(3);
 
+#[derive(Clone)]
 pub enum ChunkData {
     Solid(Voxel),
     Packed(PackedChunk),
@@ -127,6 +130,14 @@ impl ChunkData {
         }
     }
 
+    /// Every distinct voxel the chunk may contain. Packed palettes can hold voxels that were since overwritten.
+    pub fn voxel_types(&self) -> &[Voxel] {
+        match self {
+            ChunkData::Solid(voxel) => std::slice::from_ref(voxel),
+            ChunkData::Packed(packed) => &packed.palette.voxel_types,
+        }
+    }
+
     pub fn iter_voxels(&self) -> Box<dyn Iterator<Item = (LocalPos, Voxel)> + '_> {
         match self {
             ChunkData::Solid(voxel) => {
@@ -182,8 +193,11 @@ pub enum ChunkState {
     InGenerationQueue,
     /// Chunk is being generated
     Generating,
-    // TODO: Add LoadingFromDisk state here
-    // TODO: Add StaleMesh state here
+    /// Chunk is being loaded from disk. Falls back to Generating if the chunk hasn't been saved.
+    LoadingFromDisk,
+    /// Chunk voxel data has changed since it was meshed, and it's waiting to be remeshed.
+    /// The previous mesh keeps rendering until the new one has been flushed.
+    StaleMesh,
     /// Chunk has been generated and voxel data is available
     Loaded,
     // TODO: Add decoration step here
@@ -206,13 +220,15 @@ pub enum ChunkState {
 }
 
 impl ChunkState {
-    pub const TOTAL_STATES: usize = 10;
+    pub const TOTAL_STATES: usize = 12;
 
     pub const fn all() -> &'static [ChunkState] {
         &[
             ChunkState::Initial,
             ChunkState::InGenerationQueue,
             ChunkState::Generating,
+            ChunkState::LoadingFromDisk,
+            ChunkState::StaleMesh,
             ChunkState::Loaded,
             ChunkState::InMeshingQueue,
             ChunkState::Meshing,
@@ -269,9 +285,18 @@ impl ChunkNeighborState {
 pub struct Chunk<T: IChunkRenderState = ()> {
     pub position: ChunkPos,
     pub data: Option<ChunkData>,
+    /// Computed by the light engine after the data has been inserted.
+    pub light: Option<ChunkLight>,
+    /// Produced by the world generator along with the data, for every chunk so tints blend across chunk borders.
+    pub climate: Option<ChunkClimate>,
     pub state: Arc<AtomicCell<ChunkState>>,
     pub render_state: Option<T>,
+    /// Freshly uploaded render state, which replaces `render_state` once the renderer has flushed it.
+    pub pending_render_state: Option<T>,
     pub neighbor_state: Arc<ChunkNeighborState>,
+    /// Set when the voxel data differs from what was last loaded, generated or saved.
+    dirty: AtomicBool,
+    remesh_requested: Arc<AtomicBool>,
 }
 
 #[derive(Clone)]
@@ -279,6 +304,8 @@ pub struct ChunkHandle {
     pub pos: ChunkPos,
     state: Arc<AtomicCell<ChunkState>>,
     pub neighbor_state: Arc<ChunkNeighborState>,
+    /// Set when the chunk was edited while its mesh was being built or flushed.
+    remesh_requested: Arc<AtomicBool>,
 }
 
 impl Debug for ChunkHandle {
@@ -335,6 +362,48 @@ impl ChunkHandle {
             // Another thread changed the state, retry
         }
     }
+
+    /// Marks the chunk mesh as outdated after its voxel data has changed.
+    /// Returns true if the chunk transitioned to StaleMesh, in which case it should be enqueued for meshing.
+    pub fn invalidate_mesh(&self) -> bool {
+        loop {
+            match self.state() {
+                state @ (ChunkState::Ready | ChunkState::ReadyEmpty) => {
+                    if self.try_transition(state, ChunkState::StaleMesh) {
+                        return true;
+                    }
+                    // Another thread changed the state, retry
+                }
+                ChunkState::Meshing | ChunkState::WaitingForRendererFlush => {
+                    // The mesh in flight might not contain this change, so remesh once it's been flushed
+                    self.remesh_requested.store(true, Ordering::Release);
+
+                    // The renderer might have finished the chunk before the flag was set
+                    let finished =
+                        matches!(self.state(), ChunkState::Ready | ChunkState::ReadyEmpty);
+                    if !(finished && self.remesh_requested.swap(false, Ordering::AcqRel)) {
+                        return false;
+                    }
+                }
+                // Either the mesh hasn't been built yet, or a remesh is already queued
+                _ => return false,
+            }
+        }
+    }
+
+    /// Moves a flushed chunk to `ready_state` (Ready or ReadyEmpty).
+    /// Returns true if the chunk was edited in the meantime and moved to StaleMesh instead,
+    /// in which case it should be enqueued for meshing again.
+    pub fn finish_meshing(&self, ready_state: ChunkState) -> bool {
+        debug_assert!(matches!(
+            ready_state,
+            ChunkState::Ready | ChunkState::ReadyEmpty
+        ));
+
+        self.set_state(ready_state);
+        self.remesh_requested.swap(false, Ordering::AcqRel)
+            && self.try_transition(ready_state, ChunkState::StaleMesh)
+    }
 }
 
 pub trait IChunkRenderContext: Send + Clone {
@@ -370,9 +439,14 @@ impl<T: IChunkRenderState> Chunk<T> {
         Chunk {
             position,
             data: None,
+            light: None,
+            climate: None,
             state: Arc::new(AtomicCell::new(ChunkState::Initial)),
             render_state: None,
+            pending_render_state: None,
             neighbor_state: Arc::default(),
+            dirty: AtomicBool::new(false),
+            remesh_requested: Arc::default(),
         }
     }
 
@@ -382,15 +456,21 @@ impl<T: IChunkRenderState> Chunk<T> {
         Chunk {
             position,
             data: Some(data),
+            light: None,
+            climate: None,
             state: Arc::new(AtomicCell::new(ChunkState::Loaded)),
             render_state: None,
+            pending_render_state: None,
             neighbor_state: Arc::default(),
+            dirty: AtomicBool::new(false),
+            remesh_requested: Arc::default(),
         }
     }
 
     pub fn set_voxel(&mut self, pos: LocalPos, voxel: Voxel) {
         if let Some(chunk_data) = &mut self.data {
             chunk_data.set_voxel(pos, voxel);
+            self.mark_dirty();
         } else {
             panic!("Tried to set voxel on chunk with no data");
         }
@@ -404,6 +484,20 @@ impl<T: IChunkRenderState> Chunk<T> {
         }
     }
 
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+
+    pub fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Clears the dirty flag, returning whether it was set.
+    /// Callers that fail to save the chunk should mark it dirty again.
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::AcqRel)
+    }
+
     pub fn approximate_size(&self) -> usize {
         // This only counts CPU memory, add separate method for GPU memory
         size_of::<Self>()
@@ -411,11 +505,19 @@ impl<T: IChunkRenderState> Chunk<T> {
                 Some(data) => data.approximate_size(),
                 None => 0,
             }
+            + match &self.light {
+                Some(light) => light.approximate_size(),
+                None => 0,
+            }
+            + match &self.climate {
+                Some(climate) => climate.approximate_size(),
+                None => 0,
+            }
     }
 
     pub fn is_suitable_neighbor_for_meshing(&self) -> bool {
         let state = self.state.load();
-        self.data.is_some() && state < ChunkState::Unloaded
+        self.data.is_some() && self.light.is_some() && state < ChunkState::Unloaded
     }
 
     pub fn handle(&self) -> ChunkHandle {
@@ -423,9 +525,17 @@ impl<T: IChunkRenderState> Chunk<T> {
             pos: self.position,
             state: self.state.clone(),
             neighbor_state: self.neighbor_state.clone(),
+            remesh_requested: self.remesh_requested.clone(),
         }
     }
 
+    /// Replaces the current render state with the pending one, dropping the old mesh.
+    /// If there is no pending render state, the chunk was meshed as empty and the old mesh is dropped as well.
+    /// Should only be called by the renderer once the pending mesh has been flushed.
+    pub fn apply_pending_render_state(&mut self) {
+        self.render_state = self.pending_render_state.take();
+    }
+
     pub fn is_ready_for_meshing(&self) -> bool {
         self.data.is_some() && self.neighbor_state.is_ready_for_meshing()
     }
@@ -628,4 +738,31 @@ mod tests {
         state.set_neighbor_ready(Face::Right);
         assert!(state.is_ready_for_meshing());
     }
+
+    #[test]
+    fn test_invalidate_mesh() {
+        let chunk = Chunk::<()>::from_data(ChunkPos::new(0, 0, 0), ChunkData::solid(Voxel::AIR));
+        let handle = chunk.handle();
+
+        // Chunks without a mesh don't need remeshing
+        assert!(!handle.invalidate_mesh());
+        assert_eq!(handle.state(), ChunkState::Loaded);
+
+        // Ready chunks go straight to StaleMesh, and are only enqueued once
+        handle.set_state(ChunkState::Ready);
+        assert!(handle.invalidate_mesh());
+        assert_eq!(handle.state(), ChunkState::StaleMesh);
+        assert!(!handle.invalidate_mesh());
+
+        // Edits during meshing are deferred until the mesh has been flushed
+        handle.set_state(ChunkState::Meshing);
+        assert!(!handle.invalidate_mesh());
+        assert!(handle.finish_meshing(ChunkState::Ready));
+        assert_eq!(handle.state(), ChunkState::StaleMesh);
+
+        // Without edits, finishing leaves the chunk ready
+        handle.set_state(ChunkState::WaitingForRendererFlush);
+        assert!(!handle.finish_meshing(ChunkState::ReadyEmpty));
+        assert_eq!(handle.state(), ChunkState::ReadyEmpty);
+    }
 }
@@ -56,6 +56,19 @@ impl LocalPos {
         self.0.z
     }
 
+    /// Returns true if the position lies on the chunk boundary facing the given direction.
+    #[inline(always)]
+    pub fn is_on_face(&self, face: Face) -> bool {
+        match face {
+            Face::Top => self.0.y == CHUNK_SIZE - 1,
+            Face::Bottom => self.0.y == 0,
+            Face::Left => self.0.x == 0,
+            Face::Right => self.0.x == CHUNK_SIZE - 1,
+            Face::Front => self.0.z == CHUNK_SIZE - 1,
+            Face::Back => self.0.z == 0,
+        }
+    }
+
     #[inline(always)]
     pub fn to_chunk_data_index(&self) -> usize {
         let chunk_size = CHUNK_SIZE as usize;
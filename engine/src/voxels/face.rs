@@ -1,5 +1,7 @@
 use glam::{IVec3, U8Vec3};
 
+use crate::math::axis::Axis;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 #[derive(Default)]
@@ -31,6 +33,20 @@ impl Face {
         }
     }
 
+    /// Axis the face is perpendicular to
+    pub const fn axis(self) -> Axis {
+        match self {
+            Face::Top | Face::Bottom => Axis::Y,
+            Face::Left | Face::Right => Axis::X,
+            Face::Front | Face::Back => Axis::Z,
+        }
+    }
+
+    /// Whether the face points towards the positive end of its axis
+    pub const fn is_positive(self) -> bool {
+        matches!(self, Face::Top | Face::Right | Face::Front)
+    }
+
     pub const fn opposite(self) -> Face {
         match self {
             Face::Top => Face::Bottom,
@@ -3,5 +3,6 @@ pub mod chunk;
 pub mod coord;
 pub mod face;
 pub mod packed_chunk;
+pub mod raycast;
 pub mod unpacked_chunk;
 pub mod voxel;
@@ -81,6 +81,7 @@ impl Palette {
     }
 }
 
+#[derive(Clone)]
 pub struct PackedChunk {
     pub palette: Palette,
     // Data is stored in YZX order
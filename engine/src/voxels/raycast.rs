@@ -0,0 +1,159 @@
+use glam::{IVec3, Vec3};
+
+use crate::voxels::{coord::WorldPos, face::Face, voxel::Voxel};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// Position of the voxel that was hit
+    pub position: WorldPos,
+    /// Face of the hit voxel the ray entered through
+    pub face: Face,
+    /// Distance from the ray origin to the point where the ray entered the voxel
+    pub distance: f32,
+    pub voxel: Voxel,
+}
+
+impl RaycastHit {
+    /// Position of the voxel on the other side of the entered face, i.e. where a new block would be placed.
+    pub fn adjacent_position(&self) -> WorldPos {
+        self.position + WorldPos(self.face.to_ivec3())
+    }
+}
+
+/// Walks every voxel intersected by the ray in order, using the DDA algorithm by Amanatides & Woo.
+///
+/// `visit` is called with the voxel position, the face the ray entered through and the distance to the entry point.
+/// The walk stops when `visit` returns Some, or when the ray has travelled `max_distance`.
+/// `direction` doesn't need to be normalized.
+pub fn raycast_voxels<R>(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    mut visit: impl FnMut(WorldPos, Face, f32) -> Option<R>,
+) -> Option<R> {
+    // A NaN or infinite origin or distance would never reach a boundary and loop forever
+    if !origin.is_finite() || !max_distance.is_finite() {
+        return None;
+    }
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+
+    let mut voxel = origin.floor().as_ivec3();
+    let step = IVec3::new(
+        direction.x.signum() as i32,
+        direction.y.signum() as i32,
+        direction.z.signum() as i32,
+    );
+
+    // Distance along the ray between voxel boundaries on each axis
+    let t_delta = direction.recip().abs();
+
+    // Distance along the ray to the first voxel boundary on each axis
+    let next_boundary = voxel.as_vec3() + step.max(IVec3::ZERO).as_vec3();
+    let mut t_max = Vec3::select(
+        direction.cmpeq(Vec3::ZERO),
+        Vec3::INFINITY,
+        (next_boundary - origin) / direction,
+    );
+
+    // Faces entered when stepping along each axis
+    let entered_faces = [
+        if step.x > 0 { Face::Left } else { Face::Right },
+        if step.y > 0 { Face::Bottom } else { Face::Top },
+        if step.z > 0 { Face::Back } else { Face::Front },
+    ];
+
+    // The origin voxel wasn't entered through any face, so pick the one facing the ray
+    let dominant_axis = direction.abs().max_position();
+    if let Some(result) = visit(WorldPos(voxel), entered_faces[dominant_axis], 0.0) {
+        return Some(result);
+    }
+
+    loop {
+        let axis = t_max.min_position();
+        let distance = t_max[axis];
+        if distance > max_distance {
+            return None;
+        }
+
+        voxel[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        if let Some(result) = visit(WorldPos(voxel), entered_faces[axis], distance) {
+            return Some(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cast_against(
+        solid: &[WorldPos],
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> Option<(WorldPos, Face, f32)> {
+        raycast_voxels(origin, direction, max_distance, |pos, face, distance| {
+            solid.contains(&pos).then_some((pos, face, distance))
+        })
+    }
+
+    #[test]
+    fn test_raycast_hits_along_axes() {
+        let solid = [WorldPos::new(5, 0, 0), WorldPos::new(0, -3, 0)];
+
+        let (pos, face, distance) =
+            cast_against(&solid, Vec3::new(0.5, 0.5, 0.5), Vec3::X, 10.0).unwrap();
+        assert_eq!(pos, WorldPos::new(5, 0, 0));
+        assert_eq!(face, Face::Left);
+        assert!((distance - 4.5).abs() < 1e-5);
+
+        let (pos, face, distance) =
+            cast_against(&solid, Vec3::new(0.5, 0.5, 0.5), Vec3::NEG_Y, 10.0).unwrap();
+        assert_eq!(pos, WorldPos::new(0, -3, 0));
+        assert_eq!(face, Face::Top);
+        assert!((distance - 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_raycast_respects_max_distance() {
+        let solid = [WorldPos::new(5, 0, 0)];
+        assert!(cast_against(&solid, Vec3::new(0.5, 0.5, 0.5), Vec3::X, 4.0).is_none());
+    }
+
+    #[test]
+    fn test_raycast_diagonal_visits_connected_voxels() {
+        let mut visited = Vec::new();
+        raycast_voxels::<()>(
+            Vec3::new(0.2, 0.7, 0.5),
+            Vec3::new(1.0, -1.0, 0.0),
+            5.0,
+            |pos, _, _| {
+                visited.push(pos);
+                None
+            },
+        );
+
+        assert_eq!(visited[0], WorldPos::new(0, 0, 0));
+        // Each step moves exactly one voxel along a single axis
+        for pair in visited.windows(2) {
+            let delta = (pair[1].0 - pair[0].0).abs();
+            assert_eq!(delta.element_sum(), 1);
+        }
+    }
+
+    #[test]
+    fn test_raycast_rejects_non_finite_input() {
+        let solid = [WorldPos::new(1, 0, 0)];
+        let origin = Vec3::new(0.5, 0.5, 0.5);
+
+        assert!(cast_against(&solid, origin, Vec3::X, f32::INFINITY).is_none());
+        assert!(cast_against(&solid, origin, Vec3::X, f32::NAN).is_none());
+        assert!(cast_against(&solid, Vec3::NAN, Vec3::X, 10.0).is_none());
+        assert!(cast_against(&solid, Vec3::new(f32::INFINITY, 0.0, 0.0), -Vec3::X, 10.0).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use glam::U8Vec3;
 
 use crate::{
+    lighting::chunk_light::{MAX_LIGHT, PackedLight},
     math::aabb::AABB8,
     voxels::{
         chunk::{CHUNK_SIZE, CHUNK_VOLUME, Chunk, ChunkData, IChunkRenderState},
@@ -11,6 +12,8 @@ use crate::{
 
 pub struct UnpackedChunk {
     pub voxels: [Voxel; CHUNK_VOLUME],
+    /// Light of every voxel, fully lit by the sky unless copied from a lit chunk
+    pub light: [PackedLight; CHUNK_VOLUME],
 }
 
 pub enum UnpackedChunkResult {
@@ -23,6 +26,7 @@ impl UnpackedChunk {
     pub fn new() -> Self {
         UnpackedChunk {
             voxels: [Voxel::AIR; CHUNK_VOLUME],
+            light: [PackedLight::new(MAX_LIGHT, 0); CHUNK_VOLUME],
         }
     }
 
@@ -35,7 +39,7 @@ impl UnpackedChunk {
         };
 
         match chunk_data {
-            ChunkData::Solid(voxel) if *voxel == Voxel::AIR => {
+            ChunkData::Solid(voxel) if voxel.is_air() => {
                 return UnpackedChunkResult::Empty;
             }
             _ => {}
@@ -52,6 +56,10 @@ impl UnpackedChunk {
             }
         }
 
+        if let Some(light) = chunk.light.as_ref() {
+            light.copy_to(&mut unpacked_chunk.light);
+        }
+
         UnpackedChunkResult::Data(Box::new(unpacked_chunk))
     }
 
@@ -65,6 +73,11 @@ impl UnpackedChunk {
         self.voxels[index] = voxel;
     }
 
+    pub fn get_light(&self, pos: LocalPos) -> Option<PackedLight> {
+        let index = pos.to_chunk_data_index();
+        self.light.get(index).copied()
+    }
+
     pub fn compute_aabb(&self) -> AABB8 {
         let mut min = U8Vec3::splat(15);
         let mut max = U8Vec3::splat(0);
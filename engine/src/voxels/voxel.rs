@@ -30,16 +30,12 @@ impl Voxel {
     pub const DIRT: Voxel = Voxel::from_type(2);
     pub const GOLD: Voxel = Voxel::from_type(3);
 
-    pub const fn is_transparent(&self) -> bool {
-        // TODO: Support other transparent block types
+    /// Whether the voxel is empty. Use `BlockDatabaseSlim::properties` for how the block behaves.
+    pub const fn is_air(&self) -> bool {
         self.block_type() == Self::AIR.block_type()
     }
 
     pub const fn block_type_id(&self) -> BlockTypeId {
         BlockTypeId(self.block_type())
     }
-
-    pub const fn is_solid(&self) -> bool {
-        self.block_type() != Self::AIR.block_type()
-    }
 }
@@ -1,11 +1,26 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, bail};
+use dashmap::Entry;
+use glam::Vec3;
 
 use crate::{
-    assets::blocks::BlockDatabaseSlim,
-    chunk_loader::{ChunkLoader, ChunkLoaderHandle},
+    assets::blocks::{BlockDatabase, BlockDatabaseSlim, SharedBlockDatabase},
+    chunk_loader::{ChunkDataEvent, ChunkLoader, ChunkLoaderHandle, WorldAccess},
+    editing::{
+        chunk_diff::{ChunkDiff, DiffSide},
+        chunk_writer::ChunkWriter,
+        history::{EditHistory, EditTransaction},
+        world_edit::WorldEdit,
+    },
+    fluids::fluid_simulation::{FluidRules, FluidSimulation},
+    lighting::light_engine::LightEngine,
+    persistence::world_storage::WorldStorage,
     voxels::{
-        chunk::{Chunk, ChunkData, ChunkState, IChunkRenderState},
+        chunk::{Chunk, ChunkData, ChunkHandle, ChunkState, IChunkRenderState},
         coord::{ChunkPos, WorldPos},
+        face::Face,
+        raycast::{RaycastHit, raycast_voxels},
         voxel::Voxel,
     },
     world_stats::{CHUNKS_BY_STATE, WorldStatistics},
@@ -17,6 +32,15 @@ pub type WorldChunks<T> = dashmap::DashMap<ChunkPos, Chunk<T>, ahash::RandomStat
 pub struct World<T: IChunkRenderState = ()> {
     pub chunk_loader: ChunkLoaderHandle<T>,
     pub chunks: Arc<WorldChunks<T>>,
+    /// Where chunks are loaded from and saved to. Worlds without storage are purely generated.
+    pub storage: Option<Arc<WorldStorage>>,
+    /// Undo/redo history of transactions committed with `WorldEdit`
+    pub history: Mutex<EditHistory>,
+    /// Replaced when the block definitions are reloaded, see `reload_blocks`
+    pub block_database: SharedBlockDatabase,
+    /// Flowing fluids, advanced by `tick`
+    pub fluids: Mutex<FluidSimulation>,
+    light_engine: Arc<LightEngine>,
     statistics: WorldStatistics,
 }
 
@@ -29,14 +53,22 @@ pub enum ChunkEvent {
 impl<T: IChunkRenderState + Send + Sync + 'static> World<T> {
     pub fn from_generator(
         generator: impl WorldGenerator,
+        storage: Option<Arc<WorldStorage>>,
         block_database: Arc<BlockDatabaseSlim>,
         render_context: T::Context,
     ) -> Self {
-        Self::from_chunks(generator, block_database, Vec::new(), render_context)
+        Self::from_chunks(
+            generator,
+            storage,
+            block_database,
+            Vec::new(),
+            render_context,
+        )
     }
 
     pub fn from_chunks(
         generator: impl WorldGenerator,
+        storage: Option<Arc<WorldStorage>>,
         block_database: Arc<BlockDatabaseSlim>,
         initial_chunks: Vec<(ChunkPos, ChunkData)>,
         render_context: T::Context,
@@ -51,35 +83,330 @@ impl<T: IChunkRenderState + Send + Sync + 'static> World<T> {
 
         let chunks_map = initial_chunks
             .into_iter()
-            .map(|(pos, data)| (pos, Chunk::from_data(pos, data)))
+            .map(|(pos, data)| {
+                let mut chunk = Chunk::from_data(pos, data);
+                chunk.climate = Some(generator.generate_climate(pos));
+                (pos, chunk)
+            })
             .collect::<WorldChunks<T>>();
 
+        let block_database = SharedBlockDatabase::new(block_database);
+
+        // Light from the top down, so fewer chunks start out assuming open sky above them
+        let light_engine = Arc::new(LightEngine::new(block_database.clone()));
+        let mut lighting_order = initial_chunk_positions.clone();
+        lighting_order.sort_by_key(|pos| std::cmp::Reverse(pos.y()));
+        for pos in lighting_order {
+            light_engine.light_new_chunk(&chunks_map, pos);
+        }
+
         let chunks = Arc::new(chunks_map);
         let chunk_access = chunks.clone();
 
         let chunk_loader = ChunkLoader::start(
             Box::new(generator),
-            block_database,
+            storage.clone(),
+            block_database.clone(),
+            light_engine.clone(),
             chunk_access,
             render_context,
         );
         let world = World {
             chunk_loader,
             chunks,
+            storage,
+            history: Mutex::default(),
+            block_database,
+            fluids: Mutex::default(),
+            light_engine,
             statistics,
         };
-        world.update_neighbors_for_chunks(initial_chunk_positions.into_iter());
+        world.update_neighbors_for_chunks(initial_chunk_positions.iter().copied());
+        for pos in initial_chunk_positions {
+            world.schedule_loaded_fluids(pos);
+        }
         world
     }
 
     pub fn set_voxel(&self, position: WorldPos, voxel: Voxel) {
-        let chunk_id = position.to_chunk_pos();
-        self.chunks.entry(chunk_id).and_modify(|chunk| {
-            // TODO: Changes to non-existent chunks are silently ignored, is that good?
-            // If the chunk hasn't been generated yet, it will be overwritten when generation finishes
-            let local_pos = position.to_local_pos();
-            chunk.set_voxel(local_pos, voxel);
+        // TODO: Changes to non-existent chunks are silently ignored, is that good?
+        // If the chunk hasn't been generated yet, it will be overwritten when generation finishes
+        let mut stale_chunks = Vec::new();
+        let diff = self.edit_chunk(position.to_chunk_pos(), &mut stale_chunks, |writer| {
+            writer.set_voxel(position.to_local_pos(), voxel);
         });
+        if let Some(diff) = diff {
+            self.update_edited_voxels([&diff], &mut stale_chunks);
+        }
+        self.chunk_loader.remesh(stale_chunks);
+    }
+
+    /// Starts a transaction for grouping many voxel writes into a single undo step.
+    pub fn edit(&self) -> WorldEdit<'_, T> {
+        WorldEdit::new(self)
+    }
+
+    /// Runs `edit` on a loaded chunk's data while holding its lock, and returns a diff of the voxels it changed.
+    /// Chunks whose meshes need to be rebuilt are added to `stale_chunks`.
+    /// Returns None if the chunk isn't loaded.
+    pub(crate) fn edit_chunk(
+        &self,
+        chunk_pos: ChunkPos,
+        stale_chunks: &mut Vec<ChunkHandle>,
+        edit: impl FnOnce(&mut ChunkWriter),
+    ) -> Option<ChunkDiff> {
+        let mut diff = ChunkDiff::new(chunk_pos);
+
+        let handle = {
+            let mut chunk = self.chunks.get_mut(&chunk_pos)?;
+            let data = chunk.data.as_mut()?;
+            edit(&mut ChunkWriter::new(data, &mut diff));
+
+            if diff.is_empty() {
+                return Some(diff);
+            }
+
+            chunk.mark_dirty();
+            chunk.handle()
+        };
+
+        self.invalidate_edited_chunk(handle, diff.touched_faces(), stale_chunks);
+        Some(diff)
+    }
+
+    /// Runs `edit` on each of the given chunks that is loaded, and records all changes as a single undo step.
+    /// Returns the number of voxels that changed.
+    pub(crate) fn edit_chunks(
+        &self,
+        chunk_positions: impl IntoIterator<Item = ChunkPos>,
+        edit: impl FnMut(&mut ChunkWriter),
+    ) -> usize {
+        let transaction = self.write_chunks(chunk_positions, edit);
+        let changed = transaction.voxel_count();
+        self.history.lock().unwrap().push(transaction);
+        changed
+    }
+
+    /// Like `edit_chunks`, but returns the changes instead of recording them in the undo history.
+    /// Used for changes made by the world itself, like flowing fluids.
+    pub(crate) fn write_chunks(
+        &self,
+        chunk_positions: impl IntoIterator<Item = ChunkPos>,
+        mut edit: impl FnMut(&mut ChunkWriter),
+    ) -> EditTransaction {
+        let mut transaction = EditTransaction::default();
+        let mut stale_chunks = Vec::new();
+
+        for chunk_pos in chunk_positions {
+            if let Some(diff) = self.edit_chunk(chunk_pos, &mut stale_chunks, &mut edit)
+                && !diff.is_empty()
+            {
+                transaction.chunks.push(diff);
+            }
+        }
+
+        self.update_edited_voxels(&transaction.chunks, &mut stale_chunks);
+        self.chunk_loader.remesh(stale_chunks);
+        transaction
+    }
+
+    /// Invalidates the mesh of an edited chunk, and those of its neighbours across the given faces.
+    fn invalidate_edited_chunk(
+        &self,
+        handle: ChunkHandle,
+        touched_faces: u8,
+        stale_chunks: &mut Vec<ChunkHandle>,
+    ) {
+        let chunk_pos = handle.pos;
+        if handle.invalidate_mesh() {
+            stale_chunks.push(handle);
+        }
+
+        // Neighbors mesh against a copy of our border, so edits on the border affect their meshes too
+        for face in Face::all().iter().copied() {
+            if touched_faces & (1 << (face as u8)) == 0 {
+                continue;
+            }
+
+            let Some(neighbor) = self.chunks.get(&chunk_pos.get_neighbor(face)) else {
+                continue;
+            };
+
+            let neighbor_handle = neighbor.handle();
+            drop(neighbor);
+
+            if neighbor_handle.invalidate_mesh() {
+                stale_chunks.push(neighbor_handle);
+            }
+        }
+    }
+
+    /// Updates the light around the voxels changed by `diffs`, and invalidates the meshes of relit chunks.
+    /// Also schedules updates for fluids next to the changes, so they flow into or out of the edited voxels.
+    fn update_edited_voxels<'a>(
+        &self,
+        diffs: impl IntoIterator<Item = &'a ChunkDiff>,
+        stale_chunks: &mut Vec<ChunkHandle>,
+    ) {
+        let positions = diffs
+            .into_iter()
+            .flat_map(|diff| {
+                diff.iter(DiffSide::After)
+                    .map(|(pos, _)| WorldPos::from_chunk_and_voxel(diff.position, pos))
+            })
+            .collect::<Vec<_>>();
+
+        let light_changes = self
+            .light_engine
+            .update_voxels(&self.chunks, positions.iter().copied());
+        light_changes.invalidate_meshes(&self.chunks, stale_chunks);
+
+        let block_database = self.block_database.load();
+        self.fluids.lock().unwrap().voxels_changed(
+            block_database.as_ref(),
+            |pos| self.get_voxel(pos),
+            positions,
+        );
+    }
+
+    /// Reverts the most recent transaction. Returns false if there was nothing to undo.
+    pub fn undo(&self) -> anyhow::Result<bool> {
+        let mut history = self.history.lock().unwrap();
+        let Some(transaction) = history.begin_undo() else {
+            return Ok(false);
+        };
+
+        match self.apply_transaction(&transaction, DiffSide::Before) {
+            Ok(()) => {
+                history.finish_undo(transaction);
+                Ok(true)
+            }
+            Err(err) => {
+                history.cancel_undo(transaction);
+                Err(err).context("Failed to undo edit")
+            }
+        }
+    }
+
+    /// Reapplies the most recently undone transaction. Returns false if there was nothing to redo.
+    pub fn redo(&self) -> anyhow::Result<bool> {
+        let mut history = self.history.lock().unwrap();
+        let Some(transaction) = history.begin_redo() else {
+            return Ok(false);
+        };
+
+        match self.apply_transaction(&transaction, DiffSide::After) {
+            Ok(()) => {
+                history.finish_redo(transaction);
+                Ok(true)
+            }
+            Err(err) => {
+                history.cancel_redo(transaction);
+                Err(err).context("Failed to redo edit")
+            }
+        }
+    }
+
+    /// Writes one side of every diff in the transaction into the world.
+    /// Loaded chunks are edited in memory, and unloaded chunks are edited in storage.
+    fn apply_transaction(
+        &self,
+        transaction: &EditTransaction,
+        side: DiffSide,
+    ) -> anyhow::Result<()> {
+        // Chunks that are being loaded can't be safely edited in memory or on disk.
+        // Check them all before writing anything, so the transaction isn't partially applied.
+        for diff in &transaction.chunks {
+            if let Some(chunk) = self.chunks.get(&diff.position)
+                && chunk.data.is_none()
+            {
+                bail!("Chunk {:?} is still loading", diff.position);
+            }
+        }
+
+        let mut stale_chunks = Vec::new();
+        let mut applied_in_memory = Vec::new();
+
+        for diff in &transaction.chunks {
+            match self.chunks.entry(diff.position) {
+                Entry::Occupied(mut entry) => {
+                    let chunk = entry.get_mut();
+                    let Some(data) = &mut chunk.data else {
+                        // Started loading after the check above, so it's too late to back out
+                        log::warn!(
+                            "Chunk {:?} started loading during undo, skipping it",
+                            diff.position
+                        );
+                        continue;
+                    };
+
+                    diff.apply_to(data, side);
+                    chunk.mark_dirty();
+                    let handle = chunk.handle();
+                    drop(entry);
+
+                    self.invalidate_edited_chunk(handle, diff.touched_faces(), &mut stale_chunks);
+                    applied_in_memory.push(diff);
+                }
+                Entry::Vacant(entry) => {
+                    // Holding the vacant entry keeps the chunk from being loaded while it's rewritten
+                    let result = self.apply_diff_to_storage(diff, side);
+                    drop(entry);
+                    result?;
+                }
+            }
+        }
+
+        self.update_edited_voxels(applied_in_memory, &mut stale_chunks);
+        self.chunk_loader.remesh(stale_chunks);
+        Ok(())
+    }
+
+    fn apply_diff_to_storage(&self, diff: &ChunkDiff, side: DiffSide) -> anyhow::Result<()> {
+        let stored = match &self.storage {
+            Some(storage) => storage
+                .load_chunk(diff.position)?
+                .map(|data| (storage, data)),
+            None => None,
+        };
+
+        let Some((storage, mut data)) = stored else {
+            // The chunk was unloaded without being saved, so the edit was already lost
+            log::warn!(
+                "Edited chunk {:?} is no longer loaded or stored, skipping it",
+                diff.position
+            );
+            return Ok(());
+        };
+
+        diff.apply_to(&mut data, side);
+        storage.save_chunk(diff.position, &data)
+    }
+
+    /// Switches to reloaded block definitions and remeshes every loaded chunk.
+    /// Light isn't recomputed, so changes to opacity or light emission only apply to light updated afterwards.
+    pub fn reload_blocks(&self, block_database: &BlockDatabase) -> anyhow::Result<()> {
+        if let Some(storage) = &self.storage {
+            storage.update_block_ids(block_database)?;
+        }
+
+        self.block_database
+            .replace(Arc::new(BlockDatabaseSlim::from_block_database(
+                block_database,
+            )));
+
+        let handles = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.handle())
+            .collect::<Vec<_>>();
+        let stale_chunks = handles
+            .into_iter()
+            .filter(|handle| handle.invalidate_mesh())
+            .collect();
+        self.chunk_loader.remesh(stale_chunks);
+
+        Ok(())
     }
 
     pub fn get_voxel(&self, position: WorldPos) -> Option<Voxel> {
@@ -89,6 +416,97 @@ impl<T: IChunkRenderState + Send + Sync + 'static> World<T> {
         chunk.get_voxel(local_pos)
     }
 
+    /// Writes all dirty chunks to disk and syncs the region files.
+    /// Returns the number of chunks written.
+    pub fn save(&self) -> anyhow::Result<usize> {
+        let Some(storage) = &self.storage else {
+            return Ok(0);
+        };
+
+        let saved = self.chunks.save_dirty_chunks(storage);
+        storage.flush().context("Failed to flush world storage")?;
+        Ok(saved)
+    }
+
+    /// Stops background chunk loading and blocks until dirty chunks have been saved.
+    pub fn shutdown(self) {
+        self.chunk_loader.shutdown();
+    }
+
+    /// Casts a ray through the world and returns the first voxel for which `is_hit` returns true.
+    /// Voxels in chunks that aren't loaded are never hit.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        is_hit: impl Fn(Voxel) -> bool,
+    ) -> Option<RaycastHit> {
+        raycast_voxels(
+            origin,
+            direction,
+            max_distance,
+            |position, face, distance| {
+                let voxel = self.get_voxel(position)?;
+                is_hit(voxel).then_some(RaycastHit {
+                    position,
+                    face,
+                    distance,
+                    voxel,
+                })
+            },
+        )
+    }
+
+    /// Advances the world by one game update. Fluid changes go through the normal edit path,
+    /// so they're relit, remeshed and saved like any other edit, but can't be undone.
+    pub fn tick(&self) {
+        for event in self.chunk_loader.chunk_data_events.try_iter() {
+            match event {
+                ChunkDataEvent::Inserted(pos) => self.schedule_loaded_fluids(pos),
+                ChunkDataEvent::Unloaded(positions) => {
+                    self.fluids.lock().unwrap().chunks_unloaded(&positions)
+                }
+                ChunkDataEvent::Cleared => self.fluids.lock().unwrap().clear(),
+            }
+        }
+
+        let block_database = self.block_database.load();
+        let mut edit = self.edit();
+        self.fluids
+            .lock()
+            .unwrap()
+            .tick(&mut edit, block_database.as_ref());
+        edit.commit_without_history();
+    }
+
+    /// Schedules the fluids of a newly generated or loaded chunk, and the fluids next to it that were waiting for it
+    fn schedule_loaded_fluids(&self, pos: ChunkPos) {
+        let block_database = self.block_database.load();
+        let is_fluid = |voxel: &Voxel| block_database.fluid(*voxel).is_some();
+        let fluids = match self
+            .chunks
+            .get(&pos)
+            .as_deref()
+            .and_then(|chunk| chunk.data.as_ref())
+        {
+            // Most chunks have no fluids, which the palette tells without looking at every voxel
+            Some(data) if data.voxel_types().iter().any(is_fluid) => data
+                .iter_voxels()
+                .filter(|(_, voxel)| is_fluid(voxel))
+                .map(|(local, _)| WorldPos::from_chunk_and_voxel(pos, local))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        self.fluids.lock().unwrap().chunk_loaded(
+            block_database.as_ref(),
+            |pos| self.get_voxel(pos),
+            pos,
+            fluids,
+        );
+    }
+
     pub fn get_statistics(&self) -> &WorldStatistics {
         &self.statistics
     }
@@ -0,0 +1,207 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+use crate::voxels::chunk::CHUNK_SIZE;
+
+/// Columns in a chunk
+const CHUNK_COLUMNS: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
+
+/// Climate of a world column, which decides the colour of tinted blocks like grass and leaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Climate {
+    pub temperature: u8,
+    pub humidity: u8,
+}
+
+// Tint colours at the extremes of the climate, blended by temperature and humidity
+const COLD_DRY_TINT: Vec3 = Vec3::new(0.56, 0.68, 0.52);
+const COLD_WET_TINT: Vec3 = Vec3::new(0.38, 0.62, 0.45);
+const HOT_DRY_TINT: Vec3 = Vec3::new(0.76, 0.72, 0.34);
+const HOT_WET_TINT: Vec3 = Vec3::new(0.30, 0.70, 0.20);
+
+impl Climate {
+    /// Mild and fairly humid, used where the world generator doesn't produce a climate
+    pub const TEMPERATE: Climate = Climate {
+        temperature: 160,
+        humidity: 160,
+    };
+
+    /// Colour multiplied with the greyscale textures of tinted blocks, 0-1 per channel
+    pub fn tint(self) -> Vec3 {
+        let temperature = self.temperature as f32 / 255.0;
+        let humidity = self.humidity as f32 / 255.0;
+        let cold = COLD_DRY_TINT.lerp(COLD_WET_TINT, humidity);
+        let hot = HOT_DRY_TINT.lerp(HOT_WET_TINT, humidity);
+        cold.lerp(hot, temperature)
+    }
+}
+
+impl Default for Climate {
+    fn default() -> Self {
+        Self::TEMPERATE
+    }
+}
+
+/// Climate of each column of a chunk, produced by the world generator.
+/// Every chunk in a column stores the same values, so chunks don't depend on the ones above or below them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkClimate(Box<[Climate; CHUNK_COLUMNS]>);
+
+impl ChunkClimate {
+    pub fn uniform(climate: Climate) -> Self {
+        ChunkClimate(Box::new([climate; CHUNK_COLUMNS]))
+    }
+
+    /// Builds the climate from a function of the local column coordinates (x, z)
+    pub fn from_fn(mut climate: impl FnMut(u8, u8) -> Climate) -> Self {
+        let mut columns = Box::new([Climate::TEMPERATE; CHUNK_COLUMNS]);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                columns[column_index(x, z)] = climate(x, z);
+            }
+        }
+        ChunkClimate(columns)
+    }
+
+    pub fn get(&self, x: u8, z: u8) -> Climate {
+        self.0[column_index(x, z)]
+    }
+
+    pub fn approximate_size(&self) -> usize {
+        size_of::<[Climate; CHUNK_COLUMNS]>()
+    }
+}
+
+impl Default for ChunkClimate {
+    fn default() -> Self {
+        Self::uniform(Climate::TEMPERATE)
+    }
+}
+
+fn column_index(x: u8, z: u8) -> usize {
+    x as usize + z as usize * CHUNK_SIZE as usize
+}
+
+/// Tint colours are stored at the corners of cells this many voxels wide, and interpolated in between
+pub const TINT_CELL_SIZE: i32 = 4;
+/// Corners of the tint grid along each side of a chunk
+pub const TINT_GRID_SIZE: usize = CHUNK_SIZE as usize / TINT_CELL_SIZE as usize + 1;
+pub const TINT_GRID_AREA: usize = TINT_GRID_SIZE * TINT_GRID_SIZE;
+/// Each corner of the tint grid averages the columns within this many voxels, so climate borders fade out
+pub const TINT_BLEND_RADIUS: i32 = 4;
+
+/// Blended tint colours at the corners of a chunk's tint grid, indexed by `x + z * TINT_GRID_SIZE`.
+/// Colours are packed as RGBA8 with red in the lowest byte.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+pub struct ChunkTint {
+    pub colors: [u32; TINT_GRID_AREA],
+}
+
+impl ChunkTint {
+    /// Blends the climate of a chunk with its horizontal neighbours, given in the order -X, +X, -Z, +Z.
+    /// Columns of missing climates are left out of the blend, and columns past a corner of the chunk
+    /// are taken from the closest column of the -X or +X neighbour.
+    pub fn blend(center: Option<&ChunkClimate>, neighbors: [Option<&ChunkClimate>; 4]) -> Self {
+        let size = CHUNK_SIZE as i32;
+        let column = |x: i32, z: i32| -> Option<Climate> {
+            let (climate, x, z) = match (x, z) {
+                (x, z) if x < 0 => (neighbors[0], x + size, z.clamp(0, size - 1)),
+                (x, z) if x >= size => (neighbors[1], x - size, z.clamp(0, size - 1)),
+                (x, z) if z < 0 => (neighbors[2], x, z + size),
+                (x, z) if z >= size => (neighbors[3], x, z - size),
+                (x, z) => (center, x, z),
+            };
+            climate.map(|climate| climate.get(x as u8, z as u8))
+        };
+
+        let mut colors = [0; TINT_GRID_AREA];
+        for (index, color) in colors.iter_mut().enumerate() {
+            let corner_x = (index % TINT_GRID_SIZE) as i32 * TINT_CELL_SIZE;
+            let corner_z = (index / TINT_GRID_SIZE) as i32 * TINT_CELL_SIZE;
+
+            let mut sum = Vec3::ZERO;
+            let mut count = 0;
+            for z in corner_z - TINT_BLEND_RADIUS..corner_z + TINT_BLEND_RADIUS {
+                for x in corner_x - TINT_BLEND_RADIUS..corner_x + TINT_BLEND_RADIUS {
+                    if let Some(climate) = column(x, z) {
+                        sum += climate.tint();
+                        count += 1;
+                    }
+                }
+            }
+
+            let tint = if count > 0 {
+                sum / count as f32
+            } else {
+                Climate::TEMPERATE.tint()
+            };
+            *color = pack_color(tint);
+        }
+
+        ChunkTint { colors }
+    }
+
+    /// Tint colour at a corner of the grid
+    pub fn get(&self, x: usize, z: usize) -> Vec3 {
+        unpack_color(self.colors[x + z * TINT_GRID_SIZE])
+    }
+}
+
+impl Default for ChunkTint {
+    fn default() -> Self {
+        ChunkTint {
+            colors: [pack_color(Climate::TEMPERATE.tint()); TINT_GRID_AREA],
+        }
+    }
+}
+
+fn pack_color(color: Vec3) -> u32 {
+    let [r, g, b] = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0)
+        .round()
+        .to_array()
+        .map(|channel| channel as u32);
+    r | (g << 8) | (b << 16) | (0xff << 24)
+}
+
+fn unpack_color(color: u32) -> Vec3 {
+    Vec3::new(
+        (color & 0xff) as f32,
+        ((color >> 8) & 0xff) as f32,
+        ((color >> 16) & 0xff) as f32,
+    ) / 255.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tint_blends_across_chunk_borders() {
+        let cold = ChunkClimate::uniform(Climate {
+            temperature: 0,
+            humidity: 128,
+        });
+        let hot = ChunkClimate::uniform(Climate {
+            temperature: 255,
+            humidity: 128,
+        });
+
+        // A cold chunk next to a hot one fades towards it, and meets it halfway at the shared border
+        let tint = ChunkTint::blend(Some(&cold), [None, Some(&hot), None, None]);
+        let cold_tint = cold.get(0, 0).tint();
+        let hot_tint = hot.get(0, 0).tint();
+        let warmth = |x| tint.get(x, TINT_GRID_SIZE / 2).distance(cold_tint);
+
+        assert!(warmth(0) < 0.01);
+        for x in 1..TINT_GRID_SIZE {
+            assert!(warmth(x) >= warmth(x - 1));
+        }
+        let border = tint.get(TINT_GRID_SIZE - 1, TINT_GRID_SIZE / 2);
+        assert!(border.distance((cold_tint + hot_tint) / 2.0) < 0.01);
+
+        // Without any climate, everything is temperate
+        let default_tint = ChunkTint::blend(None, [None; 4]);
+        assert_eq!(default_tint, ChunkTint::default());
+    }
+}
@@ -1,9 +1,14 @@
+mod climate;
 mod noise_world_generator;
 mod test_world_generators;
 mod text_generator;
 mod world_generator;
 
-pub use noise_world_generator::generate_noise_world;
+pub use climate::{
+    ChunkClimate, ChunkTint, Climate, TINT_BLEND_RADIUS, TINT_CELL_SIZE, TINT_GRID_AREA,
+    TINT_GRID_SIZE,
+};
+pub use noise_world_generator::{NOISE_WORLD_SEED, NoiseWorldGenerator, generate_noise_world};
 pub use test_world_generators::generate_torture_test_world;
 pub use text_generator::draw_text;
 pub use world_generator::WorldGenerator;
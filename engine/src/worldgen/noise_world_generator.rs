@@ -6,6 +6,7 @@ use rayon::prelude::*;
 
 use crate::{
     assets::blocks::BlockDatabaseSlim,
+    persistence::world_storage::WorldStorage,
     voxels::{
         chunk::{CHUNK_SIZE, ChunkData, IChunkRenderState},
         coord::{ChunkPos, LocalPos},
@@ -13,9 +14,12 @@ use crate::{
         voxel::Voxel,
     },
     world::World,
-    worldgen::world_generator::WorldGenerator,
+    worldgen::{ChunkClimate, Climate, world_generator::WorldGenerator},
 };
 
+/// Seed of the world generated by `generate_noise_world`
+pub const NOISE_WORLD_SEED: u32 = 123_456;
+
 pub struct NoiseWorldGenerator {
     noise: SuperSimplex,
 }
@@ -118,15 +122,39 @@ impl WorldGenerator for NoiseWorldGenerator {
 
         ChunkData::from(chunk)
     }
+
+    fn generate_climate(&self, chunk_pos: ChunkPos) -> ChunkClimate {
+        let origin_2d = chunk_pos.origin().0.xz().as_dvec2();
+
+        const CLIMATE_FREQ: f64 = 1.0 / 1024.0;
+        // Samples the climate noise far away from the terrain noise, so they don't line up
+        const TEMPERATURE_OFFSET: DVec2 = DVec2::new(10_000.0, -10_000.0);
+        const HUMIDITY_OFFSET: DVec2 = DVec2::new(-20_000.0, 20_000.0);
+
+        let sample = |offset: DVec2, world_xz: DVec2| {
+            let value = fbm(&self.noise, offset + world_xz * CLIMATE_FREQ, 3, 2.0, 0.5);
+            // Summed octaves rarely reach the ends of the range, so it's widened a bit
+            ((value * 0.75 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+
+        ChunkClimate::from_fn(|x, z| {
+            let world_xz = origin_2d + DVec2::new(x as f64, z as f64);
+            Climate {
+                temperature: sample(TEMPERATURE_OFFSET, world_xz),
+                humidity: sample(HUMIDITY_OFFSET, world_xz),
+            }
+        })
+    }
 }
 
 #[allow(unused)]
 pub fn generate_noise_world<T: IChunkRenderState>(
     initial_size: i32,
+    storage: Option<Arc<WorldStorage>>,
     db: Arc<BlockDatabaseSlim>,
     render_context: T::Context,
 ) -> World<T> {
-    let generator = NoiseWorldGenerator::new(123_456);
+    let generator = NoiseWorldGenerator::new(NOISE_WORLD_SEED);
 
     let chunk_range = -(initial_size / 2)..(initial_size / 2);
     let range_width = chunk_range.end - chunk_range.start;
@@ -141,5 +169,5 @@ pub fn generate_noise_world<T: IChunkRenderState>(
         })
         .collect::<Vec<_>>();
 
-    World::from_chunks(generator, db, chunks, render_context)
+    World::from_chunks(generator, storage, db, chunks, render_context)
 }
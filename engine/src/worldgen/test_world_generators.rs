@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::{
     assets::blocks::BlockDatabaseSlim,
+    persistence::world_storage::WorldStorage,
     voxels::{
         chunk::{CHUNK_SIZE, ChunkData, IChunkRenderState},
         coord::{ChunkPos, LocalPos},
@@ -45,9 +46,10 @@ impl WorldGenerator for TortureTestWorldGenerator {
 
 #[allow(unused)]
 pub fn generate_torture_test_world<T: IChunkRenderState>(
+    storage: Option<Arc<WorldStorage>>,
     db: Arc<BlockDatabaseSlim>,
     render_context: T::Context,
 ) -> World<T> {
     let generator = TortureTestWorldGenerator::new(0);
-    World::from_generator(generator, db, render_context)
+    World::from_generator(generator, storage, db, render_context)
 }
@@ -1,8 +1,15 @@
-use crate::voxels::{chunk::ChunkData, coord::ChunkPos};
+use crate::{
+    voxels::{chunk::ChunkData, coord::ChunkPos},
+    worldgen::ChunkClimate,
+};
 
 pub trait WorldGenerator: Send + Sync + 'static {
     fn new(seed: u32) -> Self
     where
         Self: Sized;
     fn generate_chunk(&self, chunk_pos: ChunkPos) -> ChunkData;
+    /// Climate of each column in the chunk. It only depends on the column, not the height of the chunk.
+    fn generate_climate(&self, _chunk_pos: ChunkPos) -> ChunkClimate {
+        ChunkClimate::default()
+    }
 }
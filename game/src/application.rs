@@ -77,10 +77,11 @@ impl ApplicationHandler for Application {
         _window_id: WindowId,
         event: WindowEvent,
     ) {
-        if let Some(egui_renderer) = &mut self.game_loop.game.egui {
-            // TODO: If egui handles the event, don't pass it to the game
-            let _ = egui_renderer.handle_input(&event);
-        }
+        let egui_consumed = match &mut self.game_loop.game.egui {
+            // TODO: Skip keyboard events egui has handled as well
+            Some(egui_renderer) => egui_renderer.handle_input(&event).consumed,
+            None => false,
+        };
 
         match event {
             WindowEvent::CloseRequested => event_loop.exit(),
@@ -104,7 +105,18 @@ impl ApplicationHandler for Application {
             WindowEvent::KeyboardInput { event, .. } => {
                 self.game_loop.game.on_key_event(&event);
             }
+            // Clicks on egui windows shouldn't break or place blocks
+            WindowEvent::MouseInput { state, button, .. } if !egui_consumed => {
+                self.game_loop.game.on_mouse_input(button, state);
+            }
             _ => {}
         }
     }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(world) = self.game_loop.game.ctx.world.take() {
+            log::info!("Saving world...");
+            world.shutdown();
+        }
+    }
 }
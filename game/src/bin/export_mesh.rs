@@ -0,0 +1,61 @@
+//! Exports a region of the world as an OBJ or binary glTF mesh, without opening a window or touching the GPU.
+//!
+//! Usage: export_mesh <min_x> <min_y> <min_z> <max_x> <max_y> <max_z> <output.obj|output.glb>
+//!
+//! Chunks are loaded from the world in the configured save directory. Chunks that haven't been saved yet
+//! are generated the same way as in the game.
+
+use anyhow::{Context, bail};
+use engine::{
+    assets::blocks::BlockDatabase,
+    config::{config_manager::Config, engine_config::EngineConfig},
+    editing::shape::WorldBox,
+    formats::mesh_export::export_region_mesh,
+    persistence::world_storage::WorldStorage,
+    voxels::coord::WorldPos,
+    worldgen::{NOISE_WORLD_SEED, NoiseWorldGenerator, WorldGenerator},
+};
+
+fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init_timed();
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let [coords @ .., output] = args.as_slice() else {
+        bail!("Usage: export_mesh <min_x> <min_y> <min_z> <max_x> <max_y> <max_z> <output>");
+    };
+    let coords = coords
+        .iter()
+        .map(|arg| {
+            arg.parse::<i32>()
+                .with_context(|| format!("Invalid coordinate '{}'", arg))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let [min_x, min_y, min_z, max_x, max_y, max_z] = coords[..] else {
+        bail!("Expected 6 coordinates, got {}", coords.len());
+    };
+
+    let region = WorldBox::new(
+        WorldPos::new(min_x, min_y, min_z),
+        WorldPos::new(max_x, max_y, max_z),
+    );
+
+    let config = EngineConfig::create_manager()?;
+    let save_directory = config.get().read().unwrap().save_directory.clone();
+    let mut block_database = BlockDatabase::new();
+    block_database.load_all_blocks()?;
+
+    let storage = WorldStorage::open(&save_directory, &block_database)
+        .with_context(|| format!("Failed to open world from {:?}", save_directory))?;
+
+    let generator = NoiseWorldGenerator::new(NOISE_WORLD_SEED);
+    export_region_mesh(
+        Some(&storage),
+        &generator,
+        &block_database,
+        region,
+        output.as_ref(),
+    )?;
+
+    log::info!("Exported {:?} to {}", region, output);
+    Ok(())
+}
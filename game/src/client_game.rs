@@ -2,20 +2,24 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use egui_wgpu::ScreenDescriptor;
+use glam::Vec3;
 use renderer::{
     renderer::Renderer,
     rendering::resolution::{PhysicalSizeExt, Resolution},
 };
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, KeyEvent},
+    event::{ElementState, KeyEvent, MouseButton},
     keyboard::{KeyCode, PhysicalKey},
     window::Window,
 };
 
 use engine::{
+    assets::{asset_watcher::AssetWatcher, blocks::BlockDatabase, resource_packs::ResourcePacks},
     config::config_manager::ConfigManager,
     game_loop::{Game, GameLoopTime},
+    math::aabb::AABB,
+    voxels::{raycast::RaycastHit, voxel::Voxel},
 };
 
 use crate::{
@@ -23,13 +27,18 @@ use crate::{
     config::ClientConfig,
     egui::{
         chunk_inspector::{ChunkInspectorState, draw_chunk_inspector_ui},
+        crosshair::draw_crosshair,
         egui_instance::EguiInstance,
+        resource_packs::{ResourcePacksState, draw_resource_packs_ui},
         timeline::draw_timeline,
         world_stats::draw_world_stats_ui,
     },
     fps_counter::FpsCounter,
 };
 
+/// Maximum distance from the camera at which blocks can be broken or placed
+const BLOCK_REACH: f32 = 8.0;
+
 pub struct ClientGame {
     should_exit: bool,
     pub renderer: Option<Renderer>,
@@ -38,6 +47,13 @@ pub struct ClientGame {
     client_config: ConfigManager<ClientConfig>,
     fps_counter: FpsCounter,
     chunk_inspector: ChunkInspectorState,
+    /// Block placed with the right mouse button
+    selected_block: Voxel,
+    /// Block the camera is currently looking at, updated every frame
+    target_block: Option<RaycastHit>,
+    /// Triggers reloading blocks and textures when they change on disk
+    asset_watcher: Option<AssetWatcher>,
+    resource_packs: ResourcePacksState,
 }
 
 impl Game for ClientGame {
@@ -46,6 +62,7 @@ impl Game for ClientGame {
             egui_renderer.begin_frame();
         }
         self.fps_counter.tick();
+        self.reload_changed_assets();
     }
 
     #[profiling::function]
@@ -53,6 +70,9 @@ impl Game for ClientGame {
         // TODO: Re-enable physics when we start using it for something
         // self.ctx.physics.update(time.delta_time_s as f32);
         self.ctx.player.update(time);
+        if let Some(world) = &self.ctx.world {
+            world.tick();
+        }
 
         Ok(())
     }
@@ -67,12 +87,28 @@ impl Game for ClientGame {
         renderer.set_camera(&self.ctx.player.camera, time);
 
         if let Some(world) = &mut self.ctx.world {
-            *world.chunk_loader.camera.write().unwrap() =
-                renderer.world_renderer.camera.interpolated_camera.clone();
+            let camera = &renderer.world_renderer.camera.interpolated_camera;
+            *world.chunk_loader.camera.write().unwrap() = camera.clone();
 
             // We probably shouldn't do this every frame, but it's fine for now
             world.chunk_loader.notify_camera_moved();
+
+            let block_database = world.block_database.load();
+            self.target_block = world.raycast(
+                camera.eye,
+                camera.target - camera.eye,
+                BLOCK_REACH,
+                // Fluids can't be targeted, so blocks can be placed and broken through them
+                |voxel| {
+                    let properties = block_database.properties(voxel);
+                    properties.visible && properties.fluid.is_none()
+                },
+            );
         }
+
+        renderer
+            .world_renderer
+            .set_block_selection(self.target_block.map(|hit| hit.position));
     }
 
     #[profiling::function]
@@ -118,6 +154,17 @@ impl ClientGame {
         engine_context: ClientEngineContext,
         client_config: ConfigManager<ClientConfig>,
     ) -> Self {
+        let asset_watcher = create_asset_watcher(engine_context.block_database.resource_packs());
+        let resource_packs = ResourcePacksState::new(
+            engine_context
+                .config
+                .get()
+                .read()
+                .unwrap()
+                .resource_packs
+                .clone(),
+        );
+
         ClientGame {
             should_exit: false,
             renderer: None,
@@ -126,6 +173,10 @@ impl ClientGame {
             client_config,
             fps_counter: FpsCounter::new(),
             chunk_inspector: ChunkInspectorState::default(),
+            selected_block: Voxel::GRASS,
+            target_block: None,
+            asset_watcher,
+            resource_packs,
         }
     }
 
@@ -133,6 +184,57 @@ impl ClientGame {
         self.should_exit
     }
 
+    fn reload_changed_assets(&mut self) {
+        if !self
+            .asset_watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.has_changes())
+        {
+            return;
+        }
+
+        match self.reload_blocks() {
+            Ok(()) => log::info!("Reloaded block definitions and textures"),
+            Err(err) => log::error!("Failed to reload blocks: {:?}", err),
+        }
+    }
+
+    /// Reloads the block definitions and textures, keeping the running world in place.
+    fn reload_blocks(&mut self) -> anyhow::Result<()> {
+        let block_database = self.ctx.block_database.reload()?;
+        self.replace_blocks(block_database)
+    }
+
+    /// Switches to a different stack of resource packs, and saves it to the engine config
+    fn switch_resource_packs(&mut self, names: Vec<String>) -> anyhow::Result<()> {
+        let resource_packs = Arc::new(ResourcePacks::open(&names));
+        let block_database = self.ctx.block_database.reload_with(resource_packs)?;
+        self.replace_blocks(block_database)?;
+
+        // The watcher has to follow the new packs
+        self.asset_watcher = create_asset_watcher(self.ctx.block_database.resource_packs());
+        self.ctx
+            .config
+            .update_and_save(|config| config.resource_packs = names);
+        Ok(())
+    }
+
+    fn replace_blocks(&mut self, block_database: BlockDatabase) -> anyhow::Result<()> {
+        if let Some(renderer) = &mut self.renderer {
+            renderer.world_renderer.texture_manager.update_textures(
+                &self.ctx.block_database.world_textures,
+                &block_database.world_textures,
+            )?;
+        }
+
+        if let Some(world) = &self.ctx.world {
+            world.reload_blocks(&block_database)?;
+        }
+
+        self.ctx.block_database = Arc::new(block_database);
+        Ok(())
+    }
+
     fn draw_egui(&mut self) {
         let player = &mut self.ctx.player;
         let Some(egui_renderer) = &mut self.egui else {
@@ -140,7 +242,16 @@ impl ClientGame {
         };
 
         self.fps_counter.draw_ui(egui_renderer.ctx());
+
+        let selected_block_name = self
+            .ctx
+            .block_database
+            .get_by_id(self.selected_block.block_type_id())
+            .map(|block| block.name.as_str());
+        draw_crosshair(selected_block_name, egui_renderer.ctx());
         draw_timeline(player, egui_renderer.ctx());
+        let applied_resource_packs =
+            draw_resource_packs_ui(&mut self.resource_packs, egui_renderer.ctx());
 
         if let Some(world) = &self.ctx.world {
             draw_world_stats_ui(
@@ -160,6 +271,22 @@ impl ClientGame {
                 );
             }
         }
+
+        if let Some(names) = applied_resource_packs {
+            match self.switch_resource_packs(names) {
+                Ok(()) => {
+                    log::info!("Switched resource packs");
+                    self.resource_packs.error = None;
+                }
+                Err(err) => {
+                    log::error!("Failed to switch resource packs: {:?}", err);
+                    // Go back to the packs that are still active, so the list matches what's loaded
+                    self.resource_packs.enabled =
+                        self.ctx.config.get().read().unwrap().resource_packs.clone();
+                    self.resource_packs.error = Some(format!("{:#}", err));
+                }
+            }
+        }
     }
 
     pub fn on_resumed(&mut self, window: Arc<Window>) {
@@ -234,7 +361,100 @@ impl ClientGame {
                 let world_renderer = &mut self.renderer.as_mut().unwrap().world_renderer;
                 world_renderer.toggle_chunk_bounds();
             }
+            (KeyCode::Digit1, ElementState::Pressed) => self.select_block(0),
+            (KeyCode::Digit2, ElementState::Pressed) => self.select_block(1),
+            (KeyCode::Digit3, ElementState::Pressed) => self.select_block(2),
+            (KeyCode::Digit4, ElementState::Pressed) => self.select_block(3),
+            (KeyCode::Digit5, ElementState::Pressed) => self.select_block(4),
+            (KeyCode::Digit6, ElementState::Pressed) => self.select_block(5),
+            (KeyCode::Digit7, ElementState::Pressed) => self.select_block(6),
+            (KeyCode::Digit8, ElementState::Pressed) => self.select_block(7),
+            (KeyCode::Digit9, ElementState::Pressed) => self.select_block(8),
+            _ => {}
+        }
+    }
+
+    pub fn on_mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        if state != ElementState::Pressed {
+            return;
+        }
+
+        match button {
+            MouseButton::Left => self.break_target_block(),
+            MouseButton::Right => self.place_selected_block(),
             _ => {}
         }
     }
+
+    /// Selects the nth visible block in the block database for placement.
+    fn select_block(&mut self, index: usize) {
+        let block = self
+            .ctx
+            .block_database
+            .iter_blocks()
+            .filter(|block| block.texture_indices.is_some())
+            .nth(index);
+
+        if let Some(block) = block {
+            self.selected_block = Voxel::from_type(block.id.0);
+        }
+    }
+
+    fn break_target_block(&mut self) {
+        let (Some(world), Some(hit)) = (&self.ctx.world, self.target_block) else {
+            return;
+        };
+
+        if world.block_database.load().properties(hit.voxel).hardness < 0.0 {
+            // Unbreakable
+            return;
+        }
+
+        world.set_voxel(hit.position, Voxel::AIR);
+        self.target_block = None;
+    }
+
+    fn place_selected_block(&mut self) {
+        let (Some(world), Some(hit)) = (&self.ctx.world, self.target_block) else {
+            return;
+        };
+
+        let block_database = world.block_database.load();
+
+        // Replaceable blocks like plants are replaced directly instead of placing next to them
+        let position = if block_database.properties(hit.voxel).replaceable {
+            hit.position
+        } else {
+            hit.adjacent_position()
+        };
+        if world
+            .get_voxel(position)
+            .is_none_or(|voxel| !block_database.properties(voxel).replaceable)
+        {
+            return;
+        }
+
+        let min = position.0.as_vec3();
+        let block_bounds = AABB::new(min, min + Vec3::ONE);
+        if block_database.properties(self.selected_block).collidable
+            && self.ctx.player.bounds().intersects(&block_bounds)
+        {
+            return;
+        }
+
+        // Directional blocks point away from the face they were placed on
+        let orientation = block_database.properties(self.selected_block).orientation;
+        let voxel = Voxel::from_type_metadata(
+            self.selected_block.block_type(),
+            orientation.placement_metadata(hit.face),
+        );
+        world.set_voxel(position, voxel);
+        self.target_block = None;
+    }
+}
+
+fn create_asset_watcher(resource_packs: &ResourcePacks) -> Option<AssetWatcher> {
+    AssetWatcher::new(resource_packs)
+        .inspect_err(|err| log::warn!("Asset hot-reloading is disabled: {:?}", err))
+        .ok()
 }
@@ -6,7 +6,6 @@ use engine::{
     voxels::{
         chunk::{CHUNK_SIZE, ChunkData, ChunkState, IChunkRenderState},
         coord::ChunkPos,
-        voxel::Voxel,
     },
 };
 use glam::{IVec3, Vec3, Vec4};
@@ -260,8 +259,8 @@ fn is_pickable_render_chunk(chunk: &RenderChunk, skip_empty_chunks: bool) -> boo
     };
 
     match data {
-        ChunkData::Solid(v) => v.is_solid(),
-        ChunkData::Packed(p) => p.palette.voxel_types.iter().any(Voxel::is_solid),
+        ChunkData::Solid(v) => !v.is_air(),
+        ChunkData::Packed(p) => p.palette.voxel_types.iter().any(|v| !v.is_air()),
     }
 }
 
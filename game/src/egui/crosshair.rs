@@ -0,0 +1,36 @@
+use egui::{Align2, Color32, FontId, Id, LayerId, Order, Stroke, vec2};
+
+const CROSSHAIR_SIZE: f32 = 8.0;
+
+/// Draws a crosshair in the middle of the screen, and the name of the selected block below it.
+pub fn draw_crosshair(selected_block_name: Option<&str>, context: &egui::Context) {
+    let painter = context.layer_painter(LayerId::new(Order::Foreground, Id::new("crosshair")));
+    let screen = painter.clip_rect();
+    let center = screen.center();
+    let stroke = Stroke::new(2.0, Color32::WHITE);
+
+    painter.line_segment(
+        [
+            center - vec2(CROSSHAIR_SIZE, 0.0),
+            center + vec2(CROSSHAIR_SIZE, 0.0),
+        ],
+        stroke,
+    );
+    painter.line_segment(
+        [
+            center - vec2(0.0, CROSSHAIR_SIZE),
+            center + vec2(0.0, CROSSHAIR_SIZE),
+        ],
+        stroke,
+    );
+
+    if let Some(name) = selected_block_name {
+        painter.text(
+            screen.center_bottom() - vec2(0.0, 16.0),
+            Align2::CENTER_BOTTOM,
+            name,
+            FontId::proportional(18.0),
+            Color32::WHITE,
+        );
+    }
+}
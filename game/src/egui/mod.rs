@@ -1,4 +1,6 @@
 pub mod chunk_inspector;
+pub mod crosshair;
 pub mod egui_instance;
+pub mod resource_packs;
 pub mod timeline;
 pub mod world_stats;
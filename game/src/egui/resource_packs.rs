@@ -0,0 +1,94 @@
+use egui::{Color32, CornerRadius};
+use engine::assets::resource_packs::{RESOURCE_PACKS_DIRECTORY, available_resource_packs};
+
+pub struct ResourcePacksState {
+    /// Enabled packs in the order they're applied, edited until they're applied
+    pub enabled: Vec<String>,
+    /// Packs found in the resource pack directory
+    pub available: Vec<String>,
+    /// Why the last apply failed, shown until the next one
+    pub error: Option<String>,
+}
+
+impl ResourcePacksState {
+    pub fn new(enabled: Vec<String>) -> Self {
+        Self {
+            enabled,
+            available: available_resource_packs(),
+            error: None,
+        }
+    }
+}
+
+/// Returns the new order of enabled packs when they should be applied
+pub fn draw_resource_packs_ui(
+    state: &mut ResourcePacksState,
+    context: &egui::Context,
+) -> Option<Vec<String>> {
+    let mut apply = false;
+
+    egui::Window::new("Resource packs")
+        .default_open(false)
+        .resizable(false)
+        .show(context, |ui| {
+            ui.style_mut().visuals.window_corner_radius = CornerRadius::ZERO;
+
+            ui.label("Enabled, later packs override earlier ones:");
+            let mut moved = None;
+            let mut removed = None;
+            for (index, name) in state.enabled.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(index > 0, egui::Button::new("Up")).clicked() {
+                        moved = Some((index, index - 1));
+                    }
+                    if ui
+                        .add_enabled(index + 1 < state.enabled.len(), egui::Button::new("Down"))
+                        .clicked()
+                    {
+                        moved = Some((index, index + 1));
+                    }
+                    if ui.button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                    ui.label(name);
+                });
+            }
+            if let Some((from, to)) = moved {
+                state.enabled.swap(from, to);
+            }
+            if let Some(index) = removed {
+                state.enabled.remove(index);
+            }
+
+            ui.separator();
+            ui.label(format!("Available in {}/:", RESOURCE_PACKS_DIRECTORY));
+            let mut added = None;
+            for name in &state.available {
+                if state.enabled.contains(name) {
+                    continue;
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Add").clicked() {
+                        added = Some(name.clone());
+                    }
+                    ui.label(name);
+                });
+            }
+            if let Some(name) = added {
+                state.enabled.push(name);
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Refresh").clicked() {
+                    state.available = available_resource_packs();
+                }
+                apply = ui.button("Apply").clicked();
+            });
+            if let Some(error) = &state.error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+
+    apply.then(|| state.enabled.clone())
+}
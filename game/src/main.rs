@@ -1,4 +1,10 @@
-use engine::{config::config_manager::Config, init_engine, worldgen::generate_noise_world};
+use std::sync::Arc;
+
+use anyhow::Context;
+use engine::{
+    config::config_manager::Config, init_engine, persistence::world_storage::WorldStorage,
+    worldgen::generate_noise_world,
+};
 use winit::event_loop::{ControlFlow, EventLoop};
 
 use crate::{application::Application, config::ClientConfig};
@@ -17,11 +23,17 @@ fn main() -> anyhow::Result<()> {
     //let world = generate_torture_test_world();
     let context = init_engine()?;
     let client_config = ClientConfig::create_manager()?;
+
+    let save_directory = context.config.get().read().unwrap().save_directory.clone();
+    let storage = WorldStorage::open(&save_directory, &context.block_database)
+        .with_context(|| format!("Failed to open world from {:?}", save_directory))?;
+    let storage = Arc::new(storage);
+
     let mut app = Application::new(
         context,
         client_config,
-        Box::new(|block_database, render_context| {
-            generate_noise_world(0, block_database, render_context)
+        Box::new(move |block_database, render_context| {
+            generate_noise_world(0, Some(storage), block_database, render_context)
         }),
     );
 
@@ -27,6 +27,10 @@ fn main() {
         &"package::passes::chunk_bounds".parse().unwrap(),
         "chunk_bounds",
     );
+    wesl.build_artifact(
+        &"package::passes::block_selection".parse().unwrap(),
+        "block_selection",
+    );
     wesl.build_artifact(&"package::postfx::fxaa".parse().unwrap(), "postfx_fxaa");
     wesl.build_artifact(&"package::postfx::noise".parse().unwrap(), "postfx_noise");
     wesl.build_artifact(
@@ -17,11 +17,23 @@ pub struct GpuChunk {
     pub face_byte_offset: u32,
     pub total_face_count: u32,
     pub opaque_face_count: u32,
+    pub alpha_cutout_face_count: u32,
     pub aabb: PackedAABB,
+    /// Index of the chunk's biome tint, or `NO_TINT`
+    pub tint_index: u32,
+    pub _padding: [u32; 2],
 }
 
+/// Tint index of chunks without tinted blocks, which are drawn with the default tint.
+/// Must match NO_TINT in world_geo_draw.wesl
+pub const NO_TINT: u32 = u32::MAX;
+
 pub struct ChunkMesh {
     pub position: ChunkPos,
     pub aabb: AABB8,
     pub faces_handle: GpuHeapHandle<PackedVoxelFace>,
+    /// Alpha blended faces are kept on the CPU, so they can be re-sorted and rewritten as the camera moves.
+    /// They are stored after the opaque and cutout faces, starting at `alpha_blend_first_face`.
+    pub alpha_blend_faces: Vec<PackedVoxelFace>,
+    pub alpha_blend_first_face: u32,
 }
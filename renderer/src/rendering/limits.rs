@@ -9,6 +9,9 @@ const REQUIRED_GPU_CHUNKS: u64 = (2 * LOAD_DISTANCE + 1).pow(3) as u64;
 // However, since we are streaming chunks in and out, we need some extra headroom to avoid stalls
 pub const MAX_GPU_CHUNKS: u64 = (REQUIRED_GPU_CHUNKS as f32 * 1.25) as u64;
 
+// Only chunks with tinted blocks like grass and leaves store a biome tint, which are mostly the ones on the surface
+pub const MAX_TINTED_GPU_CHUNKS: u64 = MAX_GPU_CHUNKS / 4;
+
 // Worst case is a chunk with a checkerboard pattern, with all 6 faces visible
 // With 16*16*16 voxels, that means ((16*16*16) / 2) * 6) = 12288 faces per chunk
 #[allow(unused)]
@@ -37,6 +37,15 @@ impl<T: Pod> GpuHeapHandle<T> {
         self.allocator.write_data(self, bytemuck::cast_slice(data));
     }
 
+    /// Overwrites part of the allocation, starting at the given element
+    pub fn write_data_at(&self, first_element: u32, data: &[T]) {
+        let byte_data: &[u8] = bytemuck::cast_slice(data);
+        if byte_data.is_empty() {
+            return;
+        }
+        self.allocator.write_data_at(self, first_element, data);
+    }
+
     pub fn write_data_batched(&self, batcher: &mut BufferUpdateBatcher, data: &[T]) {
         let byte_data: &[u8] = bytemuck::cast_slice(data);
         if byte_data.is_empty() {
@@ -161,6 +170,17 @@ impl<T: Pod> GpuHeap<T> {
         );
     }
 
+    pub fn write_data_at(&self, allocation: &GpuHeapHandle<T>, first_element: u32, data: &[T]) {
+        let byte_data: &[u8] = bytemuck::cast_slice(data);
+        let start_byte = first_element as u64 * size_of::<T>() as u64;
+        assert!(start_byte + byte_data.len() as u64 <= allocation.size_bytes as u64);
+        self.queue.write_buffer(
+            &self.buffer,
+            allocation.byte_offset() as u64 + start_byte,
+            byte_data,
+        );
+    }
+
     pub fn write_data_batched(
         &self,
         batcher: &mut BufferUpdateBatcher,
@@ -0,0 +1,185 @@
+use glam::Vec3;
+use wgpu::{
+    CompareFunction, DepthStencilState, MultisampleState, PrimitiveState, RenderPipeline,
+    ShaderModuleDescriptor, ShaderStages,
+};
+
+use crate::rendering::{
+    memory::typed_buffer::GpuBuffer,
+    passes::chunk_bounds::{CUBE_LINE_INDICES, aabb_corners},
+    render_camera::CameraUniform,
+    texture::DepthTexture,
+    util::bind_group_builder::BindGroupBuilder,
+};
+
+use engine::voxels::coord::WorldPos;
+
+/// How much the outline is grown outwards from the block, so it isn't hidden by the block's own faces
+const OUTLINE_OFFSET: f32 = 0.002;
+/// 12 edges * 2 vertices per line
+const OUTLINE_VERTEX_COUNT: usize = 24;
+
+/// Draws a wireframe around the block the player is looking at.
+pub struct BlockSelectionPass {
+    pipeline: RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    selection: Option<WorldPos>,
+}
+
+impl BlockSelectionPass {
+    pub fn new(device: &wgpu::Device, camera_uniform_buffer: &GpuBuffer<CameraUniform>) -> Self {
+        let source = include_str!(concat!(env!("OUT_DIR"), "/block_selection.wgsl"));
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Block selection shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let (bind_group_layout, bind_group) =
+            BindGroupBuilder::new("block_selection", ShaderStages::VERTEX)
+                .uniform(
+                    0,
+                    "Camera uniform buffer",
+                    wgpu::BindingResource::Buffer(
+                        camera_uniform_buffer.inner().as_entire_buffer_binding(),
+                    ),
+                )
+                .build(device);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Block Selection Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            ..Default::default()
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vec3>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                // position: vec3<f32>
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        };
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Block Selection Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: Some("vs_main"),
+                buffers: &[vertex_buffer_layout],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Block selection vertex buffer"),
+            size: (OUTLINE_VERTEX_COUNT * std::mem::size_of::<Vec3>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        BlockSelectionPass {
+            pipeline,
+            bind_group,
+            vertex_buffer,
+            selection: None,
+        }
+    }
+
+    /// Sets the block to outline, or None to hide the outline.
+    pub fn set_selection(&mut self, queue: &wgpu::Queue, selection: Option<WorldPos>) {
+        if self.selection == selection {
+            return;
+        }
+
+        self.selection = selection;
+
+        let Some(position) = selection else {
+            return;
+        };
+
+        let min = position.0.as_vec3() - Vec3::splat(OUTLINE_OFFSET);
+        let max = position.0.as_vec3() + Vec3::splat(1.0 + OUTLINE_OFFSET);
+        let corners = aabb_corners(min, max);
+
+        let mut vertices = Vec::with_capacity(OUTLINE_VERTEX_COUNT);
+        for (start, end) in CUBE_LINE_INDICES.iter() {
+            vertices.push(corners[*start]);
+            vertices.push(corners[*end]);
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+    }
+
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        depth_texture: &DepthTexture,
+    ) {
+        if self.selection.is_none() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Block selection pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_texture.view(),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..OUTLINE_VERTEX_COUNT as u32, 0..1);
+    }
+}
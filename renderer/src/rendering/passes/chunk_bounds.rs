@@ -34,7 +34,7 @@ const CUBE_CORNERS: [Vec3; 8] = [
     Vec3::new(0.0, 16.0, 16.0),  // 7: back-top-left
 ];
 
-fn aabb_corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
+pub(crate) fn aabb_corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
     [
         Vec3::new(min.x, min.y, min.z), // 0: front-bottom-left
         Vec3::new(max.x, min.y, min.z), // 1: front-bottom-right
@@ -48,7 +48,7 @@ fn aabb_corners(min: Vec3, max: Vec3) -> [Vec3; 8] {
 }
 
 /// Line indices for the 12 edges of a cube
-const CUBE_LINE_INDICES: [(usize, usize); 12] = [
+pub(crate) const CUBE_LINE_INDICES: [(usize, usize); 12] = [
     // Front face
     (0, 1),
     (1, 2),
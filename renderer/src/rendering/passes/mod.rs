@@ -1,3 +1,4 @@
+pub mod block_selection;
 pub mod chunk_bounds;
 pub mod postfx;
 pub mod render_common;
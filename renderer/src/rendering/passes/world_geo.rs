@@ -27,6 +27,8 @@ pub struct WorldGeometryPass {
     reset_culling_pipeline: ComputePipeline,
     culling_pipeline: ComputePipeline,
     draw_pipeline: RenderPipeline,
+    alpha_cutout_pipeline: RenderPipeline,
+    alpha_blend_pipeline: RenderPipeline,
 
     quad_indices: GpuBuffer<[u16; 6]>,
 
@@ -38,6 +40,9 @@ pub struct WorldGeometryPass {
 
     alpha_cutout_draw_commands: GpuBufferArray<DrawIndexedIndirectArgs>,
     alpha_cutout_draw_command_count: GpuBuffer<u32>,
+
+    /// One command per input chunk, in the order of the input chunks
+    alpha_blend_draw_commands: GpuBufferArray<DrawIndexedIndirectArgs>,
 }
 
 impl WorldGeometryPass {
@@ -59,23 +64,26 @@ impl WorldGeometryPass {
                 )
                 .build(device);
 
-        let (chunks_bind_group_layout, chunks_bind_group) =
-            BindGroupBuilder::new("chunks", ShaderStages::VERTEX | ShaderStages::COMPUTE)
-                .storage_r(
-                    0,
-                    "Chunks buffer",
-                    wgpu::BindingResource::Buffer(
-                        buffers.chunks.buffer().as_entire_buffer_binding(),
-                    ),
-                )
-                .storage_r(
-                    1,
-                    "Chunk face data buffer",
-                    wgpu::BindingResource::Buffer(
-                        buffers.faces.buffer().as_entire_buffer_binding(),
-                    ),
-                )
-                .build(device);
+        let (chunks_bind_group_layout, chunks_bind_group) = BindGroupBuilder::new(
+            "chunks",
+            ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE,
+        )
+        .storage_r(
+            0,
+            "Chunks buffer",
+            wgpu::BindingResource::Buffer(buffers.chunks.buffer().as_entire_buffer_binding()),
+        )
+        .storage_r(
+            1,
+            "Chunk face data buffer",
+            wgpu::BindingResource::Buffer(buffers.faces.buffer().as_entire_buffer_binding()),
+        )
+        .storage_r(
+            2,
+            "Chunk tint buffer",
+            wgpu::BindingResource::Buffer(buffers.tints.buffer().as_entire_buffer_binding()),
+        )
+        .build(device);
 
         let culling_params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Culling params buffer"),
@@ -125,6 +133,14 @@ impl WorldGeometryPass {
             &0u32,
         );
 
+        let alpha_blend_draw_commands = GpuBufferArray::new(
+            device,
+            queue,
+            "Alpha blend draw commands buffer",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+            MAX_GPU_CHUNKS as usize,
+        );
+
         let (reset_culling_bind_group_layout, reset_culling_bind_group) =
             BindGroupBuilder::new("reset_culling", ShaderStages::COMPUTE)
                 .storage_rw(
@@ -181,10 +197,15 @@ impl WorldGeometryPass {
                             .as_entire_buffer_binding(),
                     ),
                 )
+                .storage_rw(
+                    6,
+                    "Alpha blend draw commands buffer",
+                    wgpu::BindingResource::Buffer(alpha_blend_draw_commands.binding()),
+                )
                 .build(device);
 
         let (textures_bind_group_layout, textures_bind_group) =
-            BindGroupBuilder::new("textures", ShaderStages::FRAGMENT)
+            BindGroupBuilder::new("textures", ShaderStages::VERTEX | ShaderStages::FRAGMENT)
                 .array_texture(
                     0,
                     "World texture array",
@@ -197,6 +218,22 @@ impl WorldGeometryPass {
                     wgpu::BindingResource::Sampler(texture_manager.sampler()),
                     wgpu::SamplerBindingType::Filtering,
                 )
+                .uniform(
+                    2,
+                    "Texture attributes buffer",
+                    wgpu::BindingResource::Buffer(
+                        texture_manager.texture_attributes_buffer().binding(),
+                    ),
+                )
+                .uniform(
+                    3,
+                    "Texture animation time buffer",
+                    wgpu::BindingResource::Buffer(
+                        texture_manager
+                            .animation_time_buffer()
+                            .as_entire_buffer_binding(),
+                    ),
+                )
                 .build(device);
 
         let culling_pipeline = create_draw_command_pipeline(
@@ -205,12 +242,16 @@ impl WorldGeometryPass {
             &chunks_bind_group_layout,
             &culling_bind_group_layout,
         );
-        let draw_pipeline = create_draw_pipeline(
-            device,
+        let draw_layouts = [
             &camera_bind_group_layout,
             &chunks_bind_group_layout,
             &textures_bind_group_layout,
-        );
+        ];
+        let draw_pipeline = create_draw_pipeline(device, &draw_layouts, DrawPipelineKind::Opaque);
+        let alpha_cutout_pipeline =
+            create_draw_pipeline(device, &draw_layouts, DrawPipelineKind::AlphaCutout);
+        let alpha_blend_pipeline =
+            create_draw_pipeline(device, &draw_layouts, DrawPipelineKind::AlphaBlend);
 
         let quad_indices = GpuBuffer::from_data(
             device,
@@ -226,6 +267,8 @@ impl WorldGeometryPass {
             reset_culling_bind_group,
             culling_pipeline,
             draw_pipeline,
+            alpha_cutout_pipeline,
+            alpha_blend_pipeline,
             camera_bind_group,
             chunks_bind_group,
             culling_bind_group,
@@ -241,6 +284,8 @@ impl WorldGeometryPass {
 
             alpha_cutout_draw_commands,
             alpha_cutout_draw_command_count,
+
+            alpha_blend_draw_commands,
         }
     }
 
@@ -325,24 +370,62 @@ impl WorldGeometryPass {
             wgpu::IndexFormat::Uint16,
         );
 
+        self.draw_commands(
+            &mut render_pass,
+            &self.opaque_draw_commands,
+            &self.opaque_draw_command_count,
+            max_draw_count,
+        );
+
+        // Cutout faces are drawn after opaque ones, so fully covered fragments are rejected by the depth test before texturing
+        render_pass.set_pipeline(&self.alpha_cutout_pipeline);
+        self.draw_commands(
+            &mut render_pass,
+            &self.alpha_cutout_draw_commands,
+            &self.alpha_cutout_draw_command_count,
+            max_draw_count,
+        );
+
+        // Blended faces are drawn last, with one command per input chunk so the chunks stay sorted back to front
+        render_pass.set_pipeline(&self.alpha_blend_pipeline);
+        render_pass.multi_draw_indexed_indirect(
+            self.alpha_blend_draw_commands.inner(),
+            0,
+            max_draw_count,
+        );
+    }
+
+    fn draw_commands(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        commands: &GpuBufferArray<DrawIndexedIndirectArgs>,
+        command_count: &GpuBuffer<u32>,
+        max_draw_count: u32,
+    ) {
         if self.enabled_features.multi_draw_indirect_count {
             render_pass.multi_draw_indexed_indirect_count(
-                self.opaque_draw_commands.inner(),
+                commands.inner(),
                 0,
-                self.opaque_draw_command_count.inner(),
+                command_count.inner(),
                 0,
                 max_draw_count,
             );
         } else {
-            render_pass.multi_draw_indexed_indirect(
-                self.opaque_draw_commands.inner(),
-                0,
-                max_draw_count,
-            );
+            render_pass.multi_draw_indexed_indirect(commands.inner(), 0, max_draw_count);
         }
     }
 }
 
+/// Opaque faces are culled and fully shaded. Alpha cutout faces discard transparent texels,
+/// and are drawn from both sides since plants are single quads.
+/// Alpha blended faces are blended over the scene without writing depth, so the faces behind them stay visible.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DrawPipelineKind {
+    Opaque,
+    AlphaCutout,
+    AlphaBlend,
+}
+
 fn create_reset_culling_pipeline(
     device: &wgpu::Device,
     culling_bind_group_layout: &wgpu::BindGroupLayout,
@@ -406,10 +489,29 @@ fn create_draw_command_pipeline(
 
 fn create_draw_pipeline(
     device: &wgpu::Device,
-    camera_bind_group_layout: &wgpu::BindGroupLayout,
-    chunks_bind_group_layout: &wgpu::BindGroupLayout,
-    textures_bind_group_layout: &wgpu::BindGroupLayout,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    kind: DrawPipelineKind,
 ) -> RenderPipeline {
+    let (label, fragment_entry_point, cull_mode) = match kind {
+        DrawPipelineKind::Opaque => ("World geometry pipeline", "fs_main", Some(wgpu::Face::Back)),
+        DrawPipelineKind::AlphaCutout => (
+            "World geometry alpha cutout pipeline",
+            "fs_alpha_cutout",
+            None,
+        ),
+        DrawPipelineKind::AlphaBlend => (
+            "World geometry alpha blend pipeline",
+            "fs_alpha_blend",
+            None,
+        ),
+    };
+    let is_blended = kind == DrawPipelineKind::AlphaBlend;
+    let blend = if is_blended {
+        wgpu::BlendState::ALPHA_BLENDING
+    } else {
+        wgpu::BlendState::REPLACE
+    };
+
     let source = include_str!(concat!(env!("OUT_DIR"), "/world_geo_draw.wgsl"));
     let module = device.create_shader_module(ShaderModuleDescriptor {
         label: Some("World geometry shader"),
@@ -418,16 +520,12 @@ fn create_draw_pipeline(
 
     let draw_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("World geometry pipeline layout"),
-        bind_group_layouts: &[
-            camera_bind_group_layout,
-            chunks_bind_group_layout,
-            textures_bind_group_layout,
-        ],
+        bind_group_layouts,
         ..Default::default()
     });
 
     device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some("World geometry pipeline"),
+        label: Some(label),
         layout: Some(&draw_pipeline_layout),
         vertex: VertexState {
             module: &module,
@@ -437,10 +535,10 @@ fn create_draw_pipeline(
         },
         fragment: Some(wgpu::FragmentState {
             module: &module,
-            entry_point: Some("fs_main"),
+            entry_point: Some(fragment_entry_point),
             targets: &[Some(wgpu::ColorTargetState {
                 format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                blend: Some(wgpu::BlendState::REPLACE),
+                blend: Some(blend),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
             compilation_options: Default::default(),
@@ -449,14 +547,14 @@ fn create_draw_pipeline(
             topology: wgpu::PrimitiveTopology::TriangleList,
             strip_index_format: None,
             front_face: wgpu::FrontFace::Cw,
-            cull_mode: Some(wgpu::Face::Back),
+            cull_mode,
             polygon_mode: wgpu::PolygonMode::Fill,
             unclipped_depth: false,
             conservative: false,
         },
         depth_stencil: Some(DepthStencilState {
             format: DepthTexture::DEPTH_FORMAT,
-            depth_write_enabled: true,
+            depth_write_enabled: !is_blended,
             depth_compare: CompareFunction::GreaterEqual,
             stencil: Default::default(),
             bias: Default::default(),
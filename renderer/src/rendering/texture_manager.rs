@@ -1,5 +1,6 @@
 use std::array;
 
+use anyhow::ensure;
 use bytemuck::{Pod, Zeroable};
 use image::{
     RgbaImage,
@@ -7,22 +8,58 @@ use image::{
 };
 use wgpu::{TexelCopyBufferLayout, TexelCopyTextureInfo};
 
-use engine::assets::world_textures::{TextureTransparency, WorldTextures};
+use engine::{
+    assets::world_textures::{TextureImage, WorldTextures},
+    game_loop::GameLoopTime,
+};
 
-use crate::rendering::memory::typed_buffer::GpuBufferArray;
+use crate::rendering::memory::typed_buffer::{GpuBuffer, GpuBufferArray};
 
+// Must match MAX_TEXTURES in world_geo_draw.wesl
 const MAX_TEXTURES: usize = 256;
 
+/// Uniform array elements are 16 bytes apart, so the attributes are padded to that.
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
-pub struct TextureAttributes(pub u32);
+pub struct TextureAttributes {
+    transparency: u32,
+    /// Frames of animated textures are the layers following this one, 1 for still textures
+    frame_count: u32,
+    frame_time_s: f32,
+    flags: u32,
+}
 
 impl TextureAttributes {
-    pub fn from_transparency(transparency: TextureTransparency) -> Self {
-        TextureAttributes(transparency as u32)
+    /// Animated textures blend each frame into the next one
+    const INTERPOLATE: u32 = 0x1;
+    /// Texture is multiplied with the biome tint
+    const TINTED: u32 = 0x2;
+
+    pub fn from_texture(texture: &TextureImage) -> Self {
+        let animation = texture.animation;
+        let mut flags = 0;
+        if animation.is_some_and(|animation| animation.interpolate) {
+            flags |= Self::INTERPOLATE;
+        }
+        if texture.tinted {
+            flags |= Self::TINTED;
+        }
+
+        TextureAttributes {
+            transparency: texture.transparency as u32,
+            frame_count: animation.map_or(1, |animation| animation.frame_count as u32),
+            frame_time_s: animation.map_or(1.0, |animation| animation.frame_time_s),
+            flags,
+        }
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct TextureAnimationTime {
+    now: f32,
+}
+
 pub struct TextureManager {
     queue: wgpu::Queue,
     array_texture: wgpu::Texture,
@@ -30,6 +67,7 @@ pub struct TextureManager {
     view: wgpu::TextureView,
     texture_attributes: Vec<TextureAttributes>,
     texture_attributes_buffer: GpuBufferArray<TextureAttributes>,
+    animation_time_buffer: GpuBuffer<TextureAnimationTime>,
 }
 
 pub const TEXTURE_SIZE: usize = 16;
@@ -94,6 +132,14 @@ impl TextureManager {
             MAX_TEXTURES,
         );
 
+        let animation_time_buffer = GpuBuffer::from_data(
+            device,
+            queue,
+            "Texture animation time buffer",
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            &TextureAnimationTime { now: 0.0 },
+        );
+
         Self {
             queue: queue.clone(),
             array_texture,
@@ -101,6 +147,7 @@ impl TextureManager {
             view,
             texture_attributes: Vec::new(),
             texture_attributes_buffer,
+            animation_time_buffer,
         }
     }
 
@@ -159,7 +206,7 @@ impl TextureManager {
         for (index, texture) in world_textures.textures.iter().enumerate() {
             self.upload_texture(index as u16, &texture.data);
             self.texture_attributes
-                .push(TextureAttributes::from_transparency(texture.transparency));
+                .push(TextureAttributes::from_texture(texture));
         }
 
         self.upload_texture_attributes();
@@ -167,6 +214,43 @@ impl TextureManager {
         Ok(())
     }
 
+    /// Uploads the layers that differ from `previous`, e.g. after the block definitions have been reloaded.
+    pub fn update_textures(
+        &mut self,
+        previous: &WorldTextures,
+        world_textures: &WorldTextures,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            world_textures.textures.len() <= MAX_TEXTURES,
+            "Too many world textures: {}, the maximum is {}",
+            world_textures.textures.len(),
+            MAX_TEXTURES
+        );
+
+        let changed = changed_textures(previous, world_textures);
+        for &index in &changed {
+            self.upload_texture(index as u16, &world_textures.textures[index].data);
+        }
+
+        self.texture_attributes = world_textures
+            .textures
+            .iter()
+            .map(TextureAttributes::from_texture)
+            .collect();
+        self.upload_texture_attributes();
+
+        log::info!("Updated {} world textures", changed.len());
+        Ok(())
+    }
+
+    /// Animated textures pick their frame from this time on the GPU, so nothing else has to change between frames.
+    pub fn update_animation_time(&self, time: &GameLoopTime) {
+        self.animation_time_buffer
+            .write_data(&TextureAnimationTime {
+                now: time.elapsed_time_s as f32,
+            });
+    }
+
     pub fn array_texture_view(&self) -> &wgpu::TextureView {
         &self.view
     }
@@ -178,4 +262,102 @@ impl TextureManager {
     pub fn texture_attributes_buffer(&self) -> &GpuBufferArray<TextureAttributes> {
         &self.texture_attributes_buffer
     }
+
+    pub fn animation_time_buffer(&self) -> &wgpu::Buffer {
+        self.animation_time_buffer.inner()
+    }
+}
+
+/// Indices of the textures whose layers differ from `previous`, including the ones it doesn't have yet
+fn changed_textures(previous: &WorldTextures, world_textures: &WorldTextures) -> Vec<usize> {
+    world_textures
+        .textures
+        .iter()
+        .enumerate()
+        .filter(|(index, texture)| {
+            !previous.textures.get(*index).is_some_and(|old| {
+                old.transparency == texture.transparency && old.data == texture.data
+            })
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::assets::world_textures::{TextureAnimation, TextureTransparency};
+
+    use super::*;
+
+    fn texture(animation: Option<TextureAnimation>, tinted: bool) -> TextureImage {
+        TextureImage {
+            data: RgbaImage::new(TEXTURE_SIZE as u32, TEXTURE_SIZE as u32),
+            transparency: TextureTransparency::AlphaCutout,
+            animation,
+            tinted,
+        }
+    }
+
+    #[test]
+    fn test_changed_textures_are_uploaded_again() {
+        let solid = |value| TextureImage {
+            data: RgbaImage::from_pixel(4, 4, image::Rgba([value, value, value, 255])),
+            ..texture(None, false)
+        };
+        let mut previous = WorldTextures::new();
+        previous.textures.extend([solid(1), solid(2), solid(3)]);
+
+        let mut reloaded = WorldTextures::new();
+        reloaded
+            .textures
+            .extend([solid(1), solid(5), solid(3), solid(4)]);
+        assert_eq!(changed_textures(&previous, &reloaded), [2, 4]);
+
+        reloaded.textures[2] = solid(2);
+        reloaded.textures[3].transparency = TextureTransparency::AlphaBlend;
+        assert_eq!(changed_textures(&previous, &reloaded), [3, 4]);
+    }
+
+    #[test]
+    fn test_still_texture_attributes() {
+        let attributes = TextureAttributes::from_texture(&texture(None, false));
+        assert_eq!(
+            attributes.transparency,
+            TextureTransparency::AlphaCutout as u32
+        );
+        assert_eq!(attributes.frame_count, 1);
+        assert_eq!(attributes.frame_time_s, 1.0);
+        assert_eq!(attributes.flags, 0);
+
+        let tinted = TextureAttributes::from_texture(&texture(None, true));
+        assert_eq!(tinted.flags, TextureAttributes::TINTED);
+    }
+
+    #[test]
+    fn test_animated_texture_attributes() {
+        let animation = TextureAnimation {
+            frame_count: 8,
+            frame_time_s: 0.125,
+            interpolate: false,
+        };
+        let attributes = TextureAttributes::from_texture(&texture(Some(animation), false));
+        assert_eq!(attributes.frame_count, 8);
+        assert_eq!(attributes.frame_time_s, 0.125);
+        assert_eq!(attributes.flags, 0);
+
+        let interpolated = TextureAnimation {
+            interpolate: true,
+            ..animation
+        };
+        let attributes = TextureAttributes::from_texture(&texture(Some(interpolated), true));
+        assert_eq!(
+            attributes.flags,
+            TextureAttributes::INTERPOLATE | TextureAttributes::TINTED
+        );
+        assert_eq!(
+            bytemuck::bytes_of(&attributes).len(),
+            16,
+            "Uniform array elements must stay 16 bytes"
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use std::{
-    cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
     sync::Arc,
 };
 
@@ -18,11 +18,12 @@ use engine::{
         aabb::{AABB8, PackedAABB},
         frustum::Frustum,
     },
-    mesh_generation::chunk_mesh::{ChunkMeshData, PackedVoxelFace},
+    mesh_generation::chunk_mesh::{ChunkMeshData, PackedVoxelFace, sort_faces_back_to_front},
     voxels::{
         chunk::{ChunkState, IChunkRenderContext, IChunkRenderState},
-        coord::ChunkPos,
+        coord::{ChunkPos, WorldPos, WorldPosF},
     },
+    worldgen::ChunkTint,
 };
 use wgpu::{CommandEncoder, wgt::CommandEncoderDescriptor};
 
@@ -31,14 +32,17 @@ use crate::{
     renderer_types::RenderWorld,
     rendering::{
         buffer_update_batcher::BufferUpdateBatcher,
-        chunk_mesh::{ChunkMesh, GpuChunk},
-        limits::{FACE_BUFFER_SIZE_BYTES, MAX_GPU_CHUNKS},
+        chunk_mesh::{ChunkMesh, GpuChunk, NO_TINT},
+        limits::{FACE_BUFFER_SIZE_BYTES, MAX_GPU_CHUNKS, MAX_TINTED_GPU_CHUNKS},
         memory::{
             gpu_heap::GpuHeap,
             gpu_pool::{GpuPool, GpuPoolHandle},
             typed_buffer::GpuBuffer,
         },
-        passes::{chunk_bounds::ChunkBoundsPass, sky::SkyPass, world_geo::WorldGeometryPass},
+        passes::{
+            block_selection::BlockSelectionPass, chunk_bounds::ChunkBoundsPass, sky::SkyPass,
+            world_geo::WorldGeometryPass,
+        },
         postfx::PostFxRenderer,
         render_camera::{CameraUniform, RenderCamera},
         resolution::Resolution,
@@ -47,6 +51,12 @@ use crate::{
     },
 };
 
+/// Chunks within this many chunks of the camera have their blended faces re-sorted whenever the camera enters another voxel.
+/// Further chunks are queued for a re-sort when the camera enters another chunk.
+const BLEND_SORT_RADIUS: i32 = 2;
+/// How many queued chunks outside the sort radius are re-sorted per frame, so crossing a chunk doesn't stall a frame
+const BLEND_SORT_BUDGET: usize = 8;
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 struct DebugChunkCandidate {
     dist: i32,
@@ -71,6 +81,8 @@ impl PartialOrd for DebugChunkCandidate {
 pub struct ChunkRenderState {
     pub mesh: ChunkMesh,
     pub gpu_chunk: GpuPoolHandle<GpuChunk>,
+    /// Only allocated for chunks with tinted blocks
+    pub tint: Option<GpuPoolHandle<ChunkTint>>,
 }
 
 #[derive(Clone)]
@@ -108,6 +120,19 @@ impl IChunkRenderState for ChunkRenderState {
             .expect("Failed to allocate chunk");
         let aabb = PackedAABB::try_from(mesh_data.aabb).expect("Failed to pack chunk AABB");
 
+        // Running out of tints isn't fatal, the chunk is drawn with the default tint instead
+        let tint = mesh_data.tint.as_ref().and_then(|tint_data| {
+            let tint = context.buffers.tints.allocate();
+            match &tint {
+                Some(tint) => tint.write_data_batched(&mut context.batcher, tint_data),
+                None => log::warn!(
+                    "Out of biome tints, chunk {:?} uses the default tint",
+                    mesh_data.position
+                ),
+            }
+            tint
+        });
+
         gpu_chunk.write_data_batched(
             &mut context.batcher,
             &GpuChunk {
@@ -115,11 +140,18 @@ impl IChunkRenderState for ChunkRenderState {
                 total_face_count: mesh_data.total_faces() as u32,
                 face_byte_offset: mesh.faces_handle.byte_offset(),
                 opaque_face_count: mesh_data.opaque_faces.len() as u32,
+                alpha_cutout_face_count: mesh_data.alpha_cutout_faces.len() as u32,
                 aabb,
+                tint_index: tint.as_ref().map_or(NO_TINT, |tint| tint.offset() as u32),
+                _padding: [0; 2],
             },
         );
 
-        ChunkRenderState { mesh, gpu_chunk }
+        ChunkRenderState {
+            mesh,
+            gpu_chunk,
+            tint,
+        }
     }
 
     fn chunk_gpu_id(&self) -> u64 {
@@ -138,6 +170,7 @@ pub struct CullingParams {
 pub struct WorldBuffers {
     pub faces: Arc<GpuHeap<PackedVoxelFace>>,
     pub chunks: Arc<GpuPool<GpuChunk>>,
+    pub tints: Arc<GpuPool<ChunkTint>>,
     pub camera: GpuBuffer<CameraUniform>,
 }
 
@@ -164,9 +197,18 @@ impl WorldBuffers {
             "World chunk buffer",
         );
 
+        let tint_buffer = GpuPool::new(
+            device,
+            queue,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            MAX_TINTED_GPU_CHUNKS,
+            "World chunk tint buffer",
+        );
+
         Self {
             faces: Arc::new(faces),
             chunks: Arc::new(chunk_buffer),
+            tints: Arc::new(tint_buffer),
             camera,
         }
     }
@@ -179,6 +221,8 @@ impl WorldBuffers {
         let mut all_faces = Vec::new();
         all_faces.extend_from_slice(&mesh_data.opaque_faces);
         all_faces.extend_from_slice(&mesh_data.alpha_cutout_faces);
+        let alpha_blend_first_face = all_faces.len() as u32;
+        all_faces.extend_from_slice(&mesh_data.alpha_blend_faces);
 
         let face_allocation = self
             .faces
@@ -198,6 +242,8 @@ impl WorldBuffers {
             position: mesh_data.position,
             aabb: mesh_data.aabb,
             faces_handle: face_allocation,
+            alpha_blend_faces: mesh_data.alpha_blend_faces.clone(),
+            alpha_blend_first_face,
         }
     }
 }
@@ -209,6 +255,7 @@ pub struct WorldRenderer {
     sky_pass: SkyPass,
     world_geo_pass: WorldGeometryPass,
     chunk_bounds_pass: ChunkBoundsPass,
+    block_selection_pass: BlockSelectionPass,
     pub camera: RenderCamera,
     scene_texture: Texture,
     post_fx: PostFxRenderer,
@@ -217,6 +264,12 @@ pub struct WorldRenderer {
     rendered_chunks: HashMap<ChunkPos, u32>,
     /// Cached mesh AABBs for currently rendered chunks (chunk-local coordinates).
     rendered_chunk_aabbs: HashMap<ChunkPos, AABB8>,
+    /// Rendered chunks with alpha blended faces, which are kept sorted back to front
+    blended_chunks: HashSet<ChunkPos>,
+    /// Voxel the camera was in when blended faces were last sorted
+    last_blend_sort_voxel: Option<IVec3>,
+    /// Chunks outside the sort radius waiting to be re-sorted, nearest first
+    blend_sort_queue: VecDeque<ChunkPos>,
     /// Whether to show chunk boundary wireframes for debugging
     pub show_chunk_bounds: bool,
     /// If true, the chunk bounds debug pass draws each chunk's mesh AABB instead of the full chunk.
@@ -334,6 +387,7 @@ impl WorldRenderer {
         let world_geo_pass =
             WorldGeometryPass::new(device, queue, enabled_features, &buffers, &texture_manager);
         let chunk_bounds_pass = ChunkBoundsPass::new(device, &render_camera.uniform_buffer);
+        let block_selection_pass = BlockSelectionPass::new(device, &render_camera.uniform_buffer);
         let buffers = Arc::new(buffers);
 
         let scene_texture = Texture::from_descriptor(
@@ -371,12 +425,16 @@ impl WorldRenderer {
             sky_pass,
             world_geo_pass,
             chunk_bounds_pass,
+            block_selection_pass,
             camera: render_camera,
             scene_texture,
             post_fx,
             texture_manager,
             rendered_chunks: HashMap::new(),
             rendered_chunk_aabbs: HashMap::new(),
+            blended_chunks: HashSet::new(),
+            last_blend_sort_voxel: None,
+            blend_sort_queue: VecDeque::new(),
             show_chunk_bounds: false,
             use_mesh_aabb_for_bounds: false,
         }
@@ -391,6 +449,11 @@ impl WorldRenderer {
     }
 
     pub fn sync_with_world(&mut self, world: &RenderWorld) {
+        // Chunks that were edited while their mesh was in flight
+        let mut stale_chunks = Vec::new();
+        // Chunks with blended faces that were uploaded this frame, which haven't been sorted yet
+        let mut uploaded_blended_chunks = HashSet::new();
+
         for message in world.chunk_loader.event_receiver.try_iter() {
             match message {
                 ChunkLoaderEvent::ChunkMeshesReady(chunk_mesh_updates, flush_result) => {
@@ -405,25 +468,43 @@ impl WorldRenderer {
                             continue;
                         }
 
-                        if let Some(mesh_id) = update.id {
+                        // The new mesh has been uploaded, so the previous one (if any) can be dropped.
+                        // The GPU ID is swapped in the same frame, so a remeshed chunk never disappears.
+                        if let Some(mut chunk) = world.chunks.get_mut(&update.handle.pos) {
+                            chunk.apply_pending_render_state();
+                        }
+
+                        let ready_state = if let Some(mesh_id) = update.id {
                             // Insert or replace the chunk's GPU ID
                             self.rendered_chunks
                                 .insert(update.handle.pos, mesh_id as u32);
 
-                            // Cache the chunk's mesh AABB for debug rendering.
+                            self.blended_chunks.remove(&update.handle.pos);
+
+                            // Cache the chunk's mesh AABB for debug rendering, and track chunks with blended faces.
                             if let Some(chunk) = world.chunks.get(&update.handle.pos)
                                 && let Some(render_state) = chunk.render_state.as_ref()
                             {
                                 self.rendered_chunk_aabbs
                                     .insert(update.handle.pos, render_state.mesh.aabb);
+
+                                if !render_state.mesh.alpha_blend_faces.is_empty() {
+                                    self.blended_chunks.insert(update.handle.pos);
+                                    uploaded_blended_chunks.insert(update.handle.pos);
+                                }
                             }
 
-                            update.handle.set_state(ChunkState::Ready);
+                            ChunkState::Ready
                         } else {
                             // Empty chunk - remove from rendering if it was there
                             self.rendered_chunks.remove(&update.handle.pos);
                             self.rendered_chunk_aabbs.remove(&update.handle.pos);
-                            update.handle.set_state(ChunkState::ReadyEmpty);
+                            self.blended_chunks.remove(&update.handle.pos);
+                            ChunkState::ReadyEmpty
+                        };
+
+                        if update.handle.finish_meshing(ready_state) {
+                            stale_chunks.push(update.handle);
                         }
                     }
                 }
@@ -431,14 +512,79 @@ impl WorldRenderer {
                     for pos in positions {
                         self.rendered_chunks.remove(&pos);
                         self.rendered_chunk_aabbs.remove(&pos);
+                        self.blended_chunks.remove(&pos);
                     }
                 }
                 ChunkLoaderEvent::WorldReset => {
                     self.rendered_chunks.clear();
                     self.rendered_chunk_aabbs.clear();
+                    self.blended_chunks.clear();
+                    self.blend_sort_queue.clear();
                 }
             }
         }
+
+        world.chunk_loader.remesh(stale_chunks);
+        self.sort_blended_faces(world, &uploaded_blended_chunks);
+    }
+
+    /// Sorts the blended faces of new chunks and the chunks around the camera back to front, and rewrites them on the GPU.
+    /// Further chunks are re-sorted a few per frame after the camera enters another chunk.
+    fn sort_blended_faces(&mut self, world: &RenderWorld, uploaded: &HashSet<ChunkPos>) {
+        let eye = self.camera.interpolated_camera.eye;
+        let eye_voxel = eye.floor().as_ivec3();
+        let camera_chunk = WorldPosF(eye).to_chunk_pos();
+        let previous_voxel = self.last_blend_sort_voxel.replace(eye_voxel);
+
+        let entered_chunk = previous_voxel
+            .is_none_or(|voxel| WorldPosF(voxel.as_vec3()).to_chunk_pos() != camera_chunk);
+        let sort_nearby = previous_voxel != Some(eye_voxel);
+        let distance = |pos: &ChunkPos| (pos.0 - camera_chunk.0).abs().max_element();
+
+        // Chunks queued for an older camera position are superseded by the new queue
+        if entered_chunk {
+            let mut far_chunks: Vec<_> = self
+                .blended_chunks
+                .iter()
+                .filter(|pos| distance(pos) > BLEND_SORT_RADIUS && !uploaded.contains(pos))
+                .copied()
+                .collect();
+            far_chunks.sort_unstable_by_key(distance);
+            self.blend_sort_queue = far_chunks.into();
+        }
+
+        let mut to_sort: Vec<ChunkPos> = uploaded.iter().copied().collect();
+        if sort_nearby {
+            to_sort.extend(
+                self.blended_chunks
+                    .iter()
+                    .filter(|pos| distance(pos) <= BLEND_SORT_RADIUS && !uploaded.contains(pos)),
+            );
+        }
+        let mut budget = BLEND_SORT_BUDGET;
+        while budget > 0
+            && let Some(pos) = self.blend_sort_queue.pop_front()
+        {
+            // Chunks that were unloaded or remeshed without blended faces since being queued are skipped
+            if self.blended_chunks.contains(&pos) && !uploaded.contains(&pos) {
+                to_sort.push(pos);
+                budget -= 1;
+            }
+        }
+
+        for pos in &to_sort {
+            let Some(mut chunk) = world.chunks.get_mut(pos) else {
+                continue;
+            };
+            let Some(render_state) = chunk.render_state.as_mut() else {
+                continue;
+            };
+
+            let mesh = &mut render_state.mesh;
+            sort_faces_back_to_front(&mut mesh.alpha_blend_faces, eye - pos.origin().0.as_vec3());
+            mesh.faces_handle
+                .write_data_at(mesh.alpha_blend_first_face, &mesh.alpha_blend_faces);
+        }
     }
 
     pub fn resize(&mut self, size: Resolution) {
@@ -456,9 +602,17 @@ impl WorldRenderer {
         time: &GameLoopTime,
     ) {
         self.post_fx.update(time);
+        self.texture_manager.update_animation_time(time);
 
-        // Collect chunk IDs for rendering
-        let chunk_ids: Vec<u32> = self.rendered_chunks.values().copied().collect();
+        // Collect chunk IDs for rendering. Blended faces are drawn in this order, so the furthest chunks come first.
+        let eye = self.camera.interpolated_camera.eye;
+        let mut chunks: Vec<(ChunkPos, u32)> = self
+            .rendered_chunks
+            .iter()
+            .map(|(pos, id)| (*pos, *id))
+            .collect();
+        chunks.sort_by_cached_key(|(pos, _)| Reverse(pos.center().distance_squared(eye).to_bits()));
+        let chunk_ids: Vec<u32> = chunks.into_iter().map(|(_, id)| id).collect();
 
         let frustum = self.camera.interpolated_camera.frustum;
         // Update culling params
@@ -482,6 +636,9 @@ impl WorldRenderer {
             culling_params.input_chunk_count,
         );
 
+        self.block_selection_pass
+            .render(encoder, &self.scene_texture.view, depth_texture);
+
         // Render chunk bounds wireframes if enabled
         if self.show_chunk_bounds {
             if self.use_mesh_aabb_for_bounds {
@@ -522,6 +679,12 @@ impl WorldRenderer {
         }
     }
 
+    /// Sets the block highlighted with an outline, or None to hide it
+    pub fn set_block_selection(&mut self, selection: Option<WorldPos>) {
+        self.block_selection_pass
+            .set_selection(&self.queue, selection);
+    }
+
     /// Toggle the visibility of chunk boundary wireframes for debugging
     pub fn toggle_chunk_bounds(&mut self) {
         self.show_chunk_bounds = !self.show_chunk_bounds;